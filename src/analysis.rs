@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+
+use glam::{DMat3, DVec3, Vec3};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    PipelineLayoutDescriptor, Queue, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+use crate::{enkf::Update, instance::InstancesVec};
+
+pub const ANALYSIS_WGSL: &str = include_str!("analysis.wgsl");
+
+// * SAME AS IN analysis.wgsl
+pub(crate) const WORKGROUPS: usize = 256;
+
+/// Layout of bind group 0 of `analysis.wgsl`. A pipeline of its own, so it does not count
+/// against the storage buffers of `compute.wgsl`.
+pub(crate) const BIND_GROUP_LAYOUT_ENTRIES: [BindGroupLayoutEntry; 3] = [
+    // * INSTANCE BUFFER
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * ANALYSIS
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * MOMENTS, ONE PER WORKGROUP
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+/// `Analysis` uniform of `analysis.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Default)]
+pub struct AnalysisUniform {
+    pub center: [f32; 3],
+    pub count: u32,
+    pub observation: [f32; 3],
+    pub observation_noise: f32,
+    /// Columns of the gain, padded to `vec4`.
+    pub gain: [[f32; 4]; 3],
+    pub key: u32,
+    pub cycle: u32,
+    pub _pad: [u32; 2],
+}
+
+impl AnalysisUniform {
+    fn update(update: &Update, count: usize) -> Self {
+        Self {
+            count: count as u32,
+            observation: update.observation.to_array(),
+            observation_noise: update.observation_noise,
+            gain: update
+                .gain
+                .to_cols_array_2d()
+                .map(|c| [c[0], c[1], c[2], 0.]),
+            key: update.key,
+            cycle: update.cycle,
+            ..Self::default()
+        }
+    }
+}
+
+/// `Moments` of one workgroup in `analysis.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct Moments {
+    pub sum: [f32; 3],
+    pub _pad0: f32,
+    pub diagonal: [f32; 3],
+    pub _pad1: f32,
+    /// `xy`, `xz` and `yz`.
+    pub off_diagonal: [f32; 3],
+    pub _pad2: f32,
+}
+
+/// Adds up the moments of all workgroups in `f64`, as sum of the offsets and of their outer
+/// products.
+pub fn combine(moments: &[Moments]) -> (DVec3, DMat3) {
+    moments
+        .iter()
+        .fold((DVec3::ZERO, DMat3::ZERO), |(s, m), w| {
+            let [xx, yy, zz] = w.diagonal.map(f64::from);
+            let [xy, xz, yz] = w.off_diagonal.map(f64::from);
+            let outer = DMat3::from_cols_array(&[xx, xy, xz, xy, yy, yz, xz, yz, zz]);
+            (s + Vec3::from_array(w.sum).as_dvec3(), m + outer)
+        })
+}
+
+/// Reduction and update pipelines of the ensemble Kalman filter on the GPU.
+pub struct AnalysisState {
+    moments_pipeline: ComputePipeline,
+    separation_pipeline: ComputePipeline,
+    update_pipeline: ComputePipeline,
+    bind_group: BindGroup,
+    uniform_buffer: Buffer,
+    /// One [`Moments`] per workgroup, written by [`AnalysisState::moments`].
+    pub moments_buffer: Buffer,
+}
+
+impl AnalysisState {
+    pub fn new(device: &Device, instances: &InstancesVec) -> Self {
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Analysis Buffer"),
+            size: std::mem::size_of::<AnalysisUniform>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let moments_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Moments Buffer"),
+            size: (WORKGROUPS * std::mem::size_of::<Moments>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Analysis Bind Group Layout"),
+            entries: &BIND_GROUP_LAYOUT_ENTRIES,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Analysis Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: instances.buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: moments_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Analysis Shader"),
+            source: ShaderSource::Wgsl(Cow::from(ANALYSIS_WGSL)),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Analysis Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create = |label, entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        Self {
+            moments_pipeline: create("Moments Pipeline", "cs_moments"),
+            separation_pipeline: create("Separation Pipeline", "cs_separation"),
+            update_pipeline: create("Analysis Update Pipeline", "cs_update"),
+            bind_group,
+            uniform_buffer,
+            moments_buffer,
+        }
+    }
+
+    /// Sums the offsets of the first `count` particles from `center` into the moments buffer.
+    pub fn moments(&self, device: &Device, queue: &Queue, center: Vec3, count: usize) {
+        let uniform = AnalysisUniform {
+            center: center.to_array(),
+            count: count as u32,
+            ..AnalysisUniform::default()
+        };
+        self.dispatch(device, queue, &uniform, &self.moments_pipeline);
+    }
+
+    /// Sums the distances of the twins among the first `count` particles into the `sum.x` of
+    /// the moments buffer.
+    pub fn separation(&self, device: &Device, queue: &Queue, count: usize) {
+        let uniform = AnalysisUniform {
+            count: count as u32,
+            ..AnalysisUniform::default()
+        };
+        self.dispatch(device, queue, &uniform, &self.separation_pipeline);
+    }
+
+    /// Applies `update` to the first `count` particles.
+    pub fn update(&self, device: &Device, queue: &Queue, update: &Update, count: usize) {
+        let uniform = AnalysisUniform::update(update, count);
+        self.dispatch(device, queue, &uniform, &self.update_pipeline);
+    }
+
+    fn dispatch(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        uniform: &AnalysisUniform,
+        pipeline: &ComputePipeline,
+    ) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(uniform));
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.dispatch_workgroups(WORKGROUPS as u32, 1, 1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,120 @@
+struct Instance {
+    pos: vec3<f32>,
+    rng: u32,
+    color: vec3<f32>,
+    age: u32,
+}
+
+struct Analysis {
+    center: vec3<f32>,
+    count: u32,
+    observation: vec3<f32>,
+    observation_noise: f32,
+    gain: mat3x3<f32>,
+    key: u32,
+    cycle: u32,
+}
+
+// * SUMS OF d AND d d^T OVER THE OFFSETS d = pos - center, OFF DIAGONAL AS xy, xz, yz
+struct Moments {
+    sum: vec3<f32>,
+    diagonal: vec3<f32>,
+    off_diagonal: vec3<f32>,
+}
+
+// * SAME AS IN analysis.rs
+const WORKGROUP_SIZE = 64u;
+const WORKGROUPS = 256u;
+const TAU = 6.283185307179586;
+
+@group(0) @binding(0)
+var<storage, read_write> instances: array<Instance>;
+@group(0) @binding(1)
+var<uniform> analysis: Analysis;
+@group(0) @binding(2)
+var<storage, read_write> moments: array<Moments, WORKGROUPS>;
+
+var<workgroup> partial: array<Moments, WORKGROUP_SIZE>;
+
+// * SAME AS IN compute.wgsl
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn uniform(rng: ptr<function, u32>) -> f32 {
+    *rng = pcg(*rng);
+    return f32(*rng >> 8u) * (1.0 / 16777216.0);
+}
+
+fn gaussian(rng: ptr<function, u32>) -> f32 {
+    let u1 = 1.0 - uniform(rng);
+    let u2 = uniform(rng);
+    return sqrt(-2.0 * log(u1)) * cos(TAU * u2);
+}
+
+// * EVERY THREAD SUMS A STRIDED SLICE, THEN EACH WORKGROUP REDUCES ITS THREADS
+@compute
+@workgroup_size(64)
+fn cs_moments(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group: vec3<u32>,
+) {
+    var m = Moments(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+    for (var i = group.x * WORKGROUP_SIZE + local; i < analysis.count; i += WORKGROUPS * WORKGROUP_SIZE) {
+        let d = instances[i].pos - analysis.center;
+        m.sum += d;
+        m.diagonal += d * d;
+        m.off_diagonal += d.xxy * d.yzz;
+    }
+    reduce(local, group.x, m);
+}
+
+// * SUMS THE DISTANCES OF THE TWINS 2k AND 2k + 1 INTO sum.x, LIKE twins::mean_separation
+@compute
+@workgroup_size(64)
+fn cs_separation(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group: vec3<u32>,
+) {
+    var m = Moments(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+    for (var k = group.x * WORKGROUP_SIZE + local; 2u * k + 1u < analysis.count; k += WORKGROUPS * WORKGROUP_SIZE) {
+        m.sum.x += distance(instances[2u * k].pos, instances[2u * k + 1u].pos);
+    }
+    reduce(local, group.x, m);
+}
+
+// * ADDS UP THE MOMENTS m OF EVERY THREAD OF WORKGROUP group
+fn reduce(local: u32, group: u32, m: Moments) {
+    partial[local] = m;
+    workgroupBarrier();
+
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        if local < stride {
+            let other = partial[local + stride];
+            partial[local].sum += other.sum;
+            partial[local].diagonal += other.diagonal;
+            partial[local].off_diagonal += other.off_diagonal;
+        }
+        workgroupBarrier();
+    }
+    if local == 0u {
+        moments[group] = partial[0];
+    }
+}
+
+// * MIRRORS Update::apply IN enkf.rs
+@compute
+@workgroup_size(64)
+fn cs_update(@builtin(global_invocation_id) id: vec3<u32>) {
+    for (var i = id.x; i < analysis.count; i += WORKGROUPS * WORKGROUP_SIZE) {
+        var rng = pcg(pcg(analysis.cycle ^ analysis.key) ^ i);
+        let x = gaussian(&rng);
+        let y = gaussian(&rng);
+        let z = gaussian(&rng);
+        let e = vec3<f32>(x, y, z);
+        let p = instances[i].pos;
+        instances[i].pos = p + analysis.gain * (analysis.observation + analysis.observation_noise * e - p);
+    }
+}
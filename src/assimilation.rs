@@ -0,0 +1,113 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use glam::Vec3;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, BufferUsages, Device, Queue,
+};
+
+use wgpu_lorenz::{
+    enkf::{Filter, Record},
+    instance::{DrawState, RawInstance},
+    scene::SceneError,
+    Config, Error, Simulation,
+};
+
+const TRUTH_COLOR: Vec3 = Vec3::ONE;
+
+/// CSV file records are appended to, of every analysis or of the twin separation.
+pub struct Log {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Log {
+    /// Creates the file and writes the header.
+    pub fn create(path: &Path, header: &str) -> Result<Self, Error> {
+        let io_error = |source| Error::Io {
+            path: path.to_owned(),
+            source,
+        };
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        writeln!(writer, "{header}").map_err(io_error)?;
+        Ok(Self {
+            path: path.to_owned(),
+            writer,
+        })
+    }
+
+    /// Appends `records` and flushes, so the file is complete whenever the viewer is closed.
+    pub fn write(&mut self, records: &[impl Display]) -> Result<(), Error> {
+        let write = |writer: &mut BufWriter<File>| -> io::Result<()> {
+            for record in records {
+                writeln!(writer, "{record}")?;
+            }
+            writer.flush()
+        };
+        write(&mut self.writer).map_err(|source| Error::Io {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+/// The truth as a single white particle, drawn on top of the ensemble.
+pub struct TruthMarker {
+    pub instance_buffer: Buffer,
+    pub draw_buffer: Buffer,
+}
+
+impl TruthMarker {
+    pub fn new(device: &Device, truth: Vec3) -> Self {
+        Self {
+            instance_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Truth Instance Buffer"),
+                contents: bytemuck::bytes_of(&RawInstance::new(truth, 0, 0, TRUTH_COLOR)),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            }),
+            draw_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Truth Draw Buffer"),
+                contents: bytemuck::bytes_of(&DrawState::new(1)),
+                usage: BufferUsages::INDIRECT,
+            }),
+        }
+    }
+
+    pub fn set(&self, queue: &Queue, truth: Vec3) {
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::bytes_of(&RawInstance::new(truth, 0, 0, TRUTH_COLOR)),
+        );
+    }
+}
+
+/// Runs the data assimilation of the scene headless for `steps` steps and prints the record of
+/// every analysis as CSV, or writes it to `log`.
+pub fn run(config: Config, steps: u32, log: Option<&Path>) -> Result<(), Error> {
+    let Some(assimilation) = config.assimilation else {
+        return Err(SceneError::Invalid {
+            field: "assimilation",
+            reason: "`--assimilate` needs an [assimilation] table in the scene".to_owned(),
+        }
+        .into());
+    };
+    let mut sim = Simulation::new(config)?;
+    let mut filter = Filter::new(assimilation, sim.config());
+    let records = filter.run(&mut sim, steps);
+    match log {
+        Some(path) => Log::create(path, Record::CSV_HEADER)?.write(&records)?,
+        None => {
+            println!("{}", Record::CSV_HEADER);
+            for record in &records {
+                println!("{record}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use serde::Deserialize;
+use wgpu::{Device, Queue};
+
+use crate::{
+    config::{ClockShader, Config},
+    instance::InstancesVec,
+};
+
+/// Where the particles are integrated. Both backends keep the instance buffer up to date, so
+/// rendering does not care which one is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Compute shader, see `compute.wgsl`.
+    #[default]
+    Gpu,
+    /// Multithreaded SIMD integration, uploaded to the GPU after every step.
+    Cpu,
+}
+
+/// Common interface of [`ComputeState`](crate::compute::ComputeState) and
+/// [`CpuState`](crate::cpu::CpuState).
+pub(crate) trait Stepper {
+    fn step(&mut self, device: &Device, queue: &Queue, instances: &InstancesVec, steps: u32);
+    fn update_config(&mut self, config: &Config, queue: &Queue);
+    fn update_delta_time(&mut self, delta_time: f32, queue: &Queue);
+    /// Sets the time of the next step, see [`ClockShader`].
+    fn update_clock(&mut self, clock: ClockShader, queue: &Queue);
+    fn set_colormap(&mut self, device: &Device, queue: &Queue, colormap: &[u8]);
+}
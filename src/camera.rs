@@ -1,14 +1,84 @@
 use glam::{vec3, Mat3, Mat4, Vec3};
+use serde::Deserialize;
 use wgpu::{
     util::DeviceExt, BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, Buffer,
-    BufferUsages, Device, Queue, ShaderStages, SurfaceConfiguration,
+    BufferUsages, Device, Queue, ShaderStages,
 };
 use winit::event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
 
+/// Layout of bind group 0 of `draw.wgsl`.
+pub(crate) const BIND_GROUP_LAYOUT_ENTRIES: [BindGroupLayoutEntry; 1] = [BindGroupLayoutEntry {
+    binding: 0,
+    visibility: ShaderStages::VERTEX,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    },
+    count: None,
+}];
+
 const SPEED: f32 = 100.;
-const SHIFT_SPEED: f32 = 0.1 * SPEED;
+const SHIFT_SPEED_FACTOR: f32 = 0.1;
 const SENS: f32 = 0.1;
+const FOV_Y: f32 = 45.0;
+const POSITION: [f32; 3] = [-78.22161, 135.65047, -27.054755];
+const DIRECTION: [f32; 3] = [0.5124362, -0.8005198, 0.31076893];
+
+/// Initial pose and controls of the camera.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraSettings {
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub fov_y: f32, // ! DEGREES
+    pub speed: f32,
+    pub sensitivity: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            position: POSITION,
+            direction: DIRECTION,
+            fov_y: FOV_Y,
+            speed: SPEED,
+            sensitivity: SENS,
+        }
+    }
+}
+
+// * FOLLOW CAMERA
+const CHASE_DISTANCE: f32 = 15.;
+const CHASE_HEIGHT: f32 = 3.;
+const CHASE_STIFFNESS: f32 = 5.;
+const ORBIT_DISTANCE: f32 = 40.;
+const ORBIT_MIN_DISTANCE: f32 = 2.;
+const ORBIT_ZOOM_SPEED: f32 = 1.;
+
+/// How the camera is moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    Free,
+    /// Rides behind the followed particle, looking along its velocity.
+    Chase,
+    /// Orbits the followed particle at a fixed tether length, steered by the mouse.
+    Orbit,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Free => CameraMode::Chase,
+            CameraMode::Chase => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Free,
+        }
+    }
+}
+
+/// Camera uniform on the GPU together with its pose and input state.
 pub struct Camera {
+    pub mode: CameraMode,
     pub entity: CameraEntity,
     pub uniform: CameraUniform,
     pub controller: CameraController,
@@ -17,19 +87,19 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Creates the camera and the layout of its bind group, which holds the view projection
+    /// matrix at binding 0.
     pub fn create_camera(
         device: &Device,
-        config: &SurfaceConfiguration,
+        aspect_ratio: f32,
+        settings: &CameraSettings,
     ) -> (Self, BindGroupLayout) {
-        let pos = 0.7 * vec3(-111.74516, 193.78638, -38.64965);
-        let dir = vec3(0.5124362, -0.8005198, 0.31076893);
-
         let entity = CameraEntity {
-            pos,
-            dir: dir.normalize(),
+            pos: Vec3::from_array(settings.position),
+            dir: Vec3::from_array(settings.direction).normalize(),
             up: Vec3::Y,
-            aspect_ratio: config.width as f32 / config.height as f32,
-            fov_y: 45.0,
+            aspect_ratio,
+            fov_y: settings.fov_y,
             z_near: 1.,
             z_far: 1000.0,
         };
@@ -42,16 +112,7 @@ impl Camera {
         });
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Camera Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            entries: &BIND_GROUP_LAYOUT_ENTRIES,
         });
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Camera Bind Groups"),
@@ -61,10 +122,11 @@ impl Camera {
                 resource: buffer.as_entire_binding(),
             }],
         });
-        let controller = CameraController::new(SPEED, SENS);
+        let controller = CameraController::new(settings.speed, settings.sensitivity);
 
         (
             Self {
+                mode: CameraMode::Free,
                 entity,
                 uniform,
                 controller,
@@ -78,6 +140,42 @@ impl Camera {
     pub fn update(&mut self, delta: f32, queue: &Queue) {
         self.controller
             .update_camera_entity(&mut self.entity, delta);
+        self.write_uniform(queue);
+    }
+
+    /// Moves the camera along with a particle at `target` travelling with velocity `vel`.
+    pub fn follow(&mut self, target: Vec3, vel: Vec3, delta: f32, queue: &Queue) {
+        match self.mode {
+            CameraMode::Free => return,
+            CameraMode::Chase => {
+                if let Some(vel_dir) = vel.try_normalize() {
+                    let t = 1. - (-CHASE_STIFFNESS * delta).exp();
+                    self.entity.dir = self.entity.dir.lerp(vel_dir, t).normalize();
+                }
+                self.entity.pos =
+                    target - CHASE_DISTANCE * self.entity.dir + CHASE_HEIGHT * self.entity.up;
+            }
+            CameraMode::Orbit => {
+                self.controller.update_orbit(delta);
+                let (yaw, pitch) = self.controller.orbit_angles;
+                let offset = vec3(
+                    pitch.cos() * yaw.sin(),
+                    pitch.sin(),
+                    pitch.cos() * yaw.cos(),
+                );
+                self.entity.pos = target + self.controller.orbit_distance * offset;
+                self.entity.dir = -offset;
+            }
+        }
+        self.write_uniform(queue);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32, queue: &Queue) {
+        self.entity.aspect_ratio = width as f32 / height as f32;
+        self.write_uniform(queue);
+    }
+
+    fn write_uniform(&mut self, queue: &Queue) {
         self.uniform.update(&self.entity);
         queue.write_buffer(
             &self.buffer,
@@ -86,6 +184,7 @@ impl Camera {
         );
     }
 }
+/// Pose and projection of a perspective camera.
 #[derive(Debug)]
 pub struct CameraEntity {
     pub pos: Vec3,
@@ -112,6 +211,7 @@ impl CameraEntity {
         }
     }
 }
+/// View projection matrix as uploaded to the shaders.
 #[repr(C)]
 pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
@@ -133,7 +233,9 @@ impl Default for CameraUniform {
         Self::new()
     }
 }
+/// Turns keyboard and mouse input into camera movement.
 pub struct CameraController {
+    base_speed: f32,
     speed: f32,
     sens: f32,
     is_forward_pressed: bool,
@@ -141,11 +243,14 @@ pub struct CameraController {
     is_left_pressed: bool,
     is_right_pressed: bool,
     delta: (f32, f32),
+    pub orbit_angles: (f32, f32), // ! RADIANS
+    pub orbit_distance: f32,
 }
 
 impl CameraController {
     pub fn new(speed: f32, sens: f32) -> Self {
         Self {
+            base_speed: speed,
             speed,
             is_forward_pressed: false,
             is_backward_pressed: false,
@@ -153,6 +258,8 @@ impl CameraController {
             is_right_pressed: false,
             delta: (0., 0.),
             sens,
+            orbit_angles: (0., 0.),
+            orbit_distance: ORBIT_DISTANCE,
         }
     }
 
@@ -191,9 +298,9 @@ impl CameraController {
             }
             WindowEvent::ModifiersChanged(modifier_state) => {
                 if modifier_state.shift() {
-                    self.speed = SHIFT_SPEED;
+                    self.speed = SHIFT_SPEED_FACTOR * self.base_speed;
                 } else {
-                    self.speed = SPEED
+                    self.speed = self.base_speed
                 }
                 true
             }
@@ -208,6 +315,28 @@ impl CameraController {
             false
         }
     }
+    pub fn set_speed_and_sens(&mut self, speed: f32, sens: f32) {
+        self.base_speed = speed;
+        self.speed = speed;
+        self.sens = sens;
+    }
+    pub fn clear_mouse_delta(&mut self) {
+        self.delta = (0., 0.);
+    }
+    pub fn update_orbit(&mut self, dt: f32) {
+        let (yaw, pitch) = &mut self.orbit_angles;
+        *yaw -= self.delta.0.to_radians() * self.sens;
+        *pitch = (*pitch + self.delta.1.to_radians() * self.sens).clamp(-1.5, 1.5);
+        self.delta = (0., 0.);
+
+        let zoom = ORBIT_ZOOM_SPEED * self.speed * dt;
+        if self.is_forward_pressed {
+            self.orbit_distance = (self.orbit_distance - zoom).max(ORBIT_MIN_DISTANCE);
+        }
+        if self.is_backward_pressed {
+            self.orbit_distance += zoom;
+        }
+    }
     pub fn update_camera_entity(&mut self, camera_entity: &mut CameraEntity, dt: f32) {
         camera_entity.dir = camera_entity.dir.normalize();
         let yaw = Mat3::from_rotation_y(-self.delta.0.to_radians() * self.sens);
@@ -0,0 +1,319 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use wgpu_lorenz::{
+    backend::Backend,
+    config::Config,
+    coupling::{Coupling, Topology},
+    delay::DelayKind,
+    embedding::{Embedding, Observable},
+    hyper::{HyperColor, HyperKind},
+    integrator::Integrator,
+    lorenz::DistributionShape,
+    maps::{MapKind, MapLayout},
+    scene::{RenderMode, Scene, SceneError, System},
+    strobe::Strobe,
+    twins::Twins,
+};
+
+/// GPU accelerated particle simulation of the Lorenz attractor.
+///
+/// Settings are taken from the scene file (if any) and then overridden by the flags below.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Args {
+    /// TOML scene file to load
+    pub scene: Option<PathBuf>,
+
+    #[arg(long, value_enum)]
+    pub system: Option<System>,
+    /// Run the simulation on the GPU or on the CPU
+    #[arg(long, value_enum)]
+    pub backend: Option<Backend>,
+    #[arg(long, value_enum)]
+    pub integrator: Option<Integrator>,
+    #[arg(long)]
+    pub rho: Option<f32>,
+    #[arg(long)]
+    pub sigma: Option<f32>,
+    #[arg(long)]
+    pub beta: Option<f32>,
+    #[arg(long)]
+    pub step_size_factor: Option<f32>,
+    /// Number of Lorenz-96 variables N
+    #[arg(long)]
+    pub dimensions: Option<usize>,
+    /// Lorenz-96 forcing F
+    #[arg(long)]
+    pub forcing: Option<f32>,
+    /// Iterate this map instead, selects `--system map`
+    #[arg(long, value_enum)]
+    pub map: Option<MapKind>,
+    /// Draw the iterates of a map in the plane or stacked by iteration
+    #[arg(long, value_enum)]
+    pub map_layout: Option<MapLayout>,
+    /// Simulate this 4D system instead, selects `--system hyper`
+    #[arg(long, value_enum)]
+    pub hyper: Option<HyperKind>,
+    /// Color a 4D system by speed or by the coordinate the projection drops
+    #[arg(long, value_enum)]
+    pub hyper_color: Option<HyperColor>,
+    /// Simulate this delay differential equation instead, selects `--system delay`
+    #[arg(long, value_enum)]
+    pub dde: Option<DelayKind>,
+    /// Delay τ of a delay differential equation
+    #[arg(long)]
+    pub tau: Option<f32>,
+    /// Couple the particles of a Lorenz or custom system to each other like this
+    #[arg(long, value_enum)]
+    pub coupling: Option<Topology>,
+    /// Strength of the coupling, adds one with the default topology if there is none
+    #[arg(long)]
+    pub coupling_strength: Option<f32>,
+    /// Create the particles in pairs a tiny distance apart, in contrasting colors
+    #[arg(long)]
+    pub twins: bool,
+    /// Distance the twins start at, adds twins if there are none
+    #[arg(long)]
+    pub twin_separation: Option<f32>,
+    /// dx/dt of a custom system, over `x`, `y`, `z`, `t` and the parameters. Any of
+    /// `--dx`, `--dy` and `--dz` selects `--system custom`
+    #[arg(long, allow_hyphen_values = true)]
+    pub dx: Option<String>,
+    /// dy/dt of a custom system
+    #[arg(long, allow_hyphen_values = true)]
+    pub dy: Option<String>,
+    /// dz/dt of a custom system
+    #[arg(long, allow_hyphen_values = true)]
+    pub dz: Option<String>,
+    /// Parameter of a custom system as `name=value`, may be repeated
+    #[arg(long = "param", value_parser = parse_parameter, allow_hyphen_values = true)]
+    pub parameters: Vec<(String, f32)>,
+
+    /// Number of particles, rounded down to a cube
+    #[arg(long, short = 'n')]
+    pub particles: Option<usize>,
+    #[arg(long, value_enum)]
+    pub distribution: Option<DistributionShape>,
+    /// Center of the initial distribution, as `x,y,z`
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    pub center: Option<[f32; 3]>,
+    /// Half-width, radius or standard deviation of the initial distribution
+    #[arg(long)]
+    pub extent: Option<f32>,
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Time step of the first frame, in seconds
+    #[arg(long)]
+    pub delta_time: Option<f32>,
+    /// Particles further away from the origin are respawned
+    #[arg(long)]
+    pub respawn_radius: Option<f32>,
+    /// Particles slower than this are respawned, 0 disables the check
+    #[arg(long)]
+    pub respawn_min_speed: Option<f32>,
+    /// Forcing period: advance one period per frame and show a stroboscopic sample
+    #[arg(long)]
+    pub strobe: Option<f32>,
+    /// Integration steps per forcing period with `--strobe`
+    #[arg(long)]
+    pub strobe_samples: Option<u32>,
+
+    /// Initial camera position, as `x,y,z`
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    pub camera_position: Option<[f32; 3]>,
+    /// Initial camera view direction, as `x,y,z`
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    pub camera_direction: Option<[f32; 3]>,
+    /// Vertical field of view in degrees
+    #[arg(long)]
+    pub fov_y: Option<f32>,
+    #[arg(long)]
+    pub camera_speed: Option<f32>,
+    #[arg(long)]
+    pub camera_sensitivity: Option<f32>,
+
+    /// Built-in colormap (`gradient`, `cloud`) or path to a PNG
+    #[arg(long)]
+    pub colormap: Option<String>,
+    #[arg(long, value_enum)]
+    pub render_mode: Option<RenderMode>,
+    /// Brightness each particle adds with `--render-mode density`
+    #[arg(long)]
+    pub exposure: Option<f32>,
+    #[arg(long)]
+    pub width: Option<u32>,
+    #[arg(long)]
+    pub height: Option<u32>,
+
+    /// Development mode: reload the WGSL shaders and the scene file when they change on disk
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Run headless and print a checksum of the particles after each of these step counts
+    #[arg(long, value_delimiter = ',')]
+    pub checksum_at: Vec<u32>,
+    /// Run headless and compare the checksums with a file written by `--checksum-at`
+    #[arg(long)]
+    pub expect_checksums: Option<PathBuf>,
+
+    /// Run the data assimilation of the scene headless for this many steps and print the RMSE
+    /// and spread of every analysis as CSV
+    #[arg(long)]
+    pub assimilate: Option<u32>,
+    /// Write the RMSE and spread of every analysis to this CSV file instead
+    #[arg(long)]
+    pub assimilation_log: Option<PathBuf>,
+
+    /// Run the twins of the scene headless for this many steps and print the mean separation
+    /// after every step as CSV
+    #[arg(long)]
+    pub diverge: Option<u32>,
+    /// Write the mean separation of the twins after every step to this CSV file instead, also
+    /// from the viewer
+    #[arg(long)]
+    pub divergence_log: Option<PathBuf>,
+
+    /// Write the mean, covariance, bounding box, speed range and lobe fractions of the
+    /// particles to this CSV file every frame
+    #[arg(long)]
+    pub stats_log: Option<PathBuf>,
+    /// Run headless for this many steps and print the box-counting and correlation dimension
+    /// of the particles with the log-log data they are fitted to
+    #[arg(long)]
+    pub dimension: Option<u32>,
+    /// Mark the fixed points of the flow with their stable and unstable eigen-directions,
+    /// toggled with `E`
+    #[arg(long)]
+    pub equilibria: bool,
+
+    /// Show a delay embedding of this observable next to the attractor
+    #[arg(long, value_enum)]
+    pub observable: Option<Observable>,
+    /// Delay of the embedding, in steps
+    #[arg(long)]
+    pub delay: Option<usize>,
+    /// Run headless for this many steps and suggest a delay and embedding dimension
+    #[arg(long)]
+    pub suggest_embedding: Option<u32>,
+}
+
+fn parse_vec3(s: &str) -> Result<[f32; 3], String> {
+    let parts = s
+        .split(',')
+        .map(|p| p.trim().parse::<f32>().map_err(|e| format!("`{p}`: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    parts.try_into().map_err(|parts: Vec<f32>| {
+        format!("expected 3 comma separated numbers, got {}", parts.len())
+    })
+}
+
+fn parse_parameter(s: &str) -> Result<(String, f32), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `name=value`, got `{s}`"))?;
+    let value = value
+        .trim()
+        .parse()
+        .map_err(|e| format!("`{}`: {e}", value.trim()))?;
+    Ok((name.trim().to_owned(), value))
+}
+
+impl Args {
+    /// Loads the scene file, applies the overrides and validates the result.
+    pub fn load_config(&self) -> Result<Config, SceneError> {
+        let mut scene = match &self.scene {
+            Some(path) => Scene::load(path)?,
+            None => Scene::default(),
+        };
+        self.apply(&mut scene);
+        scene.into_config()
+    }
+
+    pub fn apply(&self, scene: &mut Scene) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(&mut scene.system, &self.system);
+        set(&mut scene.backend, &self.backend);
+        set(&mut scene.integrator, &self.integrator);
+        set(&mut scene.parameters.rho, &self.rho);
+        set(&mut scene.parameters.sigma, &self.sigma);
+        set(&mut scene.parameters.beta, &self.beta);
+        set(
+            &mut scene.parameters.step_size_factor,
+            &self.step_size_factor,
+        );
+        set(&mut scene.lorenz96.dimensions, &self.dimensions);
+        set(&mut scene.lorenz96.forcing, &self.forcing);
+        if self.dx.is_some() || self.dy.is_some() || self.dz.is_some() {
+            scene.system = System::Custom;
+        }
+        if let Some(kind) = self.map {
+            scene.system = System::Map;
+            scene.map.kind = kind;
+        }
+        set(&mut scene.map.layout, &self.map_layout);
+        if let Some(kind) = self.hyper {
+            scene.system = System::Hyper;
+            scene.hyper.kind = kind;
+        }
+        set(&mut scene.hyper.color, &self.hyper_color);
+        if let Some(kind) = self.dde {
+            scene.system = System::Delay;
+            scene.delay.kind = kind;
+        }
+        if self.tau.is_some() {
+            scene.delay.tau = self.tau;
+        }
+        if self.coupling.is_some() || self.coupling_strength.is_some() {
+            let coupling = scene.coupling.get_or_insert_with(Coupling::default);
+            set(&mut coupling.topology, &self.coupling);
+            set(&mut coupling.strength, &self.coupling_strength);
+        }
+        if self.twins || self.twin_separation.is_some() {
+            let twins = scene.twins.get_or_insert_with(Twins::default);
+            set(&mut twins.separation, &self.twin_separation);
+        }
+        set(&mut scene.custom.dx, &self.dx);
+        set(&mut scene.custom.dy, &self.dy);
+        set(&mut scene.custom.dz, &self.dz);
+        scene
+            .custom
+            .parameters
+            .extend(self.parameters.iter().cloned());
+        set(&mut scene.particles, &self.particles);
+        set(&mut scene.distribution.shape, &self.distribution);
+        set(&mut scene.distribution.center, &self.center);
+        set(&mut scene.distribution.extent, &self.extent);
+        if self.seed.is_some() {
+            scene.seed = self.seed;
+        }
+        set(&mut scene.delta_time, &self.delta_time);
+        if self.observable.is_some() || self.delay.is_some() {
+            let embedding = scene.embedding.get_or_insert_with(Embedding::default);
+            set(&mut embedding.observable, &self.observable);
+            set(&mut embedding.delay, &self.delay);
+        }
+        set(&mut scene.respawn.radius, &self.respawn_radius);
+        set(&mut scene.respawn.min_speed, &self.respawn_min_speed);
+        set(&mut scene.camera.position, &self.camera_position);
+        set(&mut scene.camera.direction, &self.camera_direction);
+        set(&mut scene.camera.fov_y, &self.fov_y);
+        set(&mut scene.camera.speed, &self.camera_speed);
+        if self.strobe.is_some() || self.strobe_samples.is_some() {
+            let strobe = scene.strobe.get_or_insert_with(Strobe::default);
+            set(&mut strobe.period, &self.strobe);
+            set(&mut strobe.samples, &self.strobe_samples);
+        }
+        set(&mut scene.camera.sensitivity, &self.camera_sensitivity);
+        set(&mut scene.colormap, &self.colormap);
+        set(&mut scene.render_mode, &self.render_mode);
+        set(&mut scene.exposure, &self.exposure);
+        set(&mut scene.window.width, &self.width);
+        set(&mut scene.window.height, &self.height);
+    }
+}
@@ -9,55 +9,354 @@ use wgpu::{
 };
 
 use crate::{
-    config::{Config, ConfigComputeShader},
-    env::Environment,
+    backend::Stepper,
+    config::{ClockShader, Config, ConfigComputeShader},
+    equations::Equations,
+    error,
+    instance::InstancesVec,
+    lorenz96::MAX_DIMENSIONS,
+    scene::System,
     texture::Texture,
 };
 
+pub const COMPUTE_WGSL: &str = include_str!("compute.wgsl");
+
+const VELOCITY_BEGIN: &str = "// * BEGIN VELOCITY";
+const VELOCITY_END: &str = "// * END VELOCITY";
+
+/// `compute_wgsl` with the lines between the velocity markers replaced by `velocity`, the
+/// `lorenz_vel` of a custom system. Unchanged without `velocity` or markers.
+pub fn splice_velocity<'a>(compute_wgsl: &'a str, velocity: Option<&str>) -> Cow<'a, str> {
+    let Some(velocity) = velocity else {
+        return Cow::Borrowed(compute_wgsl);
+    };
+    let (Some(begin), Some(end)) = (
+        compute_wgsl.find(VELOCITY_BEGIN),
+        compute_wgsl.find(VELOCITY_END),
+    ) else {
+        return Cow::Borrowed(compute_wgsl);
+    };
+    let end = end + VELOCITY_END.len();
+    Cow::Owned(format!(
+        "{}{velocity}{}",
+        &compute_wgsl[..begin],
+        &compute_wgsl[end..]
+    ))
+}
+
+/// Layout of bind group 0 of `compute.wgsl`.
+pub(crate) const BIND_GROUP_LAYOUT_ENTRIES: [BindGroupLayoutEntry; 8] = [
+    // *INSTANCE BUFFER
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * CONFIG
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * DELTA TIME
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * RESPAWN COUNTER
+    BindGroupLayoutEntry {
+        binding: 3,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * DRAW STATE
+    BindGroupLayoutEntry {
+        binding: 4,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * PER-PARTICLE STATES
+    BindGroupLayoutEntry {
+        binding: 5,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * PROJECTION OF THE STATES
+    BindGroupLayoutEntry {
+        binding: 6,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * CLOCK
+    BindGroupLayoutEntry {
+        binding: 7,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+/// `projection` uniform of `compute.wgsl`, the images of the state variables.
+fn projection_uniform(config: &Config) -> [[f32; 4]; MAX_DIMENSIONS] {
+    let mut uniform = [[0.; 4]; MAX_DIMENSIONS];
+    for (u, p) in uniform.iter_mut().zip(config.projection()) {
+        *u = p.to_array();
+    }
+    uniform
+}
+
 pub struct ComputeState {
     compute_pipeline: ComputePipeline,
+    advance_pipeline: ComputePipeline,
+    lorenz96_pipeline: ComputePipeline,
+    hyper_pipeline: ComputePipeline,
+    /// Places 4D states at their projection without stepping them.
+    hyper_project_pipeline: ComputePipeline,
+    delay_pipeline: ComputePipeline,
+    /// Copies the positions of coupled particles before every step.
+    coupling_snapshot_pipeline: ComputePipeline,
+    /// Mean and synchronization error of coupled particles, in a single workgroup.
+    coupling_mean_pipeline: ComputePipeline,
+    system: System,
+    coupled: bool,
+    /// Source the pipelines were built from, before splicing in `velocity`.
+    compute_wgsl: String,
+    /// Generated `lorenz_vel` of a custom system.
+    velocity: Option<String>,
+    bind_group_layout: BindGroupLayout,
     bind_group: BindGroup,
+    config_buffer: Buffer,
     delta_time_buffer: Buffer,
+    clock_buffer: Buffer,
+    /// Number of respawned particles, a single `u32`.
+    pub respawn_buffer: Buffer,
+    projection_buffer: Buffer,
     gradient_texture: Texture,
+    num_workgroups: (u32, u32, u32),
 }
 
 impl ComputeState {
     pub fn new(
-        env: &Environment,
-        instance_buffer: &Buffer,
+        device: &Device,
+        queue: &Queue,
+        instances: &InstancesVec,
         config: &Config,
         delta_time: f32,
     ) -> Self {
-        let delta_time_buffer = env.device.create_buffer_init(&BufferInitDescriptor {
+        let delta_time_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Delta Time Buffer"),
             contents: &delta_time.to_ne_bytes(),
             usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
         });
-        let (bind_group_layout, bind_group) =
-            Self::create_bind_group(&env.device, instance_buffer, config, &delta_time_buffer);
+        let clock_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Clock Buffer"),
+            contents: bytemuck::bytes_of(&ClockShader::default()),
+            usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+        });
+        let respawn_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Respawn Counter Buffer"),
+            contents: &0u32.to_ne_bytes(),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        });
+        let projection_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Projection Buffer"),
+            contents: bytemuck::cast_slice(&projection_uniform(config)),
+            usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+        });
+        let config_buffer = ConfigComputeShader::from(config).as_buffer(device);
+        let (bind_group_layout, bind_group) = Self::create_bind_group(
+            device,
+            instances,
+            &config_buffer,
+            &delta_time_buffer,
+            &respawn_buffer,
+            &projection_buffer,
+            &clock_buffer,
+        );
 
-        let gradient_texture =
-            Texture::new(&env.device, &env.queue, include_bytes!("../gradient.png"), ShaderStages::COMPUTE);
+        let gradient_texture = Texture::new(device, queue, &config.colormap, ShaderStages::COMPUTE);
 
-        let compute_pipeline = Self::create_compute_pipeline(
-            &env.device,
+        let velocity = config.custom.as_ref().map(Equations::wgsl);
+        let (
+            compute_pipeline,
+            advance_pipeline,
+            lorenz96_pipeline,
+            hyper_pipeline,
+            hyper_project_pipeline,
+            delay_pipeline,
+            coupling_snapshot_pipeline,
+            coupling_mean_pipeline,
+        ) = Self::create_compute_pipelines(
+            device,
             &[&bind_group_layout, &gradient_texture.bind_group_layout],
+            &splice_velocity(COMPUTE_WGSL, velocity.as_deref()),
         );
 
         Self {
             compute_pipeline,
+            advance_pipeline,
+            lorenz96_pipeline,
+            hyper_pipeline,
+            hyper_project_pipeline,
+            delay_pipeline,
+            coupling_snapshot_pipeline,
+            coupling_mean_pipeline,
+            system: config.system,
+            coupled: config.coupling.is_some(),
+            compute_wgsl: COMPUTE_WGSL.to_owned(),
+            velocity,
+            bind_group_layout,
             bind_group,
+            config_buffer,
             delta_time_buffer,
+            clock_buffer,
+            respawn_buffer,
+            projection_buffer,
             gradient_texture,
+            num_workgroups: config.num_workgroups,
         }
     }
 
-    fn create_compute_pipeline(
+    /// Rebuilds the pipeline from new WGSL source. On error the old pipeline stays in use.
+    pub fn reload_shader(
+        &mut self,
         device: &Device,
-        bind_group_layouts: &[&BindGroupLayout],
-    ) -> ComputePipeline {
-        let compute_wgsl = include_str!("compute.wgsl");
+        compute_wgsl: &str,
+    ) -> Result<(), wgpu::Error> {
+        self.rebuild(device, compute_wgsl, self.velocity.clone())
+    }
+
+    /// Rebuilds the pipeline with the `lorenz_vel` of a new custom system. On error the old
+    /// pipeline stays in use.
+    pub fn set_velocity(&mut self, device: &Device, velocity: String) -> Result<(), wgpu::Error> {
+        let compute_wgsl = self.compute_wgsl.clone();
+        self.rebuild(device, &compute_wgsl, Some(velocity))
+    }
+
+    fn rebuild(
+        &mut self,
+        device: &Device,
+        compute_wgsl: &str,
+        velocity: Option<String>,
+    ) -> Result<(), wgpu::Error> {
+        (
+            self.compute_pipeline,
+            self.advance_pipeline,
+            self.lorenz96_pipeline,
+            self.hyper_pipeline,
+            self.hyper_project_pipeline,
+            self.delay_pipeline,
+            self.coupling_snapshot_pipeline,
+            self.coupling_mean_pipeline,
+        ) = error::checked(device, || {
+            Self::create_compute_pipelines(
+                device,
+                &[
+                    &self.bind_group_layout,
+                    &self.gradient_texture.bind_group_layout,
+                ],
+                &splice_velocity(compute_wgsl, velocity.as_deref()),
+            )
+        })?;
+        self.compute_wgsl = compute_wgsl.to_owned();
+        self.velocity = velocity;
+        Ok(())
+    }
+
+    pub fn update_config(&self, config: &Config, queue: &Queue) {
+        queue.write_buffer(
+            &self.config_buffer,
+            0,
+            bytemuck::bytes_of(&ConfigComputeShader::from(config)),
+        )
+    }
+
+    pub fn set_colormap(&mut self, device: &Device, queue: &Queue, colormap: &[u8]) {
+        self.gradient_texture = Texture::new(device, queue, colormap, ShaderStages::COMPUTE);
+    }
+
+    /// Writes the projection of `config`, e.g. after a new 4D rotation.
+    pub fn update_projection(&self, config: &Config, queue: &Queue) {
+        queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&projection_uniform(config)),
+        )
+    }
+
+    /// Moves every particle of a 4D system to the projection of its state, without a step.
+    pub fn project_call(&self, device: &Device, queue: &Queue) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.gradient_texture.bind_group, &[]);
+            compute_pass.set_pipeline(&self.hyper_project_pipeline);
+            compute_pass.dispatch_workgroups(
+                self.num_workgroups.0,
+                self.num_workgroups.1,
+                self.num_workgroups.2,
+            );
+        }
+        queue.submit(Some(encoder.finish()));
+    }
 
+    /// Creates the pipelines of `cs_main`, `cs_advance`, `cs_lorenz96`, `cs_hyper`,
+    /// `cs_hyper_project`, `cs_delay`, `cs_coupling_snapshot` and `cs_coupling_mean`.
+    fn create_compute_pipelines(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        compute_wgsl: &str,
+    ) -> (
+        ComputePipeline,
+        ComputePipeline,
+        ComputePipeline,
+        ComputePipeline,
+        ComputePipeline,
+        ComputePipeline,
+        ComputePipeline,
+        ComputePipeline,
+    ) {
         let compute_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Compute Shader"),
             source: ShaderSource::Wgsl(Cow::from(compute_wgsl)),
@@ -69,57 +368,38 @@ impl ComputeState {
             push_constant_ranges: &[],
         });
 
-        device.create_compute_pipeline(&ComputePipelineDescriptor {
-            label: Some("Compute Shader Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: "cs_main",
-        })
+        let create = |label, entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point,
+            })
+        };
+        (
+            create("Compute Shader Pipeline", "cs_main"),
+            create("Emitter Advance Pipeline", "cs_advance"),
+            create("Lorenz-96 Pipeline", "cs_lorenz96"),
+            create("Hyperchaos Pipeline", "cs_hyper"),
+            create("Hyperchaos Projection Pipeline", "cs_hyper_project"),
+            create("Delay Equation Pipeline", "cs_delay"),
+            create("Coupling Snapshot Pipeline", "cs_coupling_snapshot"),
+            create("Coupling Mean Pipeline", "cs_coupling_mean"),
+        )
     }
 
     fn create_bind_group(
         device: &Device,
-        instance_buffer: &Buffer,
-        config: &Config,
+        instances: &InstancesVec,
+        config_buffer: &Buffer,
         delta_buffer: &Buffer,
+        respawn_buffer: &Buffer,
+        projection_buffer: &Buffer,
+        clock_buffer: &Buffer,
     ) -> (BindGroupLayout, BindGroup) {
         let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("Compute Bind Group Layout"),
-            entries: &[
-                // *INSTANCE BUFFER
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                // * CONFIG
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                // * DELTA TIME
-                BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
+            entries: &BIND_GROUP_LAYOUT_ENTRIES,
         });
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
             label: Some("Compute Bind Group"),
@@ -128,40 +408,115 @@ impl ComputeState {
                 // * INSTANCE_BUFFER
                 BindGroupEntry {
                     binding: 0,
-                    resource: instance_buffer.as_entire_binding(),
+                    resource: instances.buffer.as_entire_binding(),
                 },
                 // * CONFIG
                 BindGroupEntry {
                     binding: 1,
-                    resource: ConfigComputeShader::from(config)
-                        .as_buffer(device)
-                        .as_entire_binding(),
+                    resource: config_buffer.as_entire_binding(),
                 },
                 // * DELTA TIME
                 BindGroupEntry {
                     binding: 2,
                     resource: delta_buffer.as_entire_binding(),
                 },
+                // * RESPAWN COUNTER
+                BindGroupEntry {
+                    binding: 3,
+                    resource: respawn_buffer.as_entire_binding(),
+                },
+                // * DRAW STATE
+                BindGroupEntry {
+                    binding: 4,
+                    resource: instances.draw_buffer.as_entire_binding(),
+                },
+                // * PER-PARTICLE STATES, COUPLING SNAPSHOT
+                BindGroupEntry {
+                    binding: 5,
+                    resource: instances.states.as_entire_binding(),
+                },
+                // * PROJECTION OF THE STATES
+                BindGroupEntry {
+                    binding: 6,
+                    resource: projection_buffer.as_entire_binding(),
+                },
+                // * CLOCK
+                BindGroupEntry {
+                    binding: 7,
+                    resource: clock_buffer.as_entire_binding(),
+                },
             ],
         });
         (bind_group_layout, bind_group)
     }
 
-    pub fn compute_call(&self, env: &Environment, num_workgroups: (u32, u32, u32)) {
-        let mut encoder = env
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor::default());
+    /// Advances every particle by `steps` time steps in a single submission.
+    pub fn compute_call(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        num_workgroups: (u32, u32, u32),
+        steps: u32,
+    ) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
-            compute_pass.set_pipeline(&self.compute_pipeline);
             compute_pass.set_bind_group(0, &self.bind_group, &[]);
             compute_pass.set_bind_group(1, &self.gradient_texture.bind_group, &[]);
-            compute_pass.dispatch_workgroups(num_workgroups.0, num_workgroups.1, num_workgroups.2);
+            let step_pipeline = match self.system {
+                System::Lorenz | System::Custom | System::Map => &self.compute_pipeline,
+                System::Lorenz96 => &self.lorenz96_pipeline,
+                System::Hyper => &self.hyper_pipeline,
+                System::Delay => &self.delay_pipeline,
+            };
+            for _ in 0..steps {
+                // * COUPLED PARTICLES ALL SEE THE POSITIONS BEFORE THE STEP
+                if self.coupled {
+                    compute_pass.set_pipeline(&self.coupling_snapshot_pipeline);
+                    compute_pass.dispatch_workgroups(
+                        num_workgroups.0,
+                        num_workgroups.1,
+                        num_workgroups.2,
+                    );
+                    compute_pass.set_pipeline(&self.coupling_mean_pipeline);
+                    compute_pass.dispatch_workgroups(1, 1, 1);
+                }
+                compute_pass.set_pipeline(step_pipeline);
+                compute_pass.dispatch_workgroups(
+                    num_workgroups.0,
+                    num_workgroups.1,
+                    num_workgroups.2,
+                );
+                compute_pass.set_pipeline(&self.advance_pipeline);
+                compute_pass.dispatch_workgroups(1, 1, 1);
+            }
         }
 
-        env.queue.submit(Some(encoder.finish()));
+        queue.submit(Some(encoder.finish()));
     }
     pub fn update_delta_time_buffer(&self, delta_time: f32, queue: &Queue) {
         queue.write_buffer(&self.delta_time_buffer, 0, &delta_time.to_ne_bytes())
     }
 }
+
+impl Stepper for ComputeState {
+    fn step(&mut self, device: &Device, queue: &Queue, _instances: &InstancesVec, steps: u32) {
+        self.compute_call(device, queue, self.num_workgroups, steps);
+    }
+
+    fn update_config(&mut self, config: &Config, queue: &Queue) {
+        ComputeState::update_config(self, config, queue);
+    }
+
+    fn update_delta_time(&mut self, delta_time: f32, queue: &Queue) {
+        self.update_delta_time_buffer(delta_time, queue);
+    }
+
+    fn update_clock(&mut self, clock: ClockShader, queue: &Queue) {
+        queue.write_buffer(&self.clock_buffer, 0, bytemuck::bytes_of(&clock))
+    }
+
+    fn set_colormap(&mut self, device: &Device, queue: &Queue, colormap: &[u8]) {
+        ComputeState::set_colormap(self, device, queue, colormap);
+    }
+}
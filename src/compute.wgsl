@@ -1,6 +1,8 @@
 struct Instance {
     pos: vec3<f32>,
+    rng: u32,
     color: vec3<f32>,
+    age: u32,
 }
 
 struct LorenzConfig {
@@ -9,11 +11,103 @@ struct LorenzConfig {
     beta: f32,
     step_size_factor: f32,
 }
+struct Respawn {
+    center: vec3<f32>,
+    extent: f32,
+    radius: f32,
+    min_speed: f32,
+    shape: u32,
+}
+struct Emitter {
+    position: vec3<f32>,
+    shape: u32,
+    end: vec3<f32>,
+    radius: f32,
+    rate: u32,
+    lifetime: u32,
+    enabled: u32,
+}
+struct Noise {
+    amplitude: vec3<f32>,
+    kind: u32,
+    key: u32,
+}
+struct Lorenz96 {
+    dimensions: u32,
+    forcing: f32,
+}
+struct Map {
+    parameters: vec4<f32>,
+    kind: u32,
+    enabled: u32,
+    scale: f32,
+    spacing: f32,
+    depth: f32,
+}
+struct Hyper {
+    parameters: vec4<f32>,
+    kind: u32,
+    color_w: u32,
+    w: f32,
+    w_min: f32,
+    w_max: f32,
+}
+struct Delay {
+    parameters: vec4<f32>,
+    kind: u32,
+    samples: u32,
+    length: u32,
+    lag: f32,
+    step_size: f32,
+    tau: f32,
+    center: f32,
+    scale: f32,
+}
+struct Coupling {
+    through: vec3<f32>,
+    topology: u32,
+    strength: f32,
+    range: f32,
+    enabled: u32,
+}
+// * COLORS OF THE FIRST AND SECOND PARTICLE OF EVERY PAIR
+struct Twins {
+    first: vec3<f32>,
+    enabled: u32,
+    second: vec3<f32>,
+}
 struct Config {
     lorenz: LorenzConfig,
     num_workgroups: vec3<u32>,
+    integrator: u32,
+    respawn: Respawn,
+    emitter: Emitter,
+    noise: Noise,
+    lorenz96: Lorenz96,
+    map: Map,
+    hyper: Hyper,
+    delay: Delay,
+    coupling: Coupling,
+    twins: Twins,
+}
+// * SIMULATION TIME AT DRAW STATE STEP `step`
+struct Clock {
+    time: f32,
+    step: u32,
 }
 
+struct DrawState {
+    vertex_count: u32,
+    instance_count: u32,
+    first_vertex: u32,
+    first_instance: u32,
+    head: u32,
+    step: u32,
+}
+
+// * SAME AS IN emitter.rs
+const DEAD = 0xffffffffu;
+
 @group(0) @binding(0)
 var<storage, read_write> instances: array<Instance>;
 
@@ -23,12 +117,41 @@ var<uniform> config: Config;
 @group(0) @binding(2)
 var<uniform> delta_time: f32;
 
+@group(0) @binding(3)
+var<storage, read_write> respawns: atomic<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> draw_state: DrawState;
+
+// * SAME AS IN lorenz96.rs
+const MAX_DIMENSIONS = 256u;
+
+// * PER-PARTICLE STATES, THE `dimensions` VARIABLES OF LORENZ-96, 4 OF A 4D SYSTEM OR THE
+// * HISTORY RING BUFFER OF A DELAY EQUATION. COUPLED PARTICLES KEEP THEIR POSITIONS BEFORE THE STEP
+// * HERE, FOLLOWED BY THEIR MEAN AND THE SYNCHRONIZATION ERRORS OF THE LAST STEPS
+@group(0) @binding(5)
+var<storage, read_write> states: array<f32>;
+
+// * IMAGE OF EVERY STATE VARIABLE IN xyz, THE DROPPED COORDINATE OF A 4D SYSTEM IN w
+@group(0) @binding(6)
+var<uniform> projection: array<vec4<f32>, MAX_DIMENSIONS>;
+
+@group(0) @binding(7)
+var<uniform> clock: Clock;
+
 
 @group(1) @binding(0)
 var t_gradient: texture_2d<f32>;
 @group(1) @binding(1)
 var s_gradient: sampler;
 
+// * TIME OF THE CURRENT STAGE, THE `t` OF CUSTOM SYSTEMS
+var<private> time: f32;
+
+// * VELOCITY THE COUPLED PARTICLES ADD OVER THE WHOLE STEP, 0 WITHOUT COUPLING
+var<private> coupling_vel: vec3<f32>;
+
+// * BEGIN VELOCITY, REPLACED FOR CUSTOM SYSTEMS
 fn lorenz_vel(lorenz_config: LorenzConfig, state: vec3<f32>) -> vec3<f32> {
     let x = state.x;
     let y = state.y;
@@ -39,6 +162,76 @@ fn lorenz_vel(lorenz_config: LorenzConfig, state: vec3<f32>) -> vec3<f32> {
         x * y - lorenz_config.beta * z,
     );
 }
+// * END VELOCITY
+
+// * THE VECTOR FIELD PLUS THE PULL OF THE COUPLED PARTICLES
+fn coupled_vel(lorenz_config: LorenzConfig, state: vec3<f32>) -> vec3<f32> {
+    return lorenz_vel(lorenz_config, state) + coupling_vel;
+}
+
+// * SAME ORDER AS Integrator IN integrator.rs
+// * EVERY STAGE SEES ITS OWN TIME, LIKE Integrator::step_at
+fn integrate(lorenz_config: LorenzConfig, h: f32, state: vec3<f32>, dw: vec3<f32>) -> vec3<f32> {
+    let t = time;
+    switch config.integrator {
+        // * HEUN
+        case 1u: {
+            let k1 = coupled_vel(lorenz_config, state);
+            time = t + h;
+            let k2 = coupled_vel(lorenz_config, state + h * k1);
+            return state + 0.5 * h * (k1 + k2);
+        }
+        // * RK4
+        case 2u: {
+            let k1 = coupled_vel(lorenz_config, state);
+            time = t + 0.5 * h;
+            let k2 = coupled_vel(lorenz_config, state + 0.5 * h * k1);
+            let k3 = coupled_vel(lorenz_config, state + 0.5 * h * k2);
+            time = t + h;
+            let k4 = coupled_vel(lorenz_config, state + h * k3);
+            return state + h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+        }
+        // * EULER-MARUYAMA
+        case 3u: {
+            return state + h * coupled_vel(lorenz_config, state) + diffusion(state, dw);
+        }
+        // * STOCHASTIC HEUN
+        case 4u: {
+            let k1 = coupled_vel(lorenz_config, state);
+            let g1 = diffusion(state, dw);
+            let predictor = state + h * k1 + g1;
+            time = t + h;
+            let k2 = coupled_vel(lorenz_config, predictor);
+            let g2 = diffusion(predictor, dw);
+            return state + 0.5 * h * (k1 + k2) + 0.5 * (g1 + g2);
+        }
+        // * EULER
+        default: {
+            return state + h * coupled_vel(lorenz_config, state);
+        }
+    }
+}
+
+fn is_stochastic() -> bool {
+    return config.integrator >= 3u;
+}
+
+// * SAME ORDER AS NoiseKind IN noise.rs
+fn diffusion(state: vec3<f32>, dw: vec3<f32>) -> vec3<f32> {
+    if config.noise.kind == 1u {
+        return state * dw;
+    }
+    return dw;
+}
+
+// * COUNTER BASED, THE NOISE ONLY DEPENDS ON KEY, PARTICLE AND STEP
+fn wiener(i: u32, h: f32) -> vec3<f32> {
+    var rng = pcg(pcg(draw_state.step ^ config.noise.key) ^ i);
+    let x = gaussian(&rng);
+    let y = gaussian(&rng);
+    let z = gaussian(&rng);
+    return config.noise.amplitude * sqrt(h) * vec3<f32>(x, y, z);
+}
 
 // fn lorenz_step(lorenz_config: LorenzConfig, dt: f32, state: vec3<f32>) -> vec3<f32> {
 //     return state + lorenz_config.step_size_factor * dt * lorenz_delta(lorenz_config, state);
@@ -51,12 +244,665 @@ fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let i = global_id.x * config.num_workgroups.x * config.num_workgroups.x
           + global_id.y * config.num_workgroups.y
           + global_id.z;
+    time = clock.time + f32(draw_state.step - clock.step) * config.lorenz.step_size_factor * delta_time;
 
-    let vel = lorenz_vel(config.lorenz, instances[i].pos);
-    let step = config.lorenz.step_size_factor * delta_time * vel;
-    
-    instances[i].pos += step;
-    instances[i].color = vel_to_color(vel);
+    if config.emitter.enabled != 0u {
+        let n = num_particles();
+        // * THE NEXT `rate` SLOTS OF THE RING BUFFER ARE (RE)BORN
+        if (i + n - draw_state.head) % n < config.emitter.rate {
+            var rng = instances[i].rng;
+            var pos = emitter_sample(config.emitter, &rng);
+            if config.map.enabled != 0u {
+                pos.z = 0.0;
+            }
+            instances[i].rng = rng;
+            instances[i].pos = pos;
+            instances[i].age = 0u;
+            instances[i].color = speed_color(system_vel(pos));
+            return;
+        }
+        if instances[i].age == DEAD {
+            return;
+        }
+        instances[i].age += 1u;
+        if instances[i].age >= config.emitter.lifetime {
+            instances[i].age = DEAD;
+            return;
+        }
+    }
+
+    var partner_distance = 0.0;
+    if config.coupling.enabled != 0u {
+        coupling_pull(i);
+        partner_distance = distance(snapshot(i), coupling_partner(i));
+    }
+
+    let pos = instances[i].pos;
+    let vel = system_vel(pos);
+    var next: vec3<f32>;
+    if config.map.enabled != 0u {
+        next = iterate_map(config.map, pos);
+    } else {
+        let h = config.lorenz.step_size_factor * delta_time;
+
+        var dw = vec3<f32>(0.0);
+        if is_stochastic() {
+            dw = wiener(i, h);
+        }
+        next = integrate(config.lorenz, h, pos, dw);
+    }
+
+    if is_unhealthy(config.respawn, next, vel) {
+        // * EMITTED PARTICLES ARE NOT RESPAWNED, THEY JUST DIE
+        if config.emitter.enabled != 0u {
+            instances[i].age = DEAD;
+            return;
+        }
+        var rng = instances[i].rng;
+        next = respawn_sample(config.respawn, &rng);
+        // * MAPS START OVER IN THE PLANE, AT THE BOTTOM OF A STACK, ALSO WHEN EMITTED
+        if config.map.enabled != 0u {
+            next.z = 0.0;
+        }
+        instances[i].rng = rng;
+        atomicAdd(&respawns, 1u);
+    }
+
+    instances[i].pos = next;
+    if config.coupling.enabled != 0u {
+        instances[i].color = sample_colormap(partner_distance / config.coupling.range);
+    } else if config.twins.enabled != 0u {
+        instances[i].color = select(config.twins.first, config.twins.second, i % 2u == 1u);
+    } else {
+        instances[i].color = speed_color(vel);
+    }
+}
+
+// * SAME AS IN coupling.rs
+const SYNC_HISTORY = 1024u;
+const COUPLING_WORKGROUP_SIZE = 64u;
+
+var<workgroup> coupling_partial: array<vec4<f32>, COUPLING_WORKGROUP_SIZE>;
+var<workgroup> coupling_mean: vec3<f32>;
+
+// * POSITION OF PARTICLE i BEFORE THE STEP
+fn snapshot(i: u32) -> vec3<f32> {
+    return vec3<f32>(states[3u * i], states[3u * i + 1u], states[3u * i + 2u]);
+}
+
+fn mean_snapshot() -> vec3<f32> {
+    let base = 3u * num_particles();
+    return vec3<f32>(states[base], states[base + 1u], states[base + 2u]);
+}
+
+// * INDEX OF THE CELL OF PARTICLE i MOVED BY d IN THE PERIODIC CUBE OF PARTICLES, LIKE
+// * lattice_index IN coupling.rs
+fn lattice_index(i: u32, d: vec3<u32>) -> u32 {
+    let side = config.num_workgroups.x;
+    let cell = vec3<u32>(i / (side * side), i / side % side, i % side);
+    let moved = (cell + d) % side;
+    return (moved.x * side + moved.y) * side + moved.z;
+}
+
+// * SAME ORDER AND OPERATIONS AS Topology AND Coupling::pull IN coupling.rs
+// * THE PULL IS TAKEN AT THE POSITIONS BEFORE THE STEP, SO SYNCHRONIZED PARTICLES FEEL NONE
+fn coupling_pull(i: u32) {
+    let n = num_particles();
+    var sum = vec3<f32>(0.0);
+    var degree = 0.0;
+    switch config.coupling.topology {
+        // * MEAN
+        case 1u: {
+            sum = mean_snapshot();
+            degree = 1.0;
+        }
+        // * RING
+        case 2u: {
+            sum = snapshot((i + n - 1u) % n) + snapshot((i + 1u) % n);
+            degree = 2.0;
+        }
+        // * LATTICE
+        case 3u: {
+            let back = config.num_workgroups.x - 1u;
+            sum = snapshot(lattice_index(i, vec3<u32>(back, 0u, 0u)))
+                + snapshot(lattice_index(i, vec3<u32>(1u, 0u, 0u)))
+                + snapshot(lattice_index(i, vec3<u32>(0u, back, 0u)))
+                + snapshot(lattice_index(i, vec3<u32>(0u, 1u, 0u)))
+                + snapshot(lattice_index(i, vec3<u32>(0u, 0u, back)))
+                + snapshot(lattice_index(i, vec3<u32>(0u, 0u, 1u)));
+            degree = 6.0;
+        }
+        // * DRIVE-RESPONSE, ODD PARTICLES RESPOND TO THE EVEN ONE BEFORE THEM
+        default: {
+            if i % 2u == 1u {
+                sum = snapshot(i - 1u);
+                degree = 1.0;
+            }
+        }
+    }
+    let weight = config.coupling.strength * config.coupling.through;
+    coupling_vel = weight * (sum - degree * snapshot(i));
+}
+
+// * SAME AS Coupling::partner_distance IN coupling.rs, WITH THE MEAN mean
+fn partner_of(i: u32, mean: vec3<f32>) -> vec3<f32> {
+    let n = num_particles();
+    switch config.coupling.topology {
+        // * MEAN
+        case 1u: {
+            return mean;
+        }
+        // * RING
+        case 2u: {
+            return snapshot((i + 1u) % n);
+        }
+        // * LATTICE
+        case 3u: {
+            return snapshot(lattice_index(i, vec3<u32>(0u, 0u, 1u)));
+        }
+        // * DRIVE-RESPONSE, AN ODD PARTICLE OUT HAS NO PARTNER
+        default: {
+            return snapshot(select(i, i ^ 1u, (i ^ 1u) < n));
+        }
+    }
+}
+
+fn coupling_partner(i: u32) -> vec3<f32> {
+    return partner_of(i, mean_snapshot());
+}
+
+// * RUNS BEFORE EVERY STEP OF COUPLED PARTICLES, SO THEY ALL SEE THE SAME POSITIONS
+@compute
+@workgroup_size(1)
+fn cs_coupling_snapshot(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x * config.num_workgroups.x * config.num_workgroups.x
+          + global_id.y * config.num_workgroups.y
+          + global_id.z;
+    let pos = instances[i].pos;
+    states[3u * i] = pos.x;
+    states[3u * i + 1u] = pos.y;
+    states[3u * i + 2u] = pos.z;
+}
+
+// * A SINGLE WORKGROUP AFTER THE SNAPSHOT: EVERY THREAD SUMS A STRIDED SLICE, THEN THE
+// * THREADS ARE REDUCED. FIRST THE MEAN, THEN THE MEAN DISTANCE FROM THE PARTNERS, WHICH IS
+// * RECORDED AT THE STEP IN A RING BUFFER. SAME AS Coupling::sync_error IN coupling.rs
+@compute
+@workgroup_size(64)
+fn cs_coupling_mean(@builtin(local_invocation_index) local: u32) {
+    let n = num_particles();
+    var sum = vec3<f32>(0.0);
+    for (var i = local; i < n; i += COUPLING_WORKGROUP_SIZE) {
+        sum += snapshot(i);
+    }
+    coupling_partial[local] = vec4<f32>(sum, 0.0);
+    workgroupBarrier();
+    for (var stride = COUPLING_WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        if local < stride {
+            coupling_partial[local] += coupling_partial[local + stride];
+        }
+        workgroupBarrier();
+    }
+    if local == 0u {
+        coupling_mean = coupling_partial[0].xyz / f32(n);
+    }
+    workgroupBarrier();
+
+    let mean = coupling_mean;
+    var error = 0.0;
+    for (var i = local; i < n; i += COUPLING_WORKGROUP_SIZE) {
+        error += distance(snapshot(i), partner_of(i, mean));
+    }
+    coupling_partial[local] = vec4<f32>(error);
+    workgroupBarrier();
+    for (var stride = COUPLING_WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        if local < stride {
+            coupling_partial[local] += coupling_partial[local + stride];
+        }
+        workgroupBarrier();
+    }
+    if local == 0u {
+        let base = 3u * n;
+        states[base] = mean.x;
+        states[base + 1u] = mean.y;
+        states[base + 2u] = mean.z;
+        states[base + 4u + draw_state.step % SYNC_HISTORY] = coupling_partial[0].x / f32(n);
+    }
+}
+
+// * SAME AS IN maps.rs, SO THE JUMPS OF A MAP SPAN THE COLORMAP
+const MAP_COLOR_SCALE = 5.0;
+
+// * SAME ORDER AND OPERATIONS AS MapKind AND Map::iterate IN maps.rs
+fn iterate_map(map: Map, pos: vec3<f32>) -> vec3<f32> {
+    let a = map.parameters.x;
+    let b = map.parameters.y;
+    let c = map.parameters.z;
+    let d = map.parameters.w;
+    let x = pos.x / map.scale;
+    let y = pos.y / map.scale;
+    var next: vec2<f32>;
+    switch map.kind {
+        // * LOZI
+        case 1u: {
+            next = vec2<f32>(1.0 - a * abs(x) + y, b * x);
+        }
+        // * CLIFFORD
+        case 2u: {
+            next = vec2<f32>(sin(a * y) + c * cos(a * x), sin(b * x) + d * cos(b * y));
+        }
+        // * DE JONG
+        case 3u: {
+            next = vec2<f32>(sin(a * y) - cos(b * x), sin(c * x) - cos(d * y));
+        }
+        // * IKEDA
+        case 4u: {
+            let t = 0.4 - 6.0 / (1.0 + x * x + y * y);
+            next = vec2<f32>(1.0 + a * (x * cos(t) - y * sin(t)), a * (x * sin(t) + y * cos(t)));
+        }
+        // * TINKERBELL
+        case 5u: {
+            next = vec2<f32>(x * x - y * y + a * x + b * y, 2.0 * x * y + c * x + d * y);
+        }
+        // * HENON
+        default: {
+            next = vec2<f32>(1.0 - a * x * x + y, b * x);
+        }
+    }
+    return vec3<f32>(next * map.scale, clamp(pos.z + map.spacing, 0.0, map.depth));
+}
+
+// * WHAT A PARTICLE AT pos IS CHECKED AND COLORED BY: ITS VELOCITY OR, FOR MAPS, THE JUMP IN
+// * THE PLANE LIKE Map::jump IN maps.rs
+fn system_vel(pos: vec3<f32>) -> vec3<f32> {
+    if config.map.enabled != 0u {
+        return vec3<f32>(iterate_map(config.map, pos).xy - pos.xy, 0.0);
+    }
+    return lorenz_vel(config.lorenz, pos);
+}
+
+// * THE JUMPS OF MAPS ARE SCALED UP TO SPAN THE COLORMAP LIKE THE SPEEDS OF THE FLOWS
+fn speed_color(vel: vec3<f32>) -> vec3<f32> {
+    if config.map.enabled != 0u {
+        return vel_to_color(vel * MAP_COLOR_SCALE);
+    }
+    return vel_to_color(vel);
+}
+
+// * LORENZ-96 VELOCITY OF VARIABLE j, INDICES ARE CYCLIC
+fn l96_vel(x: ptr<function, array<f32, MAX_DIMENSIONS>>, j: u32) -> f32 {
+    let n = config.lorenz96.dimensions;
+    return ((*x)[(j + 1u) % n] - (*x)[(j + n - 2u) % n]) * (*x)[(j + n - 1u) % n]
+        - (*x)[j] + config.lorenz96.forcing;
+}
+
+// * SAME OPERATIONS AS Lorenz96::step IN lorenz96.rs, STOCHASTIC INTEGRATORS ARE REJECTED.
+// * NOTHING IS RESPAWNED, THE SCENE REJECTS RESPAWN SETTINGS
+@compute
+@workgroup_size(1)
+fn cs_lorenz96(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x * config.num_workgroups.x * config.num_workgroups.x
+          + global_id.y * config.num_workgroups.y
+          + global_id.z;
+    let n = config.lorenz96.dimensions;
+    let base = i * n;
+    let h = config.lorenz.step_size_factor * delta_time;
+
+    var x: array<f32, MAX_DIMENSIONS>;
+    var stage: array<f32, MAX_DIMENSIONS>;
+    var k: array<f32, MAX_DIMENSIONS>;
+    var acc: array<f32, MAX_DIMENSIONS>;
+    for (var j = 0u; j < n; j++) {
+        x[j] = states[base + j];
+    }
+
+    var speed_sq = 0.0;
+    for (var j = 0u; j < n; j++) {
+        let v = l96_vel(&x, j);
+        speed_sq += v * v;
+    }
+
+    switch config.integrator {
+        // * HEUN
+        case 1u: {
+            for (var j = 0u; j < n; j++) {
+                stage[j] = x[j] + l96_vel(&x, j) * h;
+            }
+            for (var j = 0u; j < n; j++) {
+                k[j] = l96_vel(&x, j) + l96_vel(&stage, j);
+            }
+            for (var j = 0u; j < n; j++) {
+                x[j] += k[j] * (0.5 * h);
+            }
+        }
+        // * RK4
+        case 2u: {
+            for (var j = 0u; j < n; j++) {
+                acc[j] = l96_vel(&x, j);
+                stage[j] = x[j] + acc[j] * (0.5 * h);
+            }
+            for (var s = 0u; s < 2u; s++) {
+                let scale = select(h, 0.5 * h, s == 0u);
+                for (var j = 0u; j < n; j++) {
+                    k[j] = l96_vel(&stage, j);
+                }
+                for (var j = 0u; j < n; j++) {
+                    acc[j] = acc[j] + k[j] * 2.0;
+                    stage[j] = x[j] + k[j] * scale;
+                }
+            }
+            for (var j = 0u; j < n; j++) {
+                k[j] = acc[j] + l96_vel(&stage, j);
+            }
+            for (var j = 0u; j < n; j++) {
+                x[j] += k[j] * (h / 6.0);
+            }
+        }
+        // * EULER
+        default: {
+            for (var j = 0u; j < n; j++) {
+                k[j] = l96_vel(&x, j);
+            }
+            for (var j = 0u; j < n; j++) {
+                x[j] += k[j] * h;
+            }
+        }
+    }
+
+    var pos = vec3<f32>(0.0);
+    for (var j = 0u; j < n; j++) {
+        states[base + j] = x[j];
+        pos += projection[j].xyz * x[j];
+    }
+    instances[i].pos = pos;
+    instances[i].color = vel_to_color(vec3<f32>(sqrt(speed_sq), 0.0, 0.0));
+}
+
+// * SAME ORDER AND OPERATIONS AS HyperKind AND Hyper::velocity IN hyper.rs
+fn hyper_vel(s: vec4<f32>) -> vec4<f32> {
+    let a = config.hyper.parameters.x;
+    let b = config.hyper.parameters.y;
+    let c = config.hyper.parameters.z;
+    let d = config.hyper.parameters.w;
+    switch config.hyper.kind {
+        // * LORENZ-STENFLO
+        case 1u: {
+            return vec4<f32>(
+                a * (s.y - s.x) + d * s.w,
+                s.x * (c - s.z) - s.y,
+                s.x * s.y - b * s.z,
+                -s.x - a * s.w,
+            );
+        }
+        // * ROSSLER
+        default: {
+            return vec4<f32>(-s.y - s.z, s.x + a * s.y + s.w, b + s.x * s.z, -c * s.z + d * s.w);
+        }
+    }
+}
+
+// * SAME AS Hyper::step, STOCHASTIC INTEGRATORS ARE REJECTED
+fn integrate_hyper(h: f32, s: vec4<f32>) -> vec4<f32> {
+    switch config.integrator {
+        // * HEUN
+        case 1u: {
+            let k1 = hyper_vel(s);
+            let k2 = hyper_vel(s + h * k1);
+            return s + 0.5 * h * (k1 + k2);
+        }
+        // * RK4
+        case 2u: {
+            let k1 = hyper_vel(s);
+            let k2 = hyper_vel(s + 0.5 * h * k1);
+            let k3 = hyper_vel(s + 0.5 * h * k2);
+            let k4 = hyper_vel(s + h * k3);
+            return s + h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+        }
+        // * EULER
+        default: {
+            return s + h * hyper_vel(s);
+        }
+    }
+}
+
+// * DRAWS STATE s AT ITS ROTATED PROJECTION, COLORED BY ITS SPEED OR THE DROPPED COORDINATE
+fn show_hyper(i: u32, s: vec4<f32>) {
+    let image = projection[0] * s.x + projection[1] * s.y + projection[2] * s.z
+        + projection[3] * s.w;
+    instances[i].pos = image.xyz;
+    if config.hyper.color_w != 0u {
+        let range = config.hyper.w_max - config.hyper.w_min;
+        instances[i].color = sample_colormap((image.w - config.hyper.w_min) / range);
+    } else {
+        instances[i].color = vel_to_color(vec3<f32>(length(hyper_vel(s)), 0.0, 0.0));
+    }
+}
+
+fn read_hyper(i: u32) -> vec4<f32> {
+    let base = i * 4u;
+    return vec4<f32>(states[base], states[base + 1u], states[base + 2u], states[base + 3u]);
+}
+
+// * SAME OPERATIONS AS CpuState::step_hyper IN cpu.rs
+@compute
+@workgroup_size(1)
+fn cs_hyper(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x * config.num_workgroups.x * config.num_workgroups.x
+          + global_id.y * config.num_workgroups.y
+          + global_id.z;
+    let s = read_hyper(i);
+    let vel = hyper_vel(s);
+    var next = integrate_hyper(config.lorenz.step_size_factor * delta_time, s);
+
+    let r = config.respawn.radius;
+    let min_speed = config.respawn.min_speed;
+    if !(dot(next, next) <= r * r) || dot(vel, vel) < min_speed * min_speed {
+        var rng = instances[i].rng;
+        next = vec4<f32>(respawn_sample(config.respawn, &rng), config.hyper.w);
+        instances[i].rng = rng;
+        atomicAdd(&respawns, 1u);
+    }
+
+    let base = i * 4u;
+    states[base] = next.x;
+    states[base + 1u] = next.y;
+    states[base + 2u] = next.z;
+    states[base + 3u] = next.w;
+    show_hyper(i, next);
+}
+
+// * AFTER A NEW ROTATION, MOVES EVERY PARTICLE WITHOUT STEPPING IT
+@compute
+@workgroup_size(1)
+fn cs_hyper_project(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x * config.num_workgroups.x * config.num_workgroups.x
+          + global_id.y * config.num_workgroups.y
+          + global_id.z;
+    show_hyper(i, read_hyper(i));
+}
+
+// * SAME ORDER AND OPERATIONS AS DelayKind AND Delay::velocity IN delay.rs
+fn delay_vel(x: f32, x_tau: f32) -> f32 {
+    let a = config.delay.parameters.x;
+    let b = config.delay.parameters.y;
+    let c = config.delay.parameters.z;
+    switch config.delay.kind {
+        // * IKEDA
+        case 1u: {
+            return a * sin(x_tau - b) - x;
+        }
+        // * MACKEY-GLASS
+        default: {
+            return a * x_tau / (1.0 + pow(abs(x_tau), c)) - b * x;
+        }
+    }
+}
+
+// * SAME AS history_at IN delay.rs, THE CURRENT VALUE IS AT step MODULO THE LENGTH
+fn history_at(base: u32, step: u32, lag: f32) -> f32 {
+    let n = config.delay.length;
+    let head = step % n;
+    let i = u32(floor(lag));
+    let a = states[base + (head + n - i % n) % n];
+    let b = states[base + (head + n - (i + 1u) % n) % n];
+    return a + (lag - f32(i)) * (b - a);
+}
+
+fn embed(base: u32, step: u32) -> vec3<f32> {
+    let lag = config.delay.lag;
+    let x = vec3<f32>(history_at(base, step, 0.0), history_at(base, step, lag), history_at(base, step, 2.0 * lag));
+    return (x - config.delay.center) * config.delay.scale;
+}
+
+// * SAME OPERATIONS AS Delay::step IN delay.rs, STOCHASTIC INTEGRATORS ARE REJECTED. THE
+// * DELAYED VALUES OF THE STAGES ARE INTERPOLATED IN THE HISTORY
+@compute
+@workgroup_size(1)
+fn cs_delay(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x * config.num_workgroups.x * config.num_workgroups.x
+          + global_id.y * config.num_workgroups.y
+          + global_id.z;
+    let n = config.delay.length;
+    let base = i * n;
+    let step = draw_state.step;
+    let h = config.delay.step_size;
+    let m = f32(config.delay.samples);
+
+    let x = history_at(base, step, 0.0);
+    var next: f32;
+    switch config.integrator {
+        // * HEUN
+        case 1u: {
+            let k1 = delay_vel(x, history_at(base, step, m));
+            let k2 = delay_vel(x + h * k1, history_at(base, step, m - 1.0));
+            next = x + 0.5 * h * (k1 + k2);
+        }
+        // * RK4
+        case 2u: {
+            let half = history_at(base, step, m - 0.5);
+            let k1 = delay_vel(x, history_at(base, step, m));
+            let k2 = delay_vel(x + 0.5 * h * k1, half);
+            let k3 = delay_vel(x + 0.5 * h * k2, half);
+            let k4 = delay_vel(x + h * k3, history_at(base, step, m - 1.0));
+            next = x + h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+        }
+        // * EULER
+        default: {
+            next = x + h * delay_vel(x, history_at(base, step, m));
+        }
+    }
+    states[base + (step + 1u) % n] = next;
+
+    // * COLORED BY THE DRAWN DISTANCE PER DELAY, LIKE Delay::speed
+    let x_dot = delay_vel(next, history_at(base, step + 1u, m));
+    instances[i].pos = embed(base, step + 1u);
+    instances[i].color = vel_to_color(vec3<f32>(x_dot * config.delay.scale * config.delay.tau, 0.0, 0.0));
+}
+
+fn num_particles() -> u32 {
+    return config.num_workgroups.x * config.num_workgroups.y * config.num_workgroups.z;
+}
+
+// * RUNS ONCE AFTER EVERY STEP, COUNTING STEPS AND MOVING THE RING BUFFER ON
+@compute
+@workgroup_size(1)
+fn cs_advance() {
+    draw_state.step += 1u;
+    if config.emitter.enabled != 0u {
+        let n = num_particles();
+        draw_state.head = (draw_state.head + config.emitter.rate) % n;
+        draw_state.instance_count = min(draw_state.instance_count + config.emitter.rate, n);
+    }
+}
+
+// * SAME ORDER AS EmitterShape IN emitter.rs
+fn emitter_sample(emitter: Emitter, rng: ptr<function, u32>) -> vec3<f32> {
+    switch emitter.shape {
+        // * SPHERE
+        case 1u: {
+            return emitter.position + emitter.radius * unit_ball(rng);
+        }
+        // * LINE
+        case 2u: {
+            return emitter.position + (emitter.end - emitter.position) * uniform(rng);
+        }
+        // * POINT
+        default: {
+            return emitter.position;
+        }
+    }
+}
+
+// * SAME AS IN respawn.rs
+const SPHERE_TRIES = 8u;
+const TAU = 6.2831855;
+
+// * NAN FAILS EVERY COMPARISON, SO NON-FINITE PARTICLES ARE CAUGHT BY THE RADIUS CHECK
+fn is_unhealthy(respawn: Respawn, pos: vec3<f32>, vel: vec3<f32>) -> bool {
+    return !(dot(pos, pos) <= respawn.radius * respawn.radius)
+        || dot(vel, vel) < respawn.min_speed * respawn.min_speed;
+}
+
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn uniform(rng: ptr<function, u32>) -> f32 {
+    *rng = pcg(*rng);
+    return f32(*rng >> 8u) * (1.0 / 16777216.0);
+}
+
+fn uniform_vec3(rng: ptr<function, u32>) -> vec3<f32> {
+    let x = uniform(rng);
+    let y = uniform(rng);
+    let z = uniform(rng);
+    return vec3<f32>(x, y, z);
+}
+
+fn unit_ball(rng: ptr<function, u32>) -> vec3<f32> {
+    var p = vec3<f32>(0.0);
+    for (var i = 0u; i < SPHERE_TRIES; i++) {
+        p = uniform_vec3(rng) * 2.0 - 1.0;
+        if dot(p, p) <= 1.0 {
+            break;
+        }
+    }
+    if dot(p, p) > 1.0 {
+        p *= 1.0 / length(p);
+    }
+    return p;
+}
+
+// * BOX-MULLER
+fn gaussian(rng: ptr<function, u32>) -> f32 {
+    let u1 = 1.0 - uniform(rng);
+    let u2 = uniform(rng);
+    return sqrt(-2.0 * log(u1)) * cos(TAU * u2);
+}
+
+// * SAME ORDER AS DistributionShape IN lorenz.rs
+fn respawn_sample(respawn: Respawn, rng: ptr<function, u32>) -> vec3<f32> {
+    var offset: vec3<f32>;
+    switch respawn.shape {
+        // * SPHERE
+        case 1u: {
+            offset = unit_ball(rng);
+        }
+        // * GAUSSIAN
+        case 2u: {
+            let x = gaussian(rng);
+            let y = gaussian(rng);
+            let z = gaussian(rng);
+            offset = vec3<f32>(x, y, z);
+        }
+        // * CUBE
+        default: {
+            offset = uniform_vec3(rng) * 2.0 - 1.0;
+        }
+    }
+    return respawn.center + respawn.extent * offset;
 }
 
 const VEL_SCALE = 0.0025;
@@ -68,6 +914,10 @@ fn vel_to_color(vel: vec3<f32>) -> vec3<f32> {
     let mag = length(vel);
     let value = VEL_SCALE * mag;
     // return mix(SLOW_COLOR, FAST_COLOR, value);
+    return sample_colormap(value);
+}
+
+fn sample_colormap(value: f32) -> vec3<f32> {
     return textureSampleLevel(t_gradient, s_gradient, vec2<f32>(value, 0.0), 0.0).rgb;
 }
 
@@ -1,44 +1,423 @@
+use std::borrow::Cow;
+
+use glam::{Vec3, Vec4};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
     Buffer, BufferUsages, Device,
 };
+use winit::dpi::PhysicalSize;
 
-use crate::lorenz::LorenzConfig;
+use crate::{
+    backend::Backend,
+    camera::CameraSettings,
+    coupling::Coupling,
+    delay::Delay,
+    embedding::Embedding,
+    emitter::Emitter,
+    enkf::Assimilation,
+    equations::Equations,
+    hyper::{Hyper, HyperColor, HYPER_DIMENSIONS},
+    integrator::Integrator,
+    lorenz::{Distribution, LorenzConfig},
+    lorenz96::{project, Lorenz96},
+    maps::Map,
+    noise::{noise_key, Noise},
+    respawn::Respawn,
+    scene::System,
+    strobe::Strobe,
+    twins::Twins,
+};
 
+/// Default time step of the first frame.
 pub const DEFAULT_DELTA_TIME: f32 = 0.01;
 
-const NUMBER_LORENZ_POINTS: usize = 1000000;
+/// Default number of particles, before rounding down to a cube.
+pub const NUMBER_LORENZ_POINTS: usize = 1000000;
 
-const SMOOTH_SHADING: bool = false;
+/// Default render mode, `false` is shaded.
+pub const SMOOTH_SHADING: bool = false;
 
+/// Default brightness each particle adds when drawing densities.
+pub const EXPOSURE: f32 = 0.05;
+
+/// Validated runtime configuration, usually built with [`Scene::into_config`](crate::Scene::into_config).
 pub struct Config {
+    pub system: System,
+    pub backend: Backend,
+    pub integrator: Integrator,
     pub lorenz: LorenzConfig,
     pub num_lorenz_points: usize,
     pub num_workgroups: (u32, u32, u32),
     pub smooth_shading: bool,
+    /// Particles add up with this brightness each instead of being drawn as opaque discs.
+    pub density: Option<f32>,
+    pub distribution: Distribution,
+    /// Seed of all random generation, [`Simulation`](crate::Simulation) picks one if `None`.
+    pub seed: Option<u64>,
+    pub delta_time: f32,
+    pub respawn: Respawn,
+    /// Particles are emitted continuously instead of all being there from the start.
+    pub emitter: Option<Emitter>,
+    /// Noise of the stochastic integrators.
+    pub noise: Noise,
+    /// Data assimilation, run by [`Filter`](crate::enkf::Filter).
+    pub assimilation: Option<Assimilation>,
+    /// Delay embedding view.
+    pub embedding: Option<Embedding>,
+    /// The vector field with [`System::Custom`], `None` otherwise.
+    pub custom: Option<Equations>,
+    /// The model with [`System::Lorenz96`].
+    pub lorenz96: Lorenz96,
+    /// The map with [`System::Map`].
+    pub map: Map,
+    /// The 4D system with [`System::Hyper`].
+    pub hyper: Hyper,
+    /// The delay differential equation with [`System::Delay`].
+    pub delay: Delay,
+    /// Steps whole forcing periods at a time, see [`Simulation::step_periods`](crate::Simulation::step_periods).
+    pub strobe: Option<Strobe>,
+    /// Couples the particles of the Lorenz or a custom system to each other.
+    pub coupling: Option<Coupling>,
+    /// Creates the particles of the Lorenz or a custom system in pairs.
+    pub twins: Option<Twins>,
+    pub camera: CameraSettings,
+    pub colormap: Cow<'static, [u8]>,
+    pub window_size: PhysicalSize<u32>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        let temp_num_workgroups = (NUMBER_LORENZ_POINTS as f64).powf(1. / 3.) as u32;
-        Self {
-            lorenz: LorenzConfig::default(),
-            num_lorenz_points: (temp_num_workgroups as usize).pow(3),
-            num_workgroups: (
-                temp_num_workgroups,
-                temp_num_workgroups,
-                temp_num_workgroups,
-            ),
-            smooth_shading: SMOOTH_SHADING,
+impl Config {
+    /// Velocity of the Lorenz system, a custom system or the jump of a map at `p` and time `t`.
+    /// Systems with states are moved by [`Config::drawn_velocity`] instead.
+    pub fn velocity(&self, p: Vec3, t: f32) -> Vec3 {
+        match (&self.custom, self.system) {
+            (Some(equations), _) => equations.velocity(p, t),
+            (None, System::Map) => self.map.jump(p),
+            (None, _) => self.lorenz.delta(p),
+        }
+    }
+
+    /// Velocity of the drawn position of a particle at `p` and time `t`, whose state variables
+    /// are `state` at the draw state step `step`, see [`Config::state_dimensions`]. What the
+    /// chase camera looks along.
+    pub fn drawn_velocity(&self, p: Vec3, state: &[f32], step: u32, t: f32) -> Vec3 {
+        match self.system {
+            // * THE PROJECTION IS LINEAR, SO IT MAPS VELOCITIES LIKE POSITIONS
+            System::Hyper => {
+                let (velocity, _) = self
+                    .hyper
+                    .project(self.hyper.velocity(Vec4::from_slice(state)));
+                velocity
+            }
+            System::Lorenz96 => {
+                let velocity: Vec<f32> = (0..state.len())
+                    .map(|i| self.lorenz96.velocity(state, i))
+                    .collect();
+                project(
+                    &self.lorenz96.projection(self.seed.unwrap_or_default()),
+                    &velocity,
+                )
+            }
+            System::Delay => self.delay.embedding_velocity(state, step),
+            System::Lorenz | System::Custom | System::Map => self.velocity(p, t),
+        }
+    }
+
+    /// Variables per particle of systems that keep their state apart from the drawn
+    /// position, 0 for the others.
+    pub fn state_dimensions(&self) -> usize {
+        match self.system {
+            System::Lorenz96 => self.lorenz96.dimensions,
+            System::Hyper => HYPER_DIMENSIONS,
+            System::Delay => self.delay.history_len(),
+            System::Lorenz | System::Custom | System::Map => 0,
+        }
+    }
+
+    /// Size of one integration step. Delay equations always step a fixed fraction of their
+    /// delay.
+    pub fn step_size(&self) -> f32 {
+        match self.system {
+            System::Delay => self.delay.step_size(),
+            _ => self.lorenz.step_size_factor * self.delta_time,
         }
     }
+
+    /// Image of every state variable's unit vector, the drawn position in `xyz` and for 4D
+    /// systems the dropped coordinate in `w`. Empty for systems without states and for delay
+    /// equations, which are drawn at their embedding.
+    pub fn projection(&self) -> Vec<Vec4> {
+        match self.system {
+            System::Lorenz96 => self
+                .lorenz96
+                .projection(self.seed.unwrap_or_default())
+                .into_iter()
+                .map(|p| p.extend(0.))
+                .collect(),
+            System::Hyper => self.hyper.projection().to_vec(),
+            System::Lorenz | System::Custom | System::Map | System::Delay => Vec::new(),
+        }
+    }
+
+    /// Advances a single point at time `t` by one step of [`Config::delta_time`], without
+    /// noise. Maps are iterated once.
+    pub fn step_point(&self, p: Vec3, t: f32) -> Vec3 {
+        if self.system == System::Map {
+            return self.map.iterate(p);
+        }
+        let h = self.lorenz.step_size_factor * self.delta_time;
+        self.integrator.step_at(h, t, p, |t, p| self.velocity(p, t))
+    }
 }
+
+/// Rounds the requested particle count down to a cube of workgroups.
+pub fn workgroups_for(num_lorenz_points: usize) -> (usize, (u32, u32, u32)) {
+    let temp_num_workgroups = ((num_lorenz_points as f64).powf(1. / 3.) + 1e-9) as u32;
+    (
+        (temp_num_workgroups as usize).pow(3),
+        (
+            temp_num_workgroups,
+            temp_num_workgroups,
+            temp_num_workgroups,
+        ),
+    )
+}
+/// `Config` uniform of `compute.wgsl`.
 #[repr(C)]
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
 pub struct ConfigComputeShader {
-    lorenz: LorenzConfig,
-    num_workgroups: [u32; 3],
-    _pad1: f32,
+    pub(crate) lorenz: LorenzConfig,
+    pub(crate) num_workgroups: [u32; 3],
+    pub(crate) integrator: u32,
+    pub(crate) respawn: RespawnShader,
+    pub(crate) emitter: EmitterShader,
+    pub(crate) noise: NoiseShader,
+    pub(crate) lorenz96: Lorenz96Shader,
+    _pad: [u32; 2],
+    pub(crate) map: MapShader,
+    pub(crate) hyper: HyperShader,
+    pub(crate) delay: DelayShader,
+    pub(crate) coupling: CouplingShader,
+    pub(crate) twins: TwinsShader,
+}
+
+/// `Respawn` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct RespawnShader {
+    pub(crate) center: [f32; 3],
+    pub(crate) extent: f32,
+    pub(crate) radius: f32,
+    pub(crate) min_speed: f32,
+    pub(crate) shape: u32,
+    _pad: u32,
+}
+/// `Emitter` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct EmitterShader {
+    pub(crate) position: [f32; 3],
+    pub(crate) shape: u32,
+    pub(crate) end: [f32; 3],
+    pub(crate) radius: f32,
+    pub(crate) rate: u32,
+    pub(crate) lifetime: u32,
+    pub(crate) enabled: u32,
+    _pad: u32,
+}
+impl From<Option<&Emitter>> for EmitterShader {
+    fn from(emitter: Option<&Emitter>) -> Self {
+        let e = emitter.copied().unwrap_or_default();
+        Self {
+            position: e.position,
+            shape: e.shape as u32,
+            end: e.end,
+            radius: e.radius,
+            rate: e.rate,
+            lifetime: e.lifetime,
+            enabled: emitter.is_some() as u32,
+            _pad: 0,
+        }
+    }
+}
+
+/// `Noise` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct NoiseShader {
+    pub(crate) amplitude: [f32; 3],
+    pub(crate) kind: u32,
+    pub(crate) key: u32,
+    _pad: [u32; 3],
+}
+impl NoiseShader {
+    fn new(noise: &Noise, seed: u64) -> Self {
+        Self {
+            amplitude: noise.amplitude,
+            kind: noise.kind as u32,
+            key: noise_key(seed),
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// `Lorenz96` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct Lorenz96Shader {
+    pub(crate) dimensions: u32,
+    pub(crate) forcing: f32,
+}
+impl From<&Lorenz96> for Lorenz96Shader {
+    fn from(lorenz96: &Lorenz96) -> Self {
+        Self {
+            dimensions: lorenz96.dimensions as u32,
+            forcing: lorenz96.forcing,
+        }
+    }
+}
+
+/// `Map` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct MapShader {
+    pub(crate) parameters: [f32; 4],
+    pub(crate) kind: u32,
+    pub(crate) enabled: u32,
+    pub(crate) scale: f32,
+    pub(crate) spacing: f32,
+    pub(crate) depth: f32,
+    _pad: [u32; 3],
+}
+impl MapShader {
+    fn new(map: &Map, enabled: bool) -> Self {
+        let (spacing, depth) = map.stacking();
+        Self {
+            parameters: map.parameters(),
+            kind: map.kind as u32,
+            enabled: enabled as u32,
+            scale: map.scale,
+            spacing,
+            depth,
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// `Hyper` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct HyperShader {
+    pub(crate) parameters: [f32; 4],
+    pub(crate) kind: u32,
+    pub(crate) color_w: u32,
+    pub(crate) w: f32,
+    pub(crate) w_min: f32,
+    pub(crate) w_max: f32,
+    _pad: [u32; 3],
+}
+impl From<&Hyper> for HyperShader {
+    fn from(hyper: &Hyper) -> Self {
+        let [w_min, w_max] = hyper.w_range();
+        Self {
+            parameters: hyper.parameters(),
+            kind: hyper.kind as u32,
+            color_w: (hyper.color == HyperColor::W) as u32,
+            w: hyper.w,
+            w_min,
+            w_max,
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// `Delay` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct DelayShader {
+    pub(crate) parameters: [f32; 4],
+    pub(crate) kind: u32,
+    pub(crate) samples: u32,
+    pub(crate) length: u32,
+    pub(crate) lag: f32,
+    pub(crate) step_size: f32,
+    pub(crate) tau: f32,
+    pub(crate) center: f32,
+    pub(crate) scale: f32,
+}
+impl From<&Delay> for DelayShader {
+    fn from(delay: &Delay) -> Self {
+        let [a, b, c] = delay.parameters();
+        Self {
+            parameters: [a, b, c, 0.],
+            kind: delay.kind as u32,
+            samples: delay.samples,
+            length: delay.history_len() as u32,
+            lag: delay.embedding_lag(),
+            step_size: delay.step_size(),
+            tau: delay.tau(),
+            center: delay.center(),
+            scale: delay.scale(),
+        }
+    }
+}
+
+/// `Coupling` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct CouplingShader {
+    pub(crate) through: [f32; 3],
+    pub(crate) topology: u32,
+    pub(crate) strength: f32,
+    pub(crate) range: f32,
+    pub(crate) enabled: u32,
+    _pad: u32,
+}
+impl From<Option<&Coupling>> for CouplingShader {
+    fn from(coupling: Option<&Coupling>) -> Self {
+        let c = coupling.copied().unwrap_or_default();
+        Self {
+            through: c.through,
+            topology: c.topology as u32,
+            strength: c.strength,
+            range: c.range,
+            enabled: coupling.is_some() as u32,
+            _pad: 0,
+        }
+    }
+}
+
+/// `Twins` uniform of `compute.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct TwinsShader {
+    pub(crate) first: [f32; 3],
+    pub(crate) enabled: u32,
+    pub(crate) second: [f32; 3],
+    _pad: u32,
+}
+impl From<Option<&Twins>> for TwinsShader {
+    fn from(twins: Option<&Twins>) -> Self {
+        let [first, second] = twins.copied().unwrap_or_default().colors;
+        Self {
+            first,
+            enabled: twins.is_some() as u32,
+            second,
+            _pad: 0,
+        }
+    }
+}
+
+impl From<&Respawn> for RespawnShader {
+    fn from(respawn: &Respawn) -> Self {
+        Self {
+            center: respawn.distribution.center,
+            extent: respawn.distribution.extent,
+            radius: respawn.radius,
+            min_speed: respawn.min_speed,
+            shape: respawn.distribution.shape as u32,
+            _pad: 0,
+        }
+    }
 }
 impl From<&Config> for ConfigComputeShader {
     fn from(cfg: &Config) -> Self {
@@ -49,7 +428,17 @@ impl From<&Config> for ConfigComputeShader {
                 cfg.num_workgroups.1,
                 cfg.num_workgroups.2,
             ],
-            _pad1: f32::NAN,
+            integrator: cfg.integrator.shader_index(),
+            respawn: RespawnShader::from(&cfg.respawn),
+            emitter: EmitterShader::from(cfg.emitter.as_ref()),
+            noise: NoiseShader::new(&cfg.noise, cfg.seed.unwrap_or_default()),
+            lorenz96: Lorenz96Shader::from(&cfg.lorenz96),
+            _pad: [0; 2],
+            map: MapShader::new(&cfg.map, cfg.system == System::Map),
+            hyper: HyperShader::from(&cfg.hyper),
+            delay: DelayShader::from(&cfg.delay),
+            coupling: CouplingShader::from(cfg.coupling.as_ref()),
+            twins: TwinsShader::from(cfg.twins.as_ref()),
         }
     }
 }
@@ -58,29 +447,49 @@ impl ConfigComputeShader {
         device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Config Buffer"),
             contents: bytemuck::bytes_of(self),
-            usage: BufferUsages::UNIFORM,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         })
     }
 }
 
+/// `Config` uniform of `draw.wgsl`.
 #[repr(C)]
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
 pub struct ConfigDrawShader {
-    smooth_shading: u32,
+    pub(crate) smooth_shading: u32,
+    pub(crate) exposure: f32,
+    /// Height over width of the target, so the discs stay round.
+    pub(crate) aspect_ratio: f32,
 }
-impl From<&Config> for ConfigDrawShader {
-    fn from(cfg: &Config) -> Self {
+impl ConfigDrawShader {
+    /// The uniform of `cfg` for a target of `width` by `height` pixels.
+    pub fn new(cfg: &Config, (width, height): (u32, u32)) -> Self {
         Self {
             smooth_shading: cfg.smooth_shading as u32,
+            exposure: cfg.density.unwrap_or(0.),
+            aspect_ratio: height as f32 / width as f32,
         }
     }
-}
-impl ConfigDrawShader {
     pub fn as_buffer(&self, device: &Device) -> Buffer {
         device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Config Buffer"),
             contents: bytemuck::bytes_of(self),
-            usage: BufferUsages::UNIFORM,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         })
     }
 }
+
+/// `Clock` uniform of `compute.wgsl`, the simulation time at the draw state step `step`.
+/// Later steps add their own step sizes, so the time stays right when `delta_time` changes.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClockShader {
+    pub(crate) time: f32,
+    pub(crate) step: u32,
+}
+impl ClockShader {
+    /// Time of the draw state step `step`, for steps of size `h` since the clock was set.
+    pub(crate) fn time(&self, step: u32, h: f32) -> f32 {
+        self.time + step.wrapping_sub(self.step) as f32 * h
+    }
+}
@@ -0,0 +1,142 @@
+use glam::Vec3;
+use serde::Deserialize;
+
+/// Steps whose synchronization error is kept, see
+/// [`Simulation::sync_errors`](crate::Simulation::sync_errors).
+pub const SYNC_HISTORY: usize = 1024;
+
+const STRENGTH: f32 = 10.;
+const RANGE: f32 = 20.;
+
+/// Offset of the synchronization errors in the state buffer of `n` coupled particles, after
+/// their positions before the step and their mean.
+pub(crate) fn sync_offset(n: usize) -> usize {
+    3 * n + 4
+}
+
+/// Which particles pull on each other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Topology {
+    /// Pecora-Carroll pairs: every odd particle is a response pulled towards the even one
+    /// before it, which runs free as its drive.
+    #[default]
+    DriveResponse,
+    /// Every particle is pulled towards the mean of all of them.
+    Mean,
+    /// Every particle is pulled towards the one before and the one after it, in a ring.
+    Ring,
+    /// Every particle is pulled towards its six neighbours in the periodic cube of particles.
+    Lattice,
+}
+
+/// Diffusive coupling of the particles, as read from the `[coupling]` table of a scene.
+/// Particle `i` feels `strength * through * Σ (x_j - x_i)` over the particles `j` it is
+/// coupled to, all at their positions before the step and held for the whole step, so
+/// synchronized particles feel no pull. Particles are colored by the distance from their
+/// partner.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Coupling {
+    pub topology: Topology,
+    /// Coupling strength ε.
+    pub strength: f32,
+    /// Weight of the coupling on x, y and z, `[1, 0, 0]` couples through x alone.
+    pub through: [f32; 3],
+    /// Distance from the partner at the top of the colormap.
+    pub range: f32,
+}
+
+impl Default for Coupling {
+    fn default() -> Self {
+        Self {
+            topology: Topology::default(),
+            strength: STRENGTH,
+            through: [1., 0., 0.],
+            range: RANGE,
+        }
+    }
+}
+
+impl Coupling {
+    /// `strength * through`, what the differences to the coupled particles are scaled by.
+    pub fn weight(&self) -> Vec3 {
+        self.strength * Vec3::from_array(self.through)
+    }
+
+    /// Sum of the positions particle `i` is coupled to in `snapshot` and their number. `side`
+    /// is the edge of the cube of particles, `mean` the mean of `snapshot`. Mirrors
+    /// `coupling_pull` in `compute.wgsl`.
+    pub fn pull(&self, i: usize, snapshot: &[Vec3], mean: Vec3, side: usize) -> (Vec3, f32) {
+        let n = snapshot.len();
+        match self.topology {
+            Topology::DriveResponse if i % 2 == 1 => (snapshot[i - 1], 1.),
+            Topology::DriveResponse => (Vec3::ZERO, 0.),
+            Topology::Mean => (mean, 1.),
+            Topology::Ring => (snapshot[(i + n - 1) % n] + snapshot[(i + 1) % n], 2.),
+            Topology::Lattice => {
+                let sum = lattice_neighbours(i, side).map(|j| snapshot[j]).sum();
+                (sum, 6.)
+            }
+        }
+    }
+
+    /// Velocity the coupling adds to a particle at `p` before the step, for its
+    /// [`Coupling::pull`] `(sum, degree)`.
+    pub fn velocity(&self, p: Vec3, sum: Vec3, degree: f32) -> Vec3 {
+        self.weight() * (sum - degree * p)
+    }
+
+    /// Distance of particle `i` from its partner in `snapshot`: its drive or response, the
+    /// next particle of the ring or lattice row, or the mean.
+    pub fn partner_distance(&self, i: usize, snapshot: &[Vec3], mean: Vec3, side: usize) -> f32 {
+        let n = snapshot.len();
+        let partner = match self.topology {
+            // * AN ODD PARTICLE OUT HAS NO PARTNER
+            Topology::DriveResponse => snapshot[if i ^ 1 < n { i ^ 1 } else { i }],
+            Topology::Mean => mean,
+            Topology::Ring => snapshot[(i + 1) % n],
+            Topology::Lattice => snapshot[lattice_index(lattice_cell(i, side), 0, 0, 1, side)],
+        };
+        snapshot[i].distance(partner)
+    }
+
+    /// Mean of `snapshot` and the synchronization error, the mean distance of every particle
+    /// from its partner. Mirrors `cs_coupling_mean` in `compute.wgsl`.
+    pub fn sync_error(&self, snapshot: &[Vec3], side: usize) -> (Vec3, f32) {
+        let n = snapshot.len() as f32;
+        let mean = snapshot.iter().sum::<Vec3>() / n;
+        let error = (0..snapshot.len())
+            .map(|i| self.partner_distance(i, snapshot, mean, side))
+            .sum::<f32>()
+            / n;
+        (mean, error)
+    }
+}
+
+/// Coordinates of particle `i` in the cube of particles, in the order of the workgroups.
+fn lattice_cell(i: usize, side: usize) -> [usize; 3] {
+    [i / (side * side), i / side % side, i % side]
+}
+
+/// Index of the cell `[a, b, c]` moved by `da`, `db` and `dc`, wrapping around.
+fn lattice_index([a, b, c]: [usize; 3], da: usize, db: usize, dc: usize, side: usize) -> usize {
+    ((a + da) % side * side + (b + db) % side) * side + (c + dc) % side
+}
+
+/// The six neighbours of particle `i` in the periodic cube of particles.
+fn lattice_neighbours(i: usize, side: usize) -> impl Iterator<Item = usize> {
+    let cell = lattice_cell(i, side);
+    // * ADDING side - 1 STEPS BACK WITHOUT UNDERFLOW
+    let back = side - 1;
+    [
+        (back, 0, 0),
+        (1, 0, 0),
+        (0, back, 0),
+        (0, 1, 0),
+        (0, 0, back),
+        (0, 0, 1),
+    ]
+    .into_iter()
+    .map(move |(da, db, dc)| lattice_index(cell, da, db, dc, side))
+}
@@ -0,0 +1,915 @@
+use std::ops::{Add, Mul, Sub};
+
+use glam::{Vec3, Vec4};
+use image::GenericImageView;
+use rayon::prelude::*;
+use wgpu::{Device, Queue};
+use wide::{f32x8, CmpLe, CmpLt};
+
+use crate::{
+    backend::Stepper,
+    config::{ClockShader, Config},
+    coupling::{Coupling, SYNC_HISTORY},
+    delay::Delay,
+    emitter::{Emitter, DEAD},
+    equations::Equations,
+    hyper::{Hyper, HyperColor, HYPER_DIMENSIONS},
+    instance::{DrawState, InstancesVec, RawInstance},
+    integrator::Integrator,
+    lorenz::LorenzConfig,
+    lorenz96::{project, Lorenz96},
+    maps::{Map, MAP_COLOR_SCALE},
+    noise::{noise_key, Noise, NoiseKind},
+    respawn::{rng_state, Respawn},
+    scene::System,
+    twins::Twins,
+};
+
+const LANES: usize = 8;
+
+// * SAME AS IN compute.wgsl
+const VEL_SCALE: f32 = 0.0025;
+
+/// Eight points, one per SIMD lane.
+#[derive(Clone, Copy)]
+struct Vec3x8 {
+    x: f32x8,
+    y: f32x8,
+    z: f32x8,
+}
+
+impl Add for Vec3x8 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Mul for Vec3x8 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            x: self.x * rhs.x,
+            y: self.y * rhs.y,
+            z: self.z * rhs.z,
+        }
+    }
+}
+
+impl Vec3x8 {
+    const ZERO: Self = Self {
+        x: f32x8::ZERO,
+        y: f32x8::ZERO,
+        z: f32x8::ZERO,
+    };
+
+    fn length_squared(self) -> f32x8 {
+        self.x * self.x + self.y * self.y + self.z * self.z
+    }
+
+    fn lane(&self, lane: usize) -> Vec3 {
+        Vec3::new(
+            self.x.as_array_ref()[lane],
+            self.y.as_array_ref()[lane],
+            self.z.as_array_ref()[lane],
+        )
+    }
+
+    fn set_lane(&mut self, lane: usize, p: Vec3) {
+        self.x.as_array_mut()[lane] = p.x;
+        self.y.as_array_mut()[lane] = p.y;
+        self.z.as_array_mut()[lane] = p.z;
+    }
+
+    /// The lanes of `self` where `mask` is set, those of `other` elsewhere.
+    fn select(self, mask: f32x8, other: Self) -> Self {
+        Self {
+            x: mask.blend(self.x, other.x),
+            y: mask.blend(self.y, other.y),
+            z: mask.blend(self.z, other.z),
+        }
+    }
+}
+
+impl Sub for Vec3x8 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl Mul<f32x8> for Vec3x8 {
+    type Output = Self;
+    fn mul(self, rhs: f32x8) -> Self {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+impl Mul<f32> for Vec3x8 {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self {
+        let rhs = f32x8::splat(rhs);
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+fn lorenz_vel(lorenz: &LorenzConfig, p: Vec3x8) -> Vec3x8 {
+    Vec3x8 {
+        x: f32x8::splat(lorenz.sigma) * (p.y - p.x),
+        y: p.x * (f32x8::splat(lorenz.rho) - p.z) - p.y,
+        z: p.x * p.y - f32x8::splat(lorenz.beta) * p.z,
+    }
+}
+
+/// What the coupled particles add to the velocity of a chunk, see [`Coupling::pull`].
+struct Pull {
+    weight: Vec3x8,
+    sum: Vec3x8,
+    degree: f32x8,
+}
+
+impl Pull {
+    /// Padding lanes past `pulls` are not pulled.
+    fn new(weight: Vec3, pulls: &[(Vec3, f32)]) -> Self {
+        let mut sum = Vec3x8::ZERO;
+        let mut degree = [0.; LANES];
+        for (lane, (s, d)) in pulls.iter().enumerate() {
+            sum.set_lane(lane, *s);
+            degree[lane] = *d;
+        }
+        Self {
+            weight: Vec3x8::ZERO + splat(weight),
+            sum,
+            degree: f32x8::from(degree),
+        }
+    }
+
+    /// Velocity added over the whole step from the positions `p` before it, mirrors the end of
+    /// `coupling_pull` in `compute.wgsl`.
+    fn velocity(&self, p: Vec3x8) -> Vec3x8 {
+        self.weight * (self.sum - p * self.degree)
+    }
+}
+
+fn splat(p: Vec3) -> Vec3x8 {
+    Vec3x8 {
+        x: f32x8::splat(p.x),
+        y: f32x8::splat(p.y),
+        z: f32x8::splat(p.z),
+    }
+}
+
+/// Everything that moves a chunk of particles through one step.
+struct Dynamics {
+    lorenz: LorenzConfig,
+    custom: Option<Equations>,
+    map: Option<Map>,
+    integrator: Integrator,
+    h: f32,
+    clock: ClockShader,
+    noise: Noise,
+    noise_key: u32,
+    len: usize,
+}
+
+impl Dynamics {
+    /// Number of lanes of chunk `chunk` that hold a particle, the rest is padding.
+    fn lanes(&self, chunk: usize) -> usize {
+        (self.len - chunk * LANES).min(LANES)
+    }
+
+    /// Mask of the lanes of chunk `chunk` that hold a particle.
+    fn used(&self, chunk: usize) -> f32x8 {
+        let lane = f32x8::from(std::array::from_fn::<f32, LANES, _>(|lane| lane as f32));
+        lane.cmp_lt(f32x8::splat(self.lanes(chunk) as f32))
+    }
+
+    /// Velocity of chunk `chunk` at `p` in step number `step`, at the time of the step like in
+    /// `compute.wgsl`. The jump of a map.
+    fn velocity(&self, p: Vec3x8, chunk: usize, step: u32) -> Vec3x8 {
+        self.velocity_at(p, chunk, self.clock.time(step, self.h))
+    }
+
+    fn velocity_at(&self, p: Vec3x8, chunk: usize, t: f32) -> Vec3x8 {
+        if let Some(map) = &self.map {
+            return per_lane(p, self.lanes(chunk), |p| map.jump(p));
+        }
+        match &self.custom {
+            Some(equations) => {
+                let [x, y, z] = equations.velocity_x8([p.x, p.y, p.z], t);
+                Vec3x8 { x, y, z }
+            }
+            None => lorenz_vel(&self.lorenz, p),
+        }
+    }
+
+    /// Integrates chunk `chunk` at `p` through step number `step`, pulled by the coupled
+    /// particles if there are any.
+    fn advance(&self, p: Vec3x8, chunk: usize, step: u32, pull: Option<&Pull>) -> Vec3x8 {
+        if let Some(map) = &self.map {
+            return per_lane(p, self.lanes(chunk), |p| map.iterate(p));
+        }
+        let t = self.clock.time(step, self.h);
+        let coupling_vel = pull.map(|pull| pull.velocity(p));
+        let vel = |t, p| match coupling_vel {
+            Some(coupling_vel) => self.velocity_at(p, chunk, t) + coupling_vel,
+            None => self.velocity_at(p, chunk, t),
+        };
+        if !self.integrator.is_stochastic() {
+            return self.integrator.step_at(self.h, t, p, vel);
+        }
+        // * PADDING GETS NO NOISE
+        let mut dw = Vec3x8::ZERO;
+        for lane in 0..self.lanes(chunk) {
+            let index = chunk * LANES + lane;
+            dw.set_lane(lane, self.noise.wiener(self.noise_key, index, step, self.h));
+        }
+        self.integrator
+            .step_stochastic_at(self.h, t, p, vel, |p| match self.noise.kind {
+                NoiseKind::Additive => dw,
+                NoiseKind::Multiplicative => p * dw,
+            })
+    }
+}
+
+/// `f` applied to each of the first `lanes` lanes on its own, padding is left as it is.
+fn per_lane(mut p: Vec3x8, lanes: usize, f: impl Fn(Vec3) -> Vec3) -> Vec3x8 {
+    for lane in 0..lanes {
+        p.set_lane(lane, f(p.lane(lane)));
+    }
+    p
+}
+
+/// Where a respawned or emitted particle starts, maps in the plane like in `compute.wgsl`.
+fn respawned(p: Vec3, map: Option<&Map>) -> Vec3 {
+    match map {
+        Some(_) => p.truncate().extend(0.),
+        None => p,
+    }
+}
+
+fn lanes(chunk: &[f32]) -> f32x8 {
+    f32x8::from(<[f32; LANES]>::try_from(chunk).unwrap())
+}
+
+/// First row of the colormap, converted to linear RGB like the sampled sRGB texture.
+struct Colormap {
+    texels: Vec<Vec3>,
+}
+
+impl Colormap {
+    fn new(bytes: &[u8]) -> Self {
+        let image = image::load_from_memory(bytes).unwrap();
+        let srgb_to_linear = |c: u8| {
+            let c = c as f32 / 255.;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let texels = (0..image.width())
+            .map(|x| {
+                let [r, g, b, _] = image.get_pixel(x, 0).0;
+                Vec3::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+            })
+            .collect();
+        Self { texels }
+    }
+
+    // * LINEAR FILTERING WITH CLAMP TO EDGE, LIKE THE GPU SAMPLER
+    fn sample(&self, u: f32) -> Vec3 {
+        let last = self.texels.len() - 1;
+        let t = (u * self.texels.len() as f32 - 0.5).clamp(0., last as f32);
+        let i = t.floor() as usize;
+        let j = (i + 1).min(last);
+        self.texels[i].lerp(self.texels[j], t.fract())
+    }
+}
+
+/// CPU implementation of the particle integration. Particles are stored as structure of
+/// arrays, integrated eight at a time with SIMD and spread over all cores with rayon.
+/// It needs no GPU and is the reference the compute shader is tested against.
+pub struct CpuState {
+    x: Vec<f32>,
+    y: Vec<f32>,
+    z: Vec<f32>,
+    rng: Vec<u32>,
+    age: Vec<u32>,
+    len: usize,
+    lorenz: LorenzConfig,
+    integrator: Integrator,
+    delta_time: f32,
+    clock: ClockShader,
+    respawn: Respawn,
+    respawns: u64,
+    noise: Noise,
+    noise_key: u32,
+    emitter: Option<Emitter>,
+    draw_state: DrawState,
+    colormap: Colormap,
+    custom: Option<Equations>,
+    map: Option<Map>,
+    lorenz96: Option<Lorenz96>,
+    hyper: Option<Hyper>,
+    delay: Option<Delay>,
+    coupling: Option<Coupling>,
+    /// Edge of the cube of particles, for [`Topology::Lattice`](crate::coupling::Topology).
+    side: usize,
+    /// Distance of every coupled particle from its partner before the last step.
+    partner_distances: Vec<f32>,
+    /// Ring buffer of the synchronization errors, at the step modulo its length.
+    sync_errors: Vec<f32>,
+    twins: Option<Twins>,
+    projection: Vec<Vec3>,
+    states: Vec<f32>,
+}
+
+impl CpuState {
+    /// Starts from `points`, with everything else taken from `config`. The random states used
+    /// for respawning are derived from [`Config::seed`] like on the GPU. Lorenz-96 particles
+    /// start at the fixed point `x_i = F`, 4D ones at `w` of [`Config::hyper`] and delay
+    /// equations with a constant history, see [`CpuState::set_states`].
+    pub fn new(points: &[Vec3], config: &Config) -> Self {
+        // * PAD TO WHOLE LANES, PADDING IS MASKED OUT OF EVERY STEP
+        let padded = points.len().div_ceil(LANES) * LANES;
+        let mut x = vec![0.; padded];
+        let mut y = vec![0.; padded];
+        let mut z = vec![0.; padded];
+        for (i, p) in points.iter().enumerate() {
+            (x[i], y[i], z[i]) = (p.x, p.y, p.z);
+        }
+        let seed = config.seed.unwrap_or_default();
+        let rng = (0..padded).map(|i| rng_state(seed, i)).collect();
+        // * WITH AN EMITTER THE POOL STARTS OUT EMPTY, LIKE THE INSTANCE BUFFER
+        let (age, in_use) = match config.emitter {
+            Some(_) => (DEAD, 0),
+            None => (0, points.len()),
+        };
+        let mut cpu_state = Self {
+            x,
+            y,
+            z,
+            rng,
+            age: vec![age; padded],
+            len: points.len(),
+            lorenz: config.lorenz,
+            integrator: config.integrator,
+            delta_time: config.delta_time,
+            clock: ClockShader::default(),
+            respawn: config.respawn,
+            respawns: 0,
+            noise: config.noise,
+            noise_key: noise_key(seed),
+            emitter: config.emitter,
+            draw_state: DrawState::new(in_use),
+            colormap: Colormap::new(&config.colormap),
+            custom: config.custom.clone(),
+            map: (config.system == System::Map).then_some(config.map),
+            lorenz96: None,
+            hyper: None,
+            delay: None,
+            coupling: config.coupling,
+            side: config.num_workgroups.0 as usize,
+            partner_distances: vec![0.; points.len()],
+            sync_errors: vec![0.; SYNC_HISTORY],
+            twins: config.twins,
+            projection: Vec::new(),
+            states: Vec::new(),
+        };
+        if config.system == System::Lorenz96 {
+            let lorenz96 = config.lorenz96;
+            cpu_state.lorenz96 = Some(lorenz96);
+            cpu_state.projection = lorenz96.projection(seed);
+            cpu_state.set_states(&vec![lorenz96.forcing; points.len() * lorenz96.dimensions]);
+        }
+        if config.system == System::Hyper {
+            cpu_state.hyper = Some(config.hyper);
+            let starts: Vec<f32> = points
+                .iter()
+                .flat_map(|p| p.extend(config.hyper.w).to_array())
+                .collect();
+            cpu_state.set_states(&starts);
+        }
+        if config.system == System::Delay {
+            // * CONSTANT HISTORIES AT THE FIRST COORDINATE, WHICH IS DRAWN AS x(t)
+            let delay = config.delay;
+            cpu_state.delay = Some(delay);
+            let histories: Vec<f32> = points
+                .iter()
+                .flat_map(|p| {
+                    std::iter::repeat_n(p.x / delay.scale() + delay.center(), delay.history_len())
+                })
+                .collect();
+            cpu_state.set_states(&histories);
+        }
+        cpu_state
+    }
+
+    /// Advances every particle by `steps` time steps, respawning unhealthy ones like the
+    /// compute shader does.
+    pub fn step(&mut self, steps: u32) {
+        if let Some(lorenz96) = self.lorenz96 {
+            return self.step_lorenz96(lorenz96, steps);
+        }
+        if let Some(hyper) = self.hyper {
+            return self.step_hyper(hyper, steps);
+        }
+        if let Some(delay) = self.delay {
+            return self.step_delay(delay, steps);
+        }
+        if let Some(emitter) = self.emitter {
+            return self.step_emitter(emitter, steps);
+        }
+        if let Some(coupling) = self.coupling {
+            return self.step_coupled(coupling, steps);
+        }
+        self.respawns += self.advance_chunks(steps, None);
+    }
+
+    /// Like [`CpuState::step`] for coupled particles, which all take one step at a time from
+    /// their positions before the step. Mirrors `cs_coupling_snapshot`, `cs_coupling_mean`
+    /// and `cs_main` in `compute.wgsl`.
+    fn step_coupled(&mut self, coupling: Coupling, steps: u32) {
+        let side = self.side;
+        for _ in 0..steps {
+            let snapshot = self.positions();
+            let (mean, error) = coupling.sync_error(&snapshot, side);
+            self.sync_errors[self.draw_state.step as usize % SYNC_HISTORY] = error;
+            let pulls: Vec<(Vec3, f32)> = (0..self.len)
+                .into_par_iter()
+                .map(|i| coupling.pull(i, &snapshot, mean, side))
+                .collect();
+            self.partner_distances = (0..self.len)
+                .into_par_iter()
+                .map(|i| coupling.partner_distance(i, &snapshot, mean, side))
+                .collect();
+            self.respawns += self.advance_chunks(1, Some((coupling.weight(), &pulls)));
+        }
+    }
+
+    /// Integrates every chunk through `steps` steps, each particle pulled by the
+    /// `(sum, degree)` of its coupled particles times the weight if `coupling` is given.
+    /// Returns the number of respawned particles.
+    fn advance_chunks(&mut self, steps: u32, coupling: Option<(Vec3, &[(Vec3, f32)])>) -> u64 {
+        let dynamics = self.dynamics();
+        let (respawn, len, first_step) = (self.respawn, self.len, self.draw_state.step);
+        let radius_sq = f32x8::splat(respawn.radius * respawn.radius);
+        let min_speed_sq = f32x8::splat(respawn.min_speed * respawn.min_speed);
+        let respawns = self
+            .x
+            .par_chunks_mut(LANES)
+            .zip(self.y.par_chunks_mut(LANES))
+            .zip(self.z.par_chunks_mut(LANES))
+            .zip(self.rng.par_chunks_mut(LANES))
+            .enumerate()
+            .map(|(chunk, (((x, y), z), rng))| {
+                let mut p = Vec3x8 {
+                    x: lanes(x),
+                    y: lanes(y),
+                    z: lanes(z),
+                };
+                let pull = coupling.map(|(weight, pulls)| {
+                    let lanes = chunk * LANES..((chunk + 1) * LANES).min(len);
+                    Pull::new(weight, &pulls[lanes])
+                });
+                // * ONLY THE LAST CHUNK HAS PADDING, WHICH IS KEPT AT THE ORIGIN
+                let used = (dynamics.lanes(chunk) < LANES).then(|| dynamics.used(chunk));
+                let mut respawns = 0;
+                for step in 0..steps {
+                    let vel = dynamics.velocity(p, chunk, first_step.wrapping_add(step));
+                    p = dynamics.advance(p, chunk, first_step.wrapping_add(step), pull.as_ref());
+
+                    // * NAN FAILS EVERY COMPARISON, LIKE IN compute.wgsl
+                    let mut unhealthy = !p.length_squared().cmp_le(radius_sq)
+                        | vel.length_squared().cmp_lt(min_speed_sq);
+                    if let Some(used) = used {
+                        p = p.select(used, Vec3x8::ZERO);
+                        unhealthy &= used;
+                    }
+                    if unhealthy.any() {
+                        let mask = unhealthy.move_mask();
+                        for lane in (0..LANES).filter(|lane| mask & (1 << lane) != 0) {
+                            let sample = respawn.sample(&mut rng[lane]);
+                            p.set_lane(lane, respawned(sample, dynamics.map.as_ref()));
+                            respawns += 1;
+                        }
+                    }
+                }
+                x.copy_from_slice(&p.x.to_array());
+                y.copy_from_slice(&p.y.to_array());
+                z.copy_from_slice(&p.z.to_array());
+                respawns
+            })
+            .sum::<u64>();
+        self.draw_state.step = first_step.wrapping_add(steps);
+        respawns
+    }
+
+    fn dynamics(&self) -> Dynamics {
+        Dynamics {
+            lorenz: self.lorenz,
+            custom: self.custom.clone(),
+            map: self.map,
+            integrator: self.integrator,
+            h: self.lorenz.step_size_factor * self.delta_time,
+            clock: self.clock,
+            noise: self.noise,
+            noise_key: self.noise_key,
+            len: self.len,
+        }
+    }
+
+    /// Like [`CpuState::step`], but particles are emitted into the ring buffer and die
+    /// instead of being respawned. Mirrors `cs_main` and `cs_advance` in `compute.wgsl`.
+    fn step_emitter(&mut self, emitter: Emitter, steps: u32) {
+        let dynamics = self.dynamics();
+        let (respawn, len, first_step) = (self.respawn, self.len, self.draw_state.step);
+        let head = self.draw_state.head as usize;
+        let rate = emitter.rate as usize;
+        self.x
+            .par_chunks_mut(LANES)
+            .zip(self.y.par_chunks_mut(LANES))
+            .zip(self.z.par_chunks_mut(LANES))
+            .zip(self.rng.par_chunks_mut(LANES))
+            .zip(self.age.par_chunks_mut(LANES))
+            .enumerate()
+            .for_each(|(chunk, ((((x, y), z), rng), age))| {
+                let mut p = Vec3x8 {
+                    x: lanes(x),
+                    y: lanes(y),
+                    z: lanes(z),
+                };
+                for step in 0..steps {
+                    let head = (head + step as usize * rate) % len;
+                    let before = p;
+                    let vel = dynamics.velocity(p, chunk, first_step.wrapping_add(step));
+                    p = dynamics.advance(p, chunk, first_step.wrapping_add(step), None);
+
+                    for lane in 0..LANES {
+                        let index = chunk * LANES + lane;
+                        if index >= len {
+                            p.set_lane(lane, before.lane(lane));
+                            continue;
+                        }
+                        if emitter.emits(index, head, len) {
+                            let sample = emitter.sample(&mut rng[lane]);
+                            p.set_lane(lane, respawned(sample, dynamics.map.as_ref()));
+                            age[lane] = 0;
+                            continue;
+                        }
+                        if age[lane] != DEAD {
+                            age[lane] += 1;
+                        }
+                        if age[lane] >= emitter.lifetime {
+                            age[lane] = DEAD;
+                        }
+                        if age[lane] == DEAD || respawn.is_unhealthy(p.lane(lane), vel.lane(lane)) {
+                            // * DEAD PARTICLES STAY WHERE THEY DIED
+                            age[lane] = DEAD;
+                            p.set_lane(lane, before.lane(lane));
+                        }
+                    }
+                }
+                x.copy_from_slice(&p.x.to_array());
+                y.copy_from_slice(&p.y.to_array());
+                z.copy_from_slice(&p.z.to_array());
+            });
+
+        let emitted = steps as usize * rate;
+        self.draw_state.head = ((head + emitted) % len) as u32;
+        self.draw_state.instance_count =
+            (self.draw_state.instance_count as usize + emitted).min(len) as u32;
+        self.draw_state.step = first_step.wrapping_add(steps);
+    }
+
+    /// Like [`CpuState::step`] for Lorenz-96, which is integrated particle by particle and
+    /// never respawned. Mirrors `cs_lorenz96` in `compute.wgsl`.
+    fn step_lorenz96(&mut self, lorenz96: Lorenz96, steps: u32) {
+        let (integrator, n) = (self.integrator, lorenz96.dimensions);
+        let h = self.lorenz.step_size_factor * self.delta_time;
+        self.states.par_chunks_mut(n).for_each_init(
+            || vec![0.; 3 * n],
+            |scratch, state| {
+                for _ in 0..steps {
+                    lorenz96.step(integrator, h, state, scratch);
+                }
+            },
+        );
+        self.project_states();
+        self.draw_state.step = self.draw_state.step.wrapping_add(steps);
+    }
+
+    /// Like [`CpuState::step`] for 4D systems, which are integrated particle by particle.
+    /// Mirrors `cs_hyper` in `compute.wgsl`.
+    fn step_hyper(&mut self, hyper: Hyper, steps: u32) {
+        let (integrator, respawn) = (self.integrator, self.respawn);
+        let h = self.lorenz.step_size_factor * self.delta_time;
+        let radius_sq = respawn.radius * respawn.radius;
+        let min_speed_sq = respawn.min_speed * respawn.min_speed;
+        self.respawns += self
+            .states
+            .par_chunks_mut(HYPER_DIMENSIONS)
+            .zip(self.rng.par_iter_mut())
+            .map(|(state, rng)| {
+                let mut s = Vec4::from_slice(state);
+                let mut respawns = 0;
+                for _ in 0..steps {
+                    let vel = hyper.velocity(s);
+                    s = hyper.step(integrator, h, s);
+                    if !s.is_finite()
+                        || s.length_squared() > radius_sq
+                        || vel.length_squared() < min_speed_sq
+                    {
+                        s = respawn.sample(rng).extend(hyper.w);
+                        respawns += 1;
+                    }
+                }
+                s.write_to_slice(state);
+                respawns
+            })
+            .sum::<u64>();
+        self.project_states();
+        self.draw_state.step = self.draw_state.step.wrapping_add(steps);
+    }
+
+    /// Like [`CpuState::step`] for delay equations, which step their history ring buffers.
+    /// Mirrors `cs_delay` in `compute.wgsl`.
+    fn step_delay(&mut self, delay: Delay, steps: u32) {
+        let (integrator, first_step) = (self.integrator, self.draw_state.step);
+        self.states
+            .par_chunks_mut(delay.history_len())
+            .for_each(|history| {
+                for step in 0..steps {
+                    delay.step(integrator, history, first_step.wrapping_add(step));
+                }
+            });
+        self.draw_state.step = first_step.wrapping_add(steps);
+        self.project_states();
+    }
+
+    fn project_states(&mut self) {
+        if let Some(delay) = self.delay {
+            let step = self.draw_state.step;
+            for (i, history) in self.states.chunks(delay.history_len()).enumerate() {
+                let p = delay.embed(history, step);
+                (self.x[i], self.y[i], self.z[i]) = (p.x, p.y, p.z);
+            }
+        }
+        if let Some(hyper) = self.hyper {
+            for (i, state) in self.states.chunks(HYPER_DIMENSIONS).enumerate() {
+                let p = hyper.project(Vec4::from_slice(state)).0;
+                (self.x[i], self.y[i], self.z[i]) = (p.x, p.y, p.z);
+            }
+        }
+        let Some(lorenz96) = self.lorenz96 else {
+            return;
+        };
+        let n = lorenz96.dimensions;
+        for (i, state) in self.states.chunks(n).enumerate() {
+            let p = project(&self.projection, state);
+            (self.x[i], self.y[i], self.z[i]) = (p.x, p.y, p.z);
+        }
+    }
+
+    /// Variables per particle, see [`Config::state_dimensions`].
+    fn state_dimensions(&self) -> usize {
+        match (self.lorenz96, self.hyper, self.delay) {
+            (Some(lorenz96), _, _) => lorenz96.dimensions,
+            (_, Some(_), _) => HYPER_DIMENSIONS,
+            (_, _, Some(delay)) => delay.history_len(),
+            (None, None, None) => 0,
+        }
+    }
+
+    /// Replaces the Lorenz-96 or 4D states or the delay histories,
+    /// [`Config::state_dimensions`] per particle, and moves the particles to their
+    /// projection.
+    ///
+    /// # Panics
+    ///
+    /// If the system has no states or the number of states is wrong.
+    pub fn set_states(&mut self, states: &[f32]) {
+        let dimensions = self.state_dimensions();
+        assert!(dimensions > 0, "not a simulation with states");
+        assert_eq!(
+            states.len(),
+            self.len * dimensions,
+            "wrong number of states"
+        );
+        self.states = states.to_vec();
+        self.project_states();
+    }
+
+    /// Rotates a 4D system into view anew, moving the particles without stepping them.
+    pub fn set_hyper(&mut self, hyper: Hyper) {
+        if self.hyper.is_some() {
+            self.hyper = Some(hyper);
+            self.project_states();
+        }
+    }
+
+    /// Lorenz-96 or 4D states or delay histories, [`Config::state_dimensions`] per particle.
+    /// Empty for the Lorenz system.
+    pub fn states(&self) -> &[f32] {
+        &self.states
+    }
+
+    pub(crate) fn set_rng_states(&mut self, states: impl Iterator<Item = u32>) {
+        self.rng
+            .iter_mut()
+            .zip(states)
+            .for_each(|(rng, state)| *rng = state);
+    }
+
+    /// Takes over the emitter pool of another backend.
+    pub(crate) fn set_pool(&mut self, ages: impl Iterator<Item = u32>, draw_state: DrawState) {
+        self.age
+            .iter_mut()
+            .zip(ages)
+            .for_each(|(age, state)| *age = state);
+        self.draw_state = draw_state;
+    }
+
+    /// Synchronization errors of coupled particles in a ring buffer, at the step they were
+    /// taken at modulo [`SYNC_HISTORY`].
+    pub fn sync_errors(&self) -> &[f32] {
+        &self.sync_errors
+    }
+
+    /// Takes over the synchronization errors of another backend.
+    pub(crate) fn set_sync_errors(&mut self, sync_errors: &[f32]) {
+        self.sync_errors.copy_from_slice(sync_errors);
+    }
+
+    /// Indirect draw arguments and ring buffer position, as in the draw buffer.
+    pub fn draw_state(&self) -> DrawState {
+        self.draw_state
+    }
+
+    /// Number of particles respawned so far.
+    pub fn respawns(&self) -> u64 {
+        self.respawns
+    }
+
+    /// Moves the particles to `points`, keeping their random states and ages.
+    pub(crate) fn set_positions(&mut self, points: &[Vec3]) {
+        for (i, p) in points.iter().enumerate().take(self.len) {
+            (self.x[i], self.y[i], self.z[i]) = (p.x, p.y, p.z);
+        }
+    }
+
+    /// Current position of particle `index`.
+    pub fn position(&self, index: usize) -> Vec3 {
+        Vec3::new(self.x[index], self.y[index], self.z[index])
+    }
+
+    /// Current particle positions.
+    pub fn positions(&self) -> Vec<Vec3> {
+        (0..self.len).map(|i| self.position(i)).collect()
+    }
+
+    /// Particles as stored in the instance buffer, colored by speed like on the GPU.
+    pub fn raw_instances(&self) -> Vec<RawInstance> {
+        (0..self.len)
+            .into_par_iter()
+            .map(|i| {
+                let position = Vec3::new(self.x[i], self.y[i], self.z[i]);
+                if let Some(hyper) = &self.hyper {
+                    let color = self.hyper_color(hyper, i);
+                    return RawInstance::new(position, self.rng[i], self.age[i], color);
+                }
+                if let Some(coupling) = &self.coupling {
+                    let color = self
+                        .colormap
+                        .sample(self.partner_distances[i] / coupling.range);
+                    return RawInstance::new(position, self.rng[i], self.age[i], color);
+                }
+                if let Some(twins) = &self.twins {
+                    return RawInstance::new(position, self.rng[i], self.age[i], twins.color(i));
+                }
+                let speed = match (&self.lorenz96, &self.delay) {
+                    (Some(lorenz96), _) => {
+                        let n = lorenz96.dimensions;
+                        lorenz96.speed(&self.states[i * n..(i + 1) * n])
+                    }
+                    (None, Some(delay)) => {
+                        let n = delay.history_len();
+                        delay.speed(&self.states[i * n..(i + 1) * n], self.draw_state.step)
+                    }
+                    (None, None) => match &self.map {
+                        Some(map) => MAP_COLOR_SCALE * map.jump(position).length(),
+                        None => self.velocity(position).length(),
+                    },
+                };
+                let color = self.colormap.sample(VEL_SCALE * speed);
+                RawInstance::new(position, self.rng[i], self.age[i], color)
+            })
+            .collect()
+    }
+
+    /// Color of 4D particle `i`, like `show_hyper` in `compute.wgsl`.
+    fn hyper_color(&self, hyper: &Hyper, i: usize) -> Vec3 {
+        let s = Vec4::from_slice(&self.states[i * HYPER_DIMENSIONS..]);
+        match hyper.color {
+            HyperColor::Speed => self.colormap.sample(VEL_SCALE * hyper.velocity(s).length()),
+            HyperColor::W => {
+                let [lo, hi] = hyper.w_range();
+                self.colormap.sample((hyper.project(s).1 - lo) / (hi - lo))
+            }
+        }
+    }
+
+    pub fn set_parameters(&mut self, lorenz: LorenzConfig) {
+        self.lorenz = lorenz;
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    pub fn set_delta_time(&mut self, delta_time: f32) {
+        self.delta_time = delta_time;
+    }
+
+    pub fn set_equations(&mut self, equations: Equations) {
+        self.custom = Some(equations);
+    }
+
+    /// Velocity of a single particle at `p` in the current step.
+    fn velocity(&self, p: Vec3) -> Vec3 {
+        let h = self.lorenz.step_size_factor * self.delta_time;
+        let t = self.clock.time(self.draw_state.step, h);
+        match &self.custom {
+            Some(equations) => equations.velocity(p, t),
+            None => self.lorenz.delta(p),
+        }
+    }
+}
+
+impl Stepper for CpuState {
+    fn step(&mut self, _device: &Device, queue: &Queue, instances: &InstancesVec, steps: u32) {
+        CpuState::step(self, steps);
+        queue.write_buffer(
+            &instances.buffer,
+            0,
+            bytemuck::cast_slice(&self.raw_instances()),
+        );
+        queue.write_buffer(
+            &instances.draw_buffer,
+            0,
+            bytemuck::bytes_of(&self.draw_state),
+        );
+        if !self.states.is_empty() {
+            queue.write_buffer(&instances.states, 0, bytemuck::cast_slice(&self.states));
+        }
+    }
+
+    fn update_config(&mut self, config: &Config, _queue: &Queue) {
+        self.lorenz = config.lorenz;
+        self.integrator = config.integrator;
+        self.respawn = config.respawn;
+        self.emitter = config.emitter;
+        self.noise = config.noise;
+        if self.map.is_some() {
+            self.map = Some(config.map);
+        }
+        if self.hyper.is_some() {
+            self.hyper = Some(config.hyper);
+        }
+        if self.coupling.is_some() {
+            self.coupling = config.coupling;
+        }
+        if self.twins.is_some() {
+            self.twins = config.twins;
+        }
+    }
+
+    fn update_delta_time(&mut self, delta_time: f32, _queue: &Queue) {
+        self.delta_time = delta_time;
+    }
+
+    fn update_clock(&mut self, clock: ClockShader, _queue: &Queue) {
+        self.clock = clock;
+    }
+
+    fn set_colormap(&mut self, _device: &Device, _queue: &Queue, colormap: &[u8]) {
+        self.colormap = Colormap::new(colormap);
+    }
+}
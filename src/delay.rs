@@ -0,0 +1,230 @@
+use glam::Vec3;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::integrator::Integrator;
+
+/// Integration steps per delay.
+const SAMPLES: u32 = 100;
+
+/// A scalar delay differential equation `x'(t) = f(x(t), x(t - τ))`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DelayKind {
+    /// Mackey-Glass blood cell production, `a x_τ / (1 + |x_τ|^c) - b x`
+    #[default]
+    MackeyGlass,
+    /// Ikeda's optical cavity, `a sin(x_τ - b) - x`
+    Ikeda,
+}
+
+impl DelayKind {
+    /// The parameters `a`, `b` and `c` of the chaotic regime.
+    pub fn default_parameters(self) -> [f32; 3] {
+        match self {
+            DelayKind::MackeyGlass => [0.2, 0.1, 10.],
+            DelayKind::Ikeda => [4., 0., 0.],
+        }
+    }
+
+    /// The delay τ of the chaotic regime.
+    pub fn default_tau(self) -> f32 {
+        match self {
+            DelayKind::MackeyGlass => 17.,
+            DelayKind::Ikeda => 2.,
+        }
+    }
+
+    /// Range of the constant initial histories, around the attractor.
+    pub fn default_initial(self) -> [f32; 2] {
+        match self {
+            DelayKind::MackeyGlass => [0.5, 1.3],
+            DelayKind::Ikeda => [0., 3.],
+        }
+    }
+
+    /// Value drawn at the origin and drawn size of one unit, so the attractor is about as
+    /// big as the Lorenz one.
+    pub fn default_view(self) -> (f32, f32) {
+        match self {
+            DelayKind::MackeyGlass => (0.9, 40.),
+            DelayKind::Ikeda => (1.5, 10.),
+        }
+    }
+}
+
+/// A delay differential equation, as read from the `[delay]` table of a scene. Every particle
+/// keeps its past over the delay and is drawn at its delay embedding
+/// `(x(t), x(t - e), x(t - 2 e))`. Missing values take the defaults of the kind.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Delay {
+    pub kind: DelayKind,
+    pub a: Option<f32>,
+    pub b: Option<f32>,
+    pub c: Option<f32>,
+    /// The delay τ.
+    pub tau: Option<f32>,
+    /// Integration steps per delay, the step size is `τ / samples`. [`Config::delta_time`]
+    /// is ignored so the history stays evenly spaced.
+    ///
+    /// [`Config::delta_time`]: crate::Config::delta_time
+    pub samples: u32,
+    /// The embedding delay `e` of the drawn axes, `τ / 2` by default.
+    pub embedding: Option<f32>,
+    /// Range the constant initial history of every particle is drawn from.
+    pub initial: Option<[f32; 2]>,
+    /// Value drawn at the origin.
+    pub center: Option<f32>,
+    /// Drawn size of one unit.
+    pub scale: Option<f32>,
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Self {
+            kind: DelayKind::MackeyGlass,
+            a: None,
+            b: None,
+            c: None,
+            tau: None,
+            samples: SAMPLES,
+            embedding: None,
+            initial: None,
+            center: None,
+            scale: None,
+        }
+    }
+}
+
+impl Delay {
+    /// `a`, `b` and `c`, with the defaults of the kind filled in.
+    pub fn parameters(&self) -> [f32; 3] {
+        let [a, b, c] = self.kind.default_parameters();
+        [
+            self.a.unwrap_or(a),
+            self.b.unwrap_or(b),
+            self.c.unwrap_or(c),
+        ]
+    }
+
+    pub fn tau(&self) -> f32 {
+        self.tau.unwrap_or(self.kind.default_tau())
+    }
+
+    pub fn initial(&self) -> [f32; 2] {
+        self.initial.unwrap_or(self.kind.default_initial())
+    }
+
+    pub fn center(&self) -> f32 {
+        self.center.unwrap_or(self.kind.default_view().0)
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale.unwrap_or(self.kind.default_view().1)
+    }
+
+    /// Size of one integration step.
+    pub fn step_size(&self) -> f32 {
+        self.tau() / self.samples as f32
+    }
+
+    /// The embedding delay in steps, usually fractional.
+    pub fn embedding_lag(&self) -> f32 {
+        self.embedding.unwrap_or(0.5 * self.tau()) / self.step_size()
+    }
+
+    /// Samples of the history of every particle: enough to look back over the delay and
+    /// over two embedding delays, plus the current value.
+    pub fn history_len(&self) -> usize {
+        (self.samples as usize).max((2. * self.embedding_lag()).ceil() as usize) + 1
+    }
+
+    /// `x'` at `x` with the delayed value `x_tau`. Mirrors `delay_vel` in `compute.wgsl`.
+    pub fn velocity(&self, x: f32, x_tau: f32) -> f32 {
+        let [a, b, c] = self.parameters();
+        match self.kind {
+            // * |x_τ|^c, THE SAME AS x_τ^c FOR THE USUAL EVEN c AND DEFINED ON THE GPU
+            DelayKind::MackeyGlass => a * x_tau / (1. + x_tau.abs().powf(c)) - b * x,
+            DelayKind::Ikeda => a * (x_tau - b).sin() - x,
+        }
+    }
+
+    /// Constant initial histories of `n` particles, [`Delay::history_len`] samples each.
+    pub fn initial_histories(&self, n: usize, rng: &mut impl Rng) -> Vec<f32> {
+        let [lo, hi] = self.initial();
+        let len = self.history_len();
+        (0..n)
+            .flat_map(|_| std::iter::repeat_n(rng.gen_range(lo..=hi), len))
+            .collect()
+    }
+
+    /// Advances a history ring buffer from step number `step` to the next one. Mirrors
+    /// `cs_delay` in `compute.wgsl`.
+    pub fn step(&self, integrator: Integrator, history: &mut [f32], step: u32) {
+        let h = self.step_size();
+        let m = self.samples as f32;
+        let at = |lag| history_at(history, step, lag);
+        let (x, f) = (at(0.), |x, x_tau| self.velocity(x, x_tau));
+        // * THE DELAYED VALUES OF THE STAGES ARE INTERPOLATED IN THE HISTORY
+        let next = match integrator {
+            Integrator::Heun => {
+                let k1 = f(x, at(m));
+                let k2 = f(x + h * k1, at(m - 1.));
+                x + 0.5 * h * (k1 + k2)
+            }
+            Integrator::Rk4 => {
+                let half = at(m - 0.5);
+                let k1 = f(x, at(m));
+                let k2 = f(x + 0.5 * h * k1, half);
+                let k3 = f(x + 0.5 * h * k2, half);
+                let k4 = f(x + h * k3, at(m - 1.));
+                x + h / 6. * (k1 + 2. * k2 + 2. * k3 + k4)
+            }
+            Integrator::Euler => x + h * f(x, at(m)),
+            Integrator::EulerMaruyama | Integrator::StochasticHeun => {
+                unreachable!("delay rejects stochastic integrators")
+            }
+        };
+        let len = history.len();
+        history[(step as usize + 1) % len] = next;
+    }
+
+    /// Drawn position of a history at step number `step`.
+    pub fn embed(&self, history: &[f32], step: u32) -> Vec3 {
+        let lag = self.embedding_lag();
+        let at = |lag| history_at(history, step, lag);
+        (Vec3::new(at(0.), at(lag), at(2. * lag)) - self.center()) * self.scale()
+    }
+
+    /// Drawn velocity of a history at step number `step`. The history ends at the last
+    /// embedding delay, so the delayed axes take the slope of the step after them.
+    pub fn embedding_velocity(&self, history: &[f32], step: u32) -> Vec3 {
+        let at = |lag| history_at(history, step, lag);
+        let slope = |lag: f32| {
+            let after = (lag - 1.).max(0.);
+            (at(after) - at(lag)) / ((lag - after) * self.step_size())
+        };
+        let x_dot = self.velocity(at(0.), at(self.samples as f32));
+        let lag = self.embedding_lag();
+        Vec3::new(x_dot, slope(lag), slope(2. * lag)) * self.scale()
+    }
+
+    /// Drawn distance per delay at step number `step`, what particles are colored by.
+    pub fn speed(&self, history: &[f32], step: u32) -> f32 {
+        let at = |lag| history_at(history, step, lag);
+        let x_dot = self.velocity(at(0.), at(self.samples as f32));
+        (x_dot * self.scale() * self.tau()).abs()
+    }
+}
+
+/// Value `lag` steps back in a history ring buffer at step number `step`, linearly
+/// interpolated between two samples. Mirrors `history_at` in `compute.wgsl`.
+pub fn history_at(history: &[f32], step: u32, lag: f32) -> f32 {
+    let len = history.len();
+    let head = step as usize % len;
+    let i = lag.floor() as usize;
+    let sample = |back: usize| history[(head + len - back % len) % len];
+    let t = lag - i as f32;
+    sample(i) + t * (sample(i + 1) - sample(i))
+}
@@ -0,0 +1,526 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use glam::{IVec3, Vec3};
+use rand::{seq::index, Rng};
+use rayon::prelude::*;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    PipelineLayoutDescriptor, Queue, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+use crate::instance::InstancesVec;
+
+pub const DIMENSION_WGSL: &str = include_str!("dimension.wgsl");
+
+// * SAME AS IN dimension.wgsl
+pub(crate) const WORKGROUPS: usize = 256;
+/// Number of box sizes counted, grids of 2, 4, … 2^`LEVELS` boxes along the longest side of
+/// the bounding box.
+pub const LEVELS: usize = 10;
+
+// * FIT RANGE OF THE BOX COUNTS: ENOUGH BOXES TO RESOLVE THE SHAPE, FEW ENOUGH THAT MOST
+// * OCCUPIED BOXES HOLD SEVERAL PARTICLES
+const MIN_BOXES: usize = 64;
+const PARTICLES_PER_BOX: usize = 16;
+
+/// Particles paired for the correlation sums.
+pub const SAMPLES: usize = 50_000;
+/// Particles of the sample paired with all others.
+pub const REFERENCES: usize = 2000;
+// * RADII OF THE CORRELATION SUMS, AN EIGHTH OF THE LONGEST SIDE OF THE BOUNDING BOX TIMES
+// * 2^(-k / 2)
+const RADII: usize = 16;
+// * FIT RANGE OF THE CORRELATION SUMS: SMALL AGAINST THE ATTRACTOR, YET WITH ENOUGH PAIRS
+const MAX_CORRELATION: f64 = 0.05;
+const MIN_PAIRS: u64 = 1000;
+
+// * THE CELL OF A REFERENCE AND THE 26 AROUND IT
+const NEIGHBOURS: [IVec3; 27] = {
+    let mut offsets = [IVec3::ZERO; 27];
+    let mut i = 0;
+    while i < 27 {
+        offsets[i] = IVec3::new(i as i32 % 3 - 1, i as i32 / 3 % 3 - 1, i as i32 / 9 - 1);
+        i += 1;
+    }
+    offsets
+};
+
+/// Layout of bind group 0 of `dimension.wgsl`.
+pub(crate) const BIND_GROUP_LAYOUT_ENTRIES: [BindGroupLayoutEntry; 4] = [
+    // * INSTANCE BUFFER
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * GRID UNIFORM
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * HASH TABLE OF OCCUPIED BOXES
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * OCCUPIED BOXES OF EVERY LEVEL
+    BindGroupLayoutEntry {
+        binding: 3,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+/// `BoxCountUniform` of `dimension.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Default)]
+pub struct BoxCountUniform {
+    pub origin: [f32; 3],
+    pub scale: f32,
+    pub cells: u32,
+    pub count: u32,
+    pub mask: u32,
+    pub level: u32,
+}
+
+/// A cube of `cells`³ boxes over the bounding box `min`..`max`, as hashed by
+/// `dimension.wgsl`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxGrid {
+    pub origin: Vec3,
+    /// Inverse of the box size.
+    pub scale: f32,
+    pub cells: u32,
+}
+
+impl BoxGrid {
+    /// The grid of `2^level` boxes along the longest side of the bounding box.
+    pub fn new(min: Vec3, max: Vec3, level: usize) -> Self {
+        let cells = 1 << level;
+        // * A DEGENERATE BOX STILL GETS A POSITIVE SIZE
+        let extent = (max - min).max_element().max(f32::EPSILON);
+        Self {
+            origin: min,
+            scale: cells as f32 / extent,
+            cells,
+        }
+    }
+
+    /// Side of a box.
+    pub fn size(&self) -> f32 {
+        1. / self.scale
+    }
+
+    /// Index of the box holding `p`, points outside count to the nearest box.
+    pub fn key(&self, p: Vec3) -> u32 {
+        let max = (self.cells - 1) as f32;
+        let cell = ((p - self.origin) * self.scale)
+            .floor()
+            .clamp(Vec3::ZERO, Vec3::splat(max))
+            .as_uvec3();
+        cell.x + self.cells * (cell.y + self.cells * cell.z)
+    }
+}
+
+/// Bits of the table the boxes are hashed into for `particles` particles, at least 32 per
+/// particle so that few boxes share a bit.
+pub fn table_bits(particles: usize) -> usize {
+    (32 * particles).next_power_of_two().max(1024)
+}
+
+/// PCG hash, same as in `dimension.wgsl`.
+fn hash(key: u32) -> u32 {
+    let state = key.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// Linear counting: the number of distinct boxes hashed into a table of `bits` bits that
+/// set `set` of them, corrected for boxes sharing a bit.
+fn linear_count(set: usize, bits: usize) -> usize {
+    let free = (bits - set.min(bits - 1)) as f64 / bits as f64;
+    (-(bits as f64) * free.ln()).round() as usize
+}
+
+/// Number of boxes of one size holding at least one particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxCount {
+    pub size: f32,
+    /// Estimated from the bits of the hash table they set.
+    pub boxes: usize,
+}
+
+/// Fraction of particle pairs closer than `radius`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorrelationSum {
+    pub radius: f32,
+    pub sum: f64,
+    /// Number of pairs closer than `radius`.
+    pub pairs: u64,
+}
+
+/// Least squares line through the points of a log-log plot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    pub slope: f64,
+    pub intercept: f64,
+    /// Coefficient of determination, 1 for points on a straight line.
+    pub r_squared: f64,
+    /// Number of points fitted.
+    pub points: usize,
+}
+
+impl Fit {
+    /// Fit of `y` against `x`, `None` with less than two distinct `x`.
+    pub fn new(points: &[(f64, f64)]) -> Option<Self> {
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        let syy: f64 = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+        if points.len() < 2 || sxx <= 0. {
+            return None;
+        }
+        let slope = sxy / sxx;
+        Some(Self {
+            slope,
+            intercept: mean_y - slope * mean_x,
+            r_squared: if syy > 0. {
+                sxy * sxy / (sxx * syy)
+            } else {
+                1.
+            },
+            points: points.len(),
+        })
+    }
+}
+
+/// Box counts of `points` for every level of [`BoxGrid`] over the bounding box `min`..`max`,
+/// hashed into a table of `bits` bits. Mirrors `dimension.wgsl`.
+pub fn box_counts(points: &[Vec3], min: Vec3, max: Vec3, bits: usize) -> Vec<BoxCount> {
+    (1..=LEVELS)
+        .map(|level| {
+            let grid = BoxGrid::new(min, max, level);
+            let mask = bits as u32 - 1;
+            let set: HashSet<u32> = points.iter().map(|p| hash(grid.key(*p)) & mask).collect();
+            BoxCount {
+                size: grid.size(),
+                boxes: linear_count(set.len(), bits),
+            }
+        })
+        .collect()
+}
+
+/// Box-counting dimension, the slope of `ln N(ε)` against `ln 1/ε`, fitted over the box
+/// sizes that are neither too coarse nor too fine for `particles` particles. Finite samples
+/// leave the finer boxes underpopulated, so it comes out somewhat low.
+pub fn box_counting_dimension(counts: &[BoxCount], particles: usize) -> Option<Fit> {
+    let points: Vec<(f64, f64)> = counts
+        .iter()
+        .filter(|c| c.boxes >= MIN_BOXES && c.boxes * PARTICLES_PER_BOX <= particles)
+        .map(|c| (-(c.size as f64).ln(), (c.boxes as f64).ln()))
+        .collect();
+    Fit::new(&points)
+}
+
+/// Radii the correlation sums are taken at, from an eighth of the longest side `extent` of
+/// the bounding box down.
+pub fn radii(extent: f32) -> Vec<f32> {
+    (0..RADII)
+        .map(|k| extent / 8. * 0.5f32.powf(k as f32 / 2.))
+        .collect()
+}
+
+/// Grassberger–Procaccia correlation sums C(r) of `points` at every radius in `radii`: the
+/// fraction of pairs closer than r. Only a subset of `samples` particles drawn with `rng` is
+/// paired, the first `references` of them with all others of the subset, and neighbours are
+/// found through a grid of cells as wide as the largest radius.
+pub fn correlation_sums(
+    points: &[Vec3],
+    radii: &[f32],
+    samples: usize,
+    references: usize,
+    rng: &mut impl Rng,
+) -> Vec<CorrelationSum> {
+    let subset: Vec<Vec3> = index::sample(rng, points.len(), samples.min(points.len()))
+        .iter()
+        .map(|i| points[i])
+        .collect();
+    let max_radius = radii.iter().copied().fold(0., f32::max);
+    let references = references.min(subset.len());
+    if subset.len() < 2 || max_radius <= 0. {
+        return radii
+            .iter()
+            .map(|&radius| CorrelationSum {
+                radius,
+                sum: 0.,
+                pairs: 0,
+            })
+            .collect();
+    }
+    let cell = |p: Vec3| (p / max_radius).floor().as_ivec3();
+    let mut grid: HashMap<IVec3, Vec<u32>> = HashMap::new();
+    for (i, p) in subset.iter().enumerate() {
+        grid.entry(cell(*p)).or_default().push(i as u32);
+    }
+    let squared: Vec<f32> = radii.iter().map(|r| r * r).collect();
+    let pairs = (0..references)
+        .into_par_iter()
+        .map(|i| {
+            let (p, home) = (subset[i], cell(subset[i]));
+            let mut pairs = vec![0u64; radii.len()];
+            for offset in NEIGHBOURS {
+                let Some(neighbours) = grid.get(&(home + offset)) else {
+                    continue;
+                };
+                for &j in neighbours.iter().filter(|j| **j as usize != i) {
+                    let d = p.distance_squared(subset[j as usize]);
+                    for (count, r) in pairs.iter_mut().zip(&squared) {
+                        *count += (d < *r) as u64;
+                    }
+                }
+            }
+            pairs
+        })
+        .reduce(
+            || vec![0; radii.len()],
+            |a, b| a.iter().zip(&b).map(|(a, b)| a + b).collect(),
+        );
+    let total = references as f64 * (subset.len() - 1) as f64;
+    radii
+        .iter()
+        .zip(pairs)
+        .map(|(&radius, pairs)| CorrelationSum {
+            radius,
+            sum: pairs as f64 / total,
+            pairs,
+        })
+        .collect()
+}
+
+/// Correlation dimension, the slope of `ln C(r)` against `ln r`, fitted over the radii small
+/// against the attractor that still have enough pairs.
+pub fn correlation_dimension(sums: &[CorrelationSum]) -> Option<Fit> {
+    let points: Vec<(f64, f64)> = sums
+        .iter()
+        .filter(|s| s.sum <= MAX_CORRELATION && s.pairs >= MIN_PAIRS)
+        .map(|s| ((s.radius as f64).ln(), s.sum.ln()))
+        .collect();
+    Fit::new(&points)
+}
+
+/// Box counts and correlation sums of the particles with the dimensions fitted to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionReport {
+    pub particles: usize,
+    pub box_counts: Vec<BoxCount>,
+    pub box_counting: Option<Fit>,
+    pub correlation_sums: Vec<CorrelationSum>,
+    pub correlation: Option<Fit>,
+}
+
+impl DimensionReport {
+    /// Fits both dimensions of `particles` particles.
+    pub fn new(
+        particles: usize,
+        box_counts: Vec<BoxCount>,
+        correlation_sums: Vec<CorrelationSum>,
+    ) -> Self {
+        Self {
+            particles,
+            box_counting: box_counting_dimension(&box_counts, particles),
+            box_counts,
+            correlation: correlation_dimension(&correlation_sums),
+            correlation_sums,
+        }
+    }
+}
+
+impl fmt::Display for DimensionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fit = |fit: &Option<Fit>| match fit {
+            Some(fit) => format!(
+                "{:.3} (R² {:.4}, {} points)",
+                fit.slope, fit.r_squared, fit.points
+            ),
+            None => "not enough points to fit".to_owned(),
+        };
+        writeln!(f, "# box counts of {} particles", self.particles)?;
+        writeln!(f, "size,boxes,log_inverse_size,log_boxes")?;
+        for c in &self.box_counts {
+            let (size, boxes) = (c.size as f64, c.boxes as f64);
+            writeln!(f, "{},{},{},{}", c.size, c.boxes, -size.ln(), boxes.ln())?;
+        }
+        writeln!(f, "# box-counting dimension: {}", fit(&self.box_counting))?;
+        writeln!(f, "# correlation sums")?;
+        writeln!(f, "radius,sum,pairs,log_radius,log_sum")?;
+        for s in &self.correlation_sums {
+            let radius = s.radius as f64;
+            let (log_radius, log_sum) = (radius.ln(), s.sum.ln());
+            writeln!(
+                f,
+                "{},{},{},{log_radius},{log_sum}",
+                s.radius, s.sum, s.pairs
+            )?;
+        }
+        write!(f, "# correlation dimension: {}", fit(&self.correlation))
+    }
+}
+
+/// Counts the occupied boxes of every level of [`BoxGrid`] on the GPU: every particle sets the
+/// bit its box hashes to in a table, and the bits newly set are counted.
+pub struct BoxCountState {
+    pipeline: ComputePipeline,
+    bind_group: BindGroup,
+    uniform_buffer: Buffer,
+    table_buffer: Buffer,
+    bits: usize,
+    /// The bits set in every level as `u32`, written by [`BoxCountState::count`].
+    pub bits_buffer: Buffer,
+}
+
+impl BoxCountState {
+    pub fn new(device: &Device, instances: &InstancesVec, particles: usize) -> Self {
+        let bits = table_bits(particles);
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Box Count Uniform Buffer"),
+            size: std::mem::size_of::<BoxCountUniform>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let table_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Box Count Table Buffer"),
+            size: (bits / 8) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bits_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Box Count Bits Buffer"),
+            size: (LEVELS * std::mem::size_of::<u32>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Box Count Bind Group Layout"),
+            entries: &BIND_GROUP_LAYOUT_ENTRIES,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Box Count Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: instances.buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: table_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: bits_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Box Count Shader"),
+            source: ShaderSource::Wgsl(DIMENSION_WGSL.into()),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Box Count Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Box Count Pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "cs_box_count",
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            uniform_buffer,
+            table_buffer,
+            bits,
+            bits_buffer,
+        }
+    }
+
+    /// Counts the boxes of every level over the bounding box `min`..`max` holding one of the
+    /// first `count` particles into the bits buffer.
+    pub fn count(&self, device: &Device, queue: &Queue, min: Vec3, max: Vec3, count: usize) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        encoder.clear_buffer(&self.bits_buffer, 0, None);
+        queue.submit(Some(encoder.finish()));
+        // * ONE SUBMISSION PER LEVEL, SO EVERY DISPATCH SEES ITS OWN UNIFORM
+        for level in 1..=LEVELS {
+            let grid = BoxGrid::new(min, max, level);
+            let uniform = BoxCountUniform {
+                origin: grid.origin.to_array(),
+                scale: grid.scale,
+                cells: grid.cells,
+                count: count as u32,
+                mask: self.bits as u32 - 1,
+                level: level as u32 - 1,
+            };
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+            encoder.clear_buffer(&self.table_buffer, 0, None);
+            {
+                let mut compute_pass =
+                    encoder.begin_compute_pass(&ComputePassDescriptor::default());
+                compute_pass.set_pipeline(&self.pipeline);
+                compute_pass.set_bind_group(0, &self.bind_group, &[]);
+                compute_pass.dispatch_workgroups(WORKGROUPS as u32, 1, 1);
+            }
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    /// Box counts from the bytes of the bits buffer.
+    pub fn read(&self, min: Vec3, max: Vec3, bytes: &[u8]) -> Vec<BoxCount> {
+        bytemuck::cast_slice::<u8, u32>(bytes)
+            .iter()
+            .enumerate()
+            .map(|(i, &set)| BoxCount {
+                size: BoxGrid::new(min, max, i + 1).size(),
+                boxes: linear_count(set as usize, self.bits),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,61 @@
+struct Instance {
+    pos: vec3<f32>,
+    rng: u32,
+    color: vec3<f32>,
+    age: u32,
+}
+
+// * ONE GRID OF cells^3 BOXES OF SIDE 1 / scale, STARTING AT origin
+struct BoxCountUniform {
+    origin: vec3<f32>,
+    scale: f32,
+    cells: u32,
+    count: u32,
+    // * THE TABLE HAS mask + 1 BITS
+    mask: u32,
+    level: u32,
+}
+
+// * SAME AS IN dimension.rs
+const WORKGROUP_SIZE = 64u;
+const WORKGROUPS = 256u;
+const LEVELS = 10u;
+
+// * SAME AS IN emitter.rs
+const DEAD = 0xffffffffu;
+
+@group(0) @binding(0)
+var<storage, read> instances: array<Instance>;
+@group(0) @binding(1)
+var<uniform> uniforms: BoxCountUniform;
+// * ONE BIT PER HASH OF A BOX, CLEARED BEFORE EVERY LEVEL
+@group(0) @binding(2)
+var<storage, read_write> table: array<atomic<u32>>;
+// * BITS SET IN EVERY LEVEL
+@group(0) @binding(3)
+var<storage, read_write> bits: array<atomic<u32>, LEVELS>;
+
+// * PCG HASH
+fn hash(key: u32) -> u32 {
+    let state = key * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+@compute
+@workgroup_size(64)
+fn cs_box_count(@builtin(global_invocation_id) id: vec3<u32>) {
+    for (var i = id.x; i < uniforms.count; i += WORKGROUPS * WORKGROUP_SIZE) {
+        if instances[i].age == DEAD {
+            continue;
+        }
+        let offset = floor((instances[i].pos - uniforms.origin) * uniforms.scale);
+        let cell = vec3<u32>(clamp(offset, vec3<f32>(0.0), vec3<f32>(f32(uniforms.cells - 1u))));
+        let h = hash(cell.x + uniforms.cells * (cell.y + uniforms.cells * cell.z)) & uniforms.mask;
+        let bit = 1u << (h & 31u);
+        // * ONLY THE FIRST PARTICLE IN A BOX, OR IN A BOX WITH THE SAME HASH, SETS THE BIT
+        if (atomicOr(&table[h >> 5u], bit) & bit) == 0u {
+            atomicAdd(&bits[uniforms.level], 1u);
+        }
+    }
+}
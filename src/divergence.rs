@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use wgpu_lorenz::{scene::SceneError, twins::Divergence, Config, Error, Simulation};
+
+use crate::assimilation::Log;
+
+/// Runs the twins of the scene headless for `steps` steps and prints their mean separation
+/// before the first and after every step as CSV, or writes it to `log`.
+pub fn run(config: Config, steps: u32, log: Option<&Path>) -> Result<(), Error> {
+    if config.twins.is_none() {
+        return Err(SceneError::Invalid {
+            field: "twins",
+            reason: "`--diverge` needs a [twins] table in the scene or `--twins`".to_owned(),
+        }
+        .into());
+    }
+    let mut sim = Simulation::new(config)?;
+    let mut records = vec![record(&sim)];
+    for _ in 0..steps {
+        sim.step(1);
+        records.push(record(&sim));
+    }
+    match log {
+        Some(path) => Log::create(path, Divergence::CSV_HEADER)?.write(&records)?,
+        None => {
+            println!("{}", Divergence::CSV_HEADER);
+            for record in &records {
+                println!("{record}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Mean separation of the twins of `sim` now.
+pub fn record(sim: &Simulation) -> Divergence {
+    Divergence {
+        step: sim.draw_state().step,
+        time: sim.time(),
+        separation: sim.twin_separation(),
+    }
+}
@@ -15,10 +15,13 @@ struct CameraUniform {
 struct InstanceInput {
     @location(1) @align(16) pos: vec3<f32>,
     @location(2) @align(16) color: vec3<f32>,
+    @location(3) age: u32,
 }
 
 struct Config {
     smooth_shading: u32,
+    exposure: f32,
+    aspect_ratio: f32,
 }
 
 @group(0) @binding(0)
@@ -27,16 +30,22 @@ var<uniform> camera: CameraUniform;
 @group(1) @binding(0)
 var<uniform> config: Config;
 
+const DEAD = 0xffffffffu;
+
 const POINT_RADIUS = 1.;
-const ASPECT_RATIO = 0.5625;
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput
 ) -> VertexOutput {
+    // * DEAD PARTICLES END UP OUTSIDE THE CLIP VOLUME AND ARE NEVER RASTERIZED
+    if instance.age == DEAD {
+        return VertexOutput(vec4<f32>(0., 0., 2., 1.), model.position, vec4<f32>(0.));
+    }
+
     let ppos = camera.view_proj * vec4<f32>(instance.pos, 1.0);
 
-    let pos = ppos + POINT_RADIUS * vec4<f32>(ASPECT_RATIO * model.position.x, model.position.y, 0., 0.);
+    let pos = ppos + POINT_RADIUS * vec4<f32>(config.aspect_ratio * model.position.x, model.position.y, 0., 0.);
 
     return VertexOutput(pos, model.position, vec4<f32>(instance.color, 1.0));
 }
@@ -56,3 +65,12 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
 }
+
+// * ADDED UP BY THE BLEND STATE, SO BUSY PIXELS GET BRIGHT
+@fragment
+fn fs_density(in: VertexOutput) -> @location(0) vec4<f32> {
+    if dot(in.model_position, in.model_position) >= 0.25 {
+        discard;
+    }
+    return vec4<f32>(config.exposure * in.color.rgb, 1.);
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use glam::Vec3;
+use serde::Deserialize;
+
+const PARTICLES: usize = 16;
+const DELAY: usize = 30;
+const HISTORY: usize = 2000;
+
+// * EMPIRICAL DEFAULTS FROM KENNEL ET AL. (1992)
+/// Distance ratio above which a neighbour counts as false.
+pub const FNN_TOLERANCE: f32 = 15.;
+
+/// Scalar measured of every recorded particle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Observable {
+    #[default]
+    X,
+    Y,
+    Z,
+}
+
+impl Observable {
+    pub fn value(self, p: Vec3) -> f32 {
+        p[self as usize]
+    }
+}
+
+/// Delay embedding settings, as read from the `[embedding]` table of a scene. The observable
+/// of the first `particles` particles is recorded every step and the attractor is rebuilt
+/// from the delay coordinates `(s(t), s(t - τ), s(t - 2τ))`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Embedding {
+    pub observable: Observable,
+    pub particles: usize,
+    /// Delay `τ`, in steps.
+    pub delay: usize,
+    /// Samples kept per particle.
+    pub history: usize,
+    /// Where the reconstruction is drawn, relative to the true attractor.
+    pub offset: [f32; 3],
+}
+
+impl Default for Embedding {
+    fn default() -> Self {
+        Self {
+            observable: Observable::X,
+            particles: PARTICLES,
+            delay: DELAY,
+            history: HISTORY,
+            offset: [80., 0., 0.],
+        }
+    }
+}
+
+/// Recent values of the observable of every recorded particle.
+pub struct DelayRecorder {
+    settings: Embedding,
+    series: Vec<VecDeque<f32>>,
+}
+
+impl DelayRecorder {
+    pub fn new(settings: Embedding) -> Self {
+        Self {
+            settings,
+            series: vec![VecDeque::with_capacity(settings.history); settings.particles],
+        }
+    }
+
+    /// Records the observable at `positions`, one per recorded particle, dropping the oldest
+    /// sample once the history is full.
+    pub fn push(&mut self, positions: &[Vec3]) {
+        for (series, p) in self.series.iter_mut().zip(positions) {
+            if series.len() == self.settings.history {
+                series.pop_front();
+            }
+            series.push_back(self.settings.observable.value(*p));
+        }
+    }
+
+    /// Number of recorded particles.
+    pub fn series_count(&self) -> usize {
+        self.series.len()
+    }
+
+    /// Recorded values of particle `index`, oldest first.
+    pub fn series(&self, index: usize) -> &VecDeque<f32> {
+        &self.series[index]
+    }
+
+    /// Delay vectors of every sample that is at least `2τ` old, moved by the offset. Oldest
+    /// first within each particle.
+    pub fn points(&self) -> Vec<Vec3> {
+        let tau = self.settings.delay;
+        let offset = Vec3::from_array(self.settings.offset);
+        self.series
+            .iter()
+            .flat_map(|s| {
+                (2 * tau..s.len()).map(move |t| Vec3::new(s[t], s[t - tau], s[t - 2 * tau]))
+            })
+            .map(|p| p + offset)
+            .collect()
+    }
+}
+
+/// Mutual information in nats between `series` and itself `delay` samples later, estimated
+/// from a `bins` × `bins` histogram.
+pub fn mutual_information(series: &[f32], delay: usize, bins: usize) -> f32 {
+    let pairs = series.len().saturating_sub(delay);
+    if pairs == 0 {
+        return 0.;
+    }
+    let (min, max) = series
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), s| {
+            (lo.min(*s), hi.max(*s))
+        });
+    let bin = |s: f32| (((s - min) / (max - min) * bins as f32) as usize).min(bins - 1);
+    let mut joint = vec![0usize; bins * bins];
+    let (mut a, mut b) = (vec![0usize; bins], vec![0usize; bins]);
+    for t in 0..pairs {
+        let (i, j) = (bin(series[t]), bin(series[t + delay]));
+        joint[i * bins + j] += 1;
+        a[i] += 1;
+        b[j] += 1;
+    }
+    let n = pairs as f32;
+    joint
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(k, count)| {
+            let p = *count as f32 / n;
+            let (pa, pb) = (a[k / bins] as f32 / n, b[k % bins] as f32 / n);
+            p * (p / (pa * pb)).ln()
+        })
+        .sum()
+}
+
+/// Index of the first local minimum of `values`, the usual choice of delay from a mutual
+/// information curve.
+pub fn first_minimum(values: &[f32]) -> Option<usize> {
+    (1..values.len().saturating_sub(1))
+        .find(|i| values[*i] < values[i - 1] && values[*i] <= values[i + 1])
+}
+
+/// Fraction of false nearest neighbours of the `dimension` dimensional delay vectors of
+/// `series`: neighbours that fly apart by more than `tolerance` times their distance once the
+/// next delay coordinate is added. Samples closer than `delay` in time are not considered
+/// neighbours. Quadratic in the length of `series`.
+pub fn false_nearest_neighbours(
+    series: &[f32],
+    delay: usize,
+    dimension: usize,
+    tolerance: f32,
+) -> f32 {
+    let count = series.len().saturating_sub(dimension * delay);
+    let vector = |t: usize| (0..dimension).map(move |k| series[t + k * delay]);
+    let mut checked = 0;
+    let mut false_neighbours = 0;
+    for t in 0..count {
+        let nearest = (0..count)
+            .filter(|u| t.abs_diff(*u) > delay)
+            .map(|u| {
+                let d: f32 = vector(t).zip(vector(u)).map(|(a, b)| (a - b).powi(2)).sum();
+                (u, d)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((u, d)) = nearest.filter(|(_, d)| *d > 0.) else {
+            continue;
+        };
+        checked += 1;
+        let next = series[t + dimension * delay] - series[u + dimension * delay];
+        if next.abs() > tolerance * d.sqrt() {
+            false_neighbours += 1;
+        }
+    }
+    if checked == 0 {
+        return 0.;
+    }
+    false_neighbours as f32 / checked as f32
+}
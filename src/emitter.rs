@@ -0,0 +1,71 @@
+use glam::Vec3;
+use serde::Deserialize;
+
+use crate::respawn::{uniform, unit_ball};
+
+/// Age of particles that are not alive. With an emitter every particle starts out dead.
+pub const DEAD: u32 = u32::MAX;
+
+const RATE: u32 = 100;
+const LIFETIME: u32 = 1000;
+
+/// Shape particles are emitted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EmitterShape {
+    /// Everything starts at `position`.
+    Point,
+    /// Uniform in the ball of `radius` around `position`.
+    Sphere,
+    /// Uniform on the segment from `position` to `end`.
+    Line,
+}
+
+/// Continuous source of particles, as read from the `[emitter]` table of a scene. Particles
+/// are taken from the pool in ring buffer order, overwriting the oldest ones once it is full.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Emitter {
+    pub shape: EmitterShape,
+    pub position: [f32; 3],
+    /// Other end of a `line` emitter.
+    pub end: [f32; 3],
+    /// Radius of a `sphere` emitter.
+    pub radius: f32,
+    /// Particles emitted per step.
+    pub rate: u32,
+    /// Steps a particle lives before it is taken out of the simulation.
+    pub lifetime: u32,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            shape: EmitterShape::Point,
+            position: [1., 1., 1.],
+            end: [1., 1., 1.],
+            radius: 1.,
+            rate: RATE,
+            lifetime: LIFETIME,
+        }
+    }
+}
+
+impl Emitter {
+    /// Draws the position of a new particle, advancing the particle's `rng`.
+    /// Mirrors `emitter_sample` in `compute.wgsl`.
+    pub fn sample(&self, rng: &mut u32) -> Vec3 {
+        let position = Vec3::from_array(self.position);
+        match self.shape {
+            EmitterShape::Point => position,
+            EmitterShape::Sphere => position + self.radius * unit_ball(rng),
+            EmitterShape::Line => position + (Vec3::from_array(self.end) - position) * uniform(rng),
+        }
+    }
+
+    /// Whether particle `index` of a pool of `len` is (re)born in the step in which the ring
+    /// buffer starts at `head`.
+    pub fn emits(&self, index: usize, head: usize, len: usize) -> bool {
+        (index + len - head) % len < self.rate as usize
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+
+use glam::{DMat3, DVec3, Mat3, Vec3};
+use serde::Deserialize;
+
+use crate::{
+    noise::{noise_key, noise_state},
+    respawn::gaussian,
+    Config, Simulation,
+};
+
+const INTERVAL: u32 = 25;
+const OBSERVATION_NOISE: f32 = 2.;
+
+// * OWN STREAMS, SO OBSERVATIONS AND PERTURBATIONS ARE INDEPENDENT OF THE MODEL NOISE
+const OBSERVATION_SALT: u64 = 0x2f4a_97c3_1e5b_d806;
+const PERTURBATION_SALT: u64 = 0x9b1d_5e37_c2a8_406f;
+
+/// Data assimilation settings, as read from the `[assimilation]` table of a scene. A hidden
+/// truth is observed with noise every `interval` steps and the particles, the ensemble, are
+/// pulled towards each observation by a stochastic ensemble Kalman filter.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Assimilation {
+    /// Steps between two observations.
+    pub interval: u32,
+    /// Standard deviation of the observation error, the same in every component.
+    pub observation_noise: f32,
+    /// Initial state of the truth.
+    pub truth: [f32; 3],
+}
+
+impl Default for Assimilation {
+    fn default() -> Self {
+        Self {
+            interval: INTERVAL,
+            observation_noise: OBSERVATION_NOISE,
+            truth: [1., 1., 20.],
+        }
+    }
+}
+
+/// Mean and covariance of the particles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ensemble {
+    pub mean: Vec3,
+    pub covariance: Mat3,
+}
+
+impl Ensemble {
+    /// Statistics of `points`, accumulated in `f64`.
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let mean = points.iter().map(|p| p.as_dvec3()).sum::<DVec3>() / points.len() as f64;
+        let center = mean.as_vec3();
+        let (sum, squares) = points
+            .iter()
+            .fold((DVec3::ZERO, DMat3::ZERO), |(sum, squares), p| {
+                let d = (*p - center).as_dvec3();
+                (sum + d, squares + outer(d, d))
+            });
+        Self::from_moments(center, points.len(), sum, squares)
+    }
+
+    /// Statistics from the sums of `d` and `d dᵀ` over `count` offsets `d = p - center`.
+    /// Shifting by a center close to the mean keeps the cancellation small.
+    pub(crate) fn from_moments(center: Vec3, count: usize, sum: DVec3, squares: DMat3) -> Self {
+        let n = count as f64;
+        let covariance = (squares - outer(sum, sum) * (1. / n)) * (1. / (n - 1.).max(1.));
+        Self {
+            mean: center + (sum / n).as_vec3(),
+            covariance: covariance.as_mat3(),
+        }
+    }
+
+    /// Root mean square error of the mean over the three components.
+    pub fn rmse(&self, truth: Vec3) -> f32 {
+        (self.mean - truth).length() / 3f32.sqrt()
+    }
+
+    /// Root of the mean variance, what the RMSE should be for a well calibrated ensemble.
+    pub fn spread(&self) -> f32 {
+        let c = &self.covariance;
+        ((c.x_axis.x + c.y_axis.y + c.z_axis.z) / 3.).sqrt()
+    }
+
+    /// Kalman gain `P (P + R)⁻¹` for observing every component with error `R = σ² I`.
+    pub fn gain(&self, observation_noise: f32) -> Mat3 {
+        let r = Mat3::from_diagonal(Vec3::splat(observation_noise * observation_noise));
+        self.covariance * (self.covariance + r).inverse()
+    }
+}
+
+fn outer(a: DVec3, b: DVec3) -> DMat3 {
+    DMat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+/// One analysis step, applied to every particle by [`Simulation::assimilate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Update {
+    pub observation: Vec3,
+    pub observation_noise: f32,
+    pub gain: Mat3,
+    /// Key of the stream the observation perturbations are drawn from.
+    pub key: u32,
+    /// Number of the analysis, so every one draws new perturbations.
+    pub cycle: u32,
+}
+
+impl Update {
+    /// Particle `index` at `p` after the analysis: it is moved towards its own perturbed copy
+    /// of the observation. Mirrors `cs_update` in `analysis.wgsl`.
+    pub fn apply(&self, index: usize, p: Vec3) -> Vec3 {
+        let mut rng = noise_state(self.key, index, self.cycle);
+        let e = Vec3::new(gaussian(&mut rng), gaussian(&mut rng), gaussian(&mut rng));
+        p + self.gain * (self.observation + self.observation_noise * e - p)
+    }
+}
+
+/// Errors of the ensemble before and after one analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record {
+    pub step: u32,
+    /// Model time, the sum of the step sizes.
+    pub time: f32,
+    pub forecast_rmse: f32,
+    pub forecast_spread: f32,
+    pub analysis_rmse: f32,
+    pub analysis_spread: f32,
+}
+
+impl Record {
+    /// Header of the CSV lines written by the `Display` impl.
+    pub const CSV_HEADER: &'static str =
+        "step,time,forecast_rmse,forecast_spread,analysis_rmse,analysis_spread";
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{},{}",
+            self.step,
+            self.time,
+            self.forecast_rmse,
+            self.forecast_spread,
+            self.analysis_rmse,
+            self.analysis_spread
+        )
+    }
+}
+
+/// Runs the truth alongside a [`Simulation`] and assimilates its observations.
+pub struct Filter {
+    settings: Assimilation,
+    truth: Vec3,
+    step: u32,
+    time: f32,
+    cycle: u32,
+    observation_key: u32,
+    perturbation_key: u32,
+}
+
+impl Filter {
+    /// Starts at the truth of `settings`, with observations drawn from [`Config::seed`].
+    pub fn new(settings: Assimilation, config: &Config) -> Self {
+        let seed = config.seed.unwrap_or_default();
+        Self {
+            settings,
+            truth: Vec3::from_array(settings.truth),
+            step: 0,
+            time: 0.,
+            cycle: 0,
+            observation_key: noise_key(seed ^ OBSERVATION_SALT),
+            perturbation_key: noise_key(seed ^ PERTURBATION_SALT),
+        }
+    }
+
+    /// Current state of the truth.
+    pub fn truth(&self) -> Vec3 {
+        self.truth
+    }
+
+    /// Advances the simulation and the truth by `steps` steps, with an analysis after every
+    /// `interval` of them. Returns a record per analysis.
+    pub fn run(&mut self, sim: &mut Simulation, steps: u32) -> Vec<Record> {
+        let interval = self.settings.interval;
+        let mut records = Vec::new();
+        let mut left = steps;
+        while left > 0 {
+            let chunk = left.min(interval - self.step % interval);
+            sim.step(chunk);
+            let config = sim.config();
+            for _ in 0..chunk {
+                self.truth = config.step_point(self.truth, self.time);
+                self.time += config.lorenz.step_size_factor * config.delta_time;
+            }
+            self.step += chunk;
+            left -= chunk;
+            if self.step.is_multiple_of(interval) {
+                records.push(self.analyse(sim));
+            }
+        }
+        records
+    }
+
+    fn analyse(&mut self, sim: &mut Simulation) -> Record {
+        let sigma = self.settings.observation_noise;
+        let mut rng = noise_state(self.observation_key, 0, self.cycle);
+        let error = Vec3::new(gaussian(&mut rng), gaussian(&mut rng), gaussian(&mut rng));
+        let forecast = sim.ensemble();
+        sim.assimilate(&Update {
+            observation: self.truth + sigma * error,
+            observation_noise: sigma,
+            gain: forecast.gain(sigma),
+            key: self.perturbation_key,
+            cycle: self.cycle,
+        });
+        let analysis = sim.ensemble();
+        self.cycle += 1;
+        Record {
+            step: self.step,
+            time: self.time,
+            forecast_rmse: forecast.rmse(self.truth),
+            forecast_spread: forecast.spread(),
+            analysis_rmse: analysis.rmse(self.truth),
+            analysis_spread: analysis.spread(),
+        }
+    }
+}
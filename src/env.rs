@@ -1,57 +1,36 @@
-use wgpu::{
-    Backends, Device, Instance, InstanceDescriptor, Queue, Surface, SurfaceConfiguration,
-    TextureUsages,
-};
+use std::sync::Arc;
+
+use wgpu::{Device, Queue, Surface, SurfaceConfiguration, TextureUsages};
 use winit::{
     dpi::PhysicalSize,
     event_loop::EventLoop,
     window::{Window, WindowBuilder},
 };
 
+use wgpu_lorenz::{error::Error, gpu::find_device};
+
 pub struct Environment {
     pub surface: Surface,
-    pub device: Device,
-    pub queue: Queue,
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
     pub config: SurfaceConfiguration,
     pub window: Window,
     pub cursor_grab: bool,
 }
 
-const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize {
-    width: 1600,
-    height: 900,
-};
-
 impl Environment {
-    pub async fn new(event_loop: &EventLoop<()>) -> Self {
+    pub async fn new(
+        event_loop: &EventLoop<()>,
+        window_size: PhysicalSize<u32>,
+    ) -> Result<Self, Error> {
         // * CREATE CREATE WINDOW
-        let window_builder = WindowBuilder::new().with_inner_size(WINDOW_SIZE);
-        let window = window_builder.build(event_loop).unwrap();
-
-        // * CREATE INSTANCE
-        let instance = Instance::new(InstanceDescriptor {
-            backends: Backends::VULKAN,
-            ..Default::default()
-        });
+        let window_builder = WindowBuilder::new().with_inner_size(window_size);
+        let window = window_builder.build(event_loop).map_err(Error::Window)?;
 
-        // * CREATE SURFACE (unconfigured)
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        // * CREATE INSTANCE, SURFACE (unconfigured), ADAPTER, DEVICE & QUEUE
+        let (surface, adapter, device, queue) = find_device(Some(&window)).await?;
+        let surface = surface.expect("a surface is created whenever a window is given");
 
-        // * CREATE ADAPTER
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptionsBase {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .unwrap();
-        // dbg!(adapter.limits());
-        // * CREATE DEVICE & QUEUE
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
-            .await
-            .unwrap();
         // * CONFIGURE SURFACE
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -71,13 +50,22 @@ impl Environment {
         };
         surface.configure(&device, &config);
 
-        Self {
+        Ok(Self {
             surface,
-            device,
-            queue,
+            device: Arc::new(device),
+            queue: Arc::new(queue),
             config,
             window,
             cursor_grab: false,
+        })
+    }
+
+    /// Reconfigures the surface, e.g. after a resize or when it was lost.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.config.width = size.width;
+            self.config.height = size.height;
         }
+        self.surface.configure(&self.device, &self.config);
     }
 }
@@ -0,0 +1,635 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use glam::Vec3;
+use serde::Deserialize;
+use wide::f32x8;
+
+// * LARGEST INTEGER EXPONENT THAT IS EXPANDED INTO MULTIPLICATIONS
+const MAX_INTEGER_POWER: f32 = 16.;
+
+const VARIABLES: [&str; 5] = ["x", "y", "z", "t", "pi"];
+
+/// A user-defined vector field, as read from the `[custom]` table of a scene. The expressions
+/// may use `x`, `y`, `z`, the time `t`, `pi`, the parameters, `+ - * / ^`, parentheses and
+/// the functions `sin cos tan asin acos atan sinh cosh tanh exp log sqrt abs sign floor min
+/// max pow`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CustomSystem {
+    pub dx: String,
+    pub dy: String,
+    pub dz: String,
+    pub parameters: BTreeMap<String, f32>,
+}
+
+impl Default for CustomSystem {
+    fn default() -> Self {
+        Self {
+            dx: "sigma * (y - x)".to_owned(),
+            dy: "x * (rho - z) - y".to_owned(),
+            dz: "x * y - beta * z".to_owned(),
+            parameters: BTreeMap::from([
+                ("sigma".to_owned(), 10.),
+                ("rho".to_owned(), 28.),
+                ("beta".to_owned(), 8. / 3.),
+            ]),
+        }
+    }
+}
+
+/// Where and why an expression could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// `dx`, `dy`, `dz` or `parameters`.
+    pub component: &'static str,
+    pub expression: String,
+    /// Character the error was found at, counting from 0.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at column {}\n  {}\n  {}^",
+            self.message,
+            self.column + 1,
+            self.expression,
+            " ".repeat(self.column)
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Log,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Min,
+    Max,
+}
+
+impl Func {
+    const ALL: [Func; 17] = [
+        Func::Sin,
+        Func::Cos,
+        Func::Tan,
+        Func::Asin,
+        Func::Acos,
+        Func::Atan,
+        Func::Sinh,
+        Func::Cosh,
+        Func::Tanh,
+        Func::Exp,
+        Func::Log,
+        Func::Sqrt,
+        Func::Abs,
+        Func::Sign,
+        Func::Floor,
+        Func::Min,
+        Func::Max,
+    ];
+
+    /// Name in expressions, the same as in WGSL.
+    fn name(self) -> &'static str {
+        match self {
+            Func::Sin => "sin",
+            Func::Cos => "cos",
+            Func::Tan => "tan",
+            Func::Asin => "asin",
+            Func::Acos => "acos",
+            Func::Atan => "atan",
+            Func::Sinh => "sinh",
+            Func::Cosh => "cosh",
+            Func::Tanh => "tanh",
+            Func::Exp => "exp",
+            Func::Log => "log",
+            Func::Sqrt => "sqrt",
+            Func::Abs => "abs",
+            Func::Sign => "sign",
+            Func::Floor => "floor",
+            Func::Min => "min",
+            Func::Max => "max",
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Func::Min | Func::Max => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            Func::Sin => a.sin(),
+            Func::Cos => a.cos(),
+            Func::Tan => a.tan(),
+            Func::Asin => a.asin(),
+            Func::Acos => a.acos(),
+            Func::Atan => a.atan(),
+            Func::Sinh => a.sinh(),
+            Func::Cosh => a.cosh(),
+            Func::Tanh => a.tanh(),
+            Func::Exp => a.exp(),
+            Func::Log => a.ln(),
+            Func::Sqrt => a.sqrt(),
+            Func::Abs => a.abs(),
+            // * LIKE WGSL, NOT LIKE f32::signum
+            Func::Sign => {
+                if a > 0. {
+                    1.
+                } else if a < 0. {
+                    -1.
+                } else {
+                    0.
+                }
+            }
+            Func::Floor => a.floor(),
+            Func::Min => a.min(b),
+            Func::Max => a.max(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f32),
+    /// `x`, `y`, `z` or `t`.
+    Variable(usize),
+    Parameter(usize),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+/// The integer of `a ^ n` for a small integer literal `n`, which is expanded into
+/// multiplications so negative bases work like on paper.
+fn integer_power(exponent: &Expr) -> Option<i32> {
+    match exponent {
+        Expr::Number(n) if n.fract() == 0. && n.abs() <= MAX_INTEGER_POWER => Some(*n as i32),
+        _ => None,
+    }
+}
+
+/// What an expression is evaluated on, single values or eight SIMD lanes.
+trait Value:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    fn splat(v: f32) -> Self;
+    fn map(self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self;
+}
+
+impl Value for f32 {
+    fn splat(v: f32) -> Self {
+        v
+    }
+    fn map(self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+        f(self, other)
+    }
+}
+
+impl Value for f32x8 {
+    fn splat(v: f32) -> Self {
+        f32x8::splat(v)
+    }
+    fn map(self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+        let (a, b) = (self.to_array(), other.to_array());
+        f32x8::from(std::array::from_fn(|i| f(a[i], b[i])))
+    }
+}
+
+impl Expr {
+    fn eval<V: Value>(&self, variables: &[V; 4], parameters: &[f32]) -> V {
+        match self {
+            Expr::Number(n) => V::splat(*n),
+            Expr::Variable(i) => variables[*i],
+            Expr::Parameter(i) => V::splat(parameters[*i]),
+            Expr::Neg(a) => V::splat(0.) - a.eval(variables, parameters),
+            Expr::Binary(op, a, b) => {
+                let a = a.eval(variables, parameters);
+                if let (Op::Pow, Some(n)) = (op, integer_power(b)) {
+                    let product = (1..n.unsigned_abs()).fold(a, |p, _| p * a);
+                    return match n {
+                        0 => V::splat(1.),
+                        n if n < 0 => V::splat(1.) / product,
+                        _ => product,
+                    };
+                }
+                let b = b.eval(variables, parameters);
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                    Op::Pow => a.map(b, f32::powf),
+                }
+            }
+            Expr::Call(func, args) => {
+                let a = args[0].eval(variables, parameters);
+                let b = args.get(1).map_or(a, |b| b.eval(variables, parameters));
+                a.map(b, |a, b| func.apply(a, b))
+            }
+        }
+    }
+
+    fn wgsl(&self, parameters: &[f32]) -> String {
+        match self {
+            Expr::Number(n) => literal(*n),
+            Expr::Variable(i) => ["state.x", "state.y", "state.z", "time"][*i].to_owned(),
+            Expr::Parameter(i) => literal(parameters[*i]),
+            Expr::Neg(a) => format!("(-{})", a.wgsl(parameters)),
+            Expr::Binary(op, a, b) => {
+                let a = a.wgsl(parameters);
+                if let (Op::Pow, Some(n)) = (op, integer_power(b)) {
+                    let product = vec![a; n.unsigned_abs().max(1) as usize].join(" * ");
+                    return match n {
+                        0 => "1.0".to_owned(),
+                        n if n < 0 => format!("(1.0 / ({product}))"),
+                        _ => format!("({product})"),
+                    };
+                }
+                let b = b.wgsl(parameters);
+                match op {
+                    Op::Add => format!("({a} + {b})"),
+                    Op::Sub => format!("({a} - {b})"),
+                    Op::Mul => format!("({a} * {b})"),
+                    Op::Div => format!("({a} / {b})"),
+                    Op::Pow => format!("pow({a}, {b})"),
+                }
+            }
+            Expr::Call(func, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.wgsl(parameters)).collect();
+                format!("{}({})", func.name(), args.join(", "))
+            }
+        }
+    }
+}
+
+/// An `f32` literal WGSL accepts, parenthesized if negative.
+fn literal(v: f32) -> String {
+    let s = format!("{v:?}");
+    if v < 0. {
+        format!("({s})")
+    } else {
+        s
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Symbol(char),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "`{n}`"),
+            Token::Ident(name) => write!(f, "`{name}`"),
+            Token::Symbol(c) => write!(f, "`{c}`"),
+            Token::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    component: &'static str,
+    source: &'a str,
+    /// Tokens with the column they start at.
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    parameters: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        component: &'static str,
+        source: &'a str,
+        parameters: &'a [String],
+    ) -> Result<Self, ParseError> {
+        let mut parser = Self {
+            component,
+            source,
+            tokens: Vec::new(),
+            next: 0,
+            parameters,
+        };
+        let chars: Vec<char> = source.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let start = i;
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let token = if c.is_ascii_digit() || c == '.' {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // * EXPONENT, ONLY IF DIGITS FOLLOW
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    let mut j = i + 1;
+                    if j < chars.len() && matches!(chars[j], '+' | '-') {
+                        j += 1;
+                    }
+                    if j < chars.len() && chars[j].is_ascii_digit() {
+                        i = j;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                match text.parse::<f32>() {
+                    Ok(n) if n.is_finite() => Token::Number(n),
+                    _ => return Err(parser.error(start, format!("invalid number `{text}`"))),
+                }
+            } else if c.is_alphabetic() || c == '_' {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            } else if "+-*/^(),".contains(c) {
+                i += 1;
+                Token::Symbol(c)
+            } else {
+                return Err(parser.error(start, format!("unexpected character `{c}`")));
+            };
+            parser.tokens.push((token, start));
+        }
+        parser.tokens.push((Token::End, chars.len()));
+        Ok(parser)
+    }
+
+    fn error(&self, column: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            component: self.component,
+            expression: self.source.to_owned(),
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.next].clone();
+        if token.0 != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ParseError> {
+        match self.advance() {
+            (Token::Symbol(c), _) if c == symbol => Ok(()),
+            (token, column) => {
+                Err(self.error(column, format!("expected `{symbol}`, found {token}")))
+            }
+        }
+    }
+
+    /// The whole input as one expression.
+    fn parse(mut self) -> Result<Expr, ParseError> {
+        let expr = self.sum()?;
+        match self.advance() {
+            (Token::End, _) => Ok(expr),
+            (token, column) => Err(self.error(
+                column,
+                format!("unexpected {token} after a complete expression"),
+            )),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Token::Symbol('+') => Op::Add,
+                Token::Symbol('-') => Op::Sub,
+                _ => return Ok(expr),
+            };
+            self.advance();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Symbol('*') => Op::Mul,
+                Token::Symbol('/') => Op::Div,
+                _ => return Ok(expr),
+            };
+            self.advance();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    // * `-a ^ b` IS `-(a ^ b)`
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == &Token::Symbol('-') {
+            self.advance();
+            return Ok(match self.unary()? {
+                Expr::Number(n) => Expr::Number(-n),
+                expr => Expr::Neg(Box::new(expr)),
+            });
+        }
+        self.power()
+    }
+
+    // * RIGHT ASSOCIATIVE, `a ^ b ^ c` IS `a ^ (b ^ c)`
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+        if self.peek() == &Token::Symbol('^') {
+            self.advance();
+            return Ok(Expr::Binary(
+                Op::Pow,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.advance() {
+            (Token::Number(n), _) => Ok(Expr::Number(n)),
+            (Token::Symbol('('), _) => {
+                let expr = self.sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            (Token::Ident(name), column) => {
+                if self.peek() == &Token::Symbol('(') {
+                    return self.call(&name, column);
+                }
+                if let Some(i) = VARIABLES[..4].iter().position(|v| *v == name) {
+                    return Ok(Expr::Variable(i));
+                }
+                if name == "pi" {
+                    return Ok(Expr::Number(std::f32::consts::PI));
+                }
+                if let Some(i) = self.parameters.iter().position(|p| *p == name) {
+                    return Ok(Expr::Parameter(i));
+                }
+                if name == "pow" || Func::ALL.iter().any(|f| f.name() == name) {
+                    return Err(self.error(
+                        column,
+                        format!("`{name}` is a function, call it as `{name}(...)`"),
+                    ));
+                }
+                Err(self.error(
+                    column,
+                    format!("unknown variable `{name}`, expected x, y, z, t, pi or a parameter"),
+                ))
+            }
+            (token, column) => Err(self.error(
+                column,
+                format!("expected a number, variable, function or `(`, found {token}"),
+            )),
+        }
+    }
+
+    fn call(&mut self, name: &str, column: usize) -> Result<Expr, ParseError> {
+        self.advance();
+        let mut args = Vec::new();
+        if self.peek() != &Token::Symbol(')') {
+            args.push(self.sum()?);
+            while self.peek() == &Token::Symbol(',') {
+                self.advance();
+                args.push(self.sum()?);
+            }
+        }
+        self.expect(')')?;
+
+        let arity = |n: usize| if n == 1 { "1 argument" } else { "2 arguments" };
+        if name == "pow" {
+            let [a, b]: [Expr; 2] = args.try_into().map_err(|args: Vec<Expr>| {
+                self.error(
+                    column,
+                    format!("`pow` takes 2 arguments, got {}", args.len()),
+                )
+            })?;
+            return Ok(Expr::Binary(Op::Pow, Box::new(a), Box::new(b)));
+        }
+        let Some(func) = Func::ALL.iter().find(|f| f.name() == name) else {
+            return Err(self.error(column, format!("unknown function `{name}`")));
+        };
+        if args.len() != func.arity() {
+            return Err(self.error(
+                column,
+                format!("`{name}` takes {}, got {}", arity(func.arity()), args.len()),
+            ));
+        }
+        Ok(Expr::Call(*func, args))
+    }
+}
+
+/// A [`CustomSystem`] parsed into a vector field, evaluated on the CPU or turned into WGSL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equations {
+    components: [Expr; 3],
+    parameters: Vec<f32>,
+}
+
+impl Equations {
+    pub fn parse(system: &CustomSystem) -> Result<Self, ParseError> {
+        let names: Vec<String> = system.parameters.keys().cloned().collect();
+        for name in &names {
+            let error = |message: String| ParseError {
+                component: "parameters",
+                expression: name.clone(),
+                column: 0,
+                message,
+            };
+            let mut chars = name.chars();
+            let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && chars.all(|c| c.is_alphanumeric() || c == '_');
+            if !valid {
+                return Err(error(format!("`{name}` is not a valid parameter name")));
+            }
+            if VARIABLES.contains(&name.as_str())
+                || name == "pow"
+                || Func::ALL.iter().any(|f| f.name() == name)
+            {
+                return Err(error(format!("`{name}` is reserved")));
+            }
+        }
+        let parse = |component, source| Parser::new(component, source, &names)?.parse();
+        Ok(Self {
+            components: [
+                parse("dx", &system.dx)?,
+                parse("dy", &system.dy)?,
+                parse("dz", &system.dz)?,
+            ],
+            parameters: system.parameters.values().copied().collect(),
+        })
+    }
+
+    /// Velocity at `p` and time `t`.
+    pub fn velocity(&self, p: Vec3, t: f32) -> Vec3 {
+        let variables = [p.x, p.y, p.z, t];
+        Vec3::from_array(
+            self.components
+                .each_ref()
+                .map(|c| c.eval(&variables, &self.parameters)),
+        )
+    }
+
+    /// Velocity of eight points at once, with the same operations as
+    /// [`Equations::velocity`].
+    pub(crate) fn velocity_x8(&self, p: [f32x8; 3], t: f32) -> [f32x8; 3] {
+        let variables = [p[0], p[1], p[2], f32x8::splat(t)];
+        self.components
+            .each_ref()
+            .map(|c| c.eval(&variables, &self.parameters))
+    }
+
+    /// WGSL `lorenz_vel` computing this field, to be spliced into `compute.wgsl`. The
+    /// parameters are baked in as constants and `t` is the private `time` of the shader.
+    pub fn wgsl(&self) -> String {
+        let [dx, dy, dz] = self.components.each_ref().map(|c| c.wgsl(&self.parameters));
+        format!(
+            "// * GENERATED FROM THE [custom] EXPRESSIONS OF THE SCENE\n\
+             fn lorenz_vel(lorenz_config: LorenzConfig, state: vec3<f32>) -> vec3<f32> {{\n    \
+                 return vec3<f32>(\n        {dx},\n        {dy},\n        {dz},\n    );\n\
+             }}\n"
+        )
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+
+use glam::{DMat3, DVec3, Mat3, Vec3};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{config::Config, lorenz::LorenzConfig, scene::System};
+
+/// Starting points of the Newton iterations for custom systems.
+pub const STARTS: usize = 512;
+const MAX_ITERATIONS: usize = 60;
+// * A VELOCITY THIS SMALL IS AS CLOSE TO ZERO AS AN f32 FIELD GETS
+const TOLERANCE: f64 = 1e-4;
+// * EIGENVALUES WITH A REAL PART THIS SMALL RELATIVE TO THE LARGEST ONE ARE TAKEN AS ZERO
+const NEUTRAL: f64 = 1e-4;
+
+/// One eigenvalue of the Jacobian, or a complex conjugate pair, with the directions it acts
+/// along.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// A real eigenvalue with its unit eigenvector.
+    Real { value: f64, direction: Vec3 },
+    /// A complex pair `re ± i im` with an orthonormal basis of the plane it rotates in.
+    Complex { re: f64, im: f64, plane: [Vec3; 2] },
+}
+
+impl Mode {
+    /// Real part of the eigenvalue, the rate nearby trajectories grow or shrink at.
+    pub fn growth(&self) -> f64 {
+        match *self {
+            Mode::Real { value, .. } => value,
+            Mode::Complex { re, .. } => re,
+        }
+    }
+
+    /// Number of eigenvalues, 1 or 2.
+    pub fn multiplicity(&self) -> usize {
+        match self {
+            Mode::Real { .. } => 1,
+            Mode::Complex { .. } => 2,
+        }
+    }
+}
+
+/// Linear stability of an equilibrium, from the signs of the real parts of its eigenvalues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// All eigenvalues real and negative.
+    Sink,
+    /// All real parts negative, with a complex pair.
+    SpiralSink,
+    /// All eigenvalues real and positive.
+    Source,
+    /// All real parts positive, with a complex pair.
+    SpiralSource,
+    /// Real eigenvalues of both signs.
+    Saddle,
+    /// Real parts of both signs, with a complex pair.
+    SaddleFocus,
+    /// A real part is zero, linearization does not decide.
+    NonHyperbolic,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Sink => "sink",
+            Kind::SpiralSink => "spiral sink",
+            Kind::Source => "source",
+            Kind::SpiralSource => "spiral source",
+            Kind::Saddle => "saddle",
+            Kind::SaddleFocus => "saddle-focus",
+            Kind::NonHyperbolic => "non-hyperbolic",
+        })
+    }
+}
+
+/// A point where the velocity vanishes, with the eigen-decomposition of the Jacobian there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equilibrium {
+    pub point: Vec3,
+    pub jacobian: Mat3,
+    /// Real modes first, each sorted by growth from the most unstable down.
+    pub modes: Vec<Mode>,
+    pub kind: Kind,
+}
+
+impl Equilibrium {
+    pub fn new(point: Vec3, jacobian: Mat3) -> Self {
+        let modes = modes(jacobian);
+        let largest = modes
+            .iter()
+            .map(|m| match *m {
+                Mode::Real { value, .. } => value.abs(),
+                Mode::Complex { re, im, .. } => re.hypot(im),
+            })
+            .fold(1f64, f64::max);
+        let neutral = modes.iter().any(|m| m.growth().abs() < NEUTRAL * largest);
+        let unstable = modes.iter().any(|m| m.growth() > 0.);
+        let stable = modes.iter().any(|m| m.growth() < 0.);
+        let spiral = modes.iter().any(|m| matches!(m, Mode::Complex { .. }));
+        let kind = match (neutral, unstable, stable, spiral) {
+            (true, ..) => Kind::NonHyperbolic,
+            (_, true, true, false) => Kind::Saddle,
+            (_, true, true, true) => Kind::SaddleFocus,
+            (_, false, _, false) => Kind::Sink,
+            (_, false, _, true) => Kind::SpiralSink,
+            (_, true, false, false) => Kind::Source,
+            (_, true, false, true) => Kind::SpiralSource,
+        };
+        Self {
+            point,
+            jacobian,
+            modes,
+            kind,
+        }
+    }
+
+    /// Dimension of the unstable manifold, the number of eigenvalues with positive real part.
+    pub fn unstable_dimension(&self) -> usize {
+        self.modes
+            .iter()
+            .filter(|m| m.growth() > 0.)
+            .map(Mode::multiplicity)
+            .sum()
+    }
+}
+
+impl fmt::Display for Equilibrium {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = self.point;
+        write!(f, "({:.4}, {:.4}, {:.4}) {}: λ =", p.x, p.y, p.z, self.kind)?;
+        for (i, mode) in self.modes.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            match *mode {
+                Mode::Real { value, .. } => write!(f, "{separator}{value:.4}")?,
+                Mode::Complex { re, im, .. } => write!(f, "{separator}{re:.4} ± {im:.4}i")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Eigenvalues of `jacobian` with their eigenvectors or, for a complex pair, the invariant
+/// plane. From the roots of the characteristic polynomial, in `f64`.
+pub fn modes(jacobian: Mat3) -> Vec<Mode> {
+    let j = jacobian.as_dmat3();
+    let trace = j.x_axis.x + j.y_axis.y + j.z_axis.z;
+    let minors = j.y_axis.y * j.z_axis.z - j.z_axis.y * j.y_axis.z + j.x_axis.x * j.z_axis.z
+        - j.z_axis.x * j.x_axis.z
+        + j.x_axis.x * j.y_axis.y
+        - j.y_axis.x * j.x_axis.y;
+    let det = j.determinant();
+    // * λ³ + aλ² + bλ + c, DEPRESSED TO t³ + pt + q WITH λ = t - a / 3
+    let (a, b, c) = (-trace, minors, -det);
+    let p = b - a * a / 3.;
+    let q = 2. * a.powi(3) / 27. - a * b / 3. + c;
+    let shift = -a / 3.;
+    let polish = |mut l: f64| {
+        for _ in 0..3 {
+            let slope = (3. * l + 2. * a) * l + b;
+            if slope.abs() > f64::EPSILON {
+                l -= (((l + a) * l + b) * l + c) / slope;
+            }
+        }
+        l
+    };
+    let discriminant = (q / 2.).powi(2) + (p / 3.).powi(3);
+    let mut modes = if discriminant > 0. {
+        let u = (-q / 2. + discriminant.sqrt()).cbrt();
+        let v = (-q / 2. - discriminant.sqrt()).cbrt();
+        let value = polish(u + v + shift);
+        vec![
+            Mode::Real {
+                value,
+                direction: eigenvector(j, value),
+            },
+            Mode::Complex {
+                re: -(u + v) / 2. + shift,
+                im: 3f64.sqrt() / 2. * (u - v).abs(),
+                plane: invariant_plane(j, value),
+            },
+        ]
+    } else {
+        let r = 2. * (-p / 3.).max(0.).sqrt();
+        let angle = if r > 0. {
+            (3. * q / (p * r)).clamp(-1., 1.).acos() / 3.
+        } else {
+            0.
+        };
+        (0..3)
+            .map(|k| {
+                let value =
+                    polish(r * (angle - 2. * std::f64::consts::PI * k as f64 / 3.).cos() + shift);
+                Mode::Real {
+                    value,
+                    direction: eigenvector(j, value),
+                }
+            })
+            .collect()
+    };
+    modes.sort_by(|m, n| {
+        (m.multiplicity(), -m.growth())
+            .partial_cmp(&(n.multiplicity(), -n.growth()))
+            .unwrap()
+    });
+    modes
+}
+
+/// Unit vector spanning the null space of `j - value I`.
+fn eigenvector(j: DMat3, value: f64) -> Vec3 {
+    let m = (j - DMat3::from_diagonal(DVec3::splat(value))).transpose();
+    let rows = [m.x_axis, m.y_axis, m.z_axis];
+    let candidate = [
+        rows[0].cross(rows[1]),
+        rows[0].cross(rows[2]),
+        rows[1].cross(rows[2]),
+    ]
+    .into_iter()
+    .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+    .unwrap();
+    if candidate.length_squared() > 0. {
+        return candidate.normalize().as_vec3();
+    }
+    // * A REPEATED EIGENVALUE: ANY VECTOR ORTHOGONAL TO THE ROWS
+    let row = rows
+        .into_iter()
+        .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .unwrap();
+    if row.length_squared() > 0. {
+        row.any_orthonormal_vector().as_vec3()
+    } else {
+        Vec3::X
+    }
+}
+
+/// Orthonormal basis of the plane a complex pair rotates in, the range of `j - value I` for
+/// the remaining real eigenvalue.
+fn invariant_plane(j: DMat3, value: f64) -> [Vec3; 2] {
+    let m = j - DMat3::from_diagonal(DVec3::splat(value));
+    let mut columns = [m.x_axis, m.y_axis, m.z_axis];
+    columns.sort_by(|a, b| b.length_squared().total_cmp(&a.length_squared()));
+    let u = columns[0].normalize();
+    let w = columns[1..]
+        .iter()
+        .map(|c| *c - c.dot(u) * u)
+        .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .unwrap();
+    let w = if w.length_squared() > 0. {
+        w.normalize()
+    } else {
+        u.any_orthonormal_vector()
+    };
+    [u.as_vec3(), w.as_vec3()]
+}
+
+/// Jacobian of the Lorenz system at `p`.
+pub fn lorenz_jacobian(lorenz: &LorenzConfig, p: Vec3) -> Mat3 {
+    let LorenzConfig {
+        rho, sigma, beta, ..
+    } = *lorenz;
+    Mat3::from_cols(
+        Vec3::new(-sigma, rho - p.z, p.y),
+        Vec3::new(sigma, -1., p.x),
+        Vec3::new(0., -p.x, -beta),
+    )
+}
+
+/// The equilibria of the Lorenz system: the origin and, for ρ > 1, the centers C± of the two
+/// lobes at `(±√(β(ρ - 1)), ±√(β(ρ - 1)), ρ - 1)`.
+pub fn lorenz(lorenz: &LorenzConfig) -> Vec<Equilibrium> {
+    let mut points = vec![Vec3::ZERO];
+    let squared = lorenz.beta * (lorenz.rho - 1.);
+    if squared > 0. {
+        let r = squared.sqrt();
+        points.push(Vec3::new(r, r, lorenz.rho - 1.));
+        points.push(Vec3::new(-r, -r, lorenz.rho - 1.));
+    }
+    points
+        .into_iter()
+        .map(|p| Equilibrium::new(p, lorenz_jacobian(lorenz, p)))
+        .collect()
+}
+
+/// Jacobian of `f` at `p` by central differences.
+pub fn numerical_jacobian(f: impl Fn(Vec3) -> Vec3, p: Vec3) -> Mat3 {
+    let h = 1e-2 * (1. + p.abs().max_element());
+    let column = |axis: Vec3| (f(p + h * axis) - f(p - h * axis)) / (2. * h);
+    Mat3::from_cols(column(Vec3::X), column(Vec3::Y), column(Vec3::Z))
+}
+
+/// Distinct zeros of `f` reached by damped Newton iterations from every point of `starts`.
+pub fn newton(f: impl Fn(Vec3) -> Vec3, starts: &[Vec3]) -> Vec<Vec3> {
+    let residual = |p: Vec3| f(p).as_dvec3().length();
+    let mut roots: Vec<Vec3> = Vec::new();
+    for start in starts {
+        let mut p = *start;
+        let mut r = residual(p);
+        for _ in 0..MAX_ITERATIONS {
+            if r < TOLERANCE {
+                break;
+            }
+            let j = numerical_jacobian(&f, p).as_dmat3();
+            if j.determinant().abs() < f64::EPSILON {
+                break;
+            }
+            let step = j.inverse() * f(p).as_dvec3();
+            // * HALVE THE STEP UNTIL THE RESIDUAL DROPS
+            let mut scale = 1.;
+            while scale > 1e-3 {
+                let next = (p.as_dvec3() - scale * step).as_vec3();
+                let next_r = residual(next);
+                if next_r < r {
+                    (p, r) = (next, next_r);
+                    break;
+                }
+                scale /= 2.;
+            }
+            if scale <= 1e-3 {
+                break;
+            }
+        }
+        let duplicate = roots
+            .iter()
+            .any(|q| q.distance(p) < 1e-3 * (1. + p.abs().max_element()));
+        if r < TOLERANCE && p.is_finite() && !duplicate {
+            roots.push(p);
+        }
+    }
+    roots
+}
+
+/// Equilibria of the flow of `config` at time `time`: exact for the Lorenz system, from
+/// [`STARTS`] Newton iterations seeded in the box `min`..`max` for a custom one. Empty for the
+/// other systems, which are not a flow of the drawn position.
+pub fn find(config: &Config, min: Vec3, max: Vec3, time: f32) -> Vec<Equilibrium> {
+    match (&config.custom, config.system) {
+        (Some(equations), _) => {
+            let f = |p| equations.velocity(p, time);
+            let mut rng = StdRng::seed_from_u64(config.seed.unwrap_or_default());
+            let starts: Vec<Vec3> = (0..STARTS)
+                .map(|_| min + (max - min) * Vec3::from_array(rng.gen()))
+                .collect();
+            let mut points = newton(f, &starts);
+            // * THE SAME ORDER FOR THE SAME POINTS
+            points.sort_by(|a, b| a.to_array().partial_cmp(&b.to_array()).unwrap());
+            points
+                .into_iter()
+                .map(|p| Equilibrium::new(p, numerical_jacobian(f, p)))
+                .collect()
+        }
+        (None, System::Lorenz) => lorenz(&config.lorenz),
+        (None, _) => Vec::new(),
+    }
+}
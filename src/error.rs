@@ -0,0 +1,78 @@
+use std::{fmt, io, path::PathBuf};
+
+use pollster::FutureExt;
+use wgpu::{Device, ErrorFilter};
+
+use crate::scene::SceneError;
+
+/// Everything that can go wrong while setting up a simulation or the viewer.
+#[derive(Debug)]
+pub enum Error {
+    Scene(SceneError),
+    Window(winit::error::OsError),
+    /// None of the backends produced an adapter that can present to the window.
+    NoAdapter,
+    Device(wgpu::RequestDeviceError),
+    TooManyParticles {
+        requested: usize,
+        max: usize,
+    },
+    OutOfMemory,
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A regression run produced different checksums than expected.
+    ChecksumMismatch {
+        mismatches: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Scene(e) => write!(f, "{e}"),
+            Error::Window(e) => write!(f, "could not create window: {e}"),
+            Error::NoAdapter => write!(f, "no graphics adapter found, not even a software one"),
+            Error::Device(e) => write!(f, "could not create device: {e}"),
+            Error::TooManyParticles { requested, max } => write!(
+                f,
+                "{requested} particles do not fit into a storage buffer on this device (max {max})"
+            ),
+            Error::OutOfMemory => write!(f, "the GPU ran out of memory"),
+            Error::Io { path, source } => write!(f, "`{}`: {source}", path.display()),
+            Error::ChecksumMismatch { mismatches } => {
+                write!(f, "{mismatches} checksums differ from the expected ones")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Scene(e) => Some(e),
+            Error::Window(e) => Some(e),
+            Error::Device(e) => Some(e),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<SceneError> for Error {
+    fn from(e: SceneError) -> Self {
+        Error::Scene(e)
+    }
+}
+
+/// Runs `create` inside a validation error scope, so invalid shaders are returned as errors
+/// instead of bringing down the device.
+pub fn checked<T>(device: &Device, create: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    device.push_error_scope(ErrorFilter::Validation);
+    let value = create();
+    match device.pop_error_scope().block_on() {
+        Some(e) => Err(e),
+        None => Ok(value),
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use glam::Vec3;
+use wgpu::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device,
+    Maintain, MapMode, Queue,
+};
+
+use wgpu_lorenz::instance::{DrawState, RawInstance};
+
+// * NUMBER OF READBACKS THAT MAY BE IN FLIGHT AT ONCE
+const STAGING_BUFFERS: usize = 3;
+
+const RAW_INSTANCE_SIZE: BufferAddress = std::mem::size_of::<RawInstance>() as BufferAddress;
+
+const F32_SIZE: BufferAddress = std::mem::size_of::<f32>() as BufferAddress;
+
+const STEP_OFFSET: BufferAddress = std::mem::offset_of!(DrawState, step) as BufferAddress;
+
+/// The followed particle as last read back.
+pub struct Particle {
+    pub position: Vec3,
+    /// Its state variables, empty for systems without states.
+    pub state: Vec<f32>,
+    /// The draw state step the state was read at.
+    pub step: u32,
+}
+
+/// Copies a single particle out of the instance buffer every frame without waiting on the GPU,
+/// along with its state variables and the step. The copies go into a small ring of staging buffers which are
+/// mapped asynchronously, so the particle handed to the camera is usually one or two frames old
+/// instead of stalling the queue.
+pub struct ParticleReadback {
+    pub index: usize,
+    state_size: BufferAddress,
+    staging: [Buffer; STAGING_BUFFERS],
+    // * THE PARTICLE EACH SLOT WAS REQUESTED FOR, WHILE ITS READBACK IS IN FLIGHT
+    in_flight: [Option<usize>; STAGING_BUFFERS],
+    next: usize,
+    sender: Sender<(usize, bool)>,
+    receiver: Receiver<(usize, bool)>,
+    latest: Option<Particle>,
+}
+
+impl ParticleReadback {
+    /// Reads back particle `index` with `state_dimensions` state variables, see
+    /// [`Config::state_dimensions`](wgpu_lorenz::Config::state_dimensions).
+    pub fn new(device: &Device, index: usize, state_dimensions: usize) -> Self {
+        let state_size = state_dimensions as BufferAddress * F32_SIZE;
+        let staging = std::array::from_fn(|_| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some("Particle Readback Buffer"),
+                size: RAW_INSTANCE_SIZE + state_size + F32_SIZE,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        let (sender, receiver) = channel();
+        Self {
+            index,
+            state_size,
+            staging,
+            in_flight: [None; STAGING_BUFFERS],
+            next: 0,
+            sender,
+            receiver,
+            latest: None,
+        }
+    }
+
+    /// Follows another particle. Readbacks still in flight for the old one are dropped.
+    pub fn set_index(&mut self, index: usize) {
+        if index != self.index {
+            self.index = index;
+            self.latest = None;
+        }
+    }
+
+    /// Queues a copy of the followed particle, its state and the step out of the draw buffer.
+    /// Skipped if the GPU has not caught up yet.
+    pub fn request(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        instance_buffer: &Buffer,
+        state_buffer: &Buffer,
+        draw_buffer: &Buffer,
+    ) {
+        let slot = self.next;
+        if self.in_flight[slot].is_some() {
+            return;
+        }
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Particle Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(
+            instance_buffer,
+            self.index as BufferAddress * RAW_INSTANCE_SIZE,
+            &self.staging[slot],
+            0,
+            RAW_INSTANCE_SIZE,
+        );
+        if self.state_size > 0 {
+            encoder.copy_buffer_to_buffer(
+                state_buffer,
+                self.index as BufferAddress * self.state_size,
+                &self.staging[slot],
+                RAW_INSTANCE_SIZE,
+                self.state_size,
+            );
+        }
+        encoder.copy_buffer_to_buffer(
+            draw_buffer,
+            STEP_OFFSET,
+            &self.staging[slot],
+            RAW_INSTANCE_SIZE + self.state_size,
+            F32_SIZE,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let sender = self.sender.clone();
+        self.staging[slot]
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = sender.send((slot, result.is_ok()));
+            });
+        self.in_flight[slot] = Some(self.index);
+        self.next = (slot + 1) % STAGING_BUFFERS;
+    }
+
+    /// Collects every finished readback and returns the most recent known state of the
+    /// followed particle.
+    pub fn poll(&mut self, device: &Device) -> Option<&Particle> {
+        device.poll(Maintain::Poll);
+        while let Ok((slot, ok)) = self.receiver.try_recv() {
+            if ok {
+                let particle = {
+                    let bytes = self.staging[slot].slice(..).get_mapped_range();
+                    let (raw, rest) = bytes.split_at(RAW_INSTANCE_SIZE as usize);
+                    let (state, step) = rest.split_at(self.state_size as usize);
+                    Particle {
+                        position: bytemuck::from_bytes::<RawInstance>(raw).position(),
+                        state: bytemuck::cast_slice(state).to_vec(),
+                        step: *bytemuck::from_bytes(step),
+                    }
+                };
+                self.staging[slot].unmap();
+                if self.in_flight[slot] == Some(self.index) {
+                    self.latest = Some(particle);
+                }
+            }
+            self.in_flight[slot] = None;
+        }
+        self.latest.as_ref()
+    }
+}
@@ -0,0 +1,24 @@
+use wgpu_lorenz::{
+    scene::{SceneError, System},
+    Config, Error, Simulation,
+};
+
+/// Runs the scene headless for `steps` steps, so the particles settle on the attractor, and
+/// prints the box counts and correlation sums of the particles with the dimensions fitted to
+/// them.
+pub fn run(config: Config, steps: u32) -> Result<(), Error> {
+    if config.system == System::Lorenz96 {
+        return Err(SceneError::Invalid {
+            field: "system",
+            reason: "`--dimension` is not supported by Lorenz-96".to_owned(),
+        }
+        .into());
+    }
+    let mut sim = Simulation::new(config)?;
+    sim.step(steps);
+    match sim.dimension() {
+        Some(report) => println!("{report}"),
+        None => println!("# no particle alive"),
+    }
+    Ok(())
+}
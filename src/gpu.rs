@@ -0,0 +1,89 @@
+use wgpu::{Adapter, Backends, Device, Instance, InstanceDescriptor, Limits, Queue, Surface};
+use winit::window::Window;
+
+use crate::error::Error;
+
+// * BACKENDS IN ORDER OF PREFERENCE, THE SOFTWARE FALLBACK ADAPTER IS TRIED LAST
+const BACKENDS: [Backends; 4] = [
+    Backends::VULKAN,
+    Backends::METAL,
+    Backends::DX12,
+    Backends::GL,
+];
+
+/// Tries every backend in turn until a device can be created on its adapter, and returns the
+/// adapter, the device and its queue together with a surface for `window` if one was given.
+/// The chosen adapter is logged.
+pub async fn find_device(
+    window: Option<&Window>,
+) -> Result<(Option<Surface>, Adapter, Device, Queue), Error> {
+    let mut error = Error::NoAdapter;
+    let attempts = BACKENDS
+        .iter()
+        .map(|backends| (*backends, false))
+        .chain(Some((Backends::all(), true)));
+    for (backends, force_fallback_adapter) in attempts {
+        let instance = Instance::new(InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let surface = match window.map(|window| unsafe { instance.create_surface(window) }) {
+            Some(Ok(surface)) => Some(surface),
+            Some(Err(e)) => {
+                println!("No {backends:?} surface: {e}");
+                continue;
+            }
+            None => None,
+        };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptionsBase {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter,
+                compatible_surface: surface.as_ref(),
+            })
+            .await;
+        match adapter {
+            Some(adapter) => {
+                let info = adapter.get_info();
+                match request_device(&adapter).await {
+                    Ok((device, queue)) => {
+                        println!(
+                            "Using {} ({:?}, {:?})",
+                            info.name, info.backend, info.device_type
+                        );
+                        return Ok((surface, adapter, device, queue));
+                    }
+                    Err(e) => {
+                        println!("No {:?} device on {}: {e}", info.backend, info.name);
+                        error = e;
+                    }
+                }
+            }
+            None if force_fallback_adapter => println!("No fallback adapter"),
+            None => println!("No {backends:?} adapter"),
+        }
+    }
+    Err(error)
+}
+
+/// Requests a device with the lowest limits we get away with, raised to what the adapter
+/// offers for texture and buffer sizes.
+pub async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), Error> {
+    let adapter_limits = adapter.limits();
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: Limits {
+                    max_texture_dimension_2d: adapter_limits.max_texture_dimension_2d,
+                    max_storage_buffer_binding_size: adapter_limits.max_storage_buffer_binding_size,
+                    max_buffer_size: adapter_limits.max_buffer_size,
+                    ..Limits::downlevel_defaults()
+                },
+            },
+            None,
+        )
+        .await
+        .map_err(Error::Device)
+}
@@ -0,0 +1,113 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use wgpu_lorenz::config::Config;
+
+use crate::cli::Args;
+
+pub const COMPUTE_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/compute.wgsl");
+pub const DRAW_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/draw.wgsl");
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy)]
+enum FileKind {
+    ComputeShader,
+    DrawShader,
+    Scene,
+}
+
+struct WatchedFile {
+    path: PathBuf,
+    kind: FileKind,
+    modified: Option<SystemTime>,
+}
+
+pub enum Reload {
+    ComputeShader(String),
+    DrawShader(String),
+    Scene(Box<Config>),
+}
+
+/// Development mode: polls the shaders and the scene file for changes.
+pub struct HotReload {
+    args: Args,
+    files: Vec<WatchedFile>,
+    last_poll: Instant,
+}
+
+impl HotReload {
+    pub fn new(args: Args) -> Self {
+        let mut files = vec![
+            // * SHADERS START UNSEEN SO THE ON-DISK VERSION REPLACES THE EMBEDDED ONE
+            WatchedFile {
+                path: COMPUTE_SHADER_PATH.into(),
+                kind: FileKind::ComputeShader,
+                modified: None,
+            },
+            WatchedFile {
+                path: DRAW_SHADER_PATH.into(),
+                kind: FileKind::DrawShader,
+                modified: None,
+            },
+        ];
+        if let Some(path) = &args.scene {
+            files.push(WatchedFile {
+                modified: modified(path),
+                path: path.clone(),
+                kind: FileKind::Scene,
+            });
+        }
+        for file in &files {
+            println!("Watching {}", file.path.display());
+        }
+        Self {
+            args,
+            files,
+            last_poll: Instant::now() - POLL_INTERVAL,
+        }
+    }
+
+    /// Returns everything that changed since the last call. Read and validation errors are
+    /// printed and otherwise ignored, so the previous state stays in use.
+    pub fn poll(&mut self) -> Vec<Reload> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut reloads = Vec::new();
+        for file in &mut self.files {
+            let modified = modified(&file.path);
+            if modified.is_none() || modified == file.modified {
+                continue;
+            }
+            file.modified = modified;
+            let reload = match file.kind {
+                FileKind::ComputeShader => fs::read_to_string(&file.path)
+                    .map(Reload::ComputeShader)
+                    .map_err(|e| e.to_string()),
+                FileKind::DrawShader => fs::read_to_string(&file.path)
+                    .map(Reload::DrawShader)
+                    .map_err(|e| e.to_string()),
+                FileKind::Scene => self
+                    .args
+                    .load_config()
+                    .map(|config| Reload::Scene(Box::new(config)))
+                    .map_err(|e| e.to_string()),
+            };
+            match reload {
+                Ok(reload) => reloads.push(reload),
+                Err(e) => eprintln!("Could not reload {}: {e}", file.path.display()),
+            }
+        }
+        reloads
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,166 @@
+use glam::{Mat4, Vec3, Vec4};
+use serde::Deserialize;
+
+use crate::integrator::Integrator;
+
+const SCALE: f32 = 1.;
+
+/// Variables of a four-dimensional state.
+pub const HYPER_DIMENSIONS: usize = 4;
+
+/// The planes `(i, j)` of the six elementary 4D rotations, in the order of
+/// [`Hyper::rotation`]. A positive angle turns axis `i` towards axis `j`.
+pub const ROTATION_PLANES: [(usize, usize); 6] = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+
+/// A four-dimensional system with two positive Lyapunov exponents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HyperKind {
+    /// Rössler's hyperchaos, `(-y - z, x + a y + w, b + x z, -c z + d w)`
+    #[default]
+    Rossler,
+    /// Lorenz-Stenflo, `(a (y - x) + d w, x (c - z) - y, x y - b z, -x - a w)`
+    LorenzStenflo,
+}
+
+impl HyperKind {
+    /// The parameters `a`, `b`, `c` and `d` of the hyperchaotic regime.
+    pub fn default_parameters(self) -> [f32; 4] {
+        match self {
+            HyperKind::Rossler => [0.25, 3., 0.5, 0.05],
+            HyperKind::LorenzStenflo => [1., 0.7, 26., 1.5],
+        }
+    }
+
+    /// Range of `w` on the attractor.
+    pub fn default_w_range(self) -> [f32; 2] {
+        match self {
+            HyperKind::Rossler => [10., 60.],
+            HyperKind::LorenzStenflo => [-3., 3.],
+        }
+    }
+}
+
+/// What the particles of a 4D system are colored by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperColor {
+    /// Length of the 4D velocity, like every other system.
+    #[default]
+    Speed,
+    /// The fourth coordinate after the rotation, the one the projection drops.
+    W,
+}
+
+/// A 4D system, as read from the `[hyper]` table of a scene. It is drawn by rotating the
+/// state in 4D and dropping the fourth coordinate. Missing parameters take the defaults of the
+/// kind.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hyper {
+    pub kind: HyperKind,
+    pub a: Option<f32>,
+    pub b: Option<f32>,
+    pub c: Option<f32>,
+    pub d: Option<f32>,
+    /// `w` of new and respawned particles, `x`, `y` and `z` come from the distributions.
+    pub w: f32,
+    /// Drawn size of one unit.
+    pub scale: f32,
+    /// Angles in radians in the [`ROTATION_PLANES`] xy, xz, xw, yz, yw and zw, applied in
+    /// this order.
+    pub rotation: [f32; 6],
+    pub color: HyperColor,
+    /// Values of the dropped coordinate at the ends of the colormap with [`HyperColor::W`].
+    pub w_range: Option<[f32; 2]>,
+}
+
+impl Default for Hyper {
+    fn default() -> Self {
+        Self {
+            kind: HyperKind::Rossler,
+            a: None,
+            b: None,
+            c: None,
+            d: None,
+            w: 0.,
+            scale: SCALE,
+            rotation: [0.; 6],
+            color: HyperColor::Speed,
+            w_range: None,
+        }
+    }
+}
+
+impl Hyper {
+    /// `a`, `b`, `c` and `d`, with the defaults of the kind filled in.
+    pub fn parameters(&self) -> [f32; 4] {
+        let [a, b, c, d] = self.kind.default_parameters();
+        [
+            self.a.unwrap_or(a),
+            self.b.unwrap_or(b),
+            self.c.unwrap_or(c),
+            self.d.unwrap_or(d),
+        ]
+    }
+
+    /// [`Hyper::w_range`], or the range of the kind.
+    pub fn w_range(&self) -> [f32; 2] {
+        self.w_range.unwrap_or(self.kind.default_w_range())
+    }
+
+    /// Velocity at `s`. Mirrors `hyper_vel` in `compute.wgsl`.
+    pub fn velocity(&self, s: Vec4) -> Vec4 {
+        let [a, b, c, d] = self.parameters();
+        match self.kind {
+            HyperKind::Rossler => Vec4::new(
+                -s.y - s.z,
+                s.x + a * s.y + s.w,
+                b + s.x * s.z,
+                -c * s.z + d * s.w,
+            ),
+            HyperKind::LorenzStenflo => Vec4::new(
+                a * (s.y - s.x) + d * s.w,
+                s.x * (c - s.z) - s.y,
+                s.x * s.y - b * s.z,
+                -s.x - a * s.w,
+            ),
+        }
+    }
+
+    /// Advances `s` by one step of size `h`. Hyper systems reject stochastic integrators.
+    pub fn step(&self, integrator: Integrator, h: f32, s: Vec4) -> Vec4 {
+        integrator.step(h, s, |s| self.velocity(s))
+    }
+
+    /// The 4D rotation of [`Hyper::rotation`].
+    pub fn rotation_matrix(&self) -> Mat4 {
+        ROTATION_PLANES
+            .iter()
+            .zip(self.rotation)
+            .fold(Mat4::IDENTITY, |m, (&(i, j), angle)| {
+                let (sin, cos) = angle.sin_cos();
+                let mut g = Mat4::IDENTITY;
+                g.col_mut(i)[i] = cos;
+                g.col_mut(i)[j] = sin;
+                g.col_mut(j)[i] = -sin;
+                g.col_mut(j)[j] = cos;
+                g * m
+            })
+    }
+
+    /// Image of every variable's unit vector: the drawn position in `xyz`, scaled, and the
+    /// dropped coordinate in `w`. The columns of the rotation.
+    pub fn projection(&self) -> [Vec4; HYPER_DIMENSIONS] {
+        let view = Mat4::from_diagonal(Vec4::new(self.scale, self.scale, self.scale, 1.))
+            * self.rotation_matrix();
+        [view.x_axis, view.y_axis, view.z_axis, view.w_axis]
+    }
+
+    /// Drawn position of `s` and its dropped coordinate.
+    pub fn project(&self, s: Vec4) -> (Vec3, f32) {
+        let [x, y, z, w] = self.projection();
+        let image = x * s.x + y * s.y + z * s.z + w * s.w;
+        (image.truncate(), image.w)
+    }
+}
@@ -1,10 +1,13 @@
 use winit::event::{ElementState, VirtualKeyCode, WindowEvent};
 
+use rand::Rng;
+use wgpu_lorenz::backend::Backend;
+
 use crate::state::State;
 
 pub fn input(state: &mut State, event: &WindowEvent) -> bool {
-    // * HANDLE CAMERA INPUT FIRST
-    if state.camera.controller.handle_key_input(event) {
+    // * HANDLE CAMERA AND 4D ROTATION INPUT FIRST
+    if state.rotation.handle_key_input(event) || state.camera.controller.handle_key_input(event) {
         true
     } else {
         match event {
@@ -25,6 +28,114 @@ pub fn input(state: &mut State, event: &WindowEvent) -> bool {
                 state.paused = !state.paused;
                 true
             }
+            // * CYCLE CAMERA MODE
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::F)
+                    && input.state == ElementState::Released =>
+            {
+                state.camera.mode = state.camera.mode.next();
+                println!("Camera mode: {:?}", state.camera.mode);
+                true
+            }
+            // * SWITCH BETWEEN GPU AND CPU SIMULATION
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::B)
+                    && input.state == ElementState::Released =>
+            {
+                let backend = match state.sim.config().backend {
+                    Backend::Gpu => Backend::Cpu,
+                    Backend::Cpu => Backend::Gpu,
+                };
+                state.sim.set_backend(backend);
+                println!("Backend: {backend:?}");
+                true
+            }
+            // * PRINT RESPAWN STATISTIC
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::I)
+                    && input.state == ElementState::Released =>
+            {
+                println!("Respawned particles: {}", state.sim.respawn_count());
+                true
+            }
+            // * SELECT FOLLOWED PARTICLE
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::PageUp)
+                    && input.state == ElementState::Released =>
+            {
+                state.select_follow_particle(state.follow.index + 1);
+                true
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::PageDown)
+                    && input.state == ElementState::Released =>
+            {
+                let n = state.sim.num_particles();
+                state.select_follow_particle(state.follow.index + n - 1);
+                true
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::N)
+                    && input.state == ElementState::Released =>
+            {
+                let index = state.rng.gen();
+                state.select_follow_particle(index);
+                true
+            }
+            // * WEAKEN OR STRENGTHEN THE COUPLING
+            WindowEvent::KeyboardInput { input, .. }
+                if matches!(
+                    input.virtual_keycode,
+                    Some(VirtualKeyCode::LBracket | VirtualKeyCode::RBracket)
+                ) && input.state == ElementState::Released =>
+            {
+                let factor = match input.virtual_keycode {
+                    Some(VirtualKeyCode::LBracket) => 0.8,
+                    _ => 1.25,
+                };
+                state.scale_coupling(factor);
+                true
+            }
+            // * PRINT SYNCHRONIZATION ERROR
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::Y)
+                    && input.state == ElementState::Released =>
+            {
+                state.print_sync_errors();
+                true
+            }
+            // * TOGGLE ENSEMBLE STATISTICS IN THE WINDOW TITLE
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::G)
+                    && input.state == ElementState::Released =>
+            {
+                state.toggle_stats();
+                true
+            }
+            // * PRINT DIMENSION ESTIMATES
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::K)
+                    && input.state == ElementState::Released =>
+            {
+                state.print_dimension();
+                true
+            }
+            // * TOGGLE EQUILIBRIA
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::E)
+                    && input.state == ElementState::Released =>
+            {
+                state.toggle_equilibria();
+                true
+            }
+            // * PRINT TWIN SEPARATION
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::T)
+                    && input.state == ElementState::Released =>
+            {
+                state.print_twin_separation();
+                true
+            }
             // * TOGGLE CURSOR GRAB
             WindowEvent::KeyboardInput { input, .. }
                 if input.virtual_keycode == Some(VirtualKeyCode::Slash)
@@ -34,19 +145,30 @@ pub fn input(state: &mut State, event: &WindowEvent) -> bool {
                 println!("{}", state.camera.entity.dir);
 
                 if state.env.cursor_grab {
-                    state
+                    if let Err(e) = state
                         .env
                         .window
                         .set_cursor_grab(winit::window::CursorGrabMode::None)
-                        .unwrap();
+                    {
+                        eprintln!("Could not release cursor: {e}");
+                    }
 
                     state.env.window.set_cursor_visible(true);
                 } else {
-                    state
+                    // * NOT EVERY PLATFORM SUPPORTS CONFINING, SO TRY LOCKING AS WELL
+                    if let Err(e) = state
                         .env
                         .window
                         .set_cursor_grab(winit::window::CursorGrabMode::Confined)
-                        .unwrap();
+                        .or_else(|_| {
+                            state
+                                .env
+                                .window
+                                .set_cursor_grab(winit::window::CursorGrabMode::Locked)
+                        })
+                    {
+                        eprintln!("Could not grab cursor: {e}");
+                    }
                     state.env.window.set_cursor_visible(false);
                 }
                 state.env.cursor_grab = !state.env.cursor_grab;
@@ -1,41 +1,78 @@
 use glam::Vec3;
+use rand::{rngs::StdRng, Rng};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    Buffer, BufferAddress, BufferUsages, Color, Device, Queue, VertexAttribute, VertexBufferLayout,
+    Buffer, BufferAddress, BufferUsages, Color, Device, VertexAttribute, VertexBufferLayout,
     VertexStepMode,
 };
 
-use crate::lorenz::LorenzState;
+use crate::{
+    config::Config,
+    coupling::{sync_offset, SYNC_HISTORY},
+    emitter::DEAD,
+    lorenz::LorenzState,
+    respawn::rng_state,
+    vertex::SQUARE,
+};
 
+/// A particle on the CPU side.
 #[derive(Clone, Copy)]
 pub struct Instance {
     pub position: Vec3,
+    /// State of the particle's random number generator, see [`rng_state`].
+    pub rng: u32,
+    /// Steps since the particle was emitted, [`DEAD`] if it is not alive.
+    pub age: u32,
     pub color: Color,
 }
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 
+/// A particle as laid out in the instance buffer.
 pub struct RawInstance {
-    pos: [f32; 3],
-    _pad: f32,
-    color: [f32; 3],
-    _pad2: f32,
+    pub(crate) pos: [f32; 3],
+    pub(crate) rng: u32,
+    pub(crate) color: [f32; 3],
+    pub(crate) age: u32,
 }
 impl From<Instance> for RawInstance {
     fn from(instance: Instance) -> Self {
         Self {
             pos: instance.position.to_array(),
+            rng: instance.rng,
             color: [
                 instance.color.r as f32,
                 instance.color.g as f32,
                 instance.color.b as f32,
             ],
-            _pad: f32::NAN,
-            _pad2: f32::NAN,
+            age: instance.age,
         }
     }
 }
 impl RawInstance {
+    pub fn new(position: Vec3, rng: u32, age: u32, color: Vec3) -> Self {
+        Self {
+            pos: position.to_array(),
+            rng,
+            color: color.to_array(),
+            age,
+        }
+    }
+    pub fn position(&self) -> Vec3 {
+        Vec3::from_array(self.pos)
+    }
+    pub fn rng(&self) -> u32 {
+        self.rng
+    }
+    pub fn color(&self) -> Vec3 {
+        Vec3::from_array(self.color)
+    }
+    pub fn age(&self) -> u32 {
+        self.age
+    }
+    pub fn is_alive(&self) -> bool {
+        self.age != DEAD
+    }
     pub fn desc() -> VertexBufferLayout<'static> {
         VertexBufferLayout {
             array_stride: std::mem::size_of::<RawInstance>() as BufferAddress,
@@ -51,37 +88,77 @@ impl RawInstance {
                     offset: 4 * std::mem::size_of::<f32>() as u64,
                     shader_location: 2,
                 },
+                VertexAttribute {
+                    format: wgpu::VertexFormat::Uint32,
+                    offset: 7 * std::mem::size_of::<f32>() as u64,
+                    shader_location: 3,
+                },
             ],
         }
     }
 }
 
-pub struct InstancesVec {
-    pub data: Vec<Instance>,
-    pub raw: Vec<RawInstance>,
-    pub buffer: Buffer,
+/// Indirect draw arguments followed by the emitter's ring buffer position and the step
+/// counter, `DrawState` in `compute.wgsl`. Without an emitter every particle is drawn.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawState {
+    pub vertex_count: u32,
+    /// Particles in use, dead ones among them are discarded by the vertex shader.
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+    /// Index of the first particle emitted in the next step.
+    pub head: u32,
+    /// Steps simulated so far, the counter of the noise stream.
+    pub step: u32,
 }
-// const DEFAULT_COLOR: Color = Color {
-//     r: 219. / 256.,
-//     g: 285. / 256.,
-//     b: 6. / 256.,
-//     a: 1.0,
-// };
 
-const DEFAULT_COLOR: &str = "00ff00";
+impl DrawState {
+    pub fn new(instance_count: usize) -> Self {
+        Self {
+            vertex_count: SQUARE.len() as u32,
+            instance_count: instance_count as u32,
+            first_vertex: 0,
+            first_instance: 0,
+            head: 0,
+            step: 0,
+        }
+    }
+}
 
-impl From<(&LorenzState, &Device)> for InstancesVec {
-    fn from((lorenz_state, device): (&LorenzState, &wgpu::Device)) -> Self {
+/// The instance buffer and the buffers stepped along with it.
+pub struct InstancesVec {
+    pub buffer: Buffer,
+    /// A [`DrawState`], usable as indirect buffer.
+    pub draw_buffer: Buffer,
+    /// Per-particle states as `f32`, [`Config::state_dimensions`] each, the snapshot of coupled
+    /// particles or a single placeholder value for the Lorenz system.
+    pub states: Buffer,
+}
+impl From<(&LorenzState, &Device, &Config, &mut StdRng)> for InstancesVec {
+    fn from(
+        (lorenz_state, device, config, rng): (&LorenzState, &wgpu::Device, &Config, &mut StdRng),
+    ) -> Self {
+        let seed = config.seed.unwrap_or_default();
+        // * WITH AN EMITTER THE POOL STARTS OUT EMPTY
+        let (age, in_use) = match config.emitter {
+            Some(_) => (DEAD, 0),
+            None => (0, lorenz_state.points.len()),
+        };
         let instances: Vec<Instance> = lorenz_state
             .points
             .iter()
-            .map(|pos| Instance {
+            .enumerate()
+            .map(|(i, pos)| Instance {
                 position: *pos,
+                rng: rng_state(seed, i),
+                age,
                 color: Color {
-                    r: rand::random(),
-                    g: rand::random(),
-                    b: rand::random(),
-                    a: rand::random(),
+                    r: rng.gen(),
+                    g: rng.gen(),
+                    b: rng.gen(),
+                    a: rng.gen(),
                 },
             })
             .collect();
@@ -90,44 +167,38 @@ impl From<(&LorenzState, &Device)> for InstancesVec {
         let buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Instance Buffer"),
             contents: bytemuck::cast_slice(&raw),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST | BufferUsages::STORAGE,
+            usage: BufferUsages::VERTEX
+                | BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC
+                | BufferUsages::STORAGE,
+        });
+        let draw_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Draw Indirect Buffer"),
+            contents: bytemuck::bytes_of(&DrawState::new(in_use)),
+            usage: BufferUsages::INDIRECT
+                | BufferUsages::STORAGE
+                | BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC,
+        });
+        // * BINDINGS MUST NOT BE EMPTY, COUPLED PARTICLES NEED ROOM FOR THEIR SNAPSHOT
+        let scratch;
+        let states: &[f32] = if config.coupling.is_some() {
+            scratch = vec![0.; sync_offset(lorenz_state.points.len()) + SYNC_HISTORY];
+            &scratch
+        } else if lorenz_state.states.is_empty() {
+            &[0.]
+        } else {
+            &lorenz_state.states
+        };
+        let states = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("State Buffer"),
+            contents: bytemuck::cast_slice(states),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
         });
         Self {
-            data: instances,
-            raw,
             buffer,
+            draw_buffer,
+            states,
         }
     }
 }
-impl InstancesVec {
-    pub fn update(&mut self, lorenz_state: &LorenzState, queue: &Queue) {
-        let color = color_from_hex(DEFAULT_COLOR).unwrap();
-
-        self.data = lorenz_state
-            .points
-            .iter()
-            .map(|pos| Instance {
-                position: *pos,
-                color,
-            })
-            .collect();
-        self.raw = self.data.iter().map(|i| (*i).into()).collect();
-        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.raw));
-    }
-}
-
-/// For example:
-/// ```rs
-/// let color = color_from_hex("dbafea").unwrap();
-/// ```
-fn color_from_hex(hex: &str) -> Result<Color, std::num::ParseIntError> {
-    let r = u8::from_str_radix(&hex[0..2], 16)?;
-    let g = u8::from_str_radix(&hex[2..4], 16)?;
-    let b = u8::from_str_radix(&hex[4..6], 16)?;
-    Ok(Color {
-        r: (r as f64) / 256.,
-        g: (g as f64) / 256.,
-        b: (b as f64) / 256.,
-        a: 1.,
-    })
-}
@@ -0,0 +1,113 @@
+use std::ops::{Add, Mul};
+
+use serde::Deserialize;
+
+/// Explicit Runge-Kutta schemes and their stochastic counterparts, available on both
+/// backends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Integrator {
+    /// First order forward Euler.
+    #[default]
+    Euler,
+    /// Second order Heun (explicit trapezoidal rule).
+    Heun,
+    /// Classic fourth order Runge-Kutta.
+    Rk4,
+    /// Euler-Maruyama, forward Euler with [`Noise`](crate::noise::Noise) added (Itô).
+    #[serde(rename = "euler-maruyama")]
+    EulerMaruyama,
+    /// Stochastic Heun, the predictor-corrector form of Heun with noise (Stratonovich).
+    #[serde(rename = "stochastic-heun")]
+    StochasticHeun,
+}
+
+impl Integrator {
+    /// Value of the `integrator` field in the compute shader config.
+    pub fn shader_index(self) -> u32 {
+        self as u32
+    }
+
+    /// Whether the integrator adds noise.
+    pub fn is_stochastic(self) -> bool {
+        matches!(self, Integrator::EulerMaruyama | Integrator::StochasticHeun)
+    }
+
+    /// Advances `state` by one step of size `h` through the vector field `vel`. Works on
+    /// single points as well as on SIMD lanes of points. Stochastic integrators step without
+    /// noise.
+    pub fn step<S>(self, h: f32, state: S, vel: impl Fn(S) -> S) -> S
+    where
+        S: Copy + Add<Output = S> + Mul<f32, Output = S>,
+    {
+        self.step_at(h, 0., state, |_, state| vel(state))
+    }
+
+    /// Like [`Integrator::step`] for a time-dependent vector field `vel(t, state)`, from time
+    /// `t`. Every stage sees the time it is evaluated at.
+    pub fn step_at<S>(self, h: f32, t: f32, state: S, vel: impl Fn(f32, S) -> S) -> S
+    where
+        S: Copy + Add<Output = S> + Mul<f32, Output = S>,
+    {
+        let deterministic = match self {
+            Integrator::EulerMaruyama => Integrator::Euler,
+            Integrator::StochasticHeun => Integrator::Heun,
+            integrator => integrator,
+        };
+        deterministic.step_stochastic_at(h, t, state, vel, |_| unreachable!("no diffusion"))
+    }
+
+    /// Like [`Integrator::step`], with `diffusion` giving `G(X) dW` for the Wiener increment
+    /// of this step. Deterministic integrators ignore it.
+    pub fn step_stochastic<S>(
+        self,
+        h: f32,
+        state: S,
+        vel: impl Fn(S) -> S,
+        diffusion: impl Fn(S) -> S,
+    ) -> S
+    where
+        S: Copy + Add<Output = S> + Mul<f32, Output = S>,
+    {
+        self.step_stochastic_at(h, 0., state, |_, state| vel(state), diffusion)
+    }
+
+    /// Like [`Integrator::step_stochastic`] for a time-dependent drift `vel(t, state)`, from
+    /// time `t`.
+    pub fn step_stochastic_at<S>(
+        self,
+        h: f32,
+        t: f32,
+        state: S,
+        vel: impl Fn(f32, S) -> S,
+        diffusion: impl Fn(S) -> S,
+    ) -> S
+    where
+        S: Copy + Add<Output = S> + Mul<f32, Output = S>,
+    {
+        match self {
+            Integrator::Euler => state + vel(t, state) * h,
+            Integrator::EulerMaruyama => state + vel(t, state) * h + diffusion(state),
+            Integrator::StochasticHeun => {
+                let k1 = vel(t, state);
+                let g1 = diffusion(state);
+                let predictor = state + k1 * h + g1;
+                let k2 = vel(t + h, predictor);
+                let g2 = diffusion(predictor);
+                state + (k1 + k2) * (0.5 * h) + (g1 + g2) * 0.5
+            }
+            Integrator::Heun => {
+                let k1 = vel(t, state);
+                let k2 = vel(t + h, state + k1 * h);
+                state + (k1 + k2) * (0.5 * h)
+            }
+            Integrator::Rk4 => {
+                let k1 = vel(t, state);
+                let k2 = vel(t + 0.5 * h, state + k1 * (0.5 * h));
+                let k3 = vel(t + 0.5 * h, state + k2 * (0.5 * h));
+                let k4 = vel(t + h, state + k3 * h);
+                state + (k1 + k2 * 2. + k3 * 2. + k4) * (h / 6.)
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! GPU accelerated particle simulation of the Lorenz attractor.
+//!
+//! [`Simulation`] owns a cloud of particles on the GPU and advances them with a compute
+//! shader, or on the CPU with [`Backend::Cpu`]. It can run headless, read the particles back
+//! and render them to a texture; the `wgpu_lorenz` binary is an interactive viewer on top of
+//! it.
+
+mod analysis;
+/// Choice between GPU and CPU simulation.
+pub mod backend;
+/// Perspective camera and its input handling.
+pub mod camera;
+mod compute;
+/// Runtime configuration and the uniforms derived from it.
+pub mod config;
+/// Coupled particles and their synchronization.
+pub mod coupling;
+/// Multithreaded SIMD simulation on the CPU.
+pub mod cpu;
+/// Delay differential equations, drawn at their delay embedding.
+pub mod delay;
+/// Fractal dimension estimates of the particle cloud.
+pub mod dimension;
+/// Delay embedding reconstruction and estimators for its parameters.
+pub mod embedding;
+/// Continuous particle sources.
+pub mod emitter;
+/// Ensemble Kalman filter data assimilation.
+pub mod enkf;
+/// User-defined vector fields from math expressions.
+pub mod equations;
+/// Fixed points of the flow and their linear stability.
+pub mod equilibria;
+/// Error types.
+pub mod error;
+/// Adapter and device selection.
+pub mod gpu;
+/// Four-dimensional hyperchaotic systems and their rotation into view.
+pub mod hyper;
+/// Layout of the particle instance buffer.
+pub mod instance;
+/// Numerical integration schemes.
+pub mod integrator;
+/// The Lorenz system and initial particle distributions.
+pub mod lorenz;
+/// The Lorenz-96 model and its projection to three dimensions.
+pub mod lorenz96;
+/// Discrete chaotic maps.
+pub mod maps;
+/// Noise of the stochastic integrators.
+pub mod noise;
+/// Drawing particles.
+pub mod render;
+/// Respawning escaped, diverged or stalled particles.
+pub mod respawn;
+/// Scene files.
+pub mod scene;
+#[cfg(test)]
+mod shader_tests;
+/// Headless simulation API.
+pub mod simulation;
+/// Ensemble statistics reduced on the GPU every frame.
+pub mod stats;
+/// Stroboscopic sampling of forced systems.
+pub mod strobe;
+mod texture;
+/// Particles created in pairs to watch nearby trajectories diverge.
+pub mod twins;
+mod vertex;
+
+pub use backend::Backend;
+pub use config::Config;
+pub use error::Error;
+pub use integrator::Integrator;
+pub use lorenz::LorenzConfig;
+pub use scene::Scene;
+pub use simulation::Simulation;
@@ -1,8 +1,18 @@
-use glam::Vec3;
+use glam::{Vec3, Vec4};
 use rand::Rng;
+use serde::Deserialize;
 
+use crate::{
+    delay::Delay,
+    hyper::Hyper,
+    integrator::Integrator,
+    lorenz96::{project, Lorenz96},
+};
+
+/// Parameters of the Lorenz system.
 #[repr(C)]
-#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct LorenzConfig {
     pub rho: f32,
     pub sigma: f32,
@@ -22,7 +32,8 @@ impl Default for LorenzConfig {
 }
 
 impl LorenzConfig {
-    fn _delta(&self, state: Vec3) -> Vec3 {
+    /// Velocity of the Lorenz flow at `state`.
+    pub fn delta(&self, state: Vec3) -> Vec3 {
         let Vec3 { x, y, z } = state;
         Vec3 {
             x: self.sigma * (y - x),
@@ -31,31 +42,154 @@ impl LorenzConfig {
         }
     }
 
-    pub fn _step(&self, dt: f32, state: Vec3) -> Vec3 {
-        state + self.step_size_factor * dt * self._delta(state)
+    /// Advances a single point by one time step, scaled by `step_size_factor`.
+    pub fn step(&self, integrator: Integrator, dt: f32, state: Vec3) -> Vec3 {
+        integrator.step(self.step_size_factor * dt, state, |p| self.delta(p))
+    }
+}
+
+const DEFAULT_EXTENT: f32 = 50.;
+
+/// Shape of the initial particle cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DistributionShape {
+    /// Uniform in the cube `center ± extent`.
+    Cube,
+    /// Uniform in the ball of radius `extent` around `center`.
+    Sphere,
+    /// Normal around `center` with standard deviation `extent`.
+    Gaussian,
+}
+
+/// Initial distribution of the particle cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Distribution {
+    pub shape: DistributionShape,
+    pub center: [f32; 3],
+    pub extent: f32,
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Self {
+            shape: DistributionShape::Cube,
+            center: [0.; 3],
+            extent: DEFAULT_EXTENT,
+        }
+    }
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> Vec3 {
+        let n = self.extent;
+        let offset = match self.shape {
+            DistributionShape::Cube => Vec3 {
+                x: rng.gen_range(-n..n),
+                y: rng.gen_range(-n..n),
+                z: rng.gen_range(-n..n),
+            },
+            DistributionShape::Sphere => loop {
+                let p = Vec3 {
+                    x: rng.gen_range(-1f32..1.),
+                    y: rng.gen_range(-1f32..1.),
+                    z: rng.gen_range(-1f32..1.),
+                };
+                if p.length_squared() <= 1. {
+                    break n * p;
+                }
+            },
+            DistributionShape::Gaussian => {
+                n * Vec3 {
+                    x: gaussian(rng),
+                    y: gaussian(rng),
+                    z: gaussian(rng),
+                }
+            }
+        };
+        Vec3::from_array(self.center) + offset
     }
 }
 
+// * BOX-MULLER
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = 1. - rng.gen::<f32>();
+    let u2: f32 = rng.gen();
+    (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+/// Particle positions on the CPU.
 pub struct LorenzState {
     pub points: Vec<Vec3>,
+    /// Lorenz-96 or 4D states or delay histories, [`crate::Config::state_dimensions`] per
+    /// particle. Empty for the Lorenz system.
+    pub states: Vec<f32>,
 }
 impl LorenzState {
-    pub fn new(number_lorenz_points: usize) -> Self {
+    pub fn new(
+        number_lorenz_points: usize,
+        distribution: &Distribution,
+        rng: &mut impl Rng,
+    ) -> Self {
         let points = (0..number_lorenz_points)
-            .map(|_| {
-                const N: f32 = 50.;
-                Vec3 {
-                    x: rand::thread_rng().gen_range(-N..N),
-                    y: rand::thread_rng().gen_range(-N..N),
-                    z: rand::thread_rng().gen_range(-N..N),
-                }
-            })
+            .map(|_| distribution.sample(rng))
+            .collect();
+        Self {
+            points,
+            states: Vec::new(),
+        }
+    }
+
+    /// Like [`LorenzState::new`], but flattened into the plane `z = 0` where maps start.
+    pub fn flat(
+        number_lorenz_points: usize,
+        distribution: &Distribution,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut state = Self::new(number_lorenz_points, distribution, rng);
+        state.points.iter_mut().for_each(|p| p.z = 0.);
+        state
+    }
+
+    /// Random initial states of `lorenz96`, placed at their projection.
+    pub fn lorenz96(
+        number_lorenz_points: usize,
+        lorenz96: &Lorenz96,
+        seed: u64,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let states = lorenz96.initial_states(number_lorenz_points, rng);
+        let projection = lorenz96.projection(seed);
+        let points = states
+            .chunks(lorenz96.dimensions)
+            .map(|state| project(&projection, state))
+            .collect();
+        Self { points, states }
+    }
+
+    /// `x`, `y` and `z` from `distribution` with `w` of `hyper`, placed at their projection.
+    pub fn hyper(
+        number_lorenz_points: usize,
+        distribution: &Distribution,
+        hyper: &Hyper,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let starts: Vec<Vec4> = (0..number_lorenz_points)
+            .map(|_| distribution.sample(rng).extend(hyper.w))
             .collect();
-        Self { points }
+        let points = starts.iter().map(|&s| hyper.project(s).0).collect();
+        let states = starts.iter().flat_map(|s| s.to_array()).collect();
+        Self { points, states }
     }
-    pub fn _update(&mut self, dt: f32, lorenz_config: LorenzConfig) {
-        self.points
-            .iter_mut()
-            .for_each(|p| *p = lorenz_config._step(dt, *p));
+
+    /// Constant random histories of `delay`, placed at their embedding.
+    pub fn delay(number_lorenz_points: usize, delay: &Delay, rng: &mut impl Rng) -> Self {
+        let states = delay.initial_histories(number_lorenz_points, rng);
+        let points = states
+            .chunks(delay.history_len())
+            .map(|history| delay.embed(history, 0))
+            .collect();
+        Self { points, states }
     }
 }
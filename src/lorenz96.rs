@@ -0,0 +1,174 @@
+use glam::Vec3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::integrator::Integrator;
+
+/// Largest number of variables, the size of the projection uniform in `compute.wgsl`.
+pub const MAX_DIMENSIONS: usize = 256;
+
+const DIMENSIONS: usize = 40;
+const FORCING: f32 = 8.;
+const PERTURBATION: f32 = 1.;
+
+// * OWN STREAM, SO THE PROJECTION IS INDEPENDENT OF THE INITIAL CONDITIONS
+const PROJECTION_SALT: u64 = 0x6c8e_9cf5_7093_2bd5;
+
+/// How the `dimensions` variables are mapped to a position for rendering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectionKind {
+    /// Three of the variables, picked by `components`.
+    #[default]
+    Components,
+    /// A random orthonormal projection onto three dimensions, drawn from the seed.
+    Random,
+}
+
+/// The Lorenz-96 model, as read from the `[lorenz96]` table of a scene:
+/// `dx_i/dt = (x_{i+1} - x_{i-2}) x_{i-1} - x_i + F` with cyclic indices.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lorenz96 {
+    /// Number of variables `N`.
+    pub dimensions: usize,
+    /// Forcing `F`.
+    pub forcing: f32,
+    /// Initial states are `F` plus a uniform perturbation of this size in every variable.
+    pub perturbation: f32,
+    pub projection: ProjectionKind,
+    /// Variables shown as x, y and z with [`ProjectionKind::Components`].
+    pub components: [usize; 3],
+}
+
+impl Default for Lorenz96 {
+    fn default() -> Self {
+        Self {
+            dimensions: DIMENSIONS,
+            forcing: FORCING,
+            perturbation: PERTURBATION,
+            projection: ProjectionKind::Components,
+            components: [0, 1, 2],
+        }
+    }
+}
+
+impl Lorenz96 {
+    /// Component `i` of the velocity at `state`.
+    pub fn velocity(&self, state: &[f32], i: usize) -> f32 {
+        let n = state.len();
+        (state[(i + 1) % n] - state[(i + n - 2) % n]) * state[(i + n - 1) % n] - state[i]
+            + self.forcing
+    }
+
+    /// Norm of the velocity at `state`, what particles are colored by.
+    pub fn speed(&self, state: &[f32]) -> f32 {
+        (0..state.len())
+            .map(|i| self.velocity(state, i).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    /// Advances `state` by one step of size `h`, using `scratch` of three times the state's
+    /// length. Mirrors `cs_lorenz96` in `compute.wgsl` operation for operation.
+    pub fn step(&self, integrator: Integrator, h: f32, state: &mut [f32], scratch: &mut [f32]) {
+        let n = state.len();
+        let (stage, rest) = scratch.split_at_mut(n);
+        let (k, acc) = rest.split_at_mut(n);
+        match integrator {
+            Integrator::Heun => {
+                for (i, s) in stage.iter_mut().enumerate() {
+                    *s = state[i] + self.velocity(state, i) * h;
+                }
+                for (i, k) in k.iter_mut().enumerate() {
+                    *k = self.velocity(state, i) + self.velocity(stage, i);
+                }
+                for (x, k) in state.iter_mut().zip(&*k) {
+                    *x += k * (0.5 * h);
+                }
+            }
+            Integrator::Rk4 => {
+                for (i, (a, s)) in acc.iter_mut().zip(stage.iter_mut()).enumerate() {
+                    *a = self.velocity(state, i);
+                    *s = state[i] + *a * (0.5 * h);
+                }
+                for scale in [0.5 * h, h] {
+                    for (i, k) in k.iter_mut().enumerate() {
+                        *k = self.velocity(stage, i);
+                    }
+                    for ((a, s), (x, k)) in acc
+                        .iter_mut()
+                        .zip(stage.iter_mut())
+                        .zip(state.iter().zip(&*k))
+                    {
+                        *a += k * 2.;
+                        *s = x + k * scale;
+                    }
+                }
+                for (i, k) in k.iter_mut().enumerate() {
+                    *k = acc[i] + self.velocity(stage, i);
+                }
+                for (x, k) in state.iter_mut().zip(&*k) {
+                    *x += k * (h / 6.);
+                }
+            }
+            Integrator::Euler => {
+                for (i, k) in k.iter_mut().enumerate() {
+                    *k = self.velocity(state, i);
+                }
+                for (x, k) in state.iter_mut().zip(&*k) {
+                    *x += k * h;
+                }
+            }
+            Integrator::EulerMaruyama | Integrator::StochasticHeun => {
+                unreachable!("lorenz96 rejects stochastic integrators")
+            }
+        }
+    }
+
+    /// `particles` initial states, one after the other.
+    pub fn initial_states(&self, particles: usize, rng: &mut impl Rng) -> Vec<f32> {
+        let p = self.perturbation;
+        (0..particles * self.dimensions)
+            .map(|_| self.forcing + if p > 0. { rng.gen_range(-p..p) } else { 0. })
+            .collect()
+    }
+
+    /// Image of every variable's unit vector, so a state projects to the sum of its variables
+    /// times their images.
+    pub fn projection(&self, seed: u64) -> Vec<Vec3> {
+        let n = self.dimensions;
+        match self.projection {
+            ProjectionKind::Components => (0..n)
+                .map(|i| Vec3::from_array(self.components.map(|c| if c == i { 1. } else { 0. })))
+                .collect(),
+            ProjectionKind::Random => {
+                let mut rng = StdRng::seed_from_u64(seed ^ PROJECTION_SALT);
+                // * GRAM-SCHMIDT ON THREE RANDOM ROWS
+                let mut rows: Vec<Vec<f32>> = Vec::new();
+                while rows.len() < 3 {
+                    let mut row: Vec<f32> = (0..n).map(|_| rng.gen_range(-1.0..1.0)).collect();
+                    for other in &rows {
+                        let dot: f32 = row.iter().zip(other).map(|(a, b)| a * b).sum();
+                        row.iter_mut().zip(other).for_each(|(a, b)| *a -= dot * b);
+                    }
+                    let norm = row.iter().map(|a| a * a).sum::<f32>().sqrt();
+                    if norm > 1e-3 {
+                        rows.push(row.iter().map(|a| a / norm).collect());
+                    }
+                }
+                (0..n)
+                    .map(|i| Vec3::new(rows[0][i], rows[1][i], rows[2][i]))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Position of `state` for rendering.
+pub fn project(projection: &[Vec3], state: &[f32]) -> Vec3 {
+    projection
+        .iter()
+        .zip(state)
+        .fold(Vec3::ZERO, |p, (image, x)| p + *image * *x)
+}
@@ -1,54 +1,162 @@
-pub(crate) mod camera;
-mod compute;
-mod config;
-pub(crate) mod env;
-pub(crate) mod input;
-pub(crate) mod instance;
-pub(crate) mod lorenz;
-pub(crate) mod render;
-pub(crate) mod state;
-pub(crate) mod texture;
-pub(crate) mod vertex;
-
-use camera::Camera;
-use compute::ComputeState;
-use config::{Config, DEFAULT_DELTA_TIME};
+mod assimilation;
+mod cli;
+mod divergence;
+mod env;
+mod follow;
+mod fractal;
+mod hot_reload;
+mod input;
+mod reconstruction;
+mod regression;
+mod rotation;
+mod stability;
+mod state;
+
+use assimilation::{Log, TruthMarker};
+use clap::Parser;
+use cli::Args;
 use env::Environment;
-use lorenz::LorenzState;
+use follow::ParticleReadback;
+use hot_reload::HotReload;
 use pollster::FutureExt;
-use render::RenderState;
+use rand::{rngs::StdRng, SeedableRng};
+use reconstruction::DelayView;
+use rotation::RotationController;
+use stability::EquilibriumView;
 use state::State;
+use wgpu_lorenz::{
+    camera::Camera,
+    enkf::{Filter, Record},
+    render::RenderState,
+    scene::System,
+    stats::EnsembleStats,
+    twins::Divergence,
+    Error, Simulation,
+};
 use winit::event_loop::EventLoop;
 
 fn main() {
-    let config = Config::default();
+    if let Err(e) = run() {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
 
-    let event_loop = EventLoop::new();
+fn run() -> Result<(), Error> {
+    let args = Args::parse();
+    let config = args.load_config()?;
 
-    let env = Environment::new(&event_loop).block_on();
+    if !args.checksum_at.is_empty() || args.expect_checksums.is_some() {
+        return regression::run(config, &args.checksum_at, args.expect_checksums.as_deref());
+    }
+    if let Some(steps) = args.suggest_embedding {
+        return reconstruction::suggest(config, steps);
+    }
+    if let Some(steps) = args.assimilate {
+        return assimilation::run(config, steps, args.assimilation_log.as_deref());
+    }
+    if let Some(steps) = args.diverge {
+        return divergence::run(config, steps, args.divergence_log.as_deref());
+    }
+    if let Some(steps) = args.dimension {
+        return fractal::run(config, steps);
+    }
 
-    let lorenz_state = LorenzState::new(config.num_lorenz_points);
+    println!(
+        "Simulating {:?} with {} particles ({:?}, {:?})",
+        config.system, config.num_lorenz_points, config.backend, config.integrator
+    );
 
-    let (camera, camera_bind_group_layout) = Camera::create_camera(&env.device, &env.config);
+    let event_loop = EventLoop::new();
 
-    let render_state = RenderState::new(&lorenz_state, &env, camera_bind_group_layout, &config);
+    let env = Environment::new(&event_loop, config.window_size).block_on()?;
 
-    let compute_state = ComputeState::new(
-        &env,
-        &render_state.instances.buffer,
-        &config,
-        DEFAULT_DELTA_TIME,
+    let sim = Simulation::with_device(env.device.clone(), env.queue.clone(), config)?;
+    let seed = sim.config().seed.unwrap();
+    println!("Seed: {seed}");
+
+    let (camera, camera_bind_group_layout) = Camera::create_camera(
+        &env.device,
+        env.config.width as f32 / env.config.height as f32,
+        &sim.config().camera,
+    );
+
+    let render_state = RenderState::new(
+        &env.device,
+        env.config.format,
+        (env.config.width, env.config.height),
+        camera_bind_group_layout,
+        sim.config(),
     );
 
+    let follow = ParticleReadback::new(&env.device, 0, sim.config().state_dimensions());
+
+    let filter = sim
+        .config()
+        .assimilation
+        .map(|assimilation| Filter::new(assimilation, sim.config()));
+    let truth_marker = filter
+        .as_ref()
+        .map(|filter| TruthMarker::new(&env.device, filter.truth()));
+    let delay_view = sim
+        .config()
+        .embedding
+        .map(|embedding| DelayView::new(&env.device, embedding));
+    let assimilation_log = match (&filter, &args.assimilation_log) {
+        (Some(_), Some(path)) => Some(Log::create(path, Record::CSV_HEADER)?),
+        _ => None,
+    };
+    let divergence_log = match (&sim.config().twins, &args.divergence_log) {
+        (Some(_), Some(path)) => {
+            let mut log = Log::create(path, Divergence::CSV_HEADER)?;
+            log.write(&[divergence::record(&sim)])?;
+            Some(log)
+        }
+        _ => None,
+    };
+
+    let stats_log = match &args.stats_log {
+        Some(_) if sim.config().system == System::Lorenz96 => {
+            println!("Ensemble statistics are not supported by Lorenz-96");
+            None
+        }
+        Some(path) => Some(Log::create(path, EnsembleStats::CSV_HEADER)?),
+        None => None,
+    };
+
+    let equilibrium_view = match sim.config().system {
+        System::Lorenz | System::Custom if args.equilibria => {
+            Some(EquilibriumView::new(&env.device))
+        }
+        _ if args.equilibria => {
+            println!("Equilibria are only found for the Lorenz system and custom vector fields");
+            None
+        }
+        _ => None,
+    };
+
+    let hot_reload = args.watch.then(|| HotReload::new(args));
+
     let state = State {
+        delta_time: sim.config().delta_time,
         env,
         render_state,
-        lorenz_state,
+        sim,
         camera,
-        compute_state,
-        config,
-        delta_time: DEFAULT_DELTA_TIME,
+        follow,
         paused: true,
+        hot_reload,
+        filter,
+        truth_marker,
+        assimilation_log,
+        divergence_log,
+        stats_log,
+        stats_title: None,
+        stats_step: None,
+        delay_view,
+        equilibrium_view,
+        rng: StdRng::seed_from_u64(seed),
+        rotation: RotationController::default(),
     };
 
     state.run(event_loop);
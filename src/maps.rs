@@ -0,0 +1,144 @@
+use glam::Vec3;
+use serde::Deserialize;
+
+const SCALE: f32 = 20.;
+const SPACING: f32 = 0.5;
+const LAYERS: u32 = 100;
+
+// * SAME AS IN compute.wgsl
+/// Factor from the jump of a map to the speed it is colored by, so the jumps span the
+/// colormap like the speeds of the flows do.
+pub(crate) const MAP_COLOR_SCALE: f32 = 5.;
+
+/// A two-dimensional map `(x, y) -> f(x, y)`, applied once per step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MapKind {
+    /// `(1 - a x² + y, b x)`
+    #[default]
+    Henon,
+    /// `(1 - a |x| + y, b x)`
+    Lozi,
+    /// `(sin(a y) + c cos(a x), sin(b x) + d cos(b y))`
+    Clifford,
+    /// Peter de Jong, `(sin(a y) - cos(b x), sin(c x) - cos(d y))`
+    DeJong,
+    /// `1 + a (x cos τ - y sin τ, x sin τ + y cos τ)` with `τ = 0.4 - 6 / (1 + x² + y²)`
+    Ikeda,
+    /// `(x² - y² + a x + b y, 2 x y + c x + d y)`
+    Tinkerbell,
+}
+
+impl MapKind {
+    /// The parameters `a`, `b`, `c` and `d` of the well-known pictures. Unused ones are 0.
+    pub fn default_parameters(self) -> [f32; 4] {
+        match self {
+            MapKind::Henon => [1.4, 0.3, 0., 0.],
+            MapKind::Lozi => [1.7, 0.5, 0., 0.],
+            MapKind::Clifford => [-1.4, 1.6, 1., 0.7],
+            MapKind::DeJong => [1.4, -2.3, 2.4, -2.1],
+            MapKind::Ikeda => [0.918, 0., 0., 0.],
+            MapKind::Tinkerbell => [0.9, -0.6013, 2., 0.5],
+        }
+    }
+}
+
+/// Where the iterates of a map are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MapLayout {
+    /// In the plane `z = 0`.
+    #[default]
+    Plane,
+    /// Every iteration lifts a point by `spacing`, up to `layers` layers, so the transient
+    /// of fresh and respawned points is stacked below the attractor.
+    Stacked,
+}
+
+/// An iterated map, as read from the `[map]` table of a scene. Missing parameters take the
+/// defaults of the kind.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Map {
+    pub kind: MapKind,
+    pub a: Option<f32>,
+    pub b: Option<f32>,
+    pub c: Option<f32>,
+    pub d: Option<f32>,
+    pub layout: MapLayout,
+    /// Drawn size of one unit of the map, most attractors span a few units.
+    pub scale: f32,
+    /// Height of a layer with [`MapLayout::Stacked`].
+    pub spacing: f32,
+    pub layers: u32,
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self {
+            kind: MapKind::Henon,
+            a: None,
+            b: None,
+            c: None,
+            d: None,
+            layout: MapLayout::Plane,
+            scale: SCALE,
+            spacing: SPACING,
+            layers: LAYERS,
+        }
+    }
+}
+
+impl Map {
+    /// `a`, `b`, `c` and `d`, with the defaults of the kind filled in.
+    pub fn parameters(&self) -> [f32; 4] {
+        let [a, b, c, d] = self.kind.default_parameters();
+        [
+            self.a.unwrap_or(a),
+            self.b.unwrap_or(b),
+            self.c.unwrap_or(c),
+            self.d.unwrap_or(d),
+        ]
+    }
+
+    /// Lift per iteration and top of the stack, both 0 in the plane.
+    pub fn stacking(&self) -> (f32, f32) {
+        match self.layout {
+            MapLayout::Plane => (0., 0.),
+            MapLayout::Stacked => (self.spacing, self.spacing * self.layers as f32),
+        }
+    }
+
+    /// The next iterate of the drawn point `p`. Mirrors `iterate_map` in `compute.wgsl`.
+    pub fn iterate(&self, p: Vec3) -> Vec3 {
+        let [a, b, c, d] = self.parameters();
+        let (x, y) = (p.x / self.scale, p.y / self.scale);
+        let (nx, ny) = match self.kind {
+            MapKind::Henon => (1. - a * x * x + y, b * x),
+            MapKind::Lozi => (1. - a * x.abs() + y, b * x),
+            MapKind::Clifford => (
+                (a * y).sin() + c * (a * x).cos(),
+                (b * x).sin() + d * (b * y).cos(),
+            ),
+            MapKind::DeJong => ((a * y).sin() - (b * x).cos(), (c * x).sin() - (d * y).cos()),
+            MapKind::Ikeda => {
+                let t = 0.4 - 6. / (1. + x * x + y * y);
+                let (sin, cos) = (t.sin(), t.cos());
+                (1. + a * (x * cos - y * sin), a * (x * sin + y * cos))
+            }
+            MapKind::Tinkerbell => (x * x - y * y + a * x + b * y, 2. * x * y + c * x + d * y),
+        };
+        let (spacing, depth) = self.stacking();
+        Vec3::new(
+            nx * self.scale,
+            ny * self.scale,
+            (p.z + spacing).clamp(0., depth),
+        )
+    }
+
+    /// How far `p` jumps in the plane, what particles are colored by and what counts as
+    /// stalled.
+    pub fn jump(&self, p: Vec3) -> Vec3 {
+        (self.iterate(p) - p) * Vec3::new(1., 1., 0.)
+    }
+}
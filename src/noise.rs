@@ -0,0 +1,48 @@
+use glam::Vec3;
+use serde::Deserialize;
+
+use crate::respawn::{gaussian, pcg};
+
+/// How the noise of the stochastic integrators enters the system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseKind {
+    /// `dX = f(X) dt + a dW`, independent of the state.
+    #[default]
+    Additive,
+    /// `dX = f(X) dt + a X dW`, componentwise proportional to the state.
+    Multiplicative,
+}
+
+/// Noise of the stochastic integrators, as read from the `[noise]` table of a scene. The
+/// deterministic integrators ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    /// Amplitude per dimension.
+    pub amplitude: [f32; 3],
+}
+
+impl Noise {
+    /// Wiener increment of particle `index` in step `step` for a step size `h`, scaled by
+    /// the amplitude. Mirrors `wiener` in `compute.wgsl`.
+    pub fn wiener(&self, key: u32, index: usize, step: u32, h: f32) -> Vec3 {
+        let mut rng = noise_state(key, index, step);
+        let x = gaussian(&mut rng);
+        let y = gaussian(&mut rng);
+        let z = gaussian(&mut rng);
+        Vec3::from_array(self.amplitude) * h.sqrt() * Vec3::new(x, y, z)
+    }
+}
+
+/// Key of the noise stream, derived from the scene seed.
+pub fn noise_key(seed: u64) -> u32 {
+    pcg(pcg((seed >> 32) as u32) ^ !(seed as u32))
+}
+
+/// Random state the noise of particle `index` in step `step` is drawn from. It only
+/// depends on its arguments, so both backends draw the same noise in any order.
+pub fn noise_state(key: u32, index: usize, step: u32) -> u32 {
+    pcg(pcg(step ^ key) ^ index as u32)
+}
@@ -0,0 +1,116 @@
+use glam::Vec3;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, Queue,
+};
+
+use wgpu_lorenz::{
+    embedding::{
+        false_nearest_neighbours, first_minimum, mutual_information, DelayRecorder, Embedding,
+        FNN_TOLERANCE,
+    },
+    instance::{DrawState, RawInstance},
+    Config, Error, Simulation,
+};
+
+const RECONSTRUCTION_COLOR: Vec3 = Vec3::new(0.4, 0.8, 1.);
+
+// * STEPS BEFORE RECORDING, SO THE PARTICLE HAS SETTLED ON THE ATTRACTOR
+const WARMUP: u32 = 1000;
+const BINS: usize = 16;
+const MAX_DIMENSION: usize = 6;
+// * FALSE NEAREST NEIGHBOURS ARE QUADRATIC, ONLY THE LATEST SAMPLES ARE USED
+const FNN_SAMPLES: usize = 3000;
+/// Fraction of false nearest neighbours considered low enough for an embedding.
+const FNN_THRESHOLD: f32 = 0.01;
+
+/// The delay embedding of the recorded particles, drawn next to the true attractor.
+pub struct DelayView {
+    recorder: DelayRecorder,
+    pub instance_buffer: Buffer,
+    pub draw_buffer: Buffer,
+}
+
+impl DelayView {
+    pub fn new(device: &Device, settings: Embedding) -> Self {
+        let capacity = settings.particles * settings.history;
+        Self {
+            recorder: DelayRecorder::new(settings),
+            instance_buffer: device.create_buffer(&BufferDescriptor {
+                label: Some("Reconstruction Instance Buffer"),
+                size: (capacity * std::mem::size_of::<RawInstance>()) as BufferAddress,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            draw_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Reconstruction Draw Buffer"),
+                contents: bytemuck::bytes_of(&DrawState::new(0)),
+                usage: BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+            }),
+        }
+    }
+
+    /// Records the current observables and uploads the reconstruction.
+    pub fn record(&mut self, sim: &Simulation, queue: &Queue) {
+        let count = self.recorder.series_count();
+        self.recorder.push(&sim.read_particles_range(0..count));
+        let raw: Vec<RawInstance> = self
+            .recorder
+            .points()
+            .into_iter()
+            .map(|p| RawInstance::new(p, 0, 0, RECONSTRUCTION_COLOR))
+            .collect();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&raw));
+        queue.write_buffer(
+            &self.draw_buffer,
+            0,
+            bytemuck::bytes_of(&DrawState::new(raw.len())),
+        );
+    }
+}
+
+/// Records the observable of the first particle for `steps` steps headless and prints the
+/// mutual information and false nearest neighbour curves with the delay and embedding
+/// dimension they suggest.
+pub fn suggest(config: Config, steps: u32) -> Result<(), Error> {
+    let observable = config.embedding.unwrap_or_default().observable;
+    let mut sim = Simulation::new(config)?;
+    sim.step(WARMUP);
+    let series: Vec<f32> = (0..steps)
+        .map(|_| {
+            sim.step(1);
+            observable.value(sim.read_particles_range(0..1)[0])
+        })
+        .collect();
+
+    println!("# mutual information of {observable:?}, {steps} samples");
+    println!("delay,mutual_information");
+    let curve: Vec<f32> = (0..=series.len() / 4)
+        .map(|d| mutual_information(&series, d, BINS))
+        .collect();
+    for (d, mi) in curve.iter().enumerate() {
+        println!("{d},{mi}");
+    }
+    let Some(delay) = first_minimum(&curve) else {
+        println!("# no minimum, record more steps");
+        return Ok(());
+    };
+    println!("# suggested delay: {delay} steps");
+
+    let latest = &series[series.len().saturating_sub(FNN_SAMPLES)..];
+    println!("# false nearest neighbours at delay {delay}");
+    println!("dimension,fraction");
+    let mut dimension = None;
+    for m in 1..=MAX_DIMENSION {
+        let fraction = false_nearest_neighbours(latest, delay, m, FNN_TOLERANCE);
+        println!("{m},{fraction}");
+        if fraction < FNN_THRESHOLD {
+            dimension.get_or_insert(m);
+        }
+    }
+    match dimension {
+        Some(m) => println!("# suggested embedding dimension: {m}"),
+        None => println!("# no embedding dimension up to {MAX_DIMENSION} unfolds the attractor"),
+    }
+    Ok(())
+}
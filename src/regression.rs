@@ -0,0 +1,77 @@
+use std::{fs, path::Path};
+
+use wgpu_lorenz::{scene::SceneError, Config, Error, Simulation};
+
+/// Parses `<steps> <checksum>` lines as printed by [`run`]. Other lines are ignored, so the
+/// whole output of a run can be saved as expectation.
+fn parse(text: &str) -> Vec<(u32, u64)> {
+    text.lines()
+        .filter_map(|line| {
+            let (steps, checksum) = line.trim().split_once(' ')?;
+            Some((
+                steps.parse().ok()?,
+                u64::from_str_radix(checksum.trim(), 16).ok()?,
+            ))
+        })
+        .collect()
+}
+
+/// Steps a headless simulation with the fixed time step of the scene and prints a checksum
+/// of the particles after each of `checksum_at` steps. With `expected`, the checksums are
+/// compared with an earlier run (at its step counts if `checksum_at` is empty).
+pub fn run(config: Config, checksum_at: &[u32], expected: Option<&Path>) -> Result<(), Error> {
+    if config.seed.is_none() {
+        return Err(SceneError::Invalid {
+            field: "seed",
+            reason: "regression runs need a fixed seed".to_owned(),
+        }
+        .into());
+    }
+    let expected = match expected {
+        Some(path) => parse(&fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })?),
+        None => Vec::new(),
+    };
+    let mut checksum_at = if checksum_at.is_empty() {
+        expected.iter().map(|(steps, _)| *steps).collect()
+    } else {
+        checksum_at.to_vec()
+    };
+    checksum_at.sort_unstable();
+    checksum_at.dedup();
+
+    let mut sim = Simulation::new(config)?;
+    println!(
+        "# {:?} {:?} seed {}",
+        sim.config().backend,
+        sim.config().integrator,
+        sim.config().seed.unwrap()
+    );
+
+    let mut step = 0;
+    let mut mismatches = 0;
+    for steps in checksum_at {
+        sim.step(steps - step);
+        step = steps;
+        let checksum = sim.checksum();
+        println!("{steps} {checksum:016x}");
+
+        match expected.iter().find(|(s, _)| *s == steps) {
+            Some((_, e)) if *e != checksum => {
+                eprintln!("Checksum after {steps} steps differs, expected {e:016x}");
+                mismatches += 1;
+            }
+            None if !expected.is_empty() => eprintln!("No expected checksum after {steps} steps"),
+            _ => {}
+        }
+    }
+
+    println!("# {} respawns", sim.respawn_count());
+
+    if mismatches > 0 {
+        return Err(Error::ChecksumMismatch { mismatches });
+    }
+    Ok(())
+}
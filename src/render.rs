@@ -1,19 +1,21 @@
+use std::borrow::Cow;
+
 use wgpu::{
-    include_wgsl, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-    BindGroupLayoutEntry, Buffer, Color, ColorTargetState, ColorWrites, CommandEncoderDescriptor,
-    DepthBiasState, DepthStencilState, Device, Extent3d, FragmentState, MultisampleState,
-    Operations, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, RenderPipeline,
-    RenderPipelineDescriptor, ShaderStages, StencilState, SurfaceConfiguration, TextureDescriptor,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry,
+    BlendComponent, BlendFactor, BlendOperation, BlendState, Buffer, Color, ColorTargetState,
+    ColorWrites, CommandEncoderDescriptor, CompareFunction, DepthBiasState, DepthStencilState,
+    Device, Extent3d, FragmentState, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, PrimitiveTopology, Queue, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilState, TextureDescriptor,
     TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
     VertexState,
 };
 
 use crate::{
     config::{Config, ConfigDrawShader},
-    env::Environment,
-    instance::{InstancesVec, RawInstance},
-    lorenz::LorenzState,
-    vertex::{Vertex, SQUARE},
+    error,
+    instance::RawInstance,
+    vertex::Vertex,
 };
 
 const BACKGROUND_COLOR: Color = Color {
@@ -22,68 +24,144 @@ const BACKGROUND_COLOR: Color = Color {
     b: 0.3,
     a: 1.0,
 };
+
+// * DENSITIES ADD UP FROM BLACK
+const DENSITY_BACKGROUND_COLOR: Color = Color::BLACK;
+
+const ADDITIVE: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent::OVER,
+};
+
+/// Source of the built-in draw shader.
+pub const DRAW_WGSL: &str = include_str!("draw.wgsl");
+
+/// Layout of bind group 1 of `draw.wgsl`, group 0 is the camera.
+pub(crate) const CONFIG_BIND_GROUP_LAYOUT_ENTRIES: [BindGroupLayoutEntry; 1] =
+    [BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }];
+
+/// Draws the particles of an instance buffer as camera facing discs into a color target of
+/// a fixed format, opaque or added up into a density.
 pub struct RenderState {
     pub vertex_buffer: Buffer,
-    pub instances: InstancesVec,
+    pub format: TextureFormat,
     pub render_pipeline: RenderPipeline,
+    pub density_pipeline: RenderPipeline,
+    /// Whether [`RenderState::density_pipeline`] is used.
+    pub density: bool,
     pub depth_texture: TextureView,
+    pub camera_bind_group_layout: BindGroupLayout,
+    pub config_bind_group_layout: BindGroupLayout,
     pub config_bind_group: BindGroup,
+    pub config_buffer: Buffer,
+    /// Width and height of the target in pixels.
+    pub size: (u32, u32),
 }
 impl RenderState {
     pub fn new(
-        lorenz_state: &LorenzState,
-        env: &Environment,
+        device: &Device,
+        format: TextureFormat,
+        (width, height): (u32, u32),
         camera_bind_group_layout: BindGroupLayout,
         config: &Config,
     ) -> Self {
         // * CREATE DEPTH TEXTURE
-        let depth_texture = Self::create_depth_texture(&env.device, &env.config);
+        let depth_texture = Self::create_depth_texture(device, width, height);
 
-        // * CREATE VERTEX & INSTANCE BUFFERS
-        let vertex_buffer = Vertex::create_vertex_buffer(&env.device);
-        let instances = InstancesVec::from((lorenz_state, &env.device));
+        // * CREATE VERTEX BUFFER
+        let vertex_buffer = Vertex::create_vertex_buffer(device);
 
+        let config_buffer = ConfigDrawShader::new(config, (width, height)).as_buffer(device);
         let (config_bind_group_layout, config_bind_group) =
-            Self::create_bind_group(config.into(), &env.device);
+            Self::create_bind_group(&config_buffer, device);
 
-        // * CREATE RENDER PIPELINE
-        let render_pipeline = Self::create_render_pipeline(
-            &env.device,
-            &env.config,
+        // * CREATE RENDER PIPELINES
+        let (render_pipeline, density_pipeline) = Self::create_render_pipelines(
+            device,
+            format,
             &[&camera_bind_group_layout, &config_bind_group_layout],
+            DRAW_WGSL,
         );
         Self {
             vertex_buffer,
+            format,
             render_pipeline,
+            density_pipeline,
+            density: config.density.is_some(),
             depth_texture,
-            instances,
+            camera_bind_group_layout,
+            config_bind_group_layout,
             config_bind_group,
+            config_buffer,
+            size: (width, height),
         }
     }
 
+    /// Rebuilds the pipelines from new WGSL source. On error the old pipelines stay in use.
+    pub fn reload_shader(&mut self, device: &Device, draw_wgsl: &str) -> Result<(), wgpu::Error> {
+        (self.render_pipeline, self.density_pipeline) = error::checked(device, || {
+            Self::create_render_pipelines(
+                device,
+                self.format,
+                &[
+                    &self.camera_bind_group_layout,
+                    &self.config_bind_group_layout,
+                ],
+                draw_wgsl,
+            )
+        })?;
+        Ok(())
+    }
+
+    pub fn update_config(&mut self, config: &Config, queue: &Queue) {
+        self.density = config.density.is_some();
+        queue.write_buffer(
+            &self.config_buffer,
+            0,
+            bytemuck::bytes_of(&ConfigDrawShader::new(config, self.size)),
+        )
+    }
+
+    /// Draws `batches` of an instance buffer and the [`DrawState`] counting its instances
+    /// into `view`, which must have the size of the depth texture.
+    ///
+    /// [`DrawState`]: crate::instance::DrawState
     pub fn render_call(
         &self,
-        env: &Environment,
+        device: &Device,
+        queue: &Queue,
+        view: &TextureView,
         camera_bind_group: &BindGroup,
-        number_lorenz_points: usize,
+        batches: &[(&Buffer, &Buffer)],
     ) {
-        let output = env.surface.get_current_texture().unwrap();
-        let mut encoder = env
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Encoder"),
-            });
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Encoder"),
+        });
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(BACKGROUND_COLOR),
+                        load: wgpu::LoadOp::Clear(if self.density {
+                            DENSITY_BACKGROUND_COLOR
+                        } else {
+                            BACKGROUND_COLOR
+                        }),
                         store: true,
                     },
                 })],
@@ -96,79 +174,122 @@ impl RenderState {
                     stencil_ops: None,
                 }),
             });
-            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_pipeline(if self.density {
+                &self.density_pipeline
+            } else {
+                &self.render_pipeline
+            });
 
             render_pass.set_bind_group(0, camera_bind_group, &[]);
             render_pass.set_bind_group(1, &self.config_bind_group, &[]);
 
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
-            render_pass.set_vertex_buffer(1, self.instances.buffer.slice(..));
-
-            render_pass.draw(0..SQUARE.len() as u32, 0..number_lorenz_points as u32)
+            for (instance_buffer, draw_buffer) in batches {
+                render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                render_pass.draw_indirect(draw_buffer, 0);
+            }
         }
-        env.queue.submit(Some(encoder.finish()));
-        output.present();
+        queue.submit(Some(encoder.finish()));
     }
 
-    fn create_render_pipeline(
+    /// Recreates the depth texture and updates the aspect ratio of the discs for a target of
+    /// the new size.
+    pub fn resize(
+        &mut self,
         device: &Device,
-        config: &SurfaceConfiguration,
+        queue: &Queue,
+        config: &Config,
+        width: u32,
+        height: u32,
+    ) {
+        self.depth_texture = Self::create_depth_texture(device, width, height);
+        self.size = (width, height);
+        self.update_config(config, queue);
+    }
+
+    /// Creates the opaque pipeline of `fs_main` and the additive one of `fs_density`, which
+    /// neither tests nor writes depth.
+    fn create_render_pipelines(
+        device: &Device,
+        format: TextureFormat,
         bind_group_layouts: &[&BindGroupLayout],
-    ) -> RenderPipeline {
+        draw_wgsl: &str,
+    ) -> (RenderPipeline, RenderPipeline) {
         // * LOAD SHADER
-        let draw_shader = device.create_shader_module(include_wgsl!("draw.wgsl"));
+        let draw_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Draw Shader"),
+            source: ShaderSource::Wgsl(Cow::from(draw_wgsl)),
+        });
 
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
             bind_group_layouts,
             push_constant_ranges: &[],
         });
-        device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &draw_shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), RawInstance::desc()],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(DepthStencilState {
-                format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: StencilState::default(),
-                bias: DepthBiasState::default(),
-            }),
-            multisample: MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(FragmentState {
-                module: &draw_shader,
-                entry_point: "fs_main",
-                targets: &[Some(ColorTargetState {
-                    format: config.format,
-                    blend: None,
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        })
+        let create = |label, entry_point, blend, depth_write_enabled, depth_compare| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipeline_layout),
+                vertex: VertexState {
+                    module: &draw_shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc(), RawInstance::desc()],
+                },
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleStrip,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(FragmentState {
+                    module: &draw_shader,
+                    entry_point,
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+        (
+            create(
+                "Render Pipeline",
+                "fs_main",
+                None,
+                true,
+                CompareFunction::Less,
+            ),
+            create(
+                "Density Pipeline",
+                "fs_density",
+                Some(ADDITIVE),
+                false,
+                CompareFunction::Always,
+            ),
+        )
     }
-    fn create_depth_texture(device: &Device, config: &SurfaceConfiguration) -> TextureView {
+    fn create_depth_texture(device: &Device, width: u32, height: u32) -> TextureView {
         let size = Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
         let texture = device.create_texture(&TextureDescriptor {
@@ -183,29 +304,17 @@ impl RenderState {
         });
         texture.create_view(&TextureViewDescriptor::default())
     }
-    fn create_bind_group(
-        config_draw_shader: ConfigDrawShader,
-        device: &Device,
-    ) -> (BindGroupLayout, BindGroup) {
+    fn create_bind_group(config_buffer: &Buffer, device: &Device) -> (BindGroupLayout, BindGroup) {
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Render Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            entries: &CONFIG_BIND_GROUP_LAYOUT_ENTRIES,
         });
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
             label: Some("Render Bind Group"),
             layout: &bind_group_layout,
             entries: &[BindGroupEntry {
                 binding: 0,
-                resource: config_draw_shader.as_buffer(device).as_entire_binding(),
+                resource: config_buffer.as_entire_binding(),
             }],
         });
         (bind_group_layout, bind_group)
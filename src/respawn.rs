@@ -0,0 +1,111 @@
+use glam::Vec3;
+use serde::Deserialize;
+
+use crate::lorenz::{Distribution, DistributionShape};
+
+const RADIUS: f32 = 1000.;
+const MIN_SPEED: f32 = 0.01;
+
+// * REJECTION SAMPLING GIVES UP AFTER THIS MANY TRIES, SO THE SHADER STAYS BOUNDED
+const SPHERE_TRIES: u32 = 8;
+
+/// When particles are taken out of the simulation and where they come back, as read from
+/// the `[respawn]` table of a scene.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RespawnSettings {
+    /// Particles further away from the origin are respawned.
+    pub radius: f32,
+    /// Particles slower than this are considered stuck on a fixed point and respawned.
+    pub min_speed: f32,
+    /// Where respawned particles appear, the initial distribution if not given.
+    pub distribution: Option<Distribution>,
+}
+
+impl Default for RespawnSettings {
+    fn default() -> Self {
+        Self {
+            radius: RADIUS,
+            min_speed: MIN_SPEED,
+            distribution: None,
+        }
+    }
+}
+
+/// Validated respawn settings. Particles that are not finite are always respawned.
+#[derive(Debug, Clone, Copy)]
+pub struct Respawn {
+    pub radius: f32,
+    pub min_speed: f32,
+    pub distribution: Distribution,
+}
+
+impl Respawn {
+    /// Whether a particle at `pos` moving with `vel` has to be respawned.
+    pub fn is_unhealthy(&self, pos: Vec3, vel: Vec3) -> bool {
+        !pos.is_finite()
+            || pos.length_squared() > self.radius * self.radius
+            || vel.length_squared() < self.min_speed * self.min_speed
+    }
+
+    /// Draws a new position from the respawn distribution, advancing the particle's `rng`.
+    /// Mirrors `respawn_sample` in `compute.wgsl`.
+    pub fn sample(&self, rng: &mut u32) -> Vec3 {
+        let d = &self.distribution;
+        let offset = match d.shape {
+            DistributionShape::Cube => uniform_vec3(rng) * 2. - Vec3::ONE,
+            DistributionShape::Sphere => unit_ball(rng),
+            DistributionShape::Gaussian => {
+                let x = gaussian(rng);
+                let y = gaussian(rng);
+                let z = gaussian(rng);
+                Vec3::new(x, y, z)
+            }
+        };
+        Vec3::from_array(d.center) + d.extent * offset
+    }
+}
+
+/// Uniform in `[0, 1)`, advancing `rng`.
+pub(crate) fn uniform(rng: &mut u32) -> f32 {
+    *rng = pcg(*rng);
+    (*rng >> 8) as f32 * (1. / 16777216.)
+}
+
+/// Standard normal by the Box-Muller transform, advancing `rng`.
+pub(crate) fn gaussian(rng: &mut u32) -> f32 {
+    let u1 = 1. - uniform(rng);
+    let u2 = uniform(rng);
+    (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+fn uniform_vec3(rng: &mut u32) -> Vec3 {
+    let x = uniform(rng);
+    let y = uniform(rng);
+    let z = uniform(rng);
+    Vec3::new(x, y, z)
+}
+
+/// Uniform in the unit ball by rejection sampling.
+pub(crate) fn unit_ball(rng: &mut u32) -> Vec3 {
+    let mut p = Vec3::ZERO;
+    for _ in 0..SPHERE_TRIES {
+        p = uniform_vec3(rng) * 2. - Vec3::ONE;
+        if p.length_squared() <= 1. {
+            break;
+        }
+    }
+    p.clamp_length_max(1.)
+}
+
+/// PCG hash, the per particle random number generator of the compute shader.
+pub fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// Initial random state of particle `index`, the same on both backends.
+pub fn rng_state(seed: u64, index: usize) -> u32 {
+    pcg(index as u32 ^ pcg(seed as u32 ^ pcg((seed >> 32) as u32)))
+}
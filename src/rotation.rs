@@ -0,0 +1,75 @@
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use wgpu_lorenz::hyper::ROTATION_PLANES;
+
+// * RADIANS PER SECOND
+const ROTATION_SPEED: f32 = 0.5;
+
+// * ONE KEY PER PLANE OF ROTATION_PLANES: xy, xz, xw, yz, yw, zw
+const PLANE_KEYS: [VirtualKeyCode; ROTATION_PLANES.len()] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+];
+
+/// Turns the 4D rotation of a hyperchaotic system while the keys 1 to 6 are held, one per
+/// rotation plane. Shift turns the other way and 0 goes back to no rotation.
+#[derive(Default)]
+pub struct RotationController {
+    pressed: [bool; ROTATION_PLANES.len()],
+    reverse: bool,
+    reset: bool,
+}
+
+impl RotationController {
+    pub fn handle_key_input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                let is_pressed = *state == ElementState::Pressed;
+                if *keycode == VirtualKeyCode::Key0 {
+                    self.reset |= is_pressed;
+                    return true;
+                }
+                match PLANE_KEYS.iter().position(|key| key == keycode) {
+                    Some(plane) => {
+                        self.pressed[plane] = is_pressed;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            // * NOT CONSUMED, THE CAMERA USES SHIFT AS WELL
+            WindowEvent::ModifiersChanged(modifier_state) => {
+                self.reverse = modifier_state.shift();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Turns `rotation` by the keys held for `dt` seconds. Returns whether it changed.
+    pub fn update(&mut self, rotation: &mut [f32; ROTATION_PLANES.len()], dt: f32) -> bool {
+        let mut changed = false;
+        if std::mem::take(&mut self.reset) {
+            *rotation = [0.; ROTATION_PLANES.len()];
+            changed = true;
+        }
+        let step = if self.reverse { -1. } else { 1. } * ROTATION_SPEED * dt;
+        for (angle, _) in rotation.iter_mut().zip(self.pressed).filter(|(_, p)| *p) {
+            *angle = (*angle + step) % std::f32::consts::TAU;
+            changed = true;
+        }
+        changed
+    }
+}
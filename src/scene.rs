@@ -0,0 +1,747 @@
+use std::{
+    borrow::Cow,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    backend::Backend,
+    camera::CameraSettings,
+    config::{
+        workgroups_for, Config, DEFAULT_DELTA_TIME, EXPOSURE, NUMBER_LORENZ_POINTS, SMOOTH_SHADING,
+    },
+    coupling::Coupling,
+    delay::Delay,
+    embedding::Embedding,
+    emitter::{Emitter, DEAD},
+    enkf::Assimilation,
+    equations::{CustomSystem, Equations},
+    hyper::{Hyper, HYPER_DIMENSIONS},
+    integrator::Integrator,
+    lorenz::{Distribution, LorenzConfig},
+    lorenz96::{Lorenz96, MAX_DIMENSIONS},
+    maps::Map,
+    noise::Noise,
+    respawn::{Respawn, RespawnSettings},
+    strobe::Strobe,
+    twins::Twins,
+};
+
+/// Default window size.
+pub const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize {
+    width: 1600,
+    height: 900,
+};
+
+// * LARGEST INSTANCE BUFFER THAT FITS THE DEFAULT STORAGE BINDING LIMIT (128 MiB / 32 B)
+const MAX_PARTICLES: usize = 1 << 22;
+
+// * SAME LIMIT FOR THE LORENZ-96 STATE BUFFER (128 MiB / 4 B)
+const MAX_STATES: usize = 1 << 25;
+
+const BUILTIN_COLORMAPS: [(&str, &[u8]); 2] = [
+    ("gradient", include_bytes!("../gradient.png")),
+    ("cloud", include_bytes!("../cloud.png")),
+];
+
+/// Dynamical system the particles follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum System {
+    Lorenz,
+    /// Lorenz-96 with the `[lorenz96]` settings, drawn through its projection. Its particles
+    /// are never respawned.
+    Lorenz96,
+    /// The vector field given by the `[custom]` expressions.
+    Custom,
+    /// The iterated map of the `[map]` table, applied once per step.
+    Map,
+    /// The 4D system of the `[hyper]` table, drawn through its rotation.
+    Hyper,
+    /// The delay differential equation of the `[delay]` table, drawn at its delay embedding.
+    Delay,
+}
+
+/// How particles are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderMode {
+    /// Particles are drawn as little lit spheres.
+    Shaded,
+    /// Particles are drawn as flat discs.
+    Flat,
+    /// Particles add up their color, bright where they are dense. Best for maps and other
+    /// flat attractors.
+    Density,
+}
+
+/// Initial size of the viewer window.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: WINDOW_SIZE.width,
+            height: WINDOW_SIZE.height,
+        }
+    }
+}
+
+/// Everything needed to set up a run, as read from a TOML scene file.
+/// Missing fields fall back to the built-in defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub system: System,
+    pub backend: Backend,
+    pub integrator: Integrator,
+    pub parameters: LorenzConfig,
+    /// Used with `system = "lorenz96"`, together with `step_size_factor` of `parameters`.
+    pub lorenz96: Lorenz96,
+    /// Used with `system = "custom"`.
+    pub custom: CustomSystem,
+    /// Used with `system = "map"`.
+    pub map: Map,
+    /// Used with `system = "hyper"`, together with `step_size_factor` of `parameters`.
+    pub hyper: Hyper,
+    /// Used with `system = "delay"`, which has its own step size.
+    pub delay: Delay,
+    pub particles: usize,
+    pub distribution: Distribution,
+    pub seed: Option<u64>,
+    pub delta_time: f32,
+    pub respawn: RespawnSettings,
+    /// Emit particles continuously instead of seeding them all at the start.
+    pub emitter: Option<Emitter>,
+    /// Noise of the `euler-maruyama` and `stochastic-heun` integrators.
+    pub noise: Noise,
+    /// Assimilate noisy observations of a hidden truth into the particles.
+    pub assimilation: Option<Assimilation>,
+    /// Rebuild the attractor from delay coordinates of a single observable.
+    pub embedding: Option<Embedding>,
+    /// Show one sample per forcing period instead of every step.
+    pub strobe: Option<Strobe>,
+    /// Couple the particles to each other and watch them synchronize.
+    pub coupling: Option<Coupling>,
+    /// Create the particles in pairs a tiny distance apart and watch them diverge.
+    pub twins: Option<Twins>,
+    pub camera: CameraSettings,
+    /// Name of a built-in colormap (`gradient`, `cloud`) or path to a PNG.
+    pub colormap: String,
+    pub render_mode: RenderMode,
+    /// Brightness each particle adds with `render_mode = "density"`.
+    pub exposure: f32,
+    pub window: WindowSettings,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            system: System::Lorenz,
+            backend: Backend::default(),
+            integrator: Integrator::default(),
+            parameters: LorenzConfig::default(),
+            lorenz96: Lorenz96::default(),
+            custom: CustomSystem::default(),
+            map: Map::default(),
+            hyper: Hyper::default(),
+            delay: Delay::default(),
+            particles: NUMBER_LORENZ_POINTS,
+            distribution: Distribution::default(),
+            seed: None,
+            delta_time: DEFAULT_DELTA_TIME,
+            respawn: RespawnSettings::default(),
+            emitter: None,
+            noise: Noise::default(),
+            assimilation: None,
+            embedding: None,
+            strobe: None,
+            coupling: None,
+            twins: None,
+            camera: CameraSettings::default(),
+            colormap: BUILTIN_COLORMAPS[0].0.to_owned(),
+            render_mode: if SMOOTH_SHADING {
+                RenderMode::Flat
+            } else {
+                RenderMode::Shaded
+            },
+            exposure: EXPOSURE,
+            window: WindowSettings::default(),
+        }
+    }
+}
+
+/// Reading, parsing or validating a scene failed.
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Invalid {
+        field: &'static str,
+        reason: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => {
+                write!(f, "could not read `{}`: {source}", path.display())
+            }
+            SceneError::Parse { path, source } => {
+                write!(
+                    f,
+                    "could not parse scene file `{}`:\n{source}",
+                    path.display()
+                )
+            }
+            SceneError::Invalid { field, reason } => write!(f, "invalid `{field}`: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> SceneError {
+    SceneError::Invalid {
+        field,
+        reason: reason.into(),
+    }
+}
+
+/// Checks that `system` can be stepped with `integrator`. Only the Lorenz system and custom
+/// vector fields have noise, the other systems reject the stochastic integrators.
+pub(crate) fn check_integrator(system: System, integrator: Integrator) -> Result<(), SceneError> {
+    let name = match system {
+        System::Lorenz | System::Custom => return Ok(()),
+        System::Lorenz96 => "lorenz96",
+        System::Hyper => "hyper",
+        System::Delay => "delay",
+        System::Map => "maps",
+    };
+    if integrator.is_stochastic() {
+        return Err(invalid(
+            "integrator",
+            format!("stochastic integrators are not supported by {name}"),
+        ));
+    }
+    Ok(())
+}
+
+fn check_finite(field: &'static str, value: f32) -> Result<(), SceneError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(
+            field,
+            format!("must be a finite number, got {value}"),
+        ))
+    }
+}
+
+fn check_positive(field: &'static str, value: f32) -> Result<(), SceneError> {
+    check_finite(field, value)?;
+    if value > 0. {
+        Ok(())
+    } else {
+        Err(invalid(
+            field,
+            format!("must be greater than 0, got {value}"),
+        ))
+    }
+}
+
+impl Scene {
+    /// Reads and parses a scene file. Fields are only checked by [`Scene::into_config`].
+    pub fn load(path: &Path) -> Result<Self, SceneError> {
+        let text = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let mut scene: Scene = toml::from_str(&text).map_err(|source| SceneError::Parse {
+            path: path.to_owned(),
+            source,
+        })?;
+        // * COLORMAP PATHS ARE RELATIVE TO THE SCENE FILE
+        if !scene.is_builtin_colormap() {
+            if let Some(dir) = path.parent() {
+                scene.colormap = dir.join(&scene.colormap).to_string_lossy().into_owned();
+            }
+        }
+        Ok(scene)
+    }
+
+    fn is_builtin_colormap(&self) -> bool {
+        BUILTIN_COLORMAPS
+            .iter()
+            .any(|(name, _)| *name == self.colormap)
+    }
+
+    fn load_colormap(&self) -> Result<Cow<'static, [u8]>, SceneError> {
+        if let Some((_, bytes)) = BUILTIN_COLORMAPS
+            .iter()
+            .find(|(name, _)| *name == self.colormap)
+        {
+            return Ok(Cow::Borrowed(*bytes));
+        }
+        let path = PathBuf::from(&self.colormap);
+        let bytes = fs::read(&path).map_err(|source| SceneError::Io { path, source })?;
+        image::load_from_memory(&bytes)
+            .map_err(|e| invalid("colormap", format!("`{}` is not a PNG: {e}", self.colormap)))?;
+        Ok(Cow::Owned(bytes))
+    }
+
+    fn parse_custom(&self) -> Result<Equations, SceneError> {
+        for (name, value) in &self.custom.parameters {
+            if !value.is_finite() {
+                return Err(invalid(
+                    "custom.parameters",
+                    format!("`{name}` must be a finite number, got {value}"),
+                ));
+            }
+        }
+        Equations::parse(&self.custom).map_err(|e| {
+            let field = match e.component {
+                "dx" => "custom.dx",
+                "dy" => "custom.dy",
+                "dz" => "custom.dz",
+                _ => "custom.parameters",
+            };
+            invalid(field, e.to_string())
+        })
+    }
+
+    fn check_lorenz96(&self, particles: usize) -> Result<(), SceneError> {
+        let l = &self.lorenz96;
+        if !(4..=MAX_DIMENSIONS).contains(&l.dimensions) {
+            return Err(invalid(
+                "lorenz96.dimensions",
+                format!(
+                    "must be between 4 and {MAX_DIMENSIONS}, got {}",
+                    l.dimensions
+                ),
+            ));
+        }
+        if particles * l.dimensions > MAX_STATES {
+            return Err(invalid(
+                "lorenz96.dimensions",
+                format!(
+                    "{particles} particles with {} variables each exceed {MAX_STATES} variables",
+                    l.dimensions
+                ),
+            ));
+        }
+        check_finite("lorenz96.forcing", l.forcing)?;
+        check_finite("lorenz96.perturbation", l.perturbation)?;
+        if l.perturbation < 0. {
+            return Err(invalid(
+                "lorenz96.perturbation",
+                format!("must not be negative, got {}", l.perturbation),
+            ));
+        }
+        if let Some(c) = l.components.iter().find(|c| **c >= l.dimensions) {
+            return Err(invalid(
+                "lorenz96.components",
+                format!("must be below the {} dimensions, got {c}", l.dimensions),
+            ));
+        }
+        if self.emitter.is_some() {
+            return Err(invalid("emitter", "not supported by lorenz96"));
+        }
+        if self.respawn != RespawnSettings::default() {
+            return Err(invalid("respawn", "not supported by lorenz96"));
+        }
+        Ok(())
+    }
+
+    fn check_hyper(&self, particles: usize) -> Result<(), SceneError> {
+        let h = &self.hyper;
+        for (field, value) in [
+            ("hyper.a", h.a),
+            ("hyper.b", h.b),
+            ("hyper.c", h.c),
+            ("hyper.d", h.d),
+        ] {
+            value.map_or(Ok(()), |v| check_finite(field, v))?;
+        }
+        check_finite("hyper.w", h.w)?;
+        check_positive("hyper.scale", h.scale)?;
+        h.rotation
+            .iter()
+            .try_for_each(|angle| check_finite("hyper.rotation", *angle))?;
+        if let Some([lo, hi]) = h.w_range {
+            check_finite("hyper.w_range", lo)?;
+            check_finite("hyper.w_range", hi)?;
+            if lo >= hi {
+                return Err(invalid(
+                    "hyper.w_range",
+                    format!("must be increasing, got [{lo}, {hi}]"),
+                ));
+            }
+        }
+        if particles * HYPER_DIMENSIONS > MAX_STATES {
+            return Err(invalid(
+                "particles",
+                format!("{particles} particles exceed {MAX_STATES} variables in 4D"),
+            ));
+        }
+        if self.emitter.is_some() {
+            return Err(invalid("emitter", "not supported by hyper"));
+        }
+        Ok(())
+    }
+
+    fn check_delay(&self, particles: usize) -> Result<(), SceneError> {
+        let d = &self.delay;
+        for (field, value) in [("delay.a", d.a), ("delay.b", d.b), ("delay.c", d.c)] {
+            value.map_or(Ok(()), |v| check_finite(field, v))?;
+        }
+        check_positive("delay.tau", d.tau())?;
+        if d.samples == 0 {
+            return Err(invalid("delay.samples", "must be at least 1"));
+        }
+        if let Some(e) = d.embedding {
+            check_positive("delay.embedding", e)?;
+        }
+        let [lo, hi] = d.initial();
+        check_finite("delay.initial", lo)?;
+        check_finite("delay.initial", hi)?;
+        if lo > hi {
+            return Err(invalid(
+                "delay.initial",
+                format!("must not be decreasing, got [{lo}, {hi}]"),
+            ));
+        }
+        check_finite("delay.center", d.center())?;
+        check_positive("delay.scale", d.scale())?;
+        if particles.saturating_mul(d.history_len()) > MAX_STATES {
+            return Err(invalid(
+                "delay",
+                format!(
+                    "{particles} particles with {} samples of history each exceed {MAX_STATES} \
+                     variables",
+                    d.history_len()
+                ),
+            ));
+        }
+        if self.emitter.is_some() {
+            return Err(invalid("emitter", "not supported by delay"));
+        }
+        Ok(())
+    }
+
+    fn check_map(&self) -> Result<(), SceneError> {
+        let m = &self.map;
+        for (field, value) in [
+            ("map.a", m.a),
+            ("map.b", m.b),
+            ("map.c", m.c),
+            ("map.d", m.d),
+        ] {
+            value.map_or(Ok(()), |v| check_finite(field, v))?;
+        }
+        check_positive("map.scale", m.scale)?;
+        check_positive("map.spacing", m.spacing)?;
+        if m.layers == 0 {
+            return Err(invalid("map.layers", "must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Checks every field and turns the scene into the runtime [`Config`].
+    pub fn into_config(self) -> Result<Config, SceneError> {
+        let p = &self.parameters;
+        check_finite("parameters.rho", p.rho)?;
+        check_finite("parameters.sigma", p.sigma)?;
+        check_finite("parameters.beta", p.beta)?;
+        check_positive("parameters.step_size_factor", p.step_size_factor)?;
+
+        if self.particles == 0 || self.particles > MAX_PARTICLES {
+            return Err(invalid(
+                "particles",
+                format!(
+                    "must be between 1 and {MAX_PARTICLES}, got {}",
+                    self.particles
+                ),
+            ));
+        }
+
+        let d = &self.distribution;
+        d.center
+            .iter()
+            .try_for_each(|c| check_finite("distribution.center", *c))?;
+        check_positive("distribution.extent", d.extent)?;
+
+        check_positive("delta_time", self.delta_time)?;
+
+        let r = &self.respawn;
+        check_positive("respawn.radius", r.radius)?;
+        check_finite("respawn.min_speed", r.min_speed)?;
+        if r.min_speed < 0. {
+            return Err(invalid(
+                "respawn.min_speed",
+                format!("must not be negative, got {}", r.min_speed),
+            ));
+        }
+        if let Some(d) = &r.distribution {
+            d.center
+                .iter()
+                .try_for_each(|c| check_finite("respawn.distribution.center", *c))?;
+            check_positive("respawn.distribution.extent", d.extent)?;
+        }
+
+        let (num_lorenz_points, num_workgroups) = workgroups_for(self.particles);
+
+        if let Some(e) = &self.emitter {
+            e.position
+                .iter()
+                .chain(&e.end)
+                .try_for_each(|v| check_finite("emitter", *v))?;
+            check_positive("emitter.radius", e.radius)?;
+            if e.rate == 0 || e.rate as usize > num_lorenz_points {
+                return Err(invalid(
+                    "emitter.rate",
+                    format!(
+                        "must be between 1 and the {num_lorenz_points} particles, got {}",
+                        e.rate
+                    ),
+                ));
+            }
+            if e.lifetime == 0 || e.lifetime == DEAD {
+                return Err(invalid(
+                    "emitter.lifetime",
+                    format!("must be between 1 and {}, got {}", DEAD - 1, e.lifetime),
+                ));
+            }
+        }
+
+        for a in self.noise.amplitude {
+            check_finite("noise.amplitude", a)?;
+            if a < 0. {
+                return Err(invalid(
+                    "noise.amplitude",
+                    format!("must not be negative, got {a}"),
+                ));
+            }
+        }
+
+        check_integrator(self.system, self.integrator)?;
+        if self.system == System::Lorenz96 {
+            self.check_lorenz96(num_lorenz_points)?;
+        }
+        if self.system == System::Map {
+            self.check_map()?;
+        }
+        if self.system == System::Hyper {
+            self.check_hyper(num_lorenz_points)?;
+        }
+        if self.system == System::Delay {
+            self.check_delay(num_lorenz_points)?;
+        }
+        let custom = if self.system == System::Custom {
+            Some(self.parse_custom()?)
+        } else {
+            None
+        };
+
+        if let Some(a) = &self.assimilation {
+            if a.interval == 0 {
+                return Err(invalid("assimilation.interval", "must be at least 1"));
+            }
+            check_positive("assimilation.observation_noise", a.observation_noise)?;
+            a.truth
+                .iter()
+                .try_for_each(|v| check_finite("assimilation.truth", *v))?;
+            if matches!(
+                self.system,
+                System::Lorenz96 | System::Map | System::Hyper | System::Delay
+            ) {
+                return Err(invalid(
+                    "assimilation",
+                    format!("not supported by {:?}", self.system).to_lowercase(),
+                ));
+            }
+            if self.emitter.is_some() {
+                return Err(invalid("assimilation", "not supported with an emitter"));
+            }
+            if num_lorenz_points < 2 {
+                return Err(invalid("assimilation", "needs at least 2 particles"));
+            }
+        }
+
+        if let Some(s) = &self.strobe {
+            check_positive("strobe.period", s.period)?;
+            if s.samples == 0 {
+                return Err(invalid("strobe.samples", "must be at least 1"));
+            }
+            if self.system == System::Map {
+                return Err(invalid("strobe", "maps have no forcing period"));
+            }
+            if self.system == System::Delay {
+                return Err(invalid(
+                    "strobe",
+                    "delay equations step a fixed fraction of their delay",
+                ));
+            }
+            if self.assimilation.is_some() {
+                return Err(invalid("strobe", "not supported with assimilation"));
+            }
+        }
+
+        if let Some(c) = &self.coupling {
+            check_finite("coupling.strength", c.strength)?;
+            if c.strength < 0. {
+                return Err(invalid(
+                    "coupling.strength",
+                    format!("must not be negative, got {}", c.strength),
+                ));
+            }
+            c.through
+                .iter()
+                .try_for_each(|v| check_finite("coupling.through", *v))?;
+            check_positive("coupling.range", c.range)?;
+            if !matches!(self.system, System::Lorenz | System::Custom) {
+                return Err(invalid(
+                    "coupling",
+                    format!("not supported by {:?}", self.system).to_lowercase(),
+                ));
+            }
+            if self.emitter.is_some() {
+                return Err(invalid("coupling", "not supported with an emitter"));
+            }
+        }
+
+        if let Some(t) = &self.twins {
+            check_positive("twins.separation", t.separation)?;
+            t.colors
+                .iter()
+                .flatten()
+                .try_for_each(|c| check_finite("twins.colors", *c))?;
+            if !matches!(self.system, System::Lorenz | System::Custom) {
+                return Err(invalid(
+                    "twins",
+                    format!("not supported by {:?}", self.system).to_lowercase(),
+                ));
+            }
+            if self.emitter.is_some() {
+                return Err(invalid("twins", "not supported with an emitter"));
+            }
+            if self.coupling.is_some() {
+                return Err(invalid("twins", "not supported with a coupling"));
+            }
+        }
+
+        if let Some(e) = &self.embedding {
+            if e.particles == 0 || e.particles > num_lorenz_points {
+                return Err(invalid(
+                    "embedding.particles",
+                    format!(
+                        "must be between 1 and the {num_lorenz_points} particles, got {}",
+                        e.particles
+                    ),
+                ));
+            }
+            if e.delay == 0 {
+                return Err(invalid("embedding.delay", "must be at least 1"));
+            }
+            if e.history <= 2 * e.delay {
+                return Err(invalid(
+                    "embedding.history",
+                    format!("must be longer than twice the delay, got {}", e.history),
+                ));
+            }
+            if e.particles * e.history > MAX_PARTICLES {
+                return Err(invalid(
+                    "embedding",
+                    format!("particles times history must be at most {MAX_PARTICLES}"),
+                ));
+            }
+            e.offset
+                .iter()
+                .try_for_each(|v| check_finite("embedding.offset", *v))?;
+        }
+
+        let c = &self.camera;
+        c.position
+            .iter()
+            .try_for_each(|v| check_finite("camera.position", *v))?;
+        c.direction
+            .iter()
+            .try_for_each(|v| check_finite("camera.direction", *v))?;
+        if c.direction == [0.; 3] {
+            return Err(invalid("camera.direction", "must not be the zero vector"));
+        }
+        if !(c.fov_y > 0. && c.fov_y < 180.) {
+            return Err(invalid(
+                "camera.fov_y",
+                format!("must be between 0 and 180 degrees, got {}", c.fov_y),
+            ));
+        }
+        check_positive("camera.speed", c.speed)?;
+        check_positive("camera.sensitivity", c.sensitivity)?;
+
+        if self.window.width == 0 || self.window.height == 0 {
+            return Err(invalid(
+                "window",
+                format!(
+                    "size must be non-zero, got {}x{}",
+                    self.window.width, self.window.height
+                ),
+            ));
+        }
+
+        if self.render_mode == RenderMode::Density {
+            check_positive("exposure", self.exposure)?;
+        }
+
+        let colormap = self.load_colormap()?;
+
+        Ok(Config {
+            system: self.system,
+            backend: self.backend,
+            integrator: self.integrator,
+            lorenz: self.parameters,
+            num_lorenz_points,
+            num_workgroups,
+            smooth_shading: self.render_mode == RenderMode::Flat,
+            density: (self.render_mode == RenderMode::Density).then_some(self.exposure),
+            distribution: self.distribution,
+            seed: self.seed,
+            delta_time: self.delta_time,
+            respawn: Respawn {
+                radius: self.respawn.radius,
+                min_speed: self.respawn.min_speed,
+                distribution: self.respawn.distribution.unwrap_or(self.distribution),
+            },
+            emitter: self.emitter,
+            noise: self.noise,
+            assimilation: self.assimilation,
+            embedding: self.embedding,
+            lorenz96: self.lorenz96,
+            custom,
+            map: self.map,
+            hyper: self.hyper,
+            delay: self.delay,
+            strobe: self.strobe,
+            coupling: self.coupling,
+            twins: self.twins,
+            camera: self.camera,
+            colormap,
+            window_size: PhysicalSize::new(self.window.width, self.window.height),
+        })
+    }
+}
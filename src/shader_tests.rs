@@ -0,0 +1,600 @@
+//! Checks the WGSL shaders against the Rust side without a GPU: they have to validate, every
+//! binding has to match a bind group layout entry, and every uniform and instance struct has
+//! to have the layout of its hand padded Rust counterpart.
+
+use std::mem::{offset_of, size_of};
+
+use naga::{
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+    AddressSpace, ImageClass, Module, ShaderStage, StorageAccess, TypeInner,
+};
+use wgpu::{BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderStages};
+
+use crate::{
+    analysis::{self, AnalysisUniform, Moments, ANALYSIS_WGSL, WORKGROUPS},
+    camera::{self, CameraUniform},
+    compute::{self, COMPUTE_WGSL},
+    config::{
+        ClockShader, ConfigComputeShader, ConfigDrawShader, CouplingShader, DelayShader,
+        EmitterShader, HyperShader, Lorenz96Shader, MapShader, NoiseShader, RespawnShader,
+        TwinsShader,
+    },
+    dimension::{self, BoxCountUniform, DIMENSION_WGSL},
+    equations::{CustomSystem, Equations},
+    instance::{DrawState, RawInstance},
+    lorenz::LorenzConfig,
+    lorenz96::MAX_DIMENSIONS,
+    render::{self, DRAW_WGSL},
+    stats::{self, StatsShader, StatsUniform, STATS_WGSL},
+    texture,
+};
+
+fn parse(source: &str) -> (Module, ModuleInfo) {
+    let module = naga::front::wgsl::parse_str(source).unwrap_or_else(|e| {
+        panic!("{}", e.emit_to_string(source));
+    });
+    let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{e:?}"));
+    (module, info)
+}
+
+fn stages(
+    module: &Module,
+    info: &ModuleInfo,
+    global: naga::Handle<naga::GlobalVariable>,
+) -> ShaderStages {
+    let mut stages = ShaderStages::NONE;
+    for (i, entry_point) in module.entry_points.iter().enumerate() {
+        if !info.get_entry_point(i)[global].is_empty() {
+            stages |= match entry_point.stage {
+                ShaderStage::Vertex => ShaderStages::VERTEX,
+                ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                ShaderStage::Compute => ShaderStages::COMPUTE,
+            };
+        }
+    }
+    stages
+}
+
+/// Size in bytes of a WGSL type; for runtime sized arrays the size of one element.
+fn size_of_wgsl(module: &Module, ty: naga::Handle<naga::Type>) -> u32 {
+    match module.types[ty].inner {
+        TypeInner::Array {
+            size: naga::ArraySize::Dynamic,
+            stride,
+            ..
+        } => stride,
+        ref inner => inner.size(module.to_ctx()),
+    }
+}
+
+/// Compares every resource binding of `source` with the layout entries of its bind groups.
+/// `sizes` gives the Rust size of the data bound to each buffer binding.
+fn assert_bindings_match(
+    source: &str,
+    groups: &[&[BindGroupLayoutEntry]],
+    sizes: &[((u32, u32), usize)],
+) {
+    let (module, info) = parse(source);
+    let mut declared = 0;
+    for (handle, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        declared += 1;
+        let name = global.name.as_deref().unwrap_or("?");
+        let key = (binding.group, binding.binding);
+        let entry = groups
+            .get(binding.group as usize)
+            .and_then(|entries| entries.iter().find(|e| e.binding == binding.binding))
+            .unwrap_or_else(|| panic!("`{name}` {key:?} has no layout entry"));
+
+        let used = stages(&module, &info, handle);
+        assert!(
+            entry.visibility.contains(used),
+            "`{name}` {key:?} is used in {used:?} but visible in {:?}",
+            entry.visibility
+        );
+
+        match (global.space, &module.types[global.ty].inner, entry.ty) {
+            (
+                AddressSpace::Uniform,
+                _,
+                BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    ..
+                },
+            ) => {}
+            (
+                AddressSpace::Storage { access },
+                _,
+                BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only },
+                    ..
+                },
+            ) => assert_eq!(
+                read_only,
+                !access.contains(StorageAccess::STORE),
+                "`{name}` {key:?} access"
+            ),
+            (
+                AddressSpace::Handle,
+                TypeInner::Image {
+                    class: ImageClass::Sampled { multi, .. },
+                    ..
+                },
+                BindingType::Texture { multisampled, .. },
+            ) => assert_eq!(*multi, multisampled, "`{name}` {key:?} multisampling"),
+            (AddressSpace::Handle, TypeInner::Sampler { .. }, BindingType::Sampler(_)) => {}
+            (space, _, ty) => panic!("`{name}` {key:?} is {space:?} in WGSL but {ty:?} in Rust"),
+        }
+
+        if let BindingType::Buffer { .. } = entry.ty {
+            let (_, rust) = sizes
+                .iter()
+                .find(|(k, _)| *k == key)
+                .unwrap_or_else(|| panic!("no Rust size given for `{name}` {key:?}"));
+            assert_eq!(
+                size_of_wgsl(&module, global.ty) as usize,
+                *rust,
+                "size of `{name}` {key:?}"
+            );
+        }
+    }
+    let entries: usize = groups.iter().map(|entries| entries.len()).sum();
+    assert_eq!(declared, entries, "layout entries without a WGSL binding");
+}
+
+/// Compares the size and member offsets of a WGSL struct with the Rust ones.
+fn assert_struct_layout(source: &str, name: &str, size: usize, offsets: &[(&str, usize)]) {
+    let (module, _) = parse(source);
+    let (members, span) = module
+        .types
+        .iter()
+        .find_map(|(_, ty)| match &ty.inner {
+            TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => {
+                Some((members, *span))
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no struct `{name}`"));
+    assert_eq!(span as usize, size, "size of `{name}`");
+    let wgsl: Vec<_> = members
+        .iter()
+        .map(|m| (m.name.as_deref().unwrap_or("?"), m.offset as usize))
+        .collect();
+    assert_eq!(wgsl, offsets, "members of `{name}`");
+}
+
+#[test]
+fn compute_bindings_match_layouts() {
+    assert_bindings_match(
+        COMPUTE_WGSL,
+        &[
+            &compute::BIND_GROUP_LAYOUT_ENTRIES,
+            &texture::bind_group_layout_entries(ShaderStages::COMPUTE),
+        ],
+        &[
+            ((0, 0), size_of::<RawInstance>()),
+            ((0, 1), size_of::<ConfigComputeShader>()),
+            ((0, 2), size_of::<f32>()),
+            ((0, 3), size_of::<u32>()),
+            ((0, 4), size_of::<DrawState>()),
+            ((0, 5), size_of::<f32>()),
+            ((0, 6), size_of::<[[f32; 4]; MAX_DIMENSIONS]>()),
+            ((0, 7), size_of::<ClockShader>()),
+        ],
+    );
+}
+
+#[test]
+fn custom_velocity_validates() {
+    let system = CustomSystem {
+        dx: "a * sin(y) - x ^ 3 + pow(abs(z), 0.5)".to_owned(),
+        dy: "max(x, -y) * t / (1 + z ^ -2)".to_owned(),
+        dz: "-x ^ 2 + sign(y) * exp(-t) - pi".to_owned(),
+        parameters: [("a".to_owned(), -1.5)].into(),
+    };
+    let velocity = Equations::parse(&system).unwrap().wgsl();
+    let spliced = compute::splice_velocity(COMPUTE_WGSL, Some(&velocity));
+    assert!(spliced.contains(&velocity));
+    assert_eq!(spliced.matches("fn lorenz_vel(").count(), 1);
+    parse(&spliced);
+    parse(&compute::splice_velocity(STATS_WGSL, Some(&velocity)));
+}
+
+#[test]
+fn analysis_bindings_match_layouts() {
+    assert_bindings_match(
+        ANALYSIS_WGSL,
+        &[&analysis::BIND_GROUP_LAYOUT_ENTRIES],
+        &[
+            ((0, 0), size_of::<RawInstance>()),
+            ((0, 1), size_of::<AnalysisUniform>()),
+            ((0, 2), size_of::<[Moments; WORKGROUPS]>()),
+        ],
+    );
+}
+
+#[test]
+fn stats_bindings_match_layouts() {
+    assert_bindings_match(
+        STATS_WGSL,
+        &[&stats::BIND_GROUP_LAYOUT_ENTRIES],
+        &[
+            ((0, 0), size_of::<RawInstance>()),
+            ((0, 1), size_of::<StatsUniform>()),
+            ((0, 2), size_of::<[StatsShader; stats::WORKGROUPS]>()),
+            ((0, 3), size_of::<StatsShader>()),
+        ],
+    );
+}
+
+#[test]
+fn dimension_bindings_match_layouts() {
+    assert_bindings_match(
+        DIMENSION_WGSL,
+        &[&dimension::BIND_GROUP_LAYOUT_ENTRIES],
+        &[
+            ((0, 0), size_of::<RawInstance>()),
+            ((0, 1), size_of::<BoxCountUniform>()),
+            ((0, 2), size_of::<u32>()),
+            ((0, 3), size_of::<[u32; dimension::LEVELS]>()),
+        ],
+    );
+}
+
+#[test]
+fn draw_bindings_match_layouts() {
+    assert_bindings_match(
+        DRAW_WGSL,
+        &[
+            &camera::BIND_GROUP_LAYOUT_ENTRIES,
+            &render::CONFIG_BIND_GROUP_LAYOUT_ENTRIES,
+        ],
+        &[
+            ((0, 0), size_of::<CameraUniform>()),
+            ((1, 0), size_of::<ConfigDrawShader>()),
+        ],
+    );
+}
+
+#[test]
+fn compute_structs_match_rust_layout() {
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Instance",
+        size_of::<RawInstance>(),
+        &[
+            ("pos", offset_of!(RawInstance, pos)),
+            ("rng", offset_of!(RawInstance, rng)),
+            ("color", offset_of!(RawInstance, color)),
+            ("age", offset_of!(RawInstance, age)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "DrawState",
+        size_of::<DrawState>(),
+        &[
+            ("vertex_count", offset_of!(DrawState, vertex_count)),
+            ("instance_count", offset_of!(DrawState, instance_count)),
+            ("first_vertex", offset_of!(DrawState, first_vertex)),
+            ("first_instance", offset_of!(DrawState, first_instance)),
+            ("head", offset_of!(DrawState, head)),
+            ("step", offset_of!(DrawState, step)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Clock",
+        size_of::<ClockShader>(),
+        &[
+            ("time", offset_of!(ClockShader, time)),
+            ("step", offset_of!(ClockShader, step)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Lorenz96",
+        size_of::<Lorenz96Shader>(),
+        &[
+            ("dimensions", offset_of!(Lorenz96Shader, dimensions)),
+            ("forcing", offset_of!(Lorenz96Shader, forcing)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Map",
+        size_of::<MapShader>(),
+        &[
+            ("parameters", offset_of!(MapShader, parameters)),
+            ("kind", offset_of!(MapShader, kind)),
+            ("enabled", offset_of!(MapShader, enabled)),
+            ("scale", offset_of!(MapShader, scale)),
+            ("spacing", offset_of!(MapShader, spacing)),
+            ("depth", offset_of!(MapShader, depth)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Hyper",
+        size_of::<HyperShader>(),
+        &[
+            ("parameters", offset_of!(HyperShader, parameters)),
+            ("kind", offset_of!(HyperShader, kind)),
+            ("color_w", offset_of!(HyperShader, color_w)),
+            ("w", offset_of!(HyperShader, w)),
+            ("w_min", offset_of!(HyperShader, w_min)),
+            ("w_max", offset_of!(HyperShader, w_max)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Delay",
+        size_of::<DelayShader>(),
+        &[
+            ("parameters", offset_of!(DelayShader, parameters)),
+            ("kind", offset_of!(DelayShader, kind)),
+            ("samples", offset_of!(DelayShader, samples)),
+            ("length", offset_of!(DelayShader, length)),
+            ("lag", offset_of!(DelayShader, lag)),
+            ("step_size", offset_of!(DelayShader, step_size)),
+            ("tau", offset_of!(DelayShader, tau)),
+            ("center", offset_of!(DelayShader, center)),
+            ("scale", offset_of!(DelayShader, scale)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Coupling",
+        size_of::<CouplingShader>(),
+        &[
+            ("through", offset_of!(CouplingShader, through)),
+            ("topology", offset_of!(CouplingShader, topology)),
+            ("strength", offset_of!(CouplingShader, strength)),
+            ("range", offset_of!(CouplingShader, range)),
+            ("enabled", offset_of!(CouplingShader, enabled)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Twins",
+        size_of::<TwinsShader>(),
+        &[
+            ("first", offset_of!(TwinsShader, first)),
+            ("enabled", offset_of!(TwinsShader, enabled)),
+            ("second", offset_of!(TwinsShader, second)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Noise",
+        size_of::<NoiseShader>(),
+        &[
+            ("amplitude", offset_of!(NoiseShader, amplitude)),
+            ("kind", offset_of!(NoiseShader, kind)),
+            ("key", offset_of!(NoiseShader, key)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Emitter",
+        size_of::<EmitterShader>(),
+        &[
+            ("position", offset_of!(EmitterShader, position)),
+            ("shape", offset_of!(EmitterShader, shape)),
+            ("end", offset_of!(EmitterShader, end)),
+            ("radius", offset_of!(EmitterShader, radius)),
+            ("rate", offset_of!(EmitterShader, rate)),
+            ("lifetime", offset_of!(EmitterShader, lifetime)),
+            ("enabled", offset_of!(EmitterShader, enabled)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Respawn",
+        size_of::<RespawnShader>(),
+        &[
+            ("center", offset_of!(RespawnShader, center)),
+            ("extent", offset_of!(RespawnShader, extent)),
+            ("radius", offset_of!(RespawnShader, radius)),
+            ("min_speed", offset_of!(RespawnShader, min_speed)),
+            ("shape", offset_of!(RespawnShader, shape)),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "LorenzConfig",
+        size_of::<LorenzConfig>(),
+        &[
+            ("rho", offset_of!(LorenzConfig, rho)),
+            ("sigma", offset_of!(LorenzConfig, sigma)),
+            ("beta", offset_of!(LorenzConfig, beta)),
+            (
+                "step_size_factor",
+                offset_of!(LorenzConfig, step_size_factor),
+            ),
+        ],
+    );
+    assert_struct_layout(
+        COMPUTE_WGSL,
+        "Config",
+        size_of::<ConfigComputeShader>(),
+        &[
+            ("lorenz", offset_of!(ConfigComputeShader, lorenz)),
+            (
+                "num_workgroups",
+                offset_of!(ConfigComputeShader, num_workgroups),
+            ),
+            ("integrator", offset_of!(ConfigComputeShader, integrator)),
+            ("respawn", offset_of!(ConfigComputeShader, respawn)),
+            ("emitter", offset_of!(ConfigComputeShader, emitter)),
+            ("noise", offset_of!(ConfigComputeShader, noise)),
+            ("lorenz96", offset_of!(ConfigComputeShader, lorenz96)),
+            ("map", offset_of!(ConfigComputeShader, map)),
+            ("hyper", offset_of!(ConfigComputeShader, hyper)),
+            ("delay", offset_of!(ConfigComputeShader, delay)),
+            ("coupling", offset_of!(ConfigComputeShader, coupling)),
+            ("twins", offset_of!(ConfigComputeShader, twins)),
+        ],
+    );
+}
+
+#[test]
+fn analysis_structs_match_rust_layout() {
+    assert_struct_layout(
+        ANALYSIS_WGSL,
+        "Instance",
+        size_of::<RawInstance>(),
+        &[
+            ("pos", offset_of!(RawInstance, pos)),
+            ("rng", offset_of!(RawInstance, rng)),
+            ("color", offset_of!(RawInstance, color)),
+            ("age", offset_of!(RawInstance, age)),
+        ],
+    );
+    assert_struct_layout(
+        ANALYSIS_WGSL,
+        "Analysis",
+        size_of::<AnalysisUniform>(),
+        &[
+            ("center", offset_of!(AnalysisUniform, center)),
+            ("count", offset_of!(AnalysisUniform, count)),
+            ("observation", offset_of!(AnalysisUniform, observation)),
+            (
+                "observation_noise",
+                offset_of!(AnalysisUniform, observation_noise),
+            ),
+            ("gain", offset_of!(AnalysisUniform, gain)),
+            ("key", offset_of!(AnalysisUniform, key)),
+            ("cycle", offset_of!(AnalysisUniform, cycle)),
+        ],
+    );
+    assert_struct_layout(
+        ANALYSIS_WGSL,
+        "Moments",
+        size_of::<Moments>(),
+        &[
+            ("sum", offset_of!(Moments, sum)),
+            ("diagonal", offset_of!(Moments, diagonal)),
+            ("off_diagonal", offset_of!(Moments, off_diagonal)),
+        ],
+    );
+}
+
+#[test]
+fn stats_structs_match_rust_layout() {
+    assert_struct_layout(
+        STATS_WGSL,
+        "StatsUniform",
+        size_of::<StatsUniform>(),
+        &[
+            ("lorenz", offset_of!(StatsUniform, lorenz)),
+            ("center", offset_of!(StatsUniform, center)),
+            ("count", offset_of!(StatsUniform, count)),
+            ("time", offset_of!(StatsUniform, time)),
+            ("speed", offset_of!(StatsUniform, speed)),
+        ],
+    );
+    assert_struct_layout(
+        STATS_WGSL,
+        "Stats",
+        size_of::<StatsShader>(),
+        &[
+            ("sum", offset_of!(StatsShader, sum)),
+            ("count", offset_of!(StatsShader, count)),
+            ("diagonal", offset_of!(StatsShader, diagonal)),
+            ("right", offset_of!(StatsShader, right)),
+            ("off_diagonal", offset_of!(StatsShader, off_diagonal)),
+            ("min_speed", offset_of!(StatsShader, min_speed)),
+            ("lo", offset_of!(StatsShader, lo)),
+            ("max_speed", offset_of!(StatsShader, max_speed)),
+            ("hi", offset_of!(StatsShader, hi)),
+        ],
+    );
+}
+
+#[test]
+fn dimension_structs_match_rust_layout() {
+    assert_struct_layout(
+        DIMENSION_WGSL,
+        "BoxCountUniform",
+        size_of::<BoxCountUniform>(),
+        &[
+            ("origin", offset_of!(BoxCountUniform, origin)),
+            ("scale", offset_of!(BoxCountUniform, scale)),
+            ("cells", offset_of!(BoxCountUniform, cells)),
+            ("count", offset_of!(BoxCountUniform, count)),
+            ("mask", offset_of!(BoxCountUniform, mask)),
+            ("level", offset_of!(BoxCountUniform, level)),
+        ],
+    );
+}
+
+#[test]
+fn draw_structs_match_rust_layout() {
+    assert_struct_layout(
+        DRAW_WGSL,
+        "CameraUniform",
+        size_of::<CameraUniform>(),
+        &[("view_proj", offset_of!(CameraUniform, view_proj))],
+    );
+    assert_struct_layout(
+        DRAW_WGSL,
+        "Config",
+        size_of::<ConfigDrawShader>(),
+        &[
+            (
+                "smooth_shading",
+                offset_of!(ConfigDrawShader, smooth_shading),
+            ),
+            ("exposure", offset_of!(ConfigDrawShader, exposure)),
+            ("aspect_ratio", offset_of!(ConfigDrawShader, aspect_ratio)),
+        ],
+    );
+}
+
+#[test]
+fn instance_attributes_match_vertex_input() {
+    let (module, _) = parse(DRAW_WGSL);
+    let layout = RawInstance::desc();
+    assert_eq!(layout.array_stride as usize, size_of::<RawInstance>());
+    let offsets = [
+        (1, offset_of!(RawInstance, pos)),
+        (2, offset_of!(RawInstance, color)),
+        (3, offset_of!(RawInstance, age)),
+    ];
+    for (location, offset) in offsets {
+        let attribute = layout
+            .attributes
+            .iter()
+            .find(|a| a.shader_location == location)
+            .unwrap_or_else(|| panic!("no attribute at location {location}"));
+        assert_eq!(attribute.offset as usize, offset, "location {location}");
+    }
+
+    // * EVERY INSTANCE LOCATION OF vs_main IS COVERED
+    let vs_main = module
+        .entry_points
+        .iter()
+        .find(|e| e.name == "vs_main")
+        .unwrap();
+    let locations: Vec<u32> = vs_main
+        .function
+        .arguments
+        .iter()
+        .filter_map(|a| match &module.types[a.ty].inner {
+            TypeInner::Struct { members, .. } if a.name.as_deref() == Some("instance") => {
+                Some(members.iter().filter_map(|m| match m.binding {
+                    Some(naga::Binding::Location { location, .. }) => Some(location),
+                    _ => None,
+                }))
+            }
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    assert_eq!(locations, [1, 2, 3]);
+}
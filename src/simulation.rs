@@ -0,0 +1,1027 @@
+use std::{borrow::Cow, cell::OnceCell, ops::Range, sync::Arc};
+
+use glam::{Vec3, Vec4};
+use pollster::FutureExt;
+use rand::{rngs::StdRng, SeedableRng};
+use wgpu::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device,
+    Extent3d, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Maintain, MapMode, Origin3d,
+    Queue, Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::{
+    analysis::{self, AnalysisState, Moments},
+    backend::{Backend, Stepper},
+    camera::{Camera, CameraSettings},
+    compute::ComputeState,
+    config::{ClockShader, Config},
+    coupling::{sync_offset, Coupling, SYNC_HISTORY},
+    cpu::CpuState,
+    dimension::{self, BoxCount, BoxCountState, DimensionReport},
+    emitter::Emitter,
+    enkf::{Ensemble, Update},
+    equations::Equations,
+    error::Error,
+    gpu::find_device,
+    hyper::Hyper,
+    instance::{DrawState, InstancesVec, RawInstance},
+    integrator::Integrator,
+    lorenz::{LorenzConfig, LorenzState},
+    lorenz96::project,
+    maps::Map,
+    noise::Noise,
+    render::RenderState,
+    respawn::{rng_state, Respawn},
+    scene::{check_integrator, System},
+    stats::{EnsembleStats, Readback, StatsShader, StatsState, StatsUniform},
+    strobe::Strobe,
+    twins::{self, Twins},
+};
+
+/// Fraction of a period [`Simulation::step_periods`] may be off and still count as aligned.
+const ALIGNED: f64 = 1e-4;
+
+/// Format of the textures produced by [`Simulation::render_to_texture`].
+pub const RENDER_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// A particle cloud advected through the Lorenz system, on the GPU or on the CPU depending on
+/// [`Config::backend`].
+///
+/// ```no_run
+/// use wgpu_lorenz::{Scene, Simulation};
+///
+/// let config = Scene::default().into_config()?;
+/// let mut simulation = Simulation::new(config)?;
+/// simulation.step(100);
+/// let particles = simulation.read_particles();
+/// # Ok::<(), wgpu_lorenz::Error>(())
+/// ```
+pub struct Simulation {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    config: Config,
+    instances: InstancesVec,
+    compute_state: ComputeState,
+    cpu_state: Option<CpuState>,
+    // * RESPAWNS COUNTED ON THE CPU BEFORE SWITCHING BACK TO THE GPU
+    cpu_respawns: u64,
+    // * ONLY BUILT FOR DATA ASSIMILATION
+    analysis_state: OnceCell<AnalysisState>,
+    // * ONLY BUILT ONCE STATISTICS ARE ASKED FOR
+    stats_state: OnceCell<StatsState>,
+    // * STATISTICS REQUESTED BUT NOT YET POLLED, TAGGED WITH THE STEP AND UNIFORM
+    stats_readback: Option<Readback<StatsShader, (u32, StatsUniform)>>,
+    // * ON THE CPU THE STATISTICS ARE READY AT ONCE
+    cpu_stats: Option<EnsembleStats>,
+    // * MEAN OF THE LAST POLLED STATISTICS, THE COVARIANCE IS SUMMED AROUND IT
+    stats_center: Vec3,
+    // * ONLY BUILT FOR DIMENSION ESTIMATES
+    box_count_state: OnceCell<BoxCountState>,
+    /// Simulation time, the sum of all step sizes so far.
+    time: f64,
+    /// Draw state step the next step starts at, see [`ClockShader`].
+    step: u32,
+}
+
+impl Simulation {
+    /// Creates a headless simulation on the best available adapter, falling back to
+    /// software rendering if there is no GPU.
+    pub fn new(config: Config) -> Result<Self, Error> {
+        let (_, _, device, queue) = find_device(None).block_on()?;
+        Self::with_device(Arc::new(device), Arc::new(queue), config)
+    }
+
+    /// Creates a simulation on an existing device, e.g. one that also drives a window.
+    pub fn with_device(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        mut config: Config,
+    ) -> Result<Self, Error> {
+        let max_binding = device.limits().max_storage_buffer_binding_size as usize;
+        let bytes_per_particle = std::mem::size_of::<RawInstance>()
+            .max(config.state_dimensions() * std::mem::size_of::<f32>());
+        let max = max_binding / bytes_per_particle;
+        if config.num_lorenz_points > max {
+            return Err(Error::TooManyParticles {
+                requested: config.num_lorenz_points,
+                max,
+            });
+        }
+
+        // * PICK A SEED IF THERE IS NONE, SO EVERY RUN CAN BE REPEATED
+        let seed = *config.seed.get_or_insert_with(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let lorenz_state = match config.system {
+            System::Lorenz | System::Custom => {
+                let mut state =
+                    LorenzState::new(config.num_lorenz_points, &config.distribution, &mut rng);
+                if let Some(twins) = &config.twins {
+                    twins.pair(&mut state.points, &mut rng);
+                }
+                state
+            }
+            System::Map => {
+                LorenzState::flat(config.num_lorenz_points, &config.distribution, &mut rng)
+            }
+            System::Lorenz96 => {
+                LorenzState::lorenz96(config.num_lorenz_points, &config.lorenz96, seed, &mut rng)
+            }
+            System::Hyper => LorenzState::hyper(
+                config.num_lorenz_points,
+                &config.distribution,
+                &config.hyper,
+                &mut rng,
+            ),
+            System::Delay => LorenzState::delay(config.num_lorenz_points, &config.delay, &mut rng),
+        };
+        let instances = InstancesVec::from((&lorenz_state, &*device, &config, &mut rng));
+
+        let compute_state =
+            ComputeState::new(&device, &queue, &instances, &config, config.delta_time);
+
+        let cpu_state = (config.backend == Backend::Cpu).then(|| {
+            let mut cpu_state = CpuState::new(&lorenz_state.points, &config);
+            if config.state_dimensions() > 0 {
+                cpu_state.set_states(&lorenz_state.states);
+            }
+            cpu_state
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            config,
+            instances,
+            compute_state,
+            cpu_state,
+            cpu_respawns: 0,
+            analysis_state: OnceCell::new(),
+            stats_state: OnceCell::new(),
+            stats_readback: None,
+            cpu_stats: None,
+            stats_center: Vec3::ZERO,
+            box_count_state: OnceCell::new(),
+            time: 0.,
+            step: 0,
+        })
+    }
+
+    /// The device the simulation runs on.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// The queue all work is submitted to.
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// The current configuration, including changes made through the setters.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Number of particles, after rounding down to a cube.
+    pub fn num_particles(&self) -> usize {
+        self.config.num_lorenz_points
+    }
+
+    /// The particles as an array of [`RawInstance`], usable as vertex or storage buffer.
+    pub fn instance_buffer(&self) -> &Buffer {
+        &self.instances.buffer
+    }
+
+    /// The [`DrawState`] of the particles, usable as indirect buffer for drawing them.
+    pub fn draw_buffer(&self) -> &Buffer {
+        &self.instances.draw_buffer
+    }
+
+    /// The state variables of the particles as `f32`, [`Config::state_dimensions`] per
+    /// particle, on either backend.
+    pub fn state_buffer(&self) -> &Buffer {
+        &self.instances.states
+    }
+
+    /// Advances every particle by `steps` time steps of [`Config::delta_time`].
+    pub fn step(&mut self, steps: u32) {
+        let stepper: &mut dyn Stepper = match &mut self.cpu_state {
+            Some(cpu_state) => cpu_state,
+            None => &mut self.compute_state,
+        };
+        let clock = ClockShader {
+            time: self.time as f32,
+            step: self.step,
+        };
+        stepper.update_clock(clock, &self.queue);
+        stepper.step(&self.device, &self.queue, &self.instances, steps);
+        self.time += steps as f64 * self.config.step_size() as f64;
+        self.step = self.step.wrapping_add(steps);
+    }
+
+    /// Advances to the `periods`-th next multiple of the forcing period of [`Config::strobe`],
+    /// `samples` steps per period, so every call lands on the same phase of the forcing. A
+    /// simulation between two multiples reaches the first one in a single shorter step.
+    /// [`Config::delta_time`] is left as it was.
+    ///
+    /// # Panics
+    ///
+    /// If there is no [`Config::strobe`].
+    pub fn step_periods(&mut self, mut periods: u32) {
+        let strobe = self.config.strobe.expect("no strobe configured");
+        if periods == 0 {
+            return;
+        }
+        let delta_time = self.config.delta_time;
+        let step_size_factor = self.config.lorenz.step_size_factor as f64;
+        let period = strobe.period as f64;
+
+        // * STEPS ADD UP TO A PERIOD ONLY UP TO ROUNDING, SO NEARLY ALIGNED COUNTS AS ALIGNED
+        let phase = self.time.rem_euclid(period);
+        let rest = period - phase;
+        if phase > ALIGNED * period && rest > ALIGNED * period {
+            self.set_delta_time((rest / step_size_factor) as f32);
+            self.step(1);
+            periods -= 1;
+        }
+        self.set_delta_time(strobe.delta_time(step_size_factor as f32));
+        self.step(periods * strobe.samples);
+        self.time = (self.time / period).round() * period;
+        self.set_delta_time(delta_time);
+    }
+
+    /// Simulation time, the sum of the step sizes of all steps so far. It keeps running when
+    /// the particles are replaced.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Replaces the particle positions, e.g. to start from exact initial conditions. All of
+    /// them are alive afterwards, also with an emitter. The simulation time and the respawn
+    /// count carry on on both backends.
+    ///
+    /// # Panics
+    ///
+    /// If `points` does not hold exactly [`Simulation::num_particles`] positions or the
+    /// system keeps states, see [`Simulation::write_states`] instead.
+    pub fn write_particles(&mut self, points: &[Vec3]) {
+        assert_eq!(
+            self.config.state_dimensions(),
+            0,
+            "not supported by systems with states"
+        );
+        assert_eq!(
+            points.len(),
+            self.num_particles(),
+            "wrong number of particles"
+        );
+        let seed = self.config.seed.unwrap_or_default();
+        let raw: Vec<RawInstance> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| RawInstance::new(*p, rng_state(seed, i), 0, Vec3::ZERO))
+            .collect();
+        let draw_state = DrawState::new(points.len());
+        self.queue
+            .write_buffer(&self.instances.buffer, 0, bytemuck::cast_slice(&raw));
+        self.queue.write_buffer(
+            &self.instances.draw_buffer,
+            0,
+            bytemuck::bytes_of(&draw_state),
+        );
+        if let Some(cpu_state) = &mut self.cpu_state {
+            cpu_state.set_positions(points);
+            cpu_state.set_rng_states(raw.iter().map(RawInstance::rng));
+            cpu_state.set_pool(std::iter::repeat(0), draw_state);
+        }
+        self.step = draw_state.step;
+    }
+
+    /// Replaces the Lorenz-96 or 4D states or the delay histories, [`Config::state_dimensions`]
+    /// per particle. Histories are ring buffers whose current value sits at the draw state
+    /// step modulo their length.
+    ///
+    /// # Panics
+    ///
+    /// If the system has no states or the number of states is wrong.
+    pub fn write_states(&mut self, states: &[f32]) {
+        let dimensions = self.config.state_dimensions();
+        assert!(dimensions > 0, "not a simulation with states");
+        assert_eq!(
+            states.len(),
+            self.num_particles() * dimensions,
+            "wrong number of states"
+        );
+        let projection: Vec<Vec3> = self
+            .config
+            .projection()
+            .into_iter()
+            .map(Vec4::truncate)
+            .collect();
+        let position = |state: &[f32]| match self.config.system {
+            System::Delay => self.config.delay.embed(state, self.step),
+            _ => project(&projection, state),
+        };
+        let raw: Vec<RawInstance> = states
+            .chunks(dimensions)
+            .zip(self.read_raw_instances())
+            .map(|(state, old)| RawInstance::new(position(state), old.rng(), old.age(), Vec3::ZERO))
+            .collect();
+        self.queue
+            .write_buffer(&self.instances.buffer, 0, bytemuck::cast_slice(&raw));
+        self.queue
+            .write_buffer(&self.instances.states, 0, bytemuck::cast_slice(states));
+        if let Some(cpu_state) = &mut self.cpu_state {
+            cpu_state.set_states(states);
+        }
+    }
+
+    /// Copies the Lorenz-96 or 4D states or the delay histories back to the CPU,
+    /// [`Config::state_dimensions`] per particle. Empty for systems without states.
+    pub fn read_states(&self) -> Vec<f32> {
+        if self.config.state_dimensions() == 0 {
+            return Vec::new();
+        }
+        if let Some(cpu_state) = &self.cpu_state {
+            return cpu_state.states().to_vec();
+        }
+        bytemuck::cast_slice(&self.read_gpu_buffer(&self.instances.states)).to_vec()
+    }
+
+    /// Mean and covariance of the particles, reduced on the current backend.
+    ///
+    /// # Panics
+    ///
+    /// If the system is Lorenz-96.
+    pub fn ensemble(&self) -> Ensemble {
+        assert_ne!(
+            self.config.system,
+            System::Lorenz96,
+            "not supported by Lorenz-96"
+        );
+        if let Some(cpu_state) = &self.cpu_state {
+            return Ensemble::from_points(&cpu_state.positions());
+        }
+        let count = self.num_particles();
+        let analysis_state = self.analysis_state();
+        let moments = |center| {
+            analysis_state.moments(&self.device, &self.queue, center, count);
+            let bytes = self.read_gpu_buffer(&analysis_state.moments_buffer);
+            analysis::combine(bytemuck::cast_slice::<u8, Moments>(&bytes))
+        };
+        // * A ROUGH MEAN FIRST, THE COVARIANCE IS SUMMED AROUND IT
+        let (sum, squares) = moments(Vec3::ZERO);
+        let center = Ensemble::from_moments(Vec3::ZERO, count, sum, squares).mean;
+        let (sum, squares) = moments(center);
+        Ensemble::from_moments(center, count, sum, squares)
+    }
+
+    /// Mean distance of the twins `2k` and `2k + 1`, reduced on the current backend. Only
+    /// meaningful with [`Config::twins`], where it grows exponentially from the initial
+    /// separation until it saturates.
+    pub fn twin_separation(&self) -> f32 {
+        if let Some(cpu_state) = &self.cpu_state {
+            return twins::mean_separation(&cpu_state.positions());
+        }
+        let pairs = self.num_particles() / 2;
+        if pairs == 0 {
+            return 0.;
+        }
+        let analysis_state = self.analysis_state();
+        analysis_state.separation(&self.device, &self.queue, self.num_particles());
+        let bytes = self.read_gpu_buffer(&analysis_state.moments_buffer);
+        let sum: f64 = bytemuck::cast_slice::<u8, Moments>(&bytes)
+            .iter()
+            .map(|m| m.sum[0] as f64)
+            .sum();
+        (sum / pairs as f64) as f32
+    }
+
+    /// Moves every particle by one analysis step of the ensemble Kalman filter.
+    ///
+    /// # Panics
+    ///
+    /// If the system is Lorenz-96.
+    pub fn assimilate(&mut self, update: &Update) {
+        assert_ne!(
+            self.config.system,
+            System::Lorenz96,
+            "not supported by Lorenz-96"
+        );
+        if let Some(cpu_state) = &mut self.cpu_state {
+            let points: Vec<Vec3> = cpu_state
+                .positions()
+                .iter()
+                .enumerate()
+                .map(|(i, p)| update.apply(i, *p))
+                .collect();
+            cpu_state.set_positions(&points);
+            self.queue.write_buffer(
+                &self.instances.buffer,
+                0,
+                bytemuck::cast_slice(&cpu_state.raw_instances()),
+            );
+            return;
+        }
+        self.analysis_state()
+            .update(&self.device, &self.queue, update, self.num_particles());
+    }
+
+    /// Mean, covariance, bounding box, speed range and lobe fractions of the particles alive,
+    /// reduced on the current backend. Waits for the GPU, see [`Simulation::request_stats`]
+    /// for a version that does not. `None` without a particle alive.
+    ///
+    /// # Panics
+    ///
+    /// If the system is Lorenz-96.
+    pub fn stats(&self) -> Option<EnsembleStats> {
+        if self.cpu_state.is_some() {
+            return self.cpu_stats();
+        }
+        let stats_state = self.stats_state();
+        let reduce = |center| {
+            let uniform = self.stats_uniform(center);
+            stats_state.reduce(&self.device, &self.queue, &uniform);
+            let bytes = self.read_gpu_buffer(&stats_state.result_buffer);
+            StatsState::read(self.step, &uniform, &bytes)
+        };
+        // * A ROUGH MEAN FIRST, THE COVARIANCE IS SUMMED AROUND IT
+        let center = reduce(Vec3::ZERO)?.ensemble.mean;
+        reduce(center)
+    }
+
+    /// Starts reducing the statistics of the particles without waiting for the GPU. The
+    /// result is returned by a later [`Simulation::poll_stats`], usually one frame later.
+    /// The covariance is summed around the mean of the last statistics polled.
+    ///
+    /// # Panics
+    ///
+    /// If the system is Lorenz-96.
+    pub fn request_stats(&mut self) {
+        if self.cpu_state.is_some() {
+            self.cpu_stats = self.cpu_stats();
+            return;
+        }
+        let uniform = self.stats_uniform(self.stats_center);
+        let stats_state = self.stats_state();
+        stats_state.reduce(&self.device, &self.queue, &uniform);
+        let readback = self
+            .stats_readback
+            .get_or_insert_with(|| Readback::new(&self.device));
+        let source = &self.stats_state.get().unwrap().result_buffer;
+        readback.request(&self.device, &self.queue, source, (self.step, uniform));
+    }
+
+    /// The most recent statistics requested with [`Simulation::request_stats`] that have
+    /// arrived since the last call, if any.
+    pub fn poll_stats(&mut self) -> Option<EnsembleStats> {
+        let stats = match (self.cpu_stats.take(), &mut self.stats_readback) {
+            (Some(stats), _) => stats,
+            (None, Some(readback)) => {
+                let ((step, uniform), result) = readback.poll(&self.device)?;
+                StatsState::read(step, &uniform, bytemuck::bytes_of(&result))?
+            }
+            (None, None) => return None,
+        };
+        self.stats_center = stats.ensemble.mean;
+        Some(stats)
+    }
+
+    /// Number of boxes holding a particle alive for every level of
+    /// [`dimension::BoxGrid`] over their bounding box, counted on the current backend. Empty
+    /// without a particle alive.
+    ///
+    /// # Panics
+    ///
+    /// If the system is Lorenz-96.
+    pub fn box_counts(&self) -> Vec<BoxCount> {
+        match self.stats() {
+            Some(stats) => self.count_boxes(&stats, None),
+            None => Vec::new(),
+        }
+    }
+
+    /// Box-counting and correlation dimension of the particles alive, with the box counts
+    /// and correlation sums they are fitted to. `None` without a particle alive.
+    ///
+    /// # Panics
+    ///
+    /// If the system is Lorenz-96.
+    pub fn dimension(&self) -> Option<DimensionReport> {
+        let stats = self.stats()?;
+        let points = self.alive_positions();
+        let box_counts = self.count_boxes(&stats, Some(&points));
+        let radii = dimension::radii((stats.max - stats.min).max_element());
+        // * THE SAME SAMPLE FOR THE SAME SEED AND STEP
+        let seed = self.config.seed.unwrap_or_default() ^ self.step as u64;
+        let correlation_sums = dimension::correlation_sums(
+            &points,
+            &radii,
+            dimension::SAMPLES,
+            dimension::REFERENCES,
+            &mut StdRng::seed_from_u64(seed),
+        );
+        Some(DimensionReport::new(
+            stats.count,
+            box_counts,
+            correlation_sums,
+        ))
+    }
+
+    /// Box counts over the bounding box of `stats`, from the alive `points` if already read
+    /// back.
+    fn count_boxes(&self, stats: &EnsembleStats, points: Option<&[Vec3]>) -> Vec<BoxCount> {
+        if self.cpu_state.is_some() {
+            let bits = dimension::table_bits(self.num_particles());
+            return match points {
+                Some(points) => dimension::box_counts(points, stats.min, stats.max, bits),
+                None => dimension::box_counts(&self.alive_positions(), stats.min, stats.max, bits),
+            };
+        }
+        let box_count_state = self.box_count_state.get_or_init(|| {
+            BoxCountState::new(&self.device, &self.instances, self.num_particles())
+        });
+        box_count_state.count(
+            &self.device,
+            &self.queue,
+            stats.min,
+            stats.max,
+            self.num_particles(),
+        );
+        let bytes = self.read_gpu_buffer(&box_count_state.bits_buffer);
+        box_count_state.read(stats.min, stats.max, &bytes)
+    }
+
+    fn alive_positions(&self) -> Vec<Vec3> {
+        self.read_raw_instances()
+            .iter()
+            .filter(|instance| instance.is_alive())
+            .map(RawInstance::position)
+            .collect()
+    }
+
+    fn cpu_stats(&self) -> Option<EnsembleStats> {
+        assert_ne!(
+            self.config.system,
+            System::Lorenz96,
+            "not supported by Lorenz-96"
+        );
+        let points = self.alive_positions();
+        let time = self.time as f32;
+        let speed = |p| self.config.velocity(p, time).length();
+        let speed: Option<&dyn Fn(Vec3) -> f32> = self.has_speed().then_some(&speed);
+        EnsembleStats::from_points(self.step, &points, speed)
+    }
+
+    fn stats_uniform(&self, center: Vec3) -> StatsUniform {
+        assert_ne!(
+            self.config.system,
+            System::Lorenz96,
+            "not supported by Lorenz-96"
+        );
+        StatsUniform {
+            lorenz: self.config.lorenz,
+            center: center.to_array(),
+            count: self.num_particles() as u32,
+            time: self.time as f32,
+            speed: self.has_speed() as u32,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the drawn position moves with the velocity of the Lorenz or a custom system.
+    fn has_speed(&self) -> bool {
+        matches!(self.config.system, System::Lorenz | System::Custom)
+    }
+
+    fn stats_state(&self) -> &StatsState {
+        self.stats_state.get_or_init(|| {
+            let velocity = self.config.custom.as_ref().map(Equations::wgsl);
+            StatsState::new(&self.device, &self.instances, velocity.as_deref())
+        })
+    }
+
+    fn analysis_state(&self) -> &AnalysisState {
+        self.analysis_state
+            .get_or_init(|| AnalysisState::new(&self.device, &self.instances))
+    }
+
+    /// Moves the simulation to another backend, carrying over the current particles.
+    pub fn set_backend(&mut self, backend: Backend) {
+        if backend == self.config.backend {
+            return;
+        }
+        self.cpu_state = match backend {
+            Backend::Cpu => {
+                let raw = self.read_instances();
+                let points: Vec<Vec3> = raw.iter().map(RawInstance::position).collect();
+                let mut cpu_state = CpuState::new(&points, &self.config);
+                cpu_state.set_rng_states(raw.iter().map(RawInstance::rng));
+                cpu_state.set_pool(raw.iter().map(RawInstance::age), self.draw_state());
+                if self.config.state_dimensions() > 0 {
+                    cpu_state.set_states(&self.read_states());
+                }
+                if self.config.coupling.is_some() {
+                    cpu_state.set_sync_errors(&self.read_sync_ring());
+                }
+                Some(cpu_state)
+            }
+            Backend::Gpu => {
+                if let Some(cpu_state) = self
+                    .cpu_state
+                    .as_ref()
+                    .filter(|_| self.config.coupling.is_some())
+                {
+                    self.queue.write_buffer(
+                        &self.instances.states,
+                        self.sync_ring_offset(),
+                        bytemuck::cast_slice(cpu_state.sync_errors()),
+                    );
+                }
+                self.cpu_respawns += self.cpu_state.as_ref().map_or(0, CpuState::respawns);
+                None
+            }
+        };
+        self.config.backend = backend;
+    }
+
+    /// Changes when and where particles are respawned. Lorenz-96 particles never are.
+    pub fn set_respawn(&mut self, respawn: Respawn) {
+        self.config.respawn = respawn;
+        self.update_config();
+    }
+
+    /// Changes the emitter. Without one nothing happens, the particle pool is only set up
+    /// for an emitter when the simulation is created.
+    pub fn set_emitter(&mut self, emitter: Emitter) {
+        if self.config.emitter.is_some() {
+            self.config.emitter = Some(emitter);
+            self.update_config();
+        }
+    }
+
+    /// Changes the coupling of the particles. Without one nothing happens, the state buffer
+    /// is only set up for coupled particles when the simulation is created.
+    pub fn set_coupling(&mut self, coupling: Coupling) {
+        if self.config.coupling.is_some() {
+            self.config.coupling = Some(coupling);
+            self.update_config();
+        }
+    }
+
+    /// Changes the colors of the twins. Without twins nothing happens, the particles are only
+    /// created in pairs when the simulation is created.
+    pub fn set_twins(&mut self, twins: Twins) {
+        if self.config.twins.is_some() {
+            self.config.twins = Some(twins);
+            self.update_config();
+        }
+    }
+
+    /// Synchronization error of the coupled particles before each of the last
+    /// [`SYNC_HISTORY`] steps at most, oldest first. Empty without a coupling.
+    pub fn sync_errors(&self) -> Vec<f32> {
+        if self.config.coupling.is_none() {
+            return Vec::new();
+        }
+        let ring = match &self.cpu_state {
+            Some(cpu_state) => cpu_state.sync_errors().to_vec(),
+            None => self.read_sync_ring(),
+        };
+        let count = (self.step as usize).min(SYNC_HISTORY);
+        let end = self.step as usize % SYNC_HISTORY + SYNC_HISTORY;
+        (end - count..end)
+            .map(|step| ring[step % SYNC_HISTORY])
+            .collect()
+    }
+
+    /// The ring buffer of synchronization errors on the GPU.
+    fn read_sync_ring(&self) -> Vec<f32> {
+        let size = (SYNC_HISTORY * std::mem::size_of::<f32>()) as BufferAddress;
+        let bytes = self.read_gpu_range(&self.instances.states, self.sync_ring_offset(), size);
+        bytemuck::cast_slice(&bytes).to_vec()
+    }
+
+    fn sync_ring_offset(&self) -> BufferAddress {
+        (sync_offset(self.num_particles()) * std::mem::size_of::<f32>()) as BufferAddress
+    }
+
+    /// Indirect draw arguments and ring buffer position of the emitter.
+    pub fn draw_state(&self) -> DrawState {
+        if let Some(cpu_state) = &self.cpu_state {
+            return cpu_state.draw_state();
+        }
+        *bytemuck::from_bytes(&self.read_gpu_buffer(&self.instances.draw_buffer))
+    }
+
+    /// Number of particles alive, all of them without an emitter.
+    pub fn alive_particles(&self) -> usize {
+        self.read_raw_instances()
+            .iter()
+            .filter(|i| i.is_alive())
+            .count()
+    }
+
+    /// Changes the noise of the stochastic integrators.
+    pub fn set_noise(&mut self, noise: Noise) {
+        self.config.noise = noise;
+        self.update_config();
+    }
+
+    /// Number of particles respawned since the simulation was created, on either backend.
+    pub fn respawn_count(&self) -> u64 {
+        let bytes = self.read_gpu_buffer(&self.compute_state.respawn_buffer);
+        let gpu = u32::from_ne_bytes(bytes[..4].try_into().unwrap()) as u64;
+        gpu + self.cpu_respawns + self.cpu_state.as_ref().map_or(0, CpuState::respawns)
+    }
+
+    /// Changes the integration scheme. Fails like [`Scene::into_config`](crate::Scene::into_config)
+    /// for a stochastic integrator on a system without noise.
+    pub fn set_integrator(&mut self, integrator: Integrator) -> Result<(), Error> {
+        check_integrator(self.config.system, integrator)?;
+        self.config.integrator = integrator;
+        self.update_config();
+        Ok(())
+    }
+
+    /// Sets the time step used by subsequent [`Simulation::step`] calls.
+    pub fn set_delta_time(&mut self, delta_time: f32) {
+        self.config.delta_time = delta_time;
+        self.compute_state
+            .update_delta_time(delta_time, &self.queue);
+        if let Some(cpu_state) = &mut self.cpu_state {
+            cpu_state.set_delta_time(delta_time);
+        }
+    }
+
+    /// Changes the parameters of the Lorenz system.
+    pub fn set_parameters(&mut self, lorenz: LorenzConfig) {
+        self.config.lorenz = lorenz;
+        self.update_config();
+    }
+
+    /// Changes the map, which only has an effect with [`System::Map`].
+    pub fn set_map(&mut self, map: Map) {
+        self.config.map = map;
+        self.update_config();
+    }
+
+    /// Changes the 4D system, e.g. its rotation. The particles of a 4D simulation move to
+    /// their new projection right away, also between steps.
+    pub fn set_hyper(&mut self, hyper: Hyper) {
+        self.config.hyper = hyper;
+        self.update_config();
+        if self.config.system != System::Hyper {
+            return;
+        }
+        self.compute_state
+            .update_projection(&self.config, &self.queue);
+        match &mut self.cpu_state {
+            Some(cpu_state) => {
+                cpu_state.set_hyper(hyper);
+                self.queue.write_buffer(
+                    &self.instances.buffer,
+                    0,
+                    bytemuck::cast_slice(&cpu_state.raw_instances()),
+                );
+            }
+            None => self.compute_state.project_call(&self.device, &self.queue),
+        }
+    }
+
+    /// Changes the forcing period [`Simulation::step_periods`] samples at.
+    pub fn set_strobe(&mut self, strobe: Option<Strobe>) {
+        self.config.strobe = strobe;
+    }
+
+    /// Replaces the vector field of a custom system and rebuilds the compute pipeline. On
+    /// error the old field stays in use.
+    pub fn set_equations(&mut self, equations: Equations) -> Result<(), wgpu::Error> {
+        self.compute_state
+            .set_velocity(&self.device, equations.wgsl())?;
+        if let Some(cpu_state) = &mut self.cpu_state {
+            cpu_state.set_equations(equations.clone());
+        }
+        // * REBUILT WITH THE NEW FIELD WHEN ASKED FOR AGAIN
+        self.stats_state.take();
+        self.stats_readback = None;
+        self.config.custom = Some(equations);
+        Ok(())
+    }
+
+    fn update_config(&mut self) {
+        self.compute_state.update_config(&self.config, &self.queue);
+        if let Some(cpu_state) = &mut self.cpu_state {
+            Stepper::update_config(cpu_state, &self.config, &self.queue);
+        }
+    }
+
+    /// Sets whether particles are drawn flat (`true`) or shaded.
+    pub fn set_smooth_shading(&mut self, smooth_shading: bool) {
+        self.config.smooth_shading = smooth_shading;
+    }
+
+    /// Sets the brightness each particle adds when drawing densities, or draws opaque discs
+    /// with `None`.
+    pub fn set_density(&mut self, density: Option<f32>) {
+        self.config.density = density;
+    }
+
+    /// Replaces the colormap with another PNG image.
+    pub fn set_colormap(&mut self, colormap: Cow<'static, [u8]>) {
+        self.compute_state
+            .set_colormap(&self.device, &self.queue, &colormap);
+        if let Some(cpu_state) = &mut self.cpu_state {
+            Stepper::set_colormap(cpu_state, &self.device, &self.queue, &colormap);
+        }
+        self.config.colormap = colormap;
+    }
+
+    /// Rebuilds the compute pipeline from WGSL source. On error the old pipeline stays in use.
+    pub fn reload_compute_shader(&mut self, compute_wgsl: &str) -> Result<(), wgpu::Error> {
+        self.compute_state.reload_shader(&self.device, compute_wgsl)
+    }
+
+    /// Copies the particle positions back to the CPU. Blocks until the GPU is done.
+    pub fn read_particles(&self) -> Vec<Vec3> {
+        if let Some(cpu_state) = &self.cpu_state {
+            return cpu_state.positions();
+        }
+        self.read_instances()
+            .iter()
+            .map(RawInstance::position)
+            .collect()
+    }
+
+    /// Copies the positions of the particles in `range` back to the CPU, without touching the
+    /// others.
+    ///
+    /// # Panics
+    ///
+    /// If `range` goes past [`Simulation::num_particles`].
+    pub fn read_particles_range(&self, range: Range<usize>) -> Vec<Vec3> {
+        assert!(range.end <= self.num_particles(), "range out of bounds");
+        if let Some(cpu_state) = &self.cpu_state {
+            return range.map(|i| cpu_state.position(i)).collect();
+        }
+        let stride = std::mem::size_of::<RawInstance>() as BufferAddress;
+        let bytes = self.read_gpu_range(
+            &self.instances.buffer,
+            range.start as BufferAddress * stride,
+            range.len() as BufferAddress * stride,
+        );
+        bytemuck::cast_slice(&bytes)
+            .iter()
+            .map(RawInstance::position)
+            .collect()
+    }
+
+    /// Copies the particles as stored in the instance buffer back to the CPU.
+    pub fn read_raw_instances(&self) -> Vec<RawInstance> {
+        if let Some(cpu_state) = &self.cpu_state {
+            return cpu_state.raw_instances();
+        }
+        self.read_instances()
+    }
+
+    fn read_instances(&self) -> Vec<RawInstance> {
+        bytemuck::cast_slice(&self.read_gpu_buffer(&self.instances.buffer)).to_vec()
+    }
+
+    /// Copies a whole GPU buffer back to the CPU.
+    fn read_gpu_buffer(&self, buffer: &Buffer) -> Vec<u8> {
+        self.read_gpu_range(buffer, 0, buffer.size())
+    }
+
+    /// Copies `size` bytes at `offset` of a GPU buffer back to the CPU.
+    fn read_gpu_range(
+        &self,
+        buffer: &Buffer,
+        offset: BufferAddress,
+        size: BufferAddress,
+    ) -> Vec<u8> {
+        let staging = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(buffer, offset, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+        self.read_buffer(&staging)
+    }
+
+    /// Hash of the particle positions, to compare runs bit for bit. Only runs on the same
+    /// backend and hardware are expected to agree.
+    pub fn checksum(&self) -> u64 {
+        checksum(&self.read_particles())
+    }
+
+    /// Renders the particles as seen from `camera` into a new [`RENDER_FORMAT`] texture.
+    pub fn render_to_texture(&self, camera: &CameraSettings, width: u32, height: u32) -> Texture {
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: Some("Render Target"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: RENDER_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        let (camera, camera_bind_group_layout) =
+            Camera::create_camera(&self.device, width as f32 / height as f32, camera);
+        let render_state = RenderState::new(
+            &self.device,
+            RENDER_FORMAT,
+            (width, height),
+            camera_bind_group_layout,
+            &self.config,
+        );
+        render_state.render_call(
+            &self.device,
+            &self.queue,
+            &view,
+            &camera.bind_group,
+            &[(&self.instances.buffer, &self.instances.draw_buffer)],
+        );
+        texture
+    }
+
+    /// Renders like [`Simulation::render_to_texture`] and returns tightly packed RGBA8 rows.
+    pub fn render_to_image(&self, camera: &CameraSettings, width: u32, height: u32) -> Vec<u8> {
+        let texture = self.render_to_texture(camera, width, height);
+
+        let unpadded_bytes_per_row = 4 * width;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+            * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let staging = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Image Readback Buffer"),
+            size: (padded_bytes_per_row * height) as BufferAddress,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &staging,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        self.read_buffer(&staging)
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect()
+    }
+
+    fn read_buffer(&self, staging: &Buffer) -> Vec<u8> {
+        let slice = staging.slice(..);
+        slice.map_async(MapMode::Read, |result| {
+            result.expect("could not map readback buffer")
+        });
+        self.device.poll(Maintain::Wait);
+        let bytes = slice.get_mapped_range().to_vec();
+        staging.unmap();
+        bytes
+    }
+}
+
+/// 64 bit FNV-1a over the bits of every coordinate.
+pub fn checksum(points: &[Vec3]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    points
+        .iter()
+        .flat_map(|p| p.to_array())
+        .flat_map(|c| c.to_bits().to_le_bytes())
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(PRIME)
+        })
+}
@@ -0,0 +1,127 @@
+use std::f32::consts::TAU;
+
+use glam::Vec3;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, Queue,
+};
+
+use wgpu_lorenz::{
+    equations::Equations,
+    equilibria::{self, Equilibrium, Mode},
+    instance::{DrawState, RawInstance},
+    lorenz::LorenzConfig,
+    Simulation,
+};
+
+const POINT_COLOR: Vec3 = Vec3::ONE;
+const UNSTABLE_COLOR: Vec3 = Vec3::new(1., 0.35, 0.1);
+const STABLE_COLOR: Vec3 = Vec3::new(0.2, 0.5, 1.);
+
+// * MARKERS ARE DRAWN FOR AT MOST THIS MANY EQUILIBRIA
+const MAX_EQUILIBRIA: usize = 32;
+// * PARTICLES ON EACH HALF OF AN EIGEN-DIRECTION AND ON THE RING OF A COMPLEX PAIR
+const SEGMENT: usize = 24;
+const RING: usize = 64;
+const PER_EQUILIBRIUM: usize = 1 + 3 * 2 * SEGMENT + RING;
+/// Length of the eigen-directions as a fraction of the extent of the particles.
+const LENGTH: f32 = 0.08;
+
+/// The fixed points of the flow with lines along their real eigen-directions and rings in
+/// the planes of their complex pairs, red-orange where unstable and blue where stable.
+pub struct EquilibriumView {
+    /// The system the markers were computed for.
+    key: Option<(Option<Equations>, LorenzConfig)>,
+    pub instance_buffer: Buffer,
+    pub draw_buffer: Buffer,
+}
+
+impl EquilibriumView {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            key: None,
+            instance_buffer: device.create_buffer(&BufferDescriptor {
+                label: Some("Equilibrium Instance Buffer"),
+                size: (MAX_EQUILIBRIA * PER_EQUILIBRIUM * std::mem::size_of::<RawInstance>())
+                    as BufferAddress,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            draw_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Equilibrium Draw Buffer"),
+                contents: bytemuck::bytes_of(&DrawState::new(0)),
+                usage: BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+            }),
+        }
+    }
+
+    /// Recomputes and uploads the markers if the parameters or equations changed since the
+    /// last call, and prints the classification of every equilibrium.
+    pub fn update(&mut self, sim: &Simulation, queue: &Queue) {
+        let config = sim.config();
+        let key = Some((config.custom.clone(), config.lorenz));
+        if key == self.key {
+            return;
+        }
+        self.key = key;
+
+        let (min, max) = match sim.stats() {
+            Some(stats) => (stats.min, stats.max),
+            None => {
+                let d = config.distribution;
+                let center = Vec3::from_array(d.center);
+                (center - d.extent, center + d.extent)
+            }
+        };
+        let found = equilibria::find(config, min, max, sim.time() as f32);
+        println!("{} equilibria", found.len());
+        for equilibrium in &found {
+            println!("  {equilibrium}");
+        }
+        if found.len() > MAX_EQUILIBRIA {
+            println!("Marking the first {MAX_EQUILIBRIA}");
+        }
+
+        let length = LENGTH * (max - min).max_element().max(f32::EPSILON);
+        let raw: Vec<RawInstance> = found
+            .iter()
+            .take(MAX_EQUILIBRIA)
+            .flat_map(|e| markers(e, length))
+            .map(|(p, color)| RawInstance::new(p, 0, 0, color))
+            .collect();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&raw));
+        queue.write_buffer(
+            &self.draw_buffer,
+            0,
+            bytemuck::bytes_of(&DrawState::new(raw.len())),
+        );
+    }
+}
+
+/// Positions and colors of the particles marking `equilibrium`.
+fn markers(equilibrium: &Equilibrium, length: f32) -> Vec<(Vec3, Vec3)> {
+    let center = equilibrium.point;
+    let mut points = vec![(center, POINT_COLOR)];
+    for mode in &equilibrium.modes {
+        let color = if mode.growth() > 0. {
+            UNSTABLE_COLOR
+        } else {
+            STABLE_COLOR
+        };
+        match *mode {
+            Mode::Real { direction, .. } => {
+                points.extend((1..=SEGMENT).flat_map(|k| {
+                    let offset = length * k as f32 / SEGMENT as f32 * direction;
+                    [(center + offset, color), (center - offset, color)]
+                }));
+            }
+            Mode::Complex { plane: [u, w], .. } => {
+                points.extend((0..RING).map(|k| {
+                    let (sin, cos) = (TAU * k as f32 / RING as f32).sin_cos();
+                    (center + length * (cos * u + sin * w), color)
+                }));
+            }
+        }
+    }
+    points
+}
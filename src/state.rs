@@ -1,28 +1,68 @@
 use std::time::Instant;
 
+use rand::rngs::StdRng;
 use winit::event_loop::EventLoop;
 
+use wgpu::{SurfaceError, TextureViewDescriptor};
+use wgpu_lorenz::{
+    camera::{Camera, CameraMode},
+    dimension::Fit,
+    enkf::Filter,
+    render::RenderState,
+    scene::System,
+    stats::EnsembleStats,
+    Config, Error, Simulation,
+};
+
 use crate::{
-    camera::Camera, compute::ComputeState, config::Config, env::Environment, input,
-    lorenz::LorenzState, render::RenderState,
+    assimilation::{Log, TruthMarker},
+    divergence,
+    env::Environment,
+    follow::ParticleReadback,
+    hot_reload::{HotReload, Reload},
+    input,
+    reconstruction::DelayView,
+    rotation::RotationController,
+    stability::EquilibriumView,
 };
 use winit::{
+    dpi::PhysicalSize,
     event::{ElementState, Event, VirtualKeyCode, WindowEvent},
     event_loop::ControlFlow,
 };
 pub struct State {
     pub env: Environment,
     pub render_state: RenderState,
-    pub lorenz_state: LorenzState,
-    pub compute_state: ComputeState,
+    pub sim: Simulation,
     pub camera: Camera,
-    pub config: Config,
+    pub follow: ParticleReadback,
     pub delta_time: f32,
     pub paused: bool,
+    pub hot_reload: Option<HotReload>,
+    /// Data assimilation, if the scene has an `[assimilation]` table.
+    pub filter: Option<Filter>,
+    pub truth_marker: Option<TruthMarker>,
+    pub assimilation_log: Option<Log>,
+    /// Separation of the twins after every step, if the scene has a `[twins]` table.
+    pub divergence_log: Option<Log>,
+    /// Ensemble statistics of every frame, if `--stats-log` is given.
+    pub stats_log: Option<Log>,
+    /// The window title to restore while ensemble statistics are shown in its place.
+    pub stats_title: Option<String>,
+    /// Step of the last ensemble statistics received, so a paused frame is not logged twice.
+    pub stats_step: Option<u32>,
+    /// Delay embedding, if the scene has an `[embedding]` table.
+    pub delay_view: Option<DelayView>,
+    /// Fixed points with their eigen-directions, toggled with `E`.
+    pub equilibrium_view: Option<EquilibriumView>,
+    /// Seeded from the simulation seed, for random particle selection.
+    pub rng: StdRng,
+    /// Rotation of a 4D system into view.
+    pub rotation: RotationController,
 }
 
 impl State {
-    pub fn run(mut self, event_loop: EventLoop<()>) {
+    pub fn run(mut self, event_loop: EventLoop<()>) -> ! {
         // * SETUP
         let mut start = Instant::now();
         event_loop.run(move |event, _, control_flow| {
@@ -42,30 +82,54 @@ impl State {
                         ..
                     } => *control_flow = ControlFlow::Exit,
 
+                    WindowEvent::Resized(size) => self.resize(*size),
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        self.resize(**new_inner_size)
+                    }
+
                     event => {
                         input::input(&mut self, event);
                     }
                 },
                 Event::MainEventsCleared => {
+                    // * HOT RELOAD
+                    self.hot_reload();
                     // * UPDATE LORENZ
                     if !self.paused {
                         self.update_lorenz()
                     }
+                    // * ENSEMBLE STATISTICS, A FRAME BEHIND
+                    self.update_stats();
+                    // * ROTATE 4D SYSTEMS, ALSO WHILE PAUSED
+                    self.update_rotation();
+                    // * EQUILIBRIA, AFTER PARAMETER CHANGES
+                    if let Some(view) = &mut self.equilibrium_view {
+                        view.update(&self.sim, &self.env.queue);
+                    }
                     // * UPDATE CAMERA
-                    if self.env.cursor_grab {
-                        self.camera.update(self.delta_time, &self.env.queue);
+                    if self.camera.mode == CameraMode::Free {
+                        if self.env.cursor_grab {
+                            self.camera.update(self.delta_time, &self.env.queue);
+                        }
+                    } else {
+                        self.update_follow_camera();
                     }
                     // * RENDER
-                    self.render_state.render_call(
-                        &self.env,
-                        &self.camera.bind_group,
-                        self.config.num_lorenz_points,
-                    );
+                    match self.render() {
+                        Ok(()) => {}
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                            self.resize(self.env.window.inner_size())
+                        }
+                        Err(SurfaceError::OutOfMemory) => {
+                            eprintln!("error: {}", Error::OutOfMemory);
+                            *control_flow = ControlFlow::ExitWithCode(1);
+                        }
+                        Err(SurfaceError::Timeout) => eprintln!("Frame timed out"),
+                    }
                 }
                 Event::RedrawEventsCleared => {
                     self.delta_time = start.elapsed().as_secs_f32();
-                    self.compute_state
-                        .update_delta_time_buffer(self.delta_time, &self.env.queue);
+                    self.sim.set_delta_time(self.delta_time);
                     println!("{}", 1. / self.delta_time);
                     start = Instant::now();
                 }
@@ -81,12 +145,357 @@ impl State {
         })
     }
 
+    fn render(&self) -> Result<(), SurfaceError> {
+        let output = self.env.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&TextureViewDescriptor::default());
+        let mut batches = vec![(self.sim.instance_buffer(), self.sim.draw_buffer())];
+        if let Some(marker) = &self.truth_marker {
+            batches.push((&marker.instance_buffer, &marker.draw_buffer));
+        }
+        if let Some(view) = &self.delay_view {
+            batches.push((&view.instance_buffer, &view.draw_buffer));
+        }
+        if let Some(view) = &self.equilibrium_view {
+            batches.push((&view.instance_buffer, &view.draw_buffer));
+        }
+        self.render_state.render_call(
+            &self.env.device,
+            &self.env.queue,
+            &view,
+            &self.camera.bind_group,
+            &batches,
+        );
+        output.present();
+        Ok(())
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.env.resize(size);
+        let (width, height) = (self.env.config.width, self.env.config.height);
+        self.render_state.resize(
+            &self.env.device,
+            &self.env.queue,
+            self.sim.config(),
+            width,
+            height,
+        );
+        self.camera.resize(width, height, &self.env.queue);
+    }
+
+    fn hot_reload(&mut self) {
+        let Some(hot_reload) = &mut self.hot_reload else {
+            return;
+        };
+        for reload in hot_reload.poll() {
+            match reload {
+                Reload::ComputeShader(source) => match self.sim.reload_compute_shader(&source) {
+                    Ok(()) => println!("Reloaded compute shader"),
+                    Err(e) => eprintln!("Compute shader error: {e}"),
+                },
+                Reload::DrawShader(source) => {
+                    match self.render_state.reload_shader(&self.env.device, &source) {
+                        Ok(()) => println!("Reloaded draw shader"),
+                        Err(e) => eprintln!("Draw shader error: {e}"),
+                    }
+                }
+                Reload::Scene(config) => self.apply_config(*config),
+            }
+        }
+    }
+
+    /// Applies everything that can change without recreating the particle buffer.
+    fn apply_config(&mut self, config: Config) {
+        let current = self.sim.config();
+        if config.system != current.system
+            || config.num_lorenz_points != current.num_lorenz_points
+            || config.window_size != current.window_size
+        {
+            println!("System, particle count and window size only change on restart");
+        }
+        if config.lorenz96 != current.lorenz96 {
+            println!("Lorenz-96 settings only change on restart");
+        }
+        if config.delay != current.delay {
+            println!("Delay equation settings only change on restart");
+        }
+        if config.embedding != current.embedding {
+            println!("Embedding settings only change on restart");
+        }
+        if config.assimilation != current.assimilation {
+            println!("Assimilation settings only change on restart");
+        }
+        if config.emitter.is_some() != current.emitter.is_some() {
+            println!("Adding or removing the emitter only takes effect on restart");
+        }
+        if config.coupling.is_some() != current.coupling.is_some() {
+            println!("Adding or removing the coupling only takes effect on restart");
+        }
+        if config.twins.map(|t| t.separation) != current.twins.map(|t| t.separation) {
+            println!("Twins and their separation only change on restart");
+        }
+        self.sim.set_backend(config.backend);
+        if let Err(e) = self.sim.set_integrator(config.integrator) {
+            println!(
+                "Keeping the {:?} integrator: {e}",
+                self.sim.config().integrator
+            );
+        }
+        self.sim.set_parameters(config.lorenz);
+        self.sim.set_respawn(config.respawn);
+        self.sim.set_noise(config.noise);
+        if let Some(emitter) = config.emitter {
+            self.sim.set_emitter(emitter);
+        }
+        if let Some(coupling) = config.coupling {
+            self.sim.set_coupling(coupling);
+        }
+        if let Some(twins) = config.twins {
+            self.sim.set_twins(twins);
+        }
+        if let Some(equations) = config.custom {
+            if Some(&equations) != self.sim.config().custom.as_ref() {
+                match self.sim.set_equations(equations) {
+                    Ok(()) => println!("Rebuilt the custom vector field"),
+                    Err(e) => println!("Keeping the old custom vector field: {e}"),
+                }
+            }
+        }
+        self.sim.set_map(config.map);
+        self.sim.set_hyper(config.hyper);
+        self.sim.set_strobe(config.strobe);
+        self.sim.set_smooth_shading(config.smooth_shading);
+        self.sim.set_density(config.density);
+        self.render_state
+            .update_config(self.sim.config(), &self.env.queue);
+
+        if config.colormap != self.sim.config().colormap {
+            self.sim.set_colormap(config.colormap);
+        }
+
+        self.camera
+            .controller
+            .set_speed_and_sens(config.camera.speed, config.camera.sensitivity);
+        println!("Reloaded scene");
+    }
+
+    fn update_follow_camera(&mut self) {
+        if !self.env.cursor_grab {
+            self.camera.controller.clear_mouse_delta();
+        }
+        self.follow.request(
+            &self.env.device,
+            &self.env.queue,
+            self.sim.instance_buffer(),
+            self.sim.state_buffer(),
+            self.sim.draw_buffer(),
+        );
+        if let Some(target) = self.follow.poll(&self.env.device) {
+            let vel = self.sim.config().drawn_velocity(
+                target.position,
+                &target.state,
+                target.step,
+                self.sim.time() as f32,
+            );
+            self.camera
+                .follow(target.position, vel, self.delta_time, &self.env.queue);
+        }
+    }
+
+    pub fn select_follow_particle(&mut self, index: usize) {
+        let index = index % self.sim.num_particles();
+        self.follow.set_index(index);
+        println!("Following particle {index}");
+    }
+
+    /// Multiplies the coupling strength by `factor`.
+    pub fn scale_coupling(&mut self, factor: f32) {
+        let Some(mut coupling) = self.sim.config().coupling else {
+            println!("No coupling in this scene");
+            return;
+        };
+        coupling.strength *= factor;
+        self.sim.set_coupling(coupling);
+        println!("Coupling strength: {}", coupling.strength);
+    }
+
+    /// Prints the latest synchronization error and a sparkline of the ones before it.
+    pub fn print_sync_errors(&self) {
+        let errors = self.sim.sync_errors();
+        let Some(latest) = errors.last() else {
+            println!("No synchronization error yet");
+            return;
+        };
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = errors.iter().copied().fold(f32::EPSILON, f32::max);
+        // * ONE BAR PER 16 STEPS, THE WORST OF THEM
+        let sparkline: String = errors
+            .chunks(16)
+            .map(|chunk| {
+                let worst = chunk.iter().copied().fold(0., f32::max);
+                BARS[((worst / max * 7.).round() as usize).min(7)]
+            })
+            .collect();
+        println!("Synchronization error: {latest:.4} (max {max:.4}) {sparkline}");
+    }
+
+    /// Prints the mean separation of the twins and its logarithm.
+    pub fn print_twin_separation(&self) {
+        if self.sim.config().twins.is_none() {
+            println!("No twins in this scene");
+            return;
+        }
+        let record = divergence::record(&self.sim);
+        println!(
+            "Twin separation at step {}: {:.3e} (ln {:.3})",
+            record.step,
+            record.separation,
+            record.log_separation()
+        );
+    }
+
+    /// Shows the ensemble statistics in the window title instead of its usual title, or
+    /// restores it.
+    pub fn toggle_stats(&mut self) {
+        if self.sim.config().system == System::Lorenz96 {
+            println!("Ensemble statistics are not supported by Lorenz-96");
+            return;
+        }
+        match self.stats_title.take() {
+            Some(title) => self.env.window.set_title(&title),
+            None => self.stats_title = Some(self.env.window.title()),
+        }
+    }
+
+    /// Prints the box-counting and correlation dimension of the particles.
+    pub fn print_dimension(&self) {
+        if self.sim.config().system == System::Lorenz96 {
+            println!("Dimension estimates are not supported by Lorenz-96");
+            return;
+        }
+        let Some(report) = self.sim.dimension() else {
+            println!("No particle alive");
+            return;
+        };
+        let fit = |fit: Option<Fit>| match fit {
+            Some(fit) => format!("{:.3} (R² {:.4})", fit.slope, fit.r_squared),
+            None => "-".to_owned(),
+        };
+        println!(
+            "Dimension of {} particles: box-counting {}, correlation {}",
+            report.particles,
+            fit(report.box_counting),
+            fit(report.correlation)
+        );
+    }
+
+    /// Shows or hides the equilibria and their eigen-directions.
+    pub fn toggle_equilibria(&mut self) {
+        if !matches!(self.sim.config().system, System::Lorenz | System::Custom) {
+            println!("Equilibria are only found for the Lorenz system and custom vector fields");
+            return;
+        }
+        self.equilibrium_view = match self.equilibrium_view {
+            Some(_) => None,
+            None => Some(EquilibriumView::new(&self.env.device)),
+        };
+    }
+
+    /// Collects the statistics requested last frame and requests the ones of this frame.
+    fn update_stats(&mut self) {
+        if self.stats_title.is_none() && self.stats_log.is_none() {
+            return;
+        }
+        if let Some(stats) = self.sim.poll_stats() {
+            if self.stats_title.is_some() {
+                self.env.window.set_title(&stats_title(&stats));
+            }
+            if let Some(log) = &mut self.stats_log {
+                if self.stats_step != Some(stats.step) {
+                    if let Err(e) = log.write(&[stats]) {
+                        eprintln!("error: {e}");
+                        self.stats_log = None;
+                    }
+                }
+            }
+            self.stats_step = Some(stats.step);
+        }
+        self.sim.request_stats();
+    }
+
+    fn update_rotation(&mut self) {
+        if self.sim.config().system != System::Hyper {
+            return;
+        }
+        let mut hyper = self.sim.config().hyper;
+        if self.rotation.update(&mut hyper.rotation, self.delta_time) {
+            self.sim.set_hyper(hyper);
+        }
+    }
+
     pub fn update_lorenz(&mut self) {
-        self.compute_state
-            .compute_call(&self.env, self.config.num_workgroups);
-        // self.lorenz_state.update(dt);
-        // self.render_state
-        //     .instances
-        //     .update(&self.lorenz_state, &self.env.queue)
+        if self.filter.is_some() {
+            self.step_filter();
+        } else if self.sim.config().strobe.is_some() {
+            self.sim.step_periods(1);
+        } else {
+            self.sim.step(1);
+        }
+        if let Some(view) = &mut self.delay_view {
+            view.record(&self.sim, &self.env.queue);
+        }
+        if let Some(log) = &mut self.divergence_log {
+            if let Err(e) = log.write(&[divergence::record(&self.sim)]) {
+                eprintln!("error: {e}");
+                self.divergence_log = None;
+            }
+        }
+    }
+
+    fn step_filter(&mut self) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+        let records = filter.run(&mut self.sim, 1);
+        for record in &records {
+            println!(
+                "Analysis at step {}: RMSE {:.3} -> {:.3}, spread {:.3} -> {:.3}",
+                record.step,
+                record.forecast_rmse,
+                record.analysis_rmse,
+                record.forecast_spread,
+                record.analysis_spread
+            );
+        }
+        if let Some(log) = &mut self.assimilation_log {
+            if let Err(e) = log.write(&records) {
+                eprintln!("error: {e}");
+                self.assimilation_log = None;
+            }
+        }
+        if let Some(marker) = &self.truth_marker {
+            marker.set(&self.env.queue, filter.truth());
+        }
+    }
+}
+
+/// One line summary of `stats` for the window title.
+fn stats_title(stats: &EnsembleStats) -> String {
+    let mean = stats.ensemble.mean;
+    let mut title = format!(
+        "step {} | {} alive | mean ({:.2}, {:.2}, {:.2}) | spread {:.2} | lobes {:.0}% / {:.0}%",
+        stats.step,
+        stats.count,
+        mean.x,
+        mean.y,
+        mean.z,
+        stats.ensemble.spread(),
+        100. * stats.lobes[0],
+        100. * stats.lobes[1],
+    );
+    if let Some((slowest, fastest)) = stats.speed {
+        title += &format!(" | speed {slowest:.1} - {fastest:.1}");
     }
+    title
 }
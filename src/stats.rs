@@ -0,0 +1,382 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use glam::{DMat3, DVec3, Vec3};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device, Maintain, MapMode,
+    PipelineLayoutDescriptor, Queue, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+use crate::{
+    compute::splice_velocity, enkf::Ensemble, instance::InstancesVec, lorenz::LorenzConfig,
+};
+
+pub const STATS_WGSL: &str = include_str!("stats.wgsl");
+
+// * SAME AS IN stats.wgsl
+pub(crate) const WORKGROUPS: usize = 256;
+
+// * NUMBER OF READBACKS THAT MAY BE IN FLIGHT AT ONCE
+const STAGING_BUFFERS: usize = 3;
+
+/// Layout of bind group 0 of `stats.wgsl`. A pipeline of its own, so it does not count
+/// against the storage buffers of `compute.wgsl`.
+pub(crate) const BIND_GROUP_LAYOUT_ENTRIES: [BindGroupLayoutEntry; 4] = [
+    // * INSTANCE BUFFER
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * STATS UNIFORM
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * STATS, ONE PER WORKGROUP
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    // * STATS OF ALL PARTICLES
+    BindGroupLayoutEntry {
+        binding: 3,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+/// `StatsUniform` of `stats.wgsl`.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Default)]
+pub struct StatsUniform {
+    pub lorenz: LorenzConfig,
+    pub center: [f32; 3],
+    pub count: u32,
+    pub time: f32,
+    /// Whether to compute the speed, only for flows of the drawn position.
+    pub speed: u32,
+    pub _pad: [u32; 2],
+}
+
+/// `Stats` of `stats.wgsl`, of one workgroup or of all particles.
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy)]
+pub struct StatsShader {
+    pub sum: [f32; 3],
+    pub count: f32,
+    pub diagonal: [f32; 3],
+    pub right: f32,
+    /// `xy`, `xz` and `yz`.
+    pub off_diagonal: [f32; 3],
+    pub min_speed: f32,
+    pub lo: [f32; 3],
+    pub max_speed: f32,
+    pub hi: [f32; 3],
+    pub _pad: f32,
+}
+
+/// Statistics of the particles alive at one step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnsembleStats {
+    pub step: u32,
+    pub count: usize,
+    pub ensemble: Ensemble,
+    /// Corners of the bounding box.
+    pub min: Vec3,
+    pub max: Vec3,
+    /// Slowest and fastest speed, only for the Lorenz and custom systems.
+    pub speed: Option<(f32, f32)>,
+    /// Fraction of the particles on the left (x ≤ 0) and right (x > 0) lobe of the Lorenz
+    /// attractor.
+    pub lobes: [f32; 2],
+}
+
+impl EnsembleStats {
+    /// Header of the CSV lines written by the `Display` impl, the covariance as its upper
+    /// triangle.
+    pub const CSV_HEADER: &'static str = "step,count,mean_x,mean_y,mean_z,\
+        cov_xx,cov_xy,cov_xz,cov_yy,cov_yz,cov_zz,min_x,min_y,min_z,max_x,max_y,max_z,\
+        min_speed,max_speed,left,right";
+
+    /// Statistics of `points` at step `step`, accumulated in `f64`, with `speed` at every
+    /// point if given. `None` without a point. Mirrors `cs_stats` in `stats.wgsl`.
+    pub fn from_points(
+        step: u32,
+        points: &[Vec3],
+        speed: Option<&dyn Fn(Vec3) -> f32>,
+    ) -> Option<Self> {
+        if points.is_empty() {
+            return None;
+        }
+        let (min, max) = points.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(lo, hi), p| (lo.min(*p), hi.max(*p)),
+        );
+        let speed = speed.map(|speed| {
+            points.iter().fold((f32::MAX, 0f32), |(lo, hi), p| {
+                let s = speed(*p);
+                (lo.min(s), hi.max(s))
+            })
+        });
+        let right = points.iter().filter(|p| p.x > 0.).count() as f32 / points.len() as f32;
+        Some(Self {
+            step,
+            count: points.len(),
+            ensemble: Ensemble::from_points(points),
+            min,
+            max,
+            speed,
+            lobes: [1. - right, right],
+        })
+    }
+
+    /// Statistics from the `result` of `stats.wgsl` for the `uniform` it was reduced with.
+    fn from_shader(step: u32, uniform: &StatsUniform, s: &StatsShader) -> Option<Self> {
+        let count = s.count as usize;
+        if count == 0 {
+            return None;
+        }
+        let [xx, yy, zz] = s.diagonal.map(f64::from);
+        let [xy, xz, yz] = s.off_diagonal.map(f64::from);
+        let squares = DMat3::from_cols_array(&[xx, xy, xz, xy, yy, yz, xz, yz, zz]);
+        let sum = DVec3::from_array(s.sum.map(f64::from));
+        let center = Vec3::from_array(uniform.center);
+        let right = s.right / s.count;
+        Some(Self {
+            step,
+            count,
+            ensemble: Ensemble::from_moments(center, count, sum, squares),
+            min: Vec3::from_array(s.lo),
+            max: Vec3::from_array(s.hi),
+            speed: (uniform.speed != 0).then_some((s.min_speed, s.max_speed)),
+            lobes: [1. - right, right],
+        })
+    }
+}
+
+impl fmt::Display for EnsembleStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (m, c) = (self.ensemble.mean, self.ensemble.covariance);
+        write!(f, "{},{},{},{},{}", self.step, self.count, m.x, m.y, m.z)?;
+        write!(
+            f,
+            ",{},{},{},{},{},{}",
+            c.x_axis.x, c.y_axis.x, c.z_axis.x, c.y_axis.y, c.z_axis.y, c.z_axis.z
+        )?;
+        let (lo, hi) = (self.min, self.max);
+        write!(f, ",{},{},{},{},{},{}", lo.x, lo.y, lo.z, hi.x, hi.y, hi.z)?;
+        match self.speed {
+            Some((slowest, fastest)) => write!(f, ",{slowest},{fastest}")?,
+            None => write!(f, ",,")?,
+        }
+        write!(f, ",{},{}", self.lobes[0], self.lobes[1])
+    }
+}
+
+/// Two pass reduction of the particles into [`EnsembleStats`] on the GPU: every workgroup
+/// reduces a slice into a partial, then a single workgroup combines the partials into a
+/// small result buffer.
+pub struct StatsState {
+    stats_pipeline: ComputePipeline,
+    combine_pipeline: ComputePipeline,
+    bind_group: BindGroup,
+    uniform_buffer: Buffer,
+    /// The [`StatsShader`] of all particles, written by [`StatsState::reduce`].
+    pub result_buffer: Buffer,
+}
+
+impl StatsState {
+    /// `velocity` replaces the Lorenz system, see [`crate::equations::Equations::wgsl`].
+    pub fn new(device: &Device, instances: &InstancesVec, velocity: Option<&str>) -> Self {
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Stats Uniform Buffer"),
+            size: std::mem::size_of::<StatsUniform>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let partials_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Stats Partials Buffer"),
+            size: (WORKGROUPS * std::mem::size_of::<StatsShader>()) as u64,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let result_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Stats Result Buffer"),
+            size: std::mem::size_of::<StatsShader>() as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Stats Bind Group Layout"),
+            entries: &BIND_GROUP_LAYOUT_ENTRIES,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Stats Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: instances.buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: partials_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: result_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Stats Shader"),
+            source: ShaderSource::Wgsl(splice_velocity(STATS_WGSL, velocity)),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Stats Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create = |label, entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        Self {
+            stats_pipeline: create("Stats Pipeline", "cs_stats"),
+            combine_pipeline: create("Stats Combine Pipeline", "cs_stats_combine"),
+            bind_group,
+            uniform_buffer,
+            result_buffer,
+        }
+    }
+
+    /// Reduces the particles into the result buffer.
+    pub fn reduce(&self, device: &Device, queue: &Queue, uniform: &StatsUniform) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(uniform));
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_pipeline(&self.stats_pipeline);
+            compute_pass.dispatch_workgroups(WORKGROUPS as u32, 1, 1);
+            compute_pass.set_pipeline(&self.combine_pipeline);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// Statistics from the bytes of the result buffer, for the `uniform` of the reduction.
+    pub fn read(step: u32, uniform: &StatsUniform, bytes: &[u8]) -> Option<EnsembleStats> {
+        EnsembleStats::from_shader(step, uniform, bytemuck::from_bytes(bytes))
+    }
+}
+
+/// Copies a small GPU buffer holding a `T` back without waiting on the GPU. The copies go
+/// into a ring of staging buffers which are mapped asynchronously, so a result is usually
+/// available a frame after it was requested. Every request carries a tag that is handed back
+/// with its result.
+pub struct Readback<T, Tag> {
+    staging: [Buffer; STAGING_BUFFERS],
+    tags: [Option<Tag>; STAGING_BUFFERS],
+    next: usize,
+    sender: Sender<(usize, bool)>,
+    receiver: Receiver<(usize, bool)>,
+    value: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod, Tag> Readback<T, Tag> {
+    pub fn new(device: &Device) -> Self {
+        let staging = std::array::from_fn(|_| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some("Readback Buffer"),
+                size: std::mem::size_of::<T>() as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        let (sender, receiver) = channel();
+        Self {
+            staging,
+            tags: std::array::from_fn(|_| None),
+            next: 0,
+            sender,
+            receiver,
+            value: PhantomData,
+        }
+    }
+
+    /// Queues a copy of `source`. Returns `false` and skips it if the GPU has not caught up.
+    pub fn request(&mut self, device: &Device, queue: &Queue, source: &Buffer, tag: Tag) -> bool {
+        let slot = self.next;
+        if self.tags[slot].is_some() {
+            return false;
+        }
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        let size = std::mem::size_of::<T>() as u64;
+        encoder.copy_buffer_to_buffer(source, 0, &self.staging[slot], 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        let sender = self.sender.clone();
+        self.staging[slot]
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = sender.send((slot, result.is_ok()));
+            });
+        self.tags[slot] = Some(tag);
+        self.next = (slot + 1) % STAGING_BUFFERS;
+        true
+    }
+
+    /// Collects every finished copy and returns the most recent one with its tag.
+    pub fn poll(&mut self, device: &Device) -> Option<(Tag, T)> {
+        device.poll(Maintain::Poll);
+        let mut latest = None;
+        while let Ok((slot, ok)) = self.receiver.try_recv() {
+            let tag = self.tags[slot].take();
+            if ok {
+                let value = *bytemuck::from_bytes(&self.staging[slot].slice(..).get_mapped_range());
+                self.staging[slot].unmap();
+                latest = tag.map(|tag| (tag, value));
+            }
+        }
+        latest
+    }
+}
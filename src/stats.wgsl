@@ -0,0 +1,150 @@
+struct Instance {
+    pos: vec3<f32>,
+    rng: u32,
+    color: vec3<f32>,
+    age: u32,
+}
+
+struct LorenzConfig {
+    rho: f32,
+    sigma: f32,
+    beta: f32,
+    step_size_factor: f32,
+}
+
+struct StatsUniform {
+    lorenz: LorenzConfig,
+    center: vec3<f32>,
+    count: u32,
+    time: f32,
+    speed: u32,
+}
+
+// * SUMS OF d AND d d^T OVER THE OFFSETS d = pos - center, OFF DIAGONAL AS xy, xz, yz, THE
+// * BOUNDING BOX, THE SPEED RANGE AND THE NUMBER OF PARTICLES ON THE RIGHT LOBE, x > 0
+struct Stats {
+    sum: vec3<f32>,
+    count: f32,
+    diagonal: vec3<f32>,
+    right: f32,
+    off_diagonal: vec3<f32>,
+    min_speed: f32,
+    lo: vec3<f32>,
+    max_speed: f32,
+    hi: vec3<f32>,
+}
+
+// * SAME AS IN stats.rs
+const WORKGROUP_SIZE = 64u;
+const WORKGROUPS = 256u;
+const FAR = 3.4e38;
+
+// * SAME AS IN emitter.rs
+const DEAD = 0xffffffffu;
+
+@group(0) @binding(0)
+var<storage, read> instances: array<Instance>;
+@group(0) @binding(1)
+var<uniform> uniforms: StatsUniform;
+@group(0) @binding(2)
+var<storage, read_write> partials: array<Stats, WORKGROUPS>;
+@group(0) @binding(3)
+var<storage, read_write> result: Stats;
+
+var<workgroup> partial: array<Stats, WORKGROUP_SIZE>;
+
+// * THE `t` OF CUSTOM SYSTEMS
+var<private> time: f32;
+
+// * BEGIN VELOCITY, REPLACED FOR CUSTOM SYSTEMS
+fn lorenz_vel(lorenz_config: LorenzConfig, state: vec3<f32>) -> vec3<f32> {
+    let x = state.x;
+    let y = state.y;
+    let z = state.z;
+    return vec3<f32>(
+        lorenz_config.sigma * (y - x),
+        x * (lorenz_config.rho - z) - y,
+        x * y - lorenz_config.beta * z,
+    );
+}
+// * END VELOCITY
+
+fn empty() -> Stats {
+    return Stats(
+        vec3<f32>(0.0), 0.0,
+        vec3<f32>(0.0), 0.0,
+        vec3<f32>(0.0), FAR,
+        vec3<f32>(FAR), 0.0,
+        vec3<f32>(-FAR),
+    );
+}
+
+fn merge(a: Stats, b: Stats) -> Stats {
+    return Stats(
+        a.sum + b.sum, a.count + b.count,
+        a.diagonal + b.diagonal, a.right + b.right,
+        a.off_diagonal + b.off_diagonal, min(a.min_speed, b.min_speed),
+        min(a.lo, b.lo), max(a.max_speed, b.max_speed),
+        max(a.hi, b.hi),
+    );
+}
+
+// * ADDS UP THE STATS s OF EVERY THREAD OF THE WORKGROUP INTO partial[0]
+fn reduce(local: u32, s: Stats) {
+    partial[local] = s;
+    workgroupBarrier();
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        if local < stride {
+            partial[local] = merge(partial[local], partial[local + stride]);
+        }
+        workgroupBarrier();
+    }
+}
+
+// * EVERY THREAD FOLDS A STRIDED SLICE OF THE PARTICLES ALIVE, EVERY WORKGROUP ITS THREADS
+@compute
+@workgroup_size(64)
+fn cs_stats(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group: vec3<u32>,
+) {
+    time = uniforms.time;
+    var s = empty();
+    for (var i = group.x * WORKGROUP_SIZE + local; i < uniforms.count; i += WORKGROUPS * WORKGROUP_SIZE) {
+        if instances[i].age == DEAD {
+            continue;
+        }
+        let pos = instances[i].pos;
+        let d = pos - uniforms.center;
+        s.sum += d;
+        s.count += 1.0;
+        s.diagonal += d * d;
+        s.off_diagonal += d.xxy * d.yzz;
+        s.right += select(0.0, 1.0, pos.x > 0.0);
+        s.lo = min(s.lo, pos);
+        s.hi = max(s.hi, pos);
+        if uniforms.speed != 0u {
+            let speed = length(lorenz_vel(uniforms.lorenz, pos));
+            s.min_speed = min(s.min_speed, speed);
+            s.max_speed = max(s.max_speed, speed);
+        }
+    }
+    reduce(local, s);
+    if local == 0u {
+        partials[group.x] = partial[0];
+    }
+}
+
+// * A SINGLE WORKGROUP FOLDS THE PARTIALS OF ALL WORKGROUPS INTO result
+@compute
+@workgroup_size(64)
+fn cs_stats_combine(@builtin(local_invocation_index) local: u32) {
+    var s = empty();
+    for (var g = local; g < WORKGROUPS; g += WORKGROUP_SIZE) {
+        s = merge(s, partials[g]);
+    }
+    reduce(local, s);
+    if local == 0u {
+        result = partial[0];
+    }
+}
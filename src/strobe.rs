@@ -0,0 +1,33 @@
+use std::f32::consts::TAU;
+
+use serde::Deserialize;
+
+const SAMPLES: u32 = 100;
+
+/// Stroboscopic sampling of a periodically forced system, as read from the `[strobe]` table of
+/// a scene. The viewer advances one forcing period per frame, so the particles show the
+/// Poincaré section of the flow instead of moving along it.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Strobe {
+    /// Forcing period, `2π / ω` for a forcing `cos(ω t)`.
+    pub period: f32,
+    /// Integration steps per period.
+    pub samples: u32,
+}
+
+impl Default for Strobe {
+    fn default() -> Self {
+        Self {
+            period: TAU,
+            samples: SAMPLES,
+        }
+    }
+}
+
+impl Strobe {
+    /// The `delta_time` that makes `samples` steps last exactly one period.
+    pub fn delta_time(&self, step_size_factor: f32) -> f32 {
+        self.period / self.samples as f32 / step_size_factor
+    }
+}
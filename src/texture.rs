@@ -7,6 +7,28 @@ use wgpu::{
     TextureFormat, TextureSampleType, TextureUsages, TextureViewDimension,
 };
 
+/// Layout of the colormap bind group, group 1 of `compute.wgsl`.
+pub(crate) fn bind_group_layout_entries(visibility: ShaderStages) -> [BindGroupLayoutEntry; 2] {
+    [
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 1,
+            visibility,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        },
+    ]
+}
+
 pub struct Texture {
     pub bind_group_layout: BindGroupLayout,
     pub bind_group: BindGroup,
@@ -67,24 +89,7 @@ impl Texture {
 
         let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: None,
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility,
-                    ty: BindingType::Texture {
-                        sample_type: TextureSampleType::Float { filterable: true },
-                        view_dimension: TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility,
-                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
+            entries: &bind_group_layout_entries(visibility),
         });
 
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
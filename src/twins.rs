@@ -0,0 +1,106 @@
+use std::fmt;
+
+use glam::Vec3;
+use rand::Rng;
+use serde::Deserialize;
+
+const SEPARATION: f32 = 1e-4;
+const COLORS: [[f32; 3]; 2] = [[1.0, 0.35, 0.05], [0.05, 0.45, 1.0]];
+
+/// Particles created in pairs, as read from the `[twins]` table of a scene. Particle `2k + 1`
+/// starts `separation` away from particle `2k` in a random direction and the two are drawn in
+/// contrasting colors, so the butterfly effect can be watched pulling every pair apart until
+/// it spans the attractor. A respawned particle loses its twin.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Twins {
+    /// Distance ε the twins start at.
+    pub separation: f32,
+    /// Linear RGB color of the first and of the second twin of every pair.
+    pub colors: [[f32; 3]; 2],
+}
+
+impl Default for Twins {
+    fn default() -> Self {
+        Self {
+            separation: SEPARATION,
+            colors: COLORS,
+        }
+    }
+}
+
+impl Twins {
+    /// Moves the second particle of every pair in `points` `separation` away from the first.
+    pub fn pair(&self, points: &mut [Vec3], rng: &mut impl Rng) {
+        for pair in points.chunks_exact_mut(2) {
+            pair[1] = pair[0] + self.separation * random_direction(rng);
+        }
+    }
+
+    /// Color of particle `i`.
+    pub fn color(&self, i: usize) -> Vec3 {
+        Vec3::from_array(self.colors[i % 2])
+    }
+}
+
+/// Uniform on the unit sphere.
+fn random_direction(rng: &mut impl Rng) -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            rng.gen_range(-1f32..1.),
+            rng.gen_range(-1f32..1.),
+            rng.gen_range(-1f32..1.),
+        );
+        let length = p.length();
+        if length > 1e-3 && length <= 1. {
+            return p / length;
+        }
+    }
+}
+
+/// Mean distance of the twins of every complete pair in `points`, 0 without a pair. Mirrors
+/// `cs_separation` in `analysis.wgsl`.
+pub fn mean_separation(points: &[Vec3]) -> f32 {
+    let pairs = points.len() / 2;
+    if pairs == 0 {
+        return 0.;
+    }
+    let sum: f64 = points
+        .chunks_exact(2)
+        .map(|pair| pair[0].distance(pair[1]) as f64)
+        .sum();
+    (sum / pairs as f64) as f32
+}
+
+/// Mean separation of the twins at one step, a line of the divergence CSV.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Divergence {
+    pub step: u32,
+    /// Model time, the sum of the step sizes.
+    pub time: f64,
+    pub separation: f32,
+}
+
+impl Divergence {
+    /// Header of the CSV lines written by the `Display` impl.
+    pub const CSV_HEADER: &'static str = "step,time,separation,log_separation";
+
+    /// Natural logarithm of the separation, which grows with the largest Lyapunov exponent
+    /// until the twins are as far apart as the attractor is wide.
+    pub fn log_separation(&self) -> f32 {
+        self.separation.ln()
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.step,
+            self.time,
+            self.separation,
+            self.log_separation()
+        )
+    }
+}
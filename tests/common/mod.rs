@@ -0,0 +1,102 @@
+// * EVERY TEST BINARY USES ITS OWN PART OF THE HELPERS
+#![allow(dead_code)]
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, OnceLock},
+};
+
+use pollster::FutureExt;
+use wgpu::{Device, Queue};
+use wgpu_lorenz::{gpu::find_device, Backend, Config, Error, Scene, Simulation};
+
+/// Seed of the scenes that do not bring their own, so every run is the same.
+const SEED: u64 = 3;
+
+/// One device for the whole test binary. Software GL adapters do not cope well with many
+/// instances being created and dropped concurrently.
+fn device() -> Option<&'static (Arc<Device>, Arc<Queue>)> {
+    static DEVICE: OnceLock<Option<(Arc<Device>, Arc<Queue>)>> = OnceLock::new();
+    DEVICE
+        .get_or_init(|| {
+            let (_, _, device, queue) = match find_device(None).block_on() {
+                Ok(found) => found,
+                Err(Error::NoAdapter) => return None,
+                Err(e) => panic!("{e}"),
+            };
+            Some((Arc::new(device), Arc::new(queue)))
+        })
+        .as_ref()
+}
+
+/// Creates a simulation on the shared device, or `None` if this machine has no adapter.
+pub fn simulation(config: Config) -> Option<Simulation> {
+    let Some((device, queue)) = device() else {
+        eprintln!("skipping: no adapter available");
+        return None;
+    };
+    Some(Simulation::with_device(device.clone(), queue.clone(), config).unwrap())
+}
+
+/// Loads `scenes/{name}.toml`.
+pub fn scene(name: &str) -> Scene {
+    Scene::load(format!("{}/scenes/{name}.toml", env!("CARGO_MANIFEST_DIR")).as_ref()).unwrap()
+}
+
+/// `scene` on `backend` with `particles` particles, seeded if it is not already.
+pub fn config(scene: &Scene, backend: Backend, particles: usize) -> Config {
+    Scene {
+        backend,
+        particles,
+        seed: scene.seed.or(Some(SEED)),
+        ..scene.clone()
+    }
+    .into_config()
+    .unwrap()
+}
+
+/// `scene` on the GPU and on the CPU, in this order, or `None` if this machine has no
+/// adapter.
+pub fn gpu_and_cpu(scene: &Scene, particles: usize) -> Option<[Simulation; 2]> {
+    let gpu = simulation(config(scene, Backend::Gpu, particles))?;
+    let cpu = simulation(config(scene, Backend::Cpu, particles)).unwrap();
+    Some([gpu, cpu])
+}
+
+/// Checks that every particle is at most `tolerance` apart on both backends.
+pub fn assert_same_particles([gpu, cpu]: &[Simulation; 2], tolerance: f32, label: impl Debug) {
+    for (i, (g, c)) in gpu
+        .read_particles()
+        .iter()
+        .zip(cpu.read_particles())
+        .enumerate()
+    {
+        assert!(g.distance(c) < tolerance, "{label:?} {i}: {g} != {c}");
+    }
+}
+
+/// Checks that none of `scenes` makes it through validation.
+pub fn assert_rejected(scenes: impl IntoIterator<Item = Scene>) {
+    for scene in scenes {
+        let debug = format!("{scene:?}");
+        assert!(scene.into_config().is_err(), "{debug}");
+    }
+}
+
+/// Checks that the follow camera of every particle looks where the particle is drawn moving
+/// to in the next step, which must be short.
+pub fn assert_follow_direction_is_drawn_motion(sim: &mut Simulation) {
+    let dimensions = sim.config().state_dimensions();
+    let (before, states) = (sim.read_particles(), sim.read_states());
+    let (step, time) = (sim.draw_state().step, sim.time() as f32);
+    sim.step(1);
+    for (i, (before, after)) in before.iter().zip(sim.read_particles()).enumerate() {
+        let state = &states[i * dimensions..(i + 1) * dimensions];
+        let direction = sim
+            .config()
+            .drawn_velocity(*before, state, step, time)
+            .normalize();
+        let moved = (after - *before).normalize();
+        assert!(direction.dot(moved) > 0.999, "{i}: {direction} != {moved}");
+    }
+}
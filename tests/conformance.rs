@@ -0,0 +1,199 @@
+//! Runs identical initial conditions through the compute shader and the CPU reference and
+//! checks both against each other and against known properties of the Lorenz flow.
+
+mod common;
+
+use glam::{Mat3, Vec3};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use wgpu_lorenz::{
+    cpu::CpuState, equations::CustomSystem, respawn::RespawnSettings, scene::System, Backend,
+    Config, Integrator, Scene,
+};
+
+const INTEGRATORS: [Integrator; 3] = [Integrator::Euler, Integrator::Heun, Integrator::Rk4];
+
+// * A CUBE, SO EVERY POINT GETS ITS OWN WORKGROUP
+const PARTICLES: usize = 64;
+
+fn config(integrator: Integrator) -> Config {
+    Scene {
+        integrator,
+        particles: PARTICLES,
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+fn random_points(seed: u64) -> Vec<Vec3> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..PARTICLES)
+        .map(|_| {
+            Vec3::new(
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(0.0..40.0),
+            )
+        })
+        .collect()
+}
+
+fn run_cpu(integrator: Integrator, points: &[Vec3], steps: u32) -> Vec<Vec3> {
+    let mut cpu = CpuState::new(points, &config(integrator));
+    cpu.step(steps);
+    cpu.positions()
+}
+
+/// `None` if there is no adapter to run the compute shader on.
+fn run_gpu(integrator: Integrator, points: &[Vec3], steps: u32) -> Option<Vec<Vec3>> {
+    let mut sim = common::simulation(config(integrator))?;
+    sim.write_particles(points);
+    sim.step(steps);
+    Some(sim.read_particles())
+}
+
+/// Both backends, labelled for assertion messages.
+fn run_both(integrator: Integrator, points: &[Vec3], steps: u32) -> Vec<(&'static str, Vec<Vec3>)> {
+    let mut runs = vec![("cpu", run_cpu(integrator, points, steps))];
+    if let Some(gpu) = run_gpu(integrator, points, steps) {
+        runs.push(("gpu", gpu));
+    }
+    runs
+}
+
+#[test]
+fn gpu_matches_cpu_reference() {
+    let points = random_points(1);
+    for integrator in INTEGRATORS {
+        let Some(gpu) = run_gpu(integrator, &points, 20) else {
+            return;
+        };
+        let cpu = run_cpu(integrator, &points, 20);
+        for (gpu, cpu) in gpu.iter().zip(&cpu) {
+            assert!(
+                gpu.distance(*cpu) < 1e-3 * cpu.length().max(1.),
+                "{integrator:?}: {gpu} != {cpu}"
+            );
+        }
+    }
+}
+
+#[test]
+fn flow_is_symmetric_under_rotation_about_z() {
+    // * (x, y, z) -> (-x, -y, z) COMMUTES WITH THE FLOW, AND NEGATION IS EXACT IN FLOATING POINT
+    let half = random_points(2);
+    let mirrored = half[..PARTICLES / 2]
+        .iter()
+        .flat_map(|p| [*p, Vec3::new(-p.x, -p.y, p.z)])
+        .collect::<Vec<_>>();
+    for integrator in INTEGRATORS {
+        for (backend, result) in run_both(integrator, &mirrored, 50) {
+            for pair in result.chunks(2) {
+                assert_eq!(
+                    pair[1],
+                    Vec3::new(-pair[0].x, -pair[0].y, pair[0].z),
+                    "{backend} {integrator:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn volume_contracts_at_the_trace_of_the_jacobian() {
+    // * EVERY FOUR POINTS SPAN A SMALL TETRAHEDRON
+    const EPS: f32 = 0.01;
+    let tetrahedra = random_points(3)[..PARTICLES / 4]
+        .iter()
+        .flat_map(|p| {
+            [
+                *p,
+                *p + EPS * Vec3::X,
+                *p + EPS * Vec3::Y,
+                *p + EPS * Vec3::Z,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let volume = |t: &[Vec3]| Mat3::from_cols(t[1] - t[0], t[2] - t[0], t[3] - t[0]).determinant();
+
+    let steps = 20;
+    for integrator in INTEGRATORS {
+        let config = config(integrator);
+        let lorenz = config.lorenz;
+        let time = steps as f32 * lorenz.step_size_factor * config.delta_time;
+        let expected = -(lorenz.sigma + 1. + lorenz.beta) * time;
+        // * RELATIVE, THE ONE STEP DETERMINANT IS ONLY EXACT UP TO THE ORDER OF THE SCHEME
+        let tolerance = match integrator {
+            Integrator::Euler => 0.15,
+            Integrator::Heun => 0.01,
+            Integrator::Rk4 => 0.003,
+            _ => unreachable!("only deterministic integrators"),
+        };
+
+        for (backend, result) in run_both(integrator, &tetrahedra, steps) {
+            for (before, after) in tetrahedra.chunks(4).zip(result.chunks(4)) {
+                let rate = (volume(after) / volume(before)).ln();
+                assert!(
+                    (rate - expected).abs() < tolerance * expected.abs(),
+                    "{backend} {integrator:?}: log volume ratio {rate}, expected {expected}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn writing_particles_keeps_time_and_respawns_on_both_backends() {
+    // * dy/dt = cos(t), SO A CLOCK THAT STARTS OVER SHOWS IN THE POSITIONS
+    let config = |backend| -> Config {
+        Scene {
+            system: System::Custom,
+            backend,
+            integrator: Integrator::Rk4,
+            custom: CustomSystem {
+                dx: "0".to_owned(),
+                dy: "cos(t)".to_owned(),
+                dz: "0".to_owned(),
+                parameters: Default::default(),
+            },
+            particles: PARTICLES,
+            seed: Some(4),
+            respawn: RespawnSettings {
+                min_speed: 0.,
+                ..RespawnSettings::default()
+            },
+            ..Scene::default()
+        }
+        .into_config()
+        .unwrap()
+    };
+    // * THE FIRST TWO ARE RESPAWNED AFTER EVERY WRITE
+    let points = |seed| {
+        let mut points = random_points(seed);
+        points[..2].fill(Vec3::NAN);
+        points
+    };
+    let Some(mut gpu) = common::simulation(config(Backend::Gpu)) else {
+        return;
+    };
+    let mut cpu = common::simulation(config(Backend::Cpu)).unwrap();
+    for sim in [&mut gpu, &mut cpu] {
+        sim.write_particles(&points(5));
+        sim.step(100);
+        sim.write_particles(&points(6));
+        sim.step(100);
+    }
+
+    assert_eq!(gpu.time(), cpu.time());
+    assert_eq!(gpu.respawn_count(), 4);
+    assert_eq!(cpu.respawn_count(), 4);
+    let rise = Vec3::Y * (gpu.time().sin() - (gpu.time() / 2.).sin()) as f32;
+    let (gpu, cpu) = (gpu.read_particles(), cpu.read_particles());
+    for (i, (gpu, cpu)) in gpu.iter().zip(&cpu).enumerate() {
+        assert!(gpu.distance(*cpu) < 1e-3, "{i}: {gpu} != {cpu}");
+        if i >= 2 {
+            let expected = points(6)[i] + rise;
+            assert!(cpu.distance(expected) < 1e-3, "{i}: {cpu} != {expected}");
+        }
+    }
+}
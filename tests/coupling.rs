@@ -0,0 +1,152 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    coupling::{Coupling, Topology},
+    emitter::Emitter,
+    scene::System,
+    Backend, Integrator, Scene,
+};
+
+fn coupling(topology: Topology, strength: f32) -> Coupling {
+    Coupling {
+        topology,
+        strength,
+        ..Coupling::default()
+    }
+}
+
+#[test]
+fn pulls_follow_the_topology() {
+    // * A 2 x 2 x 2 CUBE, PARTICLE i SITS AT x = i
+    let snapshot: Vec<Vec3> = (0..8).map(|i| Vec3::X * i as f32).collect();
+    let mean = Vec3::X * 3.5;
+    let pull = |topology, i| coupling(topology, 1.).pull(i, &snapshot, mean, 2);
+    assert_eq!(pull(Topology::DriveResponse, 0), (Vec3::ZERO, 0.));
+    assert_eq!(pull(Topology::DriveResponse, 5), (Vec3::X * 4., 1.));
+    assert_eq!(pull(Topology::Mean, 2), (mean, 1.));
+    assert_eq!(pull(Topology::Ring, 0), (Vec3::X * 8., 2.));
+    // * ON AN EDGE OF 2 BOTH NEIGHBOURS ALONG AN AXIS ARE THE SAME CELL
+    assert_eq!(pull(Topology::Lattice, 0), (Vec3::X * 14., 6.));
+
+    let c = Coupling {
+        through: [1., 0., 0.5],
+        ..coupling(Topology::Ring, 2.)
+    };
+    let velocity = c.velocity(Vec3::ONE, Vec3::splat(4.), 2.);
+    assert_eq!(velocity, Vec3::new(4., 0., 2.));
+
+    let distance = |topology, i| coupling(topology, 1.).partner_distance(i, &snapshot, mean, 2);
+    assert_eq!(distance(Topology::DriveResponse, 6), 1.);
+    assert_eq!(distance(Topology::Mean, 0), 3.5);
+    assert_eq!(distance(Topology::Ring, 7), 7.);
+    assert_eq!(distance(Topology::Lattice, 5), 1.);
+    // * AN ODD PARTICLE OUT IS ITS OWN PARTNER
+    let odd = &snapshot[..7];
+    let c = coupling(Topology::DriveResponse, 1.);
+    assert_eq!(c.partner_distance(6, odd, mean, 2), 0.);
+    assert_eq!(c.sync_error(&snapshot, 2), (mean, 1.));
+}
+
+#[test]
+fn coupled_particles_and_sync_errors_match_on_both_backends() {
+    for topology in [
+        Topology::DriveResponse,
+        Topology::Mean,
+        Topology::Ring,
+        Topology::Lattice,
+    ] {
+        for integrator in [Integrator::Euler, Integrator::Heun, Integrator::Rk4] {
+            let scene = Scene {
+                integrator,
+                coupling: Some(coupling(topology, 2.)),
+                ..common::scene("drive-response")
+            };
+            let Some(mut sims) = common::gpu_and_cpu(&scene, 27) else {
+                return;
+            };
+            sims.iter_mut().for_each(|sim| sim.step(100));
+            common::assert_same_particles(&sims, 5e-2, (topology, integrator));
+            let [gpu, cpu] = &sims;
+            let (gpu_errors, cpu_errors) = (gpu.sync_errors(), cpu.sync_errors());
+            assert_eq!(gpu_errors.len(), 100);
+            for (g, c) in gpu_errors.iter().zip(&cpu_errors) {
+                assert!(
+                    (g - c).abs() < 1e-2,
+                    "{topology:?} {integrator:?}: {g} != {c}"
+                );
+            }
+            let (gpu_colors, cpu_colors) = (gpu.read_raw_instances(), cpu.read_raw_instances());
+            for (g, c) in gpu_colors.iter().zip(&cpu_colors) {
+                assert!((g.color() - c.color()).abs().max_element() < 5e-2);
+            }
+        }
+    }
+}
+
+#[test]
+fn drive_response_synchronizes_above_the_threshold() {
+    for backend in [Backend::Gpu, Backend::Cpu] {
+        let errors = |strength| {
+            let scene = Scene {
+                coupling: Some(coupling(Topology::DriveResponse, strength)),
+                ..common::scene("drive-response")
+            };
+            let mut sim = common::simulation(common::config(&scene, backend, 64))?;
+            sim.step(1000);
+            let start = sim.sync_errors()[0];
+            sim.step(3000);
+            let errors = sim.sync_errors();
+            assert_eq!(errors.len(), 1024);
+            Some((start, errors[1023]))
+        };
+        let Some((start, synchronized)) = errors(16.) else {
+            return;
+        };
+        assert!(start > 1., "{backend:?}: {start}");
+        assert!(synchronized < 1e-2, "{backend:?}: {synchronized}");
+        let (_, uncoupled) = errors(0.).unwrap();
+        assert!(uncoupled > 1., "{backend:?}: {uncoupled}");
+    }
+}
+
+#[test]
+fn scenes_load() {
+    for (name, topology) in [
+        ("drive-response", Topology::DriveResponse),
+        ("mean-field", Topology::Mean),
+    ] {
+        let config = common::scene(name).into_config().unwrap();
+        assert_eq!(config.coupling.unwrap().topology, topology, "{name}");
+    }
+    assert_eq!(common::scene("lorenz").coupling, None);
+
+    let coupled = |coupling| Scene {
+        particles: 1000,
+        coupling: Some(coupling),
+        ..Scene::default()
+    };
+    assert!(coupled(Coupling::default()).into_config().is_ok());
+    common::assert_rejected([
+        coupled(Coupling {
+            strength: -1.,
+            ..Coupling::default()
+        }),
+        coupled(Coupling {
+            through: [f32::NAN, 0., 0.],
+            ..Coupling::default()
+        }),
+        coupled(Coupling {
+            range: 0.,
+            ..Coupling::default()
+        }),
+        Scene {
+            system: System::Map,
+            ..coupled(Coupling::default())
+        },
+        Scene {
+            emitter: Some(Emitter::default()),
+            ..coupled(Coupling::default())
+        },
+    ]);
+}
@@ -0,0 +1,71 @@
+use glam::Vec3;
+use wgpu_lorenz::{cpu::CpuState, Integrator, Scene};
+
+fn points() -> Vec<Vec3> {
+    // * NOT A MULTIPLE OF THE SIMD WIDTH ON PURPOSE
+    (0..21)
+        .map(|i| Vec3::new(i as f32 - 10., 1. + i as f32 * 0.5, 20. - i as f32))
+        .collect()
+}
+
+#[test]
+fn matches_scalar_step_for_every_integrator() {
+    for integrator in [Integrator::Euler, Integrator::Heun, Integrator::Rk4] {
+        let config = Scene {
+            integrator,
+            ..Scene::default()
+        }
+        .into_config()
+        .unwrap();
+        let mut cpu = CpuState::new(&points(), &config);
+        cpu.step(50);
+
+        let mut expected = points();
+        for _ in 0..50 {
+            for p in &mut expected {
+                *p = config.lorenz.step(integrator, config.delta_time, *p);
+            }
+        }
+        for (simd, scalar) in cpu.positions().iter().zip(&expected) {
+            assert!(
+                simd.distance(*scalar) < 1e-4 * scalar.length().max(1.),
+                "{integrator:?}: {simd} != {scalar}"
+            );
+        }
+    }
+}
+
+#[test]
+fn higher_order_is_more_accurate() {
+    let config = Scene::default().into_config().unwrap();
+    let start = [Vec3::new(1., 1., 1.)];
+
+    // * 1000 RK4 STEPS OF A TENTH THE SIZE AS GROUND TRUTH
+    let mut reference = CpuState::new(&start, &config);
+    reference.set_integrator(Integrator::Rk4);
+    reference.set_delta_time(config.delta_time / 10.);
+    reference.step(1000);
+    let truth = reference.positions()[0];
+
+    let error = |integrator| {
+        let mut cpu = CpuState::new(&start, &config);
+        cpu.set_integrator(integrator);
+        cpu.step(100);
+        cpu.positions()[0].distance(truth)
+    };
+    let (euler, heun, rk4) = (
+        error(Integrator::Euler),
+        error(Integrator::Heun),
+        error(Integrator::Rk4),
+    );
+    assert!(rk4 < heun && heun < euler, "{euler} {heun} {rk4}");
+}
+
+#[test]
+fn raw_instances_cover_every_particle() {
+    let config = Scene::default().into_config().unwrap();
+    let cpu = CpuState::new(&points(), &config);
+    let raw = cpu.raw_instances();
+    assert_eq!(raw.len(), 21);
+    assert_eq!(raw[20].position(), points()[20]);
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use wgpu_lorenz::{
+    delay::{history_at, Delay, DelayKind},
+    scene::System,
+    strobe::Strobe,
+    Backend, Config, Integrator, Scene,
+};
+
+fn scene(path: &str) -> Scene {
+    Scene::load(format!("{}/scenes/{path}.toml", env!("CARGO_MANIFEST_DIR")).as_ref()).unwrap()
+}
+
+#[test]
+fn history_interpolates_back_around_the_ring() {
+    // * AT STEP 8 THE HEAD IS SAMPLE 2, THE OLDER ONES WRAP TO THE END
+    let history = [2., 3., 4., -1., 0., 1.];
+    assert_eq!(history_at(&history, 8, 0.), 4.);
+    assert_eq!(history_at(&history, 8, 2.), 2.);
+    assert_eq!(history_at(&history, 8, 3.), 1.);
+    assert_eq!(history_at(&history, 8, 2.5), 1.5);
+    assert_eq!(history_at(&history, 8, 4.25), -0.25);
+
+    // * A CONSTANT HISTORY AT THE FIXED POINT x = 1 OF MACKEY-GLASS STAYS THERE
+    let delay = Delay::default();
+    let mut history = vec![1.; delay.history_len()];
+    for step in 0..200 {
+        delay.step(Integrator::Rk4, &mut history, step);
+    }
+    assert!(history.iter().all(|x| (x - 1.).abs() < 1e-6), "{history:?}");
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    for (name, kind) in [
+        ("mackey-glass", DelayKind::MackeyGlass),
+        ("ikeda-dde", DelayKind::Ikeda),
+    ] {
+        for integrator in [Integrator::Euler, Integrator::Heun, Integrator::Rk4] {
+            let config = |backend| -> Config {
+                Scene {
+                    backend,
+                    integrator,
+                    particles: 27,
+                    ..scene(name)
+                }
+                .into_config()
+                .unwrap()
+            };
+            let Some(mut gpu) = common::simulation(config(Backend::Gpu)) else {
+                return;
+            };
+            let mut cpu = common::simulation(config(Backend::Cpu)).unwrap();
+            assert_eq!(cpu.config().delay.kind, kind);
+            gpu.step(150);
+            cpu.step(150);
+            let (gpu_states, cpu_states) = (gpu.read_states(), cpu.read_states());
+            assert_eq!(gpu_states.len(), 27 * cpu.config().delay.history_len());
+            for (g, c) in gpu_states.iter().zip(&cpu_states) {
+                assert!((g - c).abs() < 1e-3, "{kind:?} {integrator:?}: {g} != {c}");
+            }
+            for (g, c) in gpu.read_particles().iter().zip(cpu.read_particles()) {
+                assert!(g.distance(c) < 5e-2, "{kind:?} {integrator:?}: {g} != {c}");
+            }
+        }
+    }
+}
+
+#[test]
+fn time_advances_by_the_delay_per_samples() {
+    for backend in [Backend::Gpu, Backend::Cpu] {
+        let config = Scene {
+            backend,
+            particles: 8,
+            delta_time: 0.123,
+            ..scene("mackey-glass")
+        }
+        .into_config()
+        .unwrap();
+        let Some(mut sim) = common::simulation(config) else {
+            return;
+        };
+        sim.step(250);
+        // * 2.5 DELAYS OF 17
+        assert!((sim.time() - 42.5).abs() < 1e-3, "{}", sim.time());
+        let delay = sim.config().delay;
+        let expected: Vec<_> = sim
+            .read_states()
+            .chunks(delay.history_len())
+            .map(|h| delay.embed(h, sim.draw_state().step))
+            .collect();
+        for (p, e) in sim.read_particles().iter().zip(&expected) {
+            assert!(p.distance(*e) < 1e-3, "{p} != {e}");
+        }
+    }
+}
+
+#[test]
+fn follow_direction_is_the_motion_of_the_embedding() {
+    for name in ["mackey-glass", "ikeda-dde"] {
+        let config = Scene {
+            backend: Backend::Cpu,
+            particles: 8,
+            ..scene(name)
+        }
+        .into_config()
+        .unwrap();
+        let Some(mut sim) = common::simulation(config) else {
+            return;
+        };
+        // * PAST THE CONSTANT INITIAL HISTORY
+        sim.step(350);
+        common::assert_follow_direction_is_drawn_motion(&mut sim);
+    }
+}
+
+#[test]
+fn scenes_load() {
+    for name in ["mackey-glass", "ikeda-dde"] {
+        let config = scene(name).into_config().unwrap();
+        assert_eq!(config.system, System::Delay, "{name}");
+        assert_eq!(config.state_dimensions(), config.delay.history_len());
+        assert!(config.projection().is_empty());
+    }
+    assert_eq!(scene("ikeda-dde").delay.kind, DelayKind::Ikeda);
+
+    let delay = |delay| Scene {
+        system: System::Delay,
+        particles: 1000,
+        delay,
+        ..Scene::default()
+    };
+    assert!(delay(Delay::default()).into_config().is_ok());
+    let bad = [
+        Delay {
+            samples: 0,
+            ..Delay::default()
+        },
+        Delay {
+            tau: Some(0.),
+            ..Delay::default()
+        },
+        Delay {
+            initial: Some([2., 1.]),
+            ..Delay::default()
+        },
+        Delay {
+            scale: Some(-1.),
+            ..Delay::default()
+        },
+    ];
+    // * THE DEFAULT MILLION PARTICLES DO NOT FIT WITH THEIR HISTORIES
+    assert!(Scene {
+        particles: 1_000_000,
+        ..delay(Delay::default())
+    }
+    .into_config()
+    .is_err());
+    for d in bad {
+        assert!(delay(d).into_config().is_err(), "{d:?}");
+    }
+    assert!(Scene {
+        integrator: Integrator::EulerMaruyama,
+        ..delay(Delay::default())
+    }
+    .into_config()
+    .is_err());
+    assert!(Scene {
+        strobe: Some(Strobe::default()),
+        ..delay(Delay::default())
+    }
+    .into_config()
+    .is_err());
+}
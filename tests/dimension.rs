@@ -0,0 +1,115 @@
+mod common;
+
+use glam::Vec3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use wgpu_lorenz::{
+    dimension::{
+        box_counting_dimension, box_counts, correlation_dimension, correlation_sums, radii,
+        table_bits, Fit, LEVELS,
+    },
+    emitter::Emitter,
+    Backend, Scene,
+};
+
+#[test]
+fn fits_recover_the_dimension_of_lines_planes_and_cubes() {
+    let exact: Vec<(f64, f64)> = (0..5).map(|x| (x as f64, 3. - 2. * x as f64)).collect();
+    let fit = Fit::new(&exact).unwrap();
+    assert!((fit.slope + 2.).abs() < 1e-12 && (fit.intercept - 3.).abs() < 1e-12);
+    assert_eq!((fit.r_squared, fit.points), (1., 5));
+    assert_eq!(Fit::new(&exact[..1]), None);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let particles = 200_000;
+    for (dimension, scale) in [(1., Vec3::X), (2., Vec3::new(1., 1., 0.)), (3., Vec3::ONE)] {
+        let points: Vec<Vec3> = (0..particles)
+            .map(|_| scale * Vec3::new(rng.gen(), rng.gen(), rng.gen()))
+            .collect();
+        let counts = box_counts(&points, Vec3::ZERO, Vec3::ONE, table_bits(particles));
+        assert_eq!(counts.len(), LEVELS);
+        let boxes = box_counting_dimension(&counts, particles).unwrap();
+        let sums = correlation_sums(&points, &radii(1.), 20_000, 1000, &mut rng);
+        let correlation = correlation_dimension(&sums).unwrap();
+        for fit in [boxes, correlation] {
+            // * BOXES AND BALLS AT THE EDGE OF THE CUBE ARE ONLY PARTLY FILLED
+            assert!((fit.slope - dimension).abs() < 0.15, "{dimension}: {fit:?}");
+        }
+    }
+}
+
+#[test]
+fn lorenz_attractor_has_dimension_2_06() {
+    let config = common::config(&Scene::default(), Backend::Cpu, 262_144);
+    let Some(mut sim) = common::simulation(config) else {
+        return;
+    };
+    // * h = 0.005, SO 4000 STEPS ARE 20 TIME UNITS, ENOUGH TO SPREAD OVER THE ATTRACTOR
+    sim.step(4000);
+    let report = sim.dimension().unwrap();
+    let correlation = report.correlation.unwrap();
+    assert!((correlation.slope - 2.06).abs() < 0.05, "{report}");
+    assert!(
+        correlation.r_squared > 0.999 && correlation.points >= 8,
+        "{report}"
+    );
+    // * BOX COUNTING CONVERGES SLOWLY WITH THE NUMBER OF PARTICLES AND COMES OUT LOW
+    let box_counting = report.box_counting.unwrap();
+    assert!((1.8..2.1).contains(&box_counting.slope), "{report}");
+    assert!(box_counting.r_squared > 0.99, "{report}");
+    assert_eq!(report.box_counts, sim.box_counts());
+
+    let text = report.to_string();
+    assert!(text.contains(&format!("{:.3}", correlation.slope)));
+    assert_eq!(
+        text.lines().count(),
+        6 + report.box_counts.len() + report.correlation_sums.len()
+    );
+}
+
+#[test]
+fn gpu_box_counts_match_cpu() {
+    let config = common::config(&Scene::default(), Backend::Cpu, 32_768);
+    let Some(mut sim) = common::simulation(config) else {
+        return;
+    };
+    sim.step(2000);
+    let cpu = sim.box_counts();
+    // * THE SAME PARTICLES, COUNTED BY THE GPU HASH
+    sim.set_backend(Backend::Gpu);
+    let gpu = sim.box_counts();
+    assert_eq!(gpu.len(), LEVELS);
+    for (g, c) in gpu.iter().zip(&cpu) {
+        assert_eq!(g.size, c.size);
+        // * A PARTICLE ON A BOX BOUNDARY MAY LAND ON EITHER SIDE
+        assert!(
+            g.boxes.abs_diff(c.boxes) <= 2 + c.boxes / 1000,
+            "{gpu:?} != {cpu:?}"
+        );
+    }
+    assert!(gpu.windows(2).all(|w| w[0].boxes <= w[1].boxes), "{gpu:?}");
+}
+
+#[test]
+fn dead_particles_are_not_counted() {
+    let emitter = Scene {
+        emitter: Some(Emitter {
+            rate: 100,
+            ..Emitter::default()
+        }),
+        ..Scene::default()
+    };
+    let Some(sims) = common::gpu_and_cpu(&emitter, 4096) else {
+        return;
+    };
+    for mut sim in sims {
+        assert_eq!(sim.dimension(), None);
+        assert!(sim.box_counts().is_empty());
+        sim.step(3);
+        let report = sim.dimension().unwrap();
+        assert_eq!(report.particles, sim.alive_particles());
+        assert!(report
+            .box_counts
+            .iter()
+            .all(|c| c.boxes <= report.particles));
+    }
+}
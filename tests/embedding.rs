@@ -0,0 +1,84 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    embedding::{
+        false_nearest_neighbours, first_minimum, mutual_information, DelayRecorder, Embedding,
+        FNN_TOLERANCE,
+    },
+    Backend, Integrator, LorenzConfig, Scene,
+};
+
+/// `x` of a single trajectory on the attractor, one sample per default step.
+fn lorenz_series(samples: usize) -> Vec<f32> {
+    let lorenz = LorenzConfig::default();
+    let mut p = Vec3::new(1., 1., 20.);
+    // * SETTLE ON THE ATTRACTOR FIRST
+    for _ in 0..1000 {
+        p = lorenz.step(Integrator::Rk4, 0.01, p);
+    }
+    (0..samples)
+        .map(|_| {
+            p = lorenz.step(Integrator::Rk4, 0.01, p);
+            p.x
+        })
+        .collect()
+}
+
+#[test]
+fn lorenz_needs_three_dimensions() {
+    let series = lorenz_series(3000);
+    let curve: Vec<f32> = (0..100)
+        .map(|d| mutual_information(&series, d, 16))
+        .collect();
+    let delay = first_minimum(&curve).unwrap();
+    assert!((10..60).contains(&delay), "{delay}");
+
+    let fractions: Vec<f32> = (1..=4)
+        .map(|m| false_nearest_neighbours(&series, delay, m, FNN_TOLERANCE))
+        .collect();
+    assert!(fractions[0] > 0.2, "{fractions:?}");
+    assert!(fractions[2] < 0.02, "{fractions:?}");
+}
+
+#[test]
+fn recorder_keeps_the_history() {
+    let settings = Embedding {
+        particles: 2,
+        delay: 2,
+        history: 6,
+        offset: [10., 0., 0.],
+        ..Embedding::default()
+    };
+    let mut recorder = DelayRecorder::new(settings);
+    for t in 0..8 {
+        let x = t as f32;
+        recorder.push(&[Vec3::new(x, 0., 0.), Vec3::new(-x, 0., 0.)]);
+    }
+    assert_eq!(recorder.series(0), &[2., 3., 4., 5., 6., 7.]);
+    let points = recorder.points();
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[0], Vec3::new(16., 4., 2.));
+    assert_eq!(points[3], Vec3::new(3., -5., -3.));
+}
+
+#[test]
+fn reading_a_range_matches_all_particles() {
+    let config = || {
+        Scene {
+            particles: 1000,
+            seed: Some(3),
+            ..Scene::default()
+        }
+        .into_config()
+        .unwrap()
+    };
+    let Some(mut gpu) = common::simulation(config()) else {
+        return;
+    };
+    gpu.step(3);
+    let all = gpu.read_particles();
+    assert_eq!(gpu.read_particles_range(10..20), &all[10..20]);
+    gpu.set_backend(Backend::Cpu);
+    assert_eq!(gpu.read_particles_range(10..20), &all[10..20]);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    cpu::CpuState,
+    emitter::{Emitter, EmitterShape},
+    Backend, Config, Scene,
+};
+
+const PARTICLES: usize = 27;
+
+fn config(emitter: Emitter) -> Config {
+    Scene {
+        particles: PARTICLES,
+        seed: Some(5),
+        emitter: Some(emitter),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+fn alive(cpu: &CpuState) -> usize {
+    cpu.raw_instances().iter().filter(|i| i.is_alive()).count()
+}
+
+#[test]
+fn pool_fills_up_and_particles_die_of_age() {
+    let config = config(Emitter {
+        rate: 5,
+        lifetime: 3,
+        ..Emitter::default()
+    });
+    let mut cpu = CpuState::new(&vec![Vec3::ZERO; PARTICLES], &config);
+    assert_eq!(alive(&cpu), 0);
+    assert_eq!(cpu.draw_state().instance_count, 0);
+
+    cpu.step(1);
+    assert_eq!(alive(&cpu), 5);
+    assert_eq!(cpu.draw_state().head, 5);
+
+    // * THREE BATCHES ARE ALIVE AT ANY TIME, THE RING BUFFER WRAPS AROUND
+    cpu.step(10);
+    assert_eq!(alive(&cpu), 15);
+    assert_eq!(cpu.draw_state().head, (11 * 5 % PARTICLES) as u32);
+    assert_eq!(cpu.draw_state().instance_count, PARTICLES as u32);
+}
+
+#[test]
+fn too_high_rate_is_rejected() {
+    let scene = Scene {
+        particles: PARTICLES,
+        emitter: Some(Emitter {
+            rate: PARTICLES as u32 + 1,
+            ..Emitter::default()
+        }),
+        ..Scene::default()
+    };
+    assert!(scene.into_config().is_err());
+}
+
+#[test]
+fn gpu_emits_like_cpu() {
+    let emitter = Emitter {
+        shape: EmitterShape::Sphere,
+        position: [0., 0., 25.],
+        radius: 5.,
+        rate: 4,
+        lifetime: 5,
+        ..Emitter::default()
+    };
+    let Some(mut gpu) = common::simulation(config(emitter)) else {
+        return;
+    };
+    gpu.step(8);
+
+    let mut cpu = common::simulation(config(emitter)).unwrap();
+    cpu.set_backend(Backend::Cpu);
+    cpu.step(8);
+
+    let (gpu_draw, cpu_draw) = (gpu.draw_state(), cpu.draw_state());
+    assert_eq!(gpu_draw.head, cpu_draw.head);
+    assert_eq!(gpu_draw.instance_count, cpu_draw.instance_count);
+    assert_eq!(gpu.alive_particles(), 4 * 5);
+    assert_eq!(cpu.alive_particles(), 4 * 5);
+    for (gpu, cpu) in gpu
+        .read_raw_instances()
+        .iter()
+        .zip(&cpu.read_raw_instances())
+    {
+        assert_eq!(gpu.age(), cpu.age());
+        assert!(
+            gpu.position().distance(cpu.position()) < 1e-3,
+            "{} != {}",
+            gpu.position(),
+            cpu.position()
+        );
+    }
+}
+
+#[test]
+fn emitted_map_particles_are_colored_by_their_jump() {
+    let henon = common::scene("henon");
+    let scene = Scene {
+        emitter: Some(Emitter {
+            rate: 5,
+            ..henon.emitter.unwrap()
+        }),
+        ..henon
+    };
+    let Some(mut sim) = common::simulation(common::config(&scene, Backend::Gpu, PARTICLES)) else {
+        return;
+    };
+    sim.step(1);
+    let gpu = sim.read_raw_instances();
+
+    // * THE CPU BACKEND COLORS BY THE JUMP AT THE CURRENT POSITION
+    sim.set_backend(Backend::Cpu);
+    let cpu = sim.read_raw_instances();
+    let emitted = gpu.iter().zip(&cpu).filter(|(gpu, _)| gpu.is_alive());
+    assert_eq!(emitted.clone().count(), 5);
+    for (gpu, cpu) in emitted {
+        assert_eq!(gpu.age(), 0);
+        assert!(
+            (gpu.color() - cpu.color()).abs().max_element() < 1e-2,
+            "{} != {}",
+            gpu.color(),
+            cpu.color()
+        );
+    }
+}
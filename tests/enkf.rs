@@ -0,0 +1,112 @@
+mod common;
+
+use glam::{Mat3, Vec3};
+use wgpu_lorenz::{
+    enkf::{Assimilation, Ensemble, Filter, Update},
+    lorenz::{Distribution, DistributionShape},
+    Backend, Config, Scene,
+};
+
+fn config(backend: Backend) -> Config {
+    Scene {
+        backend,
+        particles: 4096,
+        distribution: Distribution {
+            shape: DistributionShape::Gaussian,
+            center: [0., 0., 25.],
+            extent: 8.,
+        },
+        seed: Some(5),
+        assimilation: Some(Assimilation::default()),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+#[test]
+fn ensemble_statistics() {
+    let points = [
+        Vec3::new(1., 0., 5.),
+        Vec3::new(-1., 0., 5.),
+        Vec3::new(0., 2., 5.),
+        Vec3::new(0., -2., 5.),
+    ];
+    let ensemble = Ensemble::from_points(&points);
+    assert_eq!(ensemble.mean, Vec3::new(0., 0., 5.));
+    let expected = Mat3::from_diagonal(Vec3::new(2. / 3., 8. / 3., 0.));
+    assert!(ensemble.covariance.abs_diff_eq(expected, 1e-6));
+
+    // * NO SPREAD IN Z, SO THE OBSERVATION IS IGNORED THERE
+    let gain = ensemble.gain(1.);
+    assert!((gain.x_axis.x - 0.4).abs() < 1e-6);
+    assert_eq!(gain.z_axis.z, 0.);
+}
+
+#[test]
+fn analysis_collapses_onto_truth() {
+    let config = config(Backend::Cpu);
+    let mut filter = Filter::new(config.assimilation.unwrap(), &config);
+    let Some(mut sim) = common::simulation(config) else {
+        return;
+    };
+    let records = filter.run(&mut sim, 500);
+    assert_eq!(records.len(), 20);
+    let (first, last) = (records[0], records[records.len() - 1]);
+    assert!(first.analysis_spread < first.forecast_spread);
+    assert!(
+        last.analysis_rmse < 0.2 * first.forecast_rmse,
+        "{first:?} {last:?}"
+    );
+    assert!(last.analysis_spread < 0.2 * first.forecast_spread);
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    let Some(mut gpu) = common::simulation(config(Backend::Gpu)) else {
+        return;
+    };
+    let mut cpu = common::simulation(config(Backend::Gpu)).unwrap();
+    cpu.set_backend(Backend::Cpu);
+
+    let (gpu_ensemble, cpu_ensemble) = (gpu.ensemble(), cpu.ensemble());
+    assert!(gpu_ensemble.mean.abs_diff_eq(cpu_ensemble.mean, 1e-3));
+    assert!(gpu_ensemble
+        .covariance
+        .abs_diff_eq(cpu_ensemble.covariance, 1e-2));
+
+    let update = Update {
+        observation: Vec3::new(1., 2., 20.),
+        observation_noise: 2.,
+        gain: cpu_ensemble.gain(2.),
+        key: 3,
+        cycle: 4,
+    };
+    gpu.assimilate(&update);
+    cpu.assimilate(&update);
+    for (gpu, cpu) in gpu.read_particles().iter().zip(&cpu.read_particles()) {
+        assert!(gpu.distance(*cpu) < 1e-3, "{gpu} != {cpu}");
+    }
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let scene = |assimilation| Scene {
+        particles: 4096,
+        assimilation: Some(assimilation),
+        ..Scene::default()
+    };
+    let interval = Assimilation {
+        interval: 0,
+        ..Assimilation::default()
+    };
+    assert!(scene(interval).into_config().is_err());
+    let noise = Assimilation {
+        observation_noise: 0.,
+        ..Assimilation::default()
+    };
+    assert!(scene(noise).into_config().is_err());
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/enkf.toml");
+    Scene::load(path.as_ref()).unwrap().into_config().unwrap();
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    cpu::CpuState,
+    equations::{CustomSystem, Equations},
+    scene::{SceneError, System},
+    Backend, Config, Scene,
+};
+
+fn custom(dx: &str, dy: &str, dz: &str) -> CustomSystem {
+    CustomSystem {
+        dx: dx.to_owned(),
+        dy: dy.to_owned(),
+        dz: dz.to_owned(),
+        parameters: [("a".to_owned(), 10.), ("b".to_owned(), 2.5)].into(),
+    }
+}
+
+#[test]
+fn default_matches_lorenz() {
+    let config = Scene {
+        system: System::Custom,
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap();
+    let points: Vec<Vec3> = (0..11)
+        .map(|i| Vec3::new(i as f32 - 5., 1. + i as f32, 20. - i as f32))
+        .collect();
+    let mut cpu = CpuState::new(&points, &config);
+    cpu.step(50);
+
+    let mut expected = points;
+    for _ in 0..50 {
+        for p in &mut expected {
+            *p = config.lorenz.step(config.integrator, config.delta_time, *p);
+        }
+    }
+    for (custom, lorenz) in cpu.positions().iter().zip(&expected) {
+        assert!(
+            custom.distance(*lorenz) < 1e-4 * lorenz.length().max(1.),
+            "{custom} != {lorenz}"
+        );
+    }
+}
+
+#[test]
+fn parse_errors_point_at_the_problem() {
+    let error = |dx: &str| Equations::parse(&custom(dx, "y", "z")).unwrap_err();
+
+    let e = error("a * (y - x");
+    assert_eq!((e.component, e.column), ("dx", 10));
+    assert_eq!(e.message, "expected `)`, found end of expression");
+
+    let e = error("a * q + 1");
+    assert_eq!(e.column, 4);
+    assert!(e.message.contains("unknown variable `q`"), "{e}");
+
+    let e = error("sin(x, y)");
+    assert_eq!(e.column, 0);
+    assert_eq!(e.message, "`sin` takes 1 argument, got 2");
+
+    let e = error("x $ y");
+    assert_eq!(e.column, 2);
+    assert!(e.to_string().ends_with("\n  x $ y\n    ^"), "{e}");
+
+    let mut reserved = custom("x", "y", "z");
+    reserved.parameters.insert("sin".to_owned(), 1.);
+    let e = Equations::parse(&reserved).unwrap_err();
+    assert_eq!(e.component, "parameters");
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    let config = || -> Config {
+        Scene {
+            system: System::Custom,
+            custom: custom(
+                "a * (y - x) + 2 * sin(3 * t)",
+                "x * (28 - z) - y",
+                "x ^ 2 / 20 + x * y / 20 - b * z",
+            ),
+            particles: 1000,
+            seed: Some(11),
+            ..Scene::default()
+        }
+        .into_config()
+        .unwrap()
+    };
+    let Some(mut gpu) = common::simulation(config()) else {
+        return;
+    };
+    let mut cpu = common::simulation(config()).unwrap();
+    cpu.set_backend(Backend::Cpu);
+    gpu.step(20);
+    cpu.step(20);
+    for (gpu, cpu) in gpu.read_particles().iter().zip(&cpu.read_particles()) {
+        assert!(
+            gpu.distance(*cpu) < 1e-3 * cpu.length().max(1.),
+            "{gpu} != {cpu}"
+        );
+    }
+
+    // * REPLACING THE FIELD REBUILDS THE PIPELINE
+    let drift = Equations::parse(&custom("1", "0", "0")).unwrap();
+    gpu.set_equations(drift).unwrap();
+    let before = gpu.read_particles();
+    gpu.step(5);
+    let h = config().lorenz.step_size_factor * config().delta_time;
+    for (after, before) in gpu.read_particles().iter().zip(&before) {
+        assert!(
+            after.distance(*before + Vec3::X * 5. * h) < 1e-4,
+            "{after} != {before}"
+        );
+    }
+}
+
+#[test]
+fn scene_reports_the_expression() {
+    let scene = Scene {
+        system: System::Custom,
+        custom: custom("x", "y +", "z"),
+        ..Scene::default()
+    };
+    match scene.into_config() {
+        Err(SceneError::Invalid { field, .. }) => assert_eq!(field, "custom.dy"),
+        Err(e) => panic!("{e}"),
+        Ok(_) => panic!("accepted `y +`"),
+    }
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/custom.toml");
+    let config = Scene::load(path.as_ref()).unwrap().into_config().unwrap();
+    assert!(config.custom.is_some());
+}
@@ -0,0 +1,163 @@
+use glam::{Mat3, Vec3};
+use wgpu_lorenz::{
+    equilibria::{self, lorenz_jacobian, numerical_jacobian, Equilibrium, Kind, Mode},
+    lorenz::LorenzConfig,
+    scene::System,
+    Scene,
+};
+
+fn lorenz(rho: f32) -> LorenzConfig {
+    LorenzConfig {
+        rho,
+        ..Scene::default().into_config().unwrap().lorenz
+    }
+}
+
+/// Checks `J v = λ v` for real modes and `J u, J w ∈ span(u, w)` for complex ones.
+fn assert_eigen(e: &Equilibrium) {
+    let j = e.jacobian;
+    let scale = 1. + j.to_cols_array().iter().fold(0f32, |m, x| m.max(x.abs()));
+    for mode in &e.modes {
+        match *mode {
+            Mode::Real { value, direction } => {
+                assert!((direction.length() - 1.).abs() < 1e-5);
+                let error = (j * direction - value as f32 * direction).length();
+                assert!(error < 1e-4 * scale, "{e}: {error}");
+            }
+            Mode::Complex { plane: [u, w], .. } => {
+                assert!(u.dot(w).abs() < 1e-5);
+                let normal = u.cross(w);
+                for v in [u, w] {
+                    assert!((j * v).dot(normal).abs() < 1e-4 * scale, "{e}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn lorenz_origin_is_a_saddle_and_lobe_centers_are_saddle_foci() {
+    let found = equilibria::lorenz(&lorenz(28.));
+    assert_eq!(found.len(), 3);
+    let [origin, plus, minus] = [&found[0], &found[1], &found[2]];
+    assert_eq!(origin.point, Vec3::ZERO);
+    let r = (8f32 / 3. * 27.).sqrt();
+    assert!((plus.point - Vec3::new(r, r, 27.)).length() < 1e-4);
+    assert!((minus.point - Vec3::new(-r, -r, 27.)).length() < 1e-4);
+
+    assert_eq!(origin.kind, Kind::Saddle);
+    assert_eq!(origin.unstable_dimension(), 1);
+    let values: Vec<f64> = origin.modes.iter().map(Mode::growth).collect();
+    for (value, expected) in values.iter().zip([11.828, -8. / 3., -22.828]) {
+        assert!((value - expected).abs() < 1e-3, "{origin}");
+    }
+    // * THE z AXIS IS INVARIANT, AND STABLE
+    let Mode::Real { direction, .. } = origin.modes[1] else {
+        panic!("{origin}");
+    };
+    assert!(direction.cross(Vec3::Z).length() < 1e-5, "{origin}");
+
+    for center in [plus, minus] {
+        assert_eq!(center.kind, Kind::SaddleFocus);
+        assert_eq!(center.unstable_dimension(), 2);
+        let Mode::Real { value, .. } = center.modes[0] else {
+            panic!("{center}");
+        };
+        assert!((value + 13.855).abs() < 1e-3, "{center}");
+        let Mode::Complex { re, im, .. } = center.modes[1] else {
+            panic!("{center}");
+        };
+        assert!(
+            (re - 0.094).abs() < 1e-3 && (im - 10.195).abs() < 1e-3,
+            "{center}"
+        );
+        assert!(center.to_string().contains("saddle-focus"));
+    }
+    found.iter().for_each(assert_eigen);
+}
+
+#[test]
+fn lorenz_bifurcations_change_the_number_and_kind_of_equilibria() {
+    let below = equilibria::lorenz(&lorenz(0.5));
+    assert_eq!(below.len(), 1);
+    assert_eq!(below[0].kind, Kind::Sink);
+
+    // * BEFORE THE HOPF BIFURCATION AT ρ ≈ 24.74 THE LOBE CENTERS ATTRACT
+    let stable = equilibria::lorenz(&lorenz(20.));
+    assert_eq!(stable.len(), 3);
+    assert_eq!(stable[0].kind, Kind::Saddle);
+    assert!(stable[1..].iter().all(|e| e.kind == Kind::SpiralSink));
+    assert!(stable.iter().skip(1).all(|e| e.unstable_dimension() == 0));
+
+    // * THE PITCHFORK AT ρ = 1 IS NOT HYPERBOLIC
+    let pitchfork = equilibria::lorenz(&lorenz(1.));
+    assert_eq!(pitchfork.len(), 1);
+    assert_eq!(pitchfork[0].kind, Kind::NonHyperbolic);
+    stable.iter().chain(&below).for_each(assert_eigen);
+}
+
+#[test]
+fn newton_finds_the_lorenz_equilibria_of_custom_equations() {
+    let config = Scene {
+        // * THE DEFAULT EXPRESSIONS ARE THE LORENZ SYSTEM
+        system: System::Custom,
+        seed: Some(1),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap();
+    let found = equilibria::find(&config, Vec3::splat(-30.), Vec3::splat(50.), 0.);
+    let exact = equilibria::lorenz(&lorenz(28.));
+    assert_eq!(found.len(), 3, "{found:?}");
+    for e in &exact {
+        let newton = found
+            .iter()
+            .find(|f| f.point.distance(e.point) < 1e-3)
+            .unwrap_or_else(|| panic!("{e} not in {found:?}"));
+        assert_eq!(newton.kind, e.kind);
+        for (n, m) in newton.modes.iter().zip(&e.modes) {
+            assert!((n.growth() - m.growth()).abs() < 1e-2, "{newton} != {e}");
+        }
+    }
+    found.iter().for_each(assert_eigen);
+    // * THE SAME POINTS IN THE SAME ORDER EVERY TIME
+    assert_eq!(
+        found,
+        equilibria::find(&config, Vec3::splat(-30.), Vec3::splat(50.), 0.)
+    );
+}
+
+#[test]
+fn jacobians_and_classification_of_linear_systems() {
+    let p = Vec3::new(1., -2., 3.);
+    let config = lorenz(28.);
+    let numerical = numerical_jacobian(|p| config.delta(p), p);
+    let analytic = lorenz_jacobian(&config, p);
+    let error = (numerical - analytic).to_cols_array();
+    assert!(
+        error.iter().all(|d| d.abs() < 1e-3),
+        "{numerical} != {analytic}"
+    );
+
+    let diagonal = |values: [f32; 3]| Mat3::from_diagonal(Vec3::from_array(values));
+    let rotation = |re: f32, real: f32| {
+        Mat3::from_cols(
+            Vec3::new(re, 2., 0.),
+            Vec3::new(-2., re, 0.),
+            Vec3::new(0., 0., real),
+        )
+    };
+    for (jacobian, kind) in [
+        (diagonal([-1., -2., -3.]), Kind::Sink),
+        (diagonal([1., 2., 3.]), Kind::Source),
+        (diagonal([1., -2., -2.]), Kind::Saddle),
+        (diagonal([0., -1., -2.]), Kind::NonHyperbolic),
+        (rotation(-1., -3.), Kind::SpiralSink),
+        (rotation(1., 3.), Kind::SpiralSource),
+        (rotation(1., -3.), Kind::SaddleFocus),
+    ] {
+        let e = Equilibrium::new(Vec3::ZERO, jacobian);
+        assert_eq!(e.kind, kind, "{e}");
+        assert_eigen(&e);
+    }
+}
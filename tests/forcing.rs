@@ -0,0 +1,119 @@
+mod common;
+
+use std::f64::consts::TAU;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    equations::CustomSystem, respawn::RespawnSettings, scene::System, strobe::Strobe, Backend,
+    Config, Integrator, Scene,
+};
+
+fn scene(path: &str) -> Scene {
+    Scene::load(format!("{}/scenes/{path}.toml", env!("CARGO_MANIFEST_DIR")).as_ref()).unwrap()
+}
+
+fn close(a: &[Vec3], b: &[Vec3], tolerance: f32) {
+    for (a, b) in a.iter().zip(b) {
+        assert!(a.distance(*b) < tolerance, "{a} != {b}");
+    }
+}
+
+#[test]
+fn time_survives_delta_time_changes() {
+    for backend in [Backend::Gpu, Backend::Cpu] {
+        let config = Scene {
+            system: System::Custom,
+            backend,
+            integrator: Integrator::Rk4,
+            custom: CustomSystem {
+                dx: "0".to_owned(),
+                dy: "cos(t)".to_owned(),
+                dz: "0".to_owned(),
+                parameters: Default::default(),
+            },
+            particles: 8,
+            seed: Some(1),
+            respawn: RespawnSettings {
+                min_speed: 0.,
+                ..RespawnSettings::default()
+            },
+            ..Scene::default()
+        }
+        .into_config()
+        .unwrap();
+        let Some(mut sim) = common::simulation(config) else {
+            return;
+        };
+        let start = sim.read_particles();
+        sim.step(100);
+        sim.set_delta_time(0.03);
+        sim.step(100);
+        // * 100 STEPS OF 0.5 * 0.01 AND 100 OF 0.5 * 0.03
+        assert!((sim.time() - 2.).abs() < 1e-6, "{}", sim.time());
+        let expected: Vec<Vec3> = start.iter().map(|p| *p + Vec3::Y * 2f32.sin()).collect();
+        close(&sim.read_particles(), &expected, 1e-4);
+    }
+}
+
+#[test]
+fn strobe_samples_whole_periods_on_both_backends() {
+    let config = |backend| -> Config {
+        Scene {
+            backend,
+            particles: 27,
+            ..scene("duffing")
+        }
+        .into_config()
+        .unwrap()
+    };
+    let Some(mut gpu) = common::simulation(config(Backend::Gpu)) else {
+        return;
+    };
+    let mut cpu = common::simulation(config(Backend::Cpu)).unwrap();
+    let period = config(Backend::Cpu).strobe.unwrap().period as f64;
+    for sim in [&mut gpu, &mut cpu] {
+        // * START BETWEEN TWO PERIODS, THE FIRST CALL CATCHES UP
+        sim.step(37);
+        sim.step_periods(1);
+        assert_eq!(sim.time(), period);
+        sim.step_periods(2);
+        assert_eq!(sim.time(), 3. * period);
+        assert_eq!(sim.config().delta_time, 0.01);
+        assert!((period - TAU / 1.2).abs() < 1e-6);
+    }
+    close(&gpu.read_particles(), &cpu.read_particles(), 1e-2);
+}
+
+#[test]
+fn scenes_load() {
+    for name in ["duffing", "van-der-pol", "modulated-lorenz"] {
+        let config = scene(name).into_config().unwrap();
+        assert_eq!(config.system, System::Custom);
+        assert!(config.custom.is_some(), "{name}");
+    }
+
+    let strobe = |strobe, system| Scene {
+        system,
+        strobe: Some(strobe),
+        ..Scene::default()
+    };
+    assert!(strobe(Strobe::default(), System::Custom)
+        .into_config()
+        .is_ok());
+    let bad = [
+        Strobe {
+            period: 0.,
+            ..Strobe::default()
+        },
+        Strobe {
+            samples: 0,
+            ..Strobe::default()
+        },
+    ];
+    for s in bad {
+        assert!(strobe(s, System::Lorenz).into_config().is_err(), "{s:?}");
+    }
+    assert!(strobe(Strobe::default(), System::Map)
+        .into_config()
+        .is_err());
+}
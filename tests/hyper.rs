@@ -0,0 +1,167 @@
+mod common;
+
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Mat4, Vec3, Vec4};
+use wgpu_lorenz::{
+    hyper::{Hyper, HyperColor, HyperKind},
+    scene::System,
+    Backend, Config, Integrator, Scene,
+};
+
+fn scene(path: &str) -> Scene {
+    Scene::load(format!("{}/scenes/{path}.toml", env!("CARGO_MANIFEST_DIR")).as_ref()).unwrap()
+}
+
+fn close(a: &[Vec3], b: &[Vec3], tolerance: f32) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert!(a.distance(*b) < tolerance, "{a} != {b}");
+    }
+}
+
+#[test]
+fn rotation_is_orthonormal_and_turns_x_towards_w() {
+    let hyper = Hyper {
+        rotation: [0.3, -1.2, 0.7, 2.5, 0.1, -0.4],
+        ..Hyper::default()
+    };
+    let r = hyper.rotation_matrix();
+    assert!((r.transpose() * r).abs_diff_eq(Mat4::IDENTITY, 1e-6));
+
+    // * A QUARTER TURN IN xw DROPS x AND DRAWS -w IN ITS PLACE
+    let hyper = Hyper {
+        rotation: [0., 0., FRAC_PI_2, 0., 0., 0.],
+        scale: 2.,
+        ..Hyper::default()
+    };
+    let (drawn, dropped) = hyper.project(Vec4::new(1., 2., 3., 4.));
+    assert!(drawn.abs_diff_eq(Vec3::new(-8., 4., 6.), 1e-5), "{drawn}");
+    assert!((dropped - 1.).abs() < 1e-5, "{dropped}");
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    for kind in [HyperKind::Rossler, HyperKind::LorenzStenflo] {
+        for integrator in [Integrator::Euler, Integrator::Heun, Integrator::Rk4] {
+            let config = |backend| -> Config {
+                Scene {
+                    backend,
+                    integrator,
+                    particles: 27,
+                    hyper: Hyper {
+                        kind,
+                        rotation: [0.2, 0., 0.5, 0., -0.3, 0.],
+                        color: HyperColor::W,
+                        ..scene("rossler4d").hyper
+                    },
+                    ..scene("rossler4d")
+                }
+                .into_config()
+                .unwrap()
+            };
+            let Some(mut gpu) = common::simulation(config(Backend::Gpu)) else {
+                return;
+            };
+            let mut cpu = common::simulation(config(Backend::Cpu)).unwrap();
+            gpu.step(50);
+            cpu.step(50);
+            close(&gpu.read_particles(), &cpu.read_particles(), 1e-2);
+            let (gpu_states, cpu_states) = (gpu.read_states(), cpu.read_states());
+            assert_eq!(gpu_states.len(), 4 * 27);
+            for (g, c) in gpu_states.iter().zip(&cpu_states) {
+                assert!((g - c).abs() < 1e-2, "{kind:?} {integrator:?}: {g} != {c}");
+            }
+            assert_eq!(gpu.respawn_count(), cpu.respawn_count());
+        }
+    }
+}
+
+#[test]
+fn set_hyper_rotates_without_stepping() {
+    for backend in [Backend::Gpu, Backend::Cpu] {
+        let config = Scene {
+            backend,
+            particles: 8,
+            ..scene("lorenz-stenflo")
+        }
+        .into_config()
+        .unwrap();
+        let Some(mut sim) = common::simulation(config) else {
+            return;
+        };
+        sim.step(10);
+        let (states, step) = (sim.read_states(), sim.draw_state().step);
+
+        let mut hyper = sim.config().hyper;
+        hyper.rotation = [0.4, 0.1, -0.8, 0., 1.1, 0.3];
+        sim.set_hyper(hyper);
+        assert_eq!(sim.read_states(), states);
+        assert_eq!(sim.draw_state().step, step);
+        let expected: Vec<Vec3> = states
+            .chunks(4)
+            .map(|s| hyper.project(Vec4::from_slice(s)).0)
+            .collect();
+        close(&sim.read_particles(), &expected, 1e-3);
+    }
+}
+
+#[test]
+fn follow_direction_is_the_rotated_4d_velocity() {
+    let config = Scene {
+        backend: Backend::Cpu,
+        particles: 8,
+        hyper: Hyper {
+            rotation: [0.3, -1.2, 0.7, 2.5, 0.1, -0.4],
+            ..scene("rossler4d").hyper
+        },
+        ..scene("rossler4d")
+    }
+    .into_config()
+    .unwrap();
+    let Some(mut sim) = common::simulation(config) else {
+        return;
+    };
+    sim.step(100);
+    common::assert_follow_direction_is_drawn_motion(&mut sim);
+}
+
+#[test]
+fn scenes_load() {
+    for name in ["rossler4d", "lorenz-stenflo"] {
+        let config = scene(name).into_config().unwrap();
+        assert_eq!(config.system, System::Hyper, "{name}");
+        assert_eq!(config.state_dimensions(), 4);
+    }
+    assert_eq!(scene("lorenz-stenflo").hyper.kind, HyperKind::LorenzStenflo);
+
+    let hyper = |hyper, integrator| Scene {
+        system: System::Hyper,
+        integrator,
+        hyper,
+        ..Scene::default()
+    };
+    assert!(hyper(Hyper::default(), Integrator::Rk4)
+        .into_config()
+        .is_ok());
+    let bad = [
+        Hyper {
+            scale: 0.,
+            ..Hyper::default()
+        },
+        Hyper {
+            w_range: Some([1., 1.]),
+            ..Hyper::default()
+        },
+        Hyper {
+            a: Some(f32::NAN),
+            ..Hyper::default()
+        },
+    ];
+    for h in bad {
+        assert!(hyper(h, Integrator::Rk4).into_config().is_err(), "{h:?}");
+    }
+    assert!(hyper(Hyper::default(), Integrator::EulerMaruyama)
+        .into_config()
+        .is_err());
+}
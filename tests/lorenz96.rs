@@ -0,0 +1,153 @@
+mod common;
+
+use wgpu_lorenz::{
+    cpu::CpuState,
+    lorenz96::{Lorenz96, ProjectionKind},
+    respawn::RespawnSettings,
+    scene::System,
+    Backend, Config, Integrator, Scene,
+};
+
+// * A CUBE, SO EVERY POINT GETS ITS OWN WORKGROUP
+const PARTICLES: usize = 27;
+
+fn config(integrator: Integrator, lorenz96: Lorenz96) -> Config {
+    Scene {
+        system: System::Lorenz96,
+        integrator,
+        lorenz96,
+        particles: PARTICLES,
+        seed: Some(11),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+#[test]
+fn forcing_is_a_fixed_point() {
+    let lorenz96 = Lorenz96::default();
+    let config = config(Integrator::Rk4, lorenz96);
+    let mut cpu = CpuState::new(&[glam::Vec3::ZERO; PARTICLES], &config);
+    cpu.step(100);
+    assert!(cpu.states().iter().all(|x| *x == lorenz96.forcing));
+}
+
+#[test]
+fn random_projection_is_orthonormal() {
+    let lorenz96 = Lorenz96 {
+        projection: ProjectionKind::Random,
+        ..Lorenz96::default()
+    };
+    let images = lorenz96.projection(3);
+    assert_eq!(images.len(), lorenz96.dimensions);
+    for a in 0..3 {
+        for b in 0..3 {
+            let dot: f32 = images.iter().map(|i| i[a] * i[b]).sum();
+            let expected = if a == b { 1. } else { 0. };
+            assert!((dot - expected).abs() < 1e-5, "rows {a} and {b}: {dot}");
+        }
+    }
+}
+
+#[test]
+fn follow_direction_is_the_projected_tendency() {
+    for projection in [ProjectionKind::Components, ProjectionKind::Random] {
+        let lorenz96 = Lorenz96 {
+            projection,
+            ..Lorenz96::default()
+        };
+        let config = Config {
+            backend: Backend::Cpu,
+            delta_time: 0.001,
+            ..config(Integrator::Rk4, lorenz96)
+        };
+        let Some(mut sim) = common::simulation(config) else {
+            return;
+        };
+        sim.step(1000);
+        common::assert_follow_direction_is_drawn_motion(&mut sim);
+    }
+}
+
+#[test]
+fn stochastic_integrators_are_rejected_at_runtime() {
+    let Some(mut sim) = common::simulation(config(Integrator::Rk4, Lorenz96::default())) else {
+        return;
+    };
+    for integrator in [Integrator::EulerMaruyama, Integrator::StochasticHeun] {
+        assert!(sim.set_integrator(integrator).is_err(), "{integrator:?}");
+    }
+    assert_eq!(sim.config().integrator, Integrator::Rk4);
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let scene = |integrator, lorenz96| Scene {
+        system: System::Lorenz96,
+        integrator,
+        lorenz96,
+        particles: PARTICLES,
+        ..Scene::default()
+    };
+    let components = Lorenz96 {
+        dimensions: 8,
+        components: [0, 1, 8],
+        ..Lorenz96::default()
+    };
+    assert!(scene(Integrator::Euler, components).into_config().is_err());
+    let too_small = Lorenz96 {
+        dimensions: 3,
+        ..Lorenz96::default()
+    };
+    assert!(scene(Integrator::Euler, too_small).into_config().is_err());
+    assert!(scene(Integrator::EulerMaruyama, Lorenz96::default())
+        .into_config()
+        .is_err());
+    let respawn = Scene {
+        respawn: RespawnSettings {
+            radius: 50.,
+            ..RespawnSettings::default()
+        },
+        ..scene(Integrator::Euler, Lorenz96::default())
+    };
+    assert!(respawn.into_config().is_err());
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/lorenz96.toml");
+    Scene::load(path.as_ref()).unwrap().into_config().unwrap();
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    for integrator in [Integrator::Euler, Integrator::Heun, Integrator::Rk4] {
+        let config = || {
+            config(
+                integrator,
+                Lorenz96 {
+                    projection: ProjectionKind::Random,
+                    ..Lorenz96::default()
+                },
+            )
+        };
+        let Some(mut gpu) = common::simulation(config()) else {
+            return;
+        };
+        gpu.step(40);
+
+        // * HALFWAY ON THE GPU, SO THE STATES HAVE TO CARRY OVER
+        let mut cpu = common::simulation(config()).unwrap();
+        cpu.step(20);
+        cpu.set_backend(Backend::Cpu);
+        cpu.step(20);
+
+        for (gpu, cpu) in gpu.read_states().iter().zip(&cpu.read_states()) {
+            assert!(
+                (gpu - cpu).abs() < 1e-3 * gpu.abs().max(1.),
+                "{integrator:?}: {gpu} != {cpu}"
+            );
+        }
+        for (gpu, cpu) in gpu.read_particles().iter().zip(&cpu.read_particles()) {
+            assert!(gpu.distance(*cpu) < 1e-2, "{integrator:?}: {gpu} != {cpu}");
+        }
+    }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    lorenz::{Distribution, DistributionShape},
+    maps::{Map, MapKind, MapLayout},
+    scene::{RenderMode, System},
+    Backend, Config, Integrator, Scene,
+};
+
+const KINDS: [MapKind; 6] = [
+    MapKind::Henon,
+    MapKind::Lozi,
+    MapKind::Clifford,
+    MapKind::DeJong,
+    MapKind::Ikeda,
+    MapKind::Tinkerbell,
+];
+
+#[test]
+fn iterates_the_formula() {
+    let map = Map {
+        b: Some(0.2),
+        scale: 10.,
+        ..Map::default()
+    };
+    assert_eq!(map.parameters(), [1.4, 0.2, 0., 0.]);
+    let next = map.iterate(Vec3::new(5., 2., 3.));
+    let (x, y) = (0.5, 0.2);
+    assert!(next.abs_diff_eq(
+        Vec3::new(10. * (1. - 1.4 * x * x + y), 10. * 0.2 * x, 0.),
+        1e-5
+    ));
+
+    // * A STACK GROWS BY ONE LAYER PER ITERATION UP TO THE TOP
+    let stacked = Map {
+        layout: MapLayout::Stacked,
+        spacing: 1.,
+        layers: 3,
+        ..map
+    };
+    let mut p = Vec3::ZERO;
+    let heights: Vec<f32> = (0..5)
+        .map(|_| {
+            p = stacked.iterate(p);
+            p.z
+        })
+        .collect();
+    assert_eq!(heights, [1., 2., 3., 3., 3.]);
+}
+
+fn config(kind: MapKind, backend: Backend) -> Config {
+    // * TINKERBELL ONLY ATTRACTS A SMALL REGION
+    let center = match kind {
+        MapKind::Tinkerbell => [-14.4, -12.8, 0.],
+        _ => [0., 0., 0.],
+    };
+    Scene {
+        system: System::Map,
+        backend,
+        map: Map {
+            kind,
+            layout: MapLayout::Stacked,
+            ..Map::default()
+        },
+        particles: 1000,
+        distribution: Distribution {
+            shape: DistributionShape::Cube,
+            center,
+            extent: 1.,
+        },
+        seed: Some(4),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    for kind in KINDS {
+        let Some(mut gpu) = common::simulation(config(kind, Backend::Gpu)) else {
+            return;
+        };
+        let mut cpu = common::simulation(config(kind, Backend::Cpu)).unwrap();
+        gpu.step(8);
+        cpu.step(8);
+        let (gpu, cpu) = (gpu.read_particles(), cpu.read_particles());
+        assert!(cpu.iter().all(|p| p.z == 4.), "{kind:?}");
+        for (gpu, cpu) in gpu.iter().zip(&cpu) {
+            assert!(gpu.distance(*cpu) < 1e-3, "{kind:?}: {gpu} != {cpu}");
+        }
+    }
+}
+
+#[test]
+fn scenes_load() {
+    for name in ["clifford", "henon"] {
+        let path = format!("{}/scenes/{name}.toml", env!("CARGO_MANIFEST_DIR"));
+        let config = Scene::load(path.as_ref()).unwrap().into_config().unwrap();
+        assert_eq!(config.system, System::Map);
+    }
+
+    let scene = || Scene {
+        system: System::Map,
+        render_mode: RenderMode::Density,
+        ..Scene::default()
+    };
+    assert_eq!(scene().into_config().unwrap().density, Some(0.05));
+    let stochastic = Scene {
+        integrator: Integrator::EulerMaruyama,
+        ..scene()
+    };
+    assert!(stochastic.into_config().is_err());
+    let exposure = Scene {
+        exposure: 0.,
+        ..scene()
+    };
+    assert!(exposure.into_config().is_err());
+}
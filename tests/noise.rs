@@ -0,0 +1,96 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    cpu::CpuState,
+    noise::{Noise, NoiseKind},
+    Backend, Config, Integrator, Scene,
+};
+
+// * A CUBE, SO EVERY POINT GETS ITS OWN WORKGROUP
+const PARTICLES: usize = 27;
+
+fn config(integrator: Integrator, kind: NoiseKind, amplitude: f32, seed: u64) -> Config {
+    Scene {
+        integrator,
+        noise: Noise {
+            kind,
+            amplitude: [amplitude, 2. * amplitude, amplitude],
+        },
+        particles: PARTICLES,
+        seed: Some(seed),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+fn points() -> Vec<Vec3> {
+    (0..PARTICLES)
+        .map(|i| Vec3::new(i as f32 - 13., 5., 20. + i as f32 * 0.5))
+        .collect()
+}
+
+fn run_cpu(config: &Config, steps: u32) -> Vec<Vec3> {
+    let mut cpu = CpuState::new(&points(), config);
+    cpu.step(steps);
+    cpu.positions()
+}
+
+#[test]
+fn without_noise_stochastic_integrators_are_deterministic() {
+    for (stochastic, deterministic) in [
+        (Integrator::EulerMaruyama, Integrator::Euler),
+        (Integrator::StochasticHeun, Integrator::Heun),
+    ] {
+        assert_eq!(
+            run_cpu(&config(stochastic, NoiseKind::Additive, 0., 1), 100),
+            run_cpu(&config(deterministic, NoiseKind::Additive, 0., 1), 100),
+            "{stochastic:?}"
+        );
+    }
+}
+
+#[test]
+fn noise_follows_the_seed() {
+    let run = |seed| {
+        run_cpu(
+            &config(Integrator::EulerMaruyama, NoiseKind::Additive, 1., seed),
+            10,
+        )
+    };
+    assert_eq!(run(1), run(1));
+    assert_ne!(run(1), run(2));
+    assert_ne!(
+        run(1),
+        run_cpu(&config(Integrator::Euler, NoiseKind::Additive, 1., 1), 10)
+    );
+}
+
+#[test]
+fn gpu_noise_matches_cpu() {
+    for integrator in [Integrator::EulerMaruyama, Integrator::StochasticHeun] {
+        for kind in [NoiseKind::Additive, NoiseKind::Multiplicative] {
+            let config = || config(integrator, kind, 0.5, 7);
+            let Some(mut gpu) = common::simulation(config()) else {
+                return;
+            };
+            gpu.write_particles(&points());
+            gpu.step(10);
+
+            // * HALFWAY ON THE GPU, SO THE STEP COUNTER HAS TO CARRY OVER
+            let mut cpu = common::simulation(config()).unwrap();
+            cpu.write_particles(&points());
+            cpu.step(5);
+            cpu.set_backend(Backend::Cpu);
+            cpu.step(5);
+
+            for (gpu, cpu) in gpu.read_particles().iter().zip(&cpu.read_particles()) {
+                assert!(
+                    gpu.distance(*cpu) < 1e-3 * gpu.length().max(1.),
+                    "{integrator:?} {kind:?}: {gpu} != {cpu}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Golden checksums of the CPU reference. If an intentional change to the integration alters
+//! them, update the table with `wgpu_lorenz -n 1000 --seed 42 --backend cpu --integrator <name>
+//! --checksum-at 0,100,1000`.
+
+mod common;
+
+use rand::{rngs::StdRng, SeedableRng};
+use wgpu_lorenz::{
+    cpu::CpuState, lorenz::LorenzState, simulation::checksum, Backend, Config, Integrator, Scene,
+};
+
+const SEED: u64 = 42;
+
+const GOLDEN: [(Integrator, [(u32, u64); 3]); 3] = [
+    (
+        Integrator::Euler,
+        [
+            (0, 0x01b1930614a7e985),
+            (100, 0xc30cc5bf8d26410e),
+            (1000, 0x990bb728c80a87e3),
+        ],
+    ),
+    (
+        Integrator::Heun,
+        [
+            (0, 0x01b1930614a7e985),
+            (100, 0x482a6a992f5c51c0),
+            (1000, 0x01a4b0d071bf4e50),
+        ],
+    ),
+    (
+        Integrator::Rk4,
+        [
+            (0, 0x01b1930614a7e985),
+            (100, 0x584c52356d6fca7b),
+            (1000, 0x09a67cc5b8b61025),
+        ],
+    ),
+];
+
+fn config(integrator: Integrator, backend: Backend) -> Config {
+    Scene {
+        integrator,
+        backend,
+        particles: 1000,
+        seed: Some(SEED),
+        ..Scene::default()
+    }
+    .into_config()
+    .unwrap()
+}
+
+#[test]
+fn cpu_reference_matches_golden_checksums() {
+    for (integrator, golden) in GOLDEN {
+        let config = config(integrator, Backend::Cpu);
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let state = LorenzState::new(config.num_lorenz_points, &config.distribution, &mut rng);
+        let mut cpu = CpuState::new(&state.points, &config);
+
+        let mut step = 0;
+        for (steps, expected) in golden {
+            cpu.step(steps - step);
+            step = steps;
+            let actual = checksum(&cpu.positions());
+            assert_eq!(
+                actual, expected,
+                "{integrator:?} after {steps} steps: {actual:016x} != {expected:016x}"
+            );
+        }
+    }
+}
+
+#[test]
+fn simulation_is_seeded_like_the_reference() {
+    let Some(mut sim) = common::simulation(config(Integrator::Rk4, Backend::Cpu)) else {
+        return;
+    };
+    sim.step(100);
+    assert_eq!(sim.checksum(), GOLDEN[2].1[1].1);
+}
+
+#[test]
+fn gpu_checksums_are_repeatable() {
+    let checksum = || {
+        let mut sim = common::simulation(config(Integrator::Heun, Backend::Gpu))?;
+        sim.step(100);
+        Some(sim.checksum())
+    };
+    let (Some(a), Some(b)) = (checksum(), checksum()) else {
+        return;
+    };
+    assert_eq!(a, b);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    cpu::CpuState,
+    lorenz::{Distribution, DistributionShape},
+    respawn::RespawnSettings,
+    Backend, Config, LorenzConfig, Scene,
+};
+
+// * A CUBE, SO EVERY POINT GETS ITS OWN WORKGROUP
+const PARTICLES: usize = 27;
+
+fn config(scene: Scene) -> Config {
+    Scene {
+        particles: PARTICLES,
+        seed: Some(3),
+        ..scene
+    }
+    .into_config()
+    .unwrap()
+}
+
+fn far_away() -> Vec<Vec3> {
+    (0..PARTICLES)
+        .map(|i| match i % 3 {
+            0 => Vec3::splat(f32::NAN),
+            1 => Vec3::new(f32::INFINITY, 0., 0.),
+            _ => Vec3::new(0., 5000., 0.),
+        })
+        .collect()
+}
+
+#[test]
+fn diverging_particles_are_respawned() {
+    let config = config(Scene::default());
+    let mut cpu = CpuState::new(&far_away(), &config);
+    cpu.step(1);
+    assert_eq!(cpu.respawns(), PARTICLES as u64);
+    for p in cpu.positions() {
+        assert!(p.abs().max_element() <= 50., "{p}");
+    }
+}
+
+#[test]
+fn stalled_particles_are_respawned() {
+    // * BELOW RHO = 1 THE ORIGIN ATTRACTS EVERYTHING
+    let config = config(Scene {
+        parameters: LorenzConfig {
+            rho: 0.5,
+            ..LorenzConfig::default()
+        },
+        respawn: RespawnSettings {
+            min_speed: 1.,
+            ..RespawnSettings::default()
+        },
+        ..Scene::default()
+    });
+    let points = vec![Vec3::new(1., 1., 1.); PARTICLES];
+    let mut cpu = CpuState::new(&points, &config);
+    cpu.step(1000);
+    assert!(cpu.respawns() > 0);
+}
+
+#[test]
+fn healthy_particles_are_left_alone() {
+    let config = config(Scene::default());
+    let points = vec![Vec3::new(1., 1., 20.); PARTICLES];
+    let mut cpu = CpuState::new(&points, &config);
+    cpu.step(100);
+    assert_eq!(cpu.respawns(), 0);
+}
+
+#[test]
+fn gpu_respawns_like_cpu() {
+    for shape in [
+        DistributionShape::Cube,
+        DistributionShape::Sphere,
+        DistributionShape::Gaussian,
+    ] {
+        let config = || {
+            config(Scene {
+                respawn: RespawnSettings {
+                    distribution: Some(Distribution {
+                        shape,
+                        center: [0., 0., 25.],
+                        extent: 10.,
+                    }),
+                    ..RespawnSettings::default()
+                },
+                ..Scene::default()
+            })
+        };
+        let Some(mut gpu) = common::simulation(config()) else {
+            return;
+        };
+        gpu.write_particles(&far_away());
+        gpu.step(1);
+
+        let mut cpu = common::simulation(config()).unwrap();
+        cpu.write_particles(&far_away());
+        cpu.set_backend(Backend::Cpu);
+        cpu.step(1);
+
+        assert_eq!(gpu.respawn_count(), PARTICLES as u64);
+        assert_eq!(cpu.respawn_count(), PARTICLES as u64);
+        for (gpu, cpu) in gpu.read_particles().iter().zip(&cpu.read_particles()) {
+            assert!(gpu.distance(*cpu) < 1e-3, "{shape:?}: {gpu} != {cpu}");
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use wgpu_lorenz::{scene::SceneError, Scene};
+
+#[test]
+fn default_scene_is_valid() {
+    let config = Scene::default().into_config().unwrap();
+    assert!(config.num_lorenz_points > 0);
+    let (x, y, z) = config.num_workgroups;
+    assert_eq!((x * y * z) as usize, config.num_lorenz_points);
+}
+
+#[test]
+fn particle_count_is_rounded_down_to_a_cube() {
+    for (requested, expected) in [(1, 1), (8, 8), (26, 8), (27, 27), (1000, 1000)] {
+        let scene = Scene {
+            particles: requested,
+            ..Scene::default()
+        };
+        assert_eq!(scene.into_config().unwrap().num_lorenz_points, expected);
+    }
+}
+
+#[test]
+fn rejects_invalid_fields() {
+    let mut scene = Scene {
+        particles: 0,
+        ..Scene::default()
+    };
+    assert!(matches!(
+        scene.clone().into_config(),
+        Err(SceneError::Invalid {
+            field: "particles",
+            ..
+        })
+    ));
+
+    scene.particles = 100;
+    scene.parameters.sigma = f32::NAN;
+    assert!(matches!(
+        scene.into_config(),
+        Err(SceneError::Invalid {
+            field: "parameters.sigma",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn reports_parse_errors_with_the_path() {
+    let path = std::env::temp_dir().join("wgpu_lorenz_bad_scene.toml");
+    std::fs::write(&path, "particles = \"many\"\n").unwrap();
+    let error = Scene::load(&path).unwrap_err();
+    assert!(matches!(error, SceneError::Parse { .. }));
+    assert!(error.to_string().contains("wgpu_lorenz_bad_scene.toml"));
+}
+
+#[test]
+fn loads_the_example_scene() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/lorenz.toml");
+    let scene = Scene::load(path.as_ref()).unwrap();
+    assert_eq!(scene.seed, Some(42));
+    scene.into_config().unwrap();
+}
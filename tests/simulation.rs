@@ -0,0 +1,126 @@
+mod common;
+
+use wgpu_lorenz::{camera::CameraSettings, Backend, Integrator, LorenzConfig, Scene, Simulation};
+
+fn simulation(particles: usize, seed: u64) -> Option<Simulation> {
+    let scene = Scene {
+        particles,
+        seed: Some(seed),
+        ..Scene::default()
+    };
+    common::simulation(scene.into_config().unwrap())
+}
+
+#[test]
+fn reads_back_every_particle() {
+    let Some(sim) = simulation(1000, 1) else {
+        return;
+    };
+    assert_eq!(sim.num_particles(), 1000);
+    assert_eq!(sim.read_particles().len(), 1000);
+}
+
+#[test]
+fn same_seed_same_particles() {
+    let (Some(a), Some(b)) = (simulation(512, 7), simulation(512, 7)) else {
+        return;
+    };
+    assert_eq!(a.read_particles(), b.read_particles());
+}
+
+#[test]
+fn step_matches_explicit_euler() {
+    let Some(mut sim) = simulation(512, 2) else {
+        return;
+    };
+    let lorenz = sim.config().lorenz;
+    let dt = sim.config().delta_time;
+    let mut expected = sim.read_particles();
+    sim.step(10);
+    for _ in 0..10 {
+        for p in &mut expected {
+            *p += lorenz.step_size_factor * dt * lorenz.delta(*p);
+        }
+    }
+    for (gpu, cpu) in sim.read_particles().iter().zip(&expected) {
+        assert!(
+            gpu.distance(*cpu) < 1e-3 * cpu.length().max(1.),
+            "{gpu} != {cpu}"
+        );
+    }
+}
+
+#[test]
+fn zero_delta_time_freezes_particles() {
+    let Some(mut sim) = simulation(512, 3) else {
+        return;
+    };
+    let before = sim.read_particles();
+    sim.set_delta_time(0.);
+    sim.step(5);
+    assert_eq!(sim.read_particles(), before);
+}
+
+#[test]
+fn parameters_change_the_flow() {
+    let (Some(mut a), Some(mut b)) = (simulation(512, 4), simulation(512, 4)) else {
+        return;
+    };
+    b.set_parameters(LorenzConfig {
+        rho: 15.,
+        ..LorenzConfig::default()
+    });
+    a.step(20);
+    b.step(20);
+    assert_ne!(a.read_particles(), b.read_particles());
+}
+
+#[test]
+fn renders_to_image() {
+    let Some(sim) = simulation(4096, 5) else {
+        return;
+    };
+    let (width, height) = (64, 48);
+    let image = sim.render_to_image(&CameraSettings::default(), width, height);
+    assert_eq!(image.len(), (4 * width * height) as usize);
+    let background = &image[..4];
+    assert!(
+        image.chunks(4).any(|pixel| pixel != background),
+        "no particle was drawn"
+    );
+}
+
+#[test]
+fn gpu_matches_cpu_backend_for_every_integrator() {
+    for integrator in [Integrator::Euler, Integrator::Heun, Integrator::Rk4] {
+        let (Some(mut gpu), Some(mut cpu)) = (simulation(512, 6), simulation(512, 6)) else {
+            return;
+        };
+        gpu.set_integrator(integrator).unwrap();
+        cpu.set_integrator(integrator).unwrap();
+        cpu.set_backend(Backend::Cpu);
+        gpu.step(10);
+        cpu.step(10);
+        for (gpu, cpu) in gpu.read_particles().iter().zip(&cpu.read_particles()) {
+            assert!(
+                gpu.distance(*cpu) < 1e-3 * cpu.length().max(1.),
+                "{integrator:?}: {gpu} != {cpu}"
+            );
+        }
+    }
+}
+
+#[test]
+fn switching_backend_keeps_particles() {
+    let Some(mut sim) = simulation(512, 8) else {
+        return;
+    };
+    sim.step(3);
+    let before = sim.read_particles();
+    sim.set_backend(Backend::Cpu);
+    assert_eq!(sim.read_particles(), before);
+    sim.step(3);
+    let after = sim.read_particles();
+    sim.set_backend(Backend::Gpu);
+    assert_eq!(sim.read_particles(), after);
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use glam::Vec3;
+use wgpu_lorenz::{
+    emitter::Emitter, instance::RawInstance, stats::EnsembleStats, Backend, Scene, Simulation,
+};
+
+const PARTICLES: usize = 4096;
+
+fn alive_points(sim: &Simulation) -> Vec<Vec3> {
+    sim.read_raw_instances()
+        .iter()
+        .filter(|instance| instance.is_alive())
+        .map(RawInstance::position)
+        .collect()
+}
+
+/// Stats reduced on the CPU from the particles of `sim`, as `cs_stats` does on the GPU.
+fn mirror(sim: &Simulation, speed: bool) -> EnsembleStats {
+    let config = sim.config();
+    let time = sim.time() as f32;
+    let velocity = |p| config.velocity(p, time).length();
+    let speed: Option<&dyn Fn(Vec3) -> f32> = speed.then_some(&velocity);
+    EnsembleStats::from_points(sim.draw_state().step, &alive_points(sim), speed).unwrap()
+}
+
+fn assert_close(a: &EnsembleStats, b: &EnsembleStats) {
+    assert_eq!(a.count, b.count);
+    let (ea, eb) = (a.ensemble, b.ensemble);
+    assert!(
+        (ea.mean - eb.mean).abs().max_element() < 1e-3,
+        "{a:?} != {b:?}"
+    );
+    let covariance = (ea.covariance - eb.covariance).to_cols_array();
+    assert!(covariance.iter().all(|d| d.abs() < 1e-2), "{a:?} != {b:?}");
+    assert!((a.min - b.min).abs().max_element() < 1e-5, "{a:?} != {b:?}");
+    assert!((a.max - b.max).abs().max_element() < 1e-5, "{a:?} != {b:?}");
+    assert!((a.lobes[1] - b.lobes[1]).abs() < 1e-6, "{a:?} != {b:?}");
+    match (a.speed, b.speed) {
+        (Some((a0, a1)), Some((b0, b1))) => {
+            assert!((a0 - b0).abs() < 1e-2 * b1 && (a1 - b1).abs() < 1e-2 * b1);
+        }
+        (a, b) => assert_eq!(a, b),
+    }
+}
+
+#[test]
+fn reductions_match_the_particles_on_both_backends() {
+    for path in ["lorenz", "custom"] {
+        let Some(mut sims) = common::gpu_and_cpu(&common::scene(path), PARTICLES) else {
+            return;
+        };
+        for sim in &mut sims {
+            sim.step(500);
+            let stats = sim.stats().unwrap();
+            assert_eq!(stats.step, 500);
+            assert_close(&stats, &mirror(sim, true));
+        }
+    }
+}
+
+#[test]
+fn requested_stats_arrive_a_frame_later() {
+    let Some(sims) = common::gpu_and_cpu(&common::scene("lorenz"), PARTICLES) else {
+        return;
+    };
+    for mut sim in sims {
+        assert_eq!(sim.poll_stats(), None);
+        sim.step(100);
+        sim.request_stats();
+        let requested = sim.stats().unwrap();
+        sim.step(1);
+        let polled = (0..1000)
+            .find_map(|_| {
+                let stats = sim.poll_stats();
+                if stats.is_none() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                stats
+            })
+            .unwrap();
+        // * THE FIRST REQUEST SUMS AROUND THE ORIGIN, SO ONLY ROUGHLY THE SAME COVARIANCE
+        assert_eq!(polled.step, requested.step);
+        assert!((polled.ensemble.mean - requested.ensemble.mean).length() < 1e-3);
+        assert!((polled.ensemble.spread() - requested.ensemble.spread()).abs() < 1e-2);
+        assert_eq!(sim.poll_stats(), None, "{:?}", sim.config().backend);
+    }
+}
+
+#[test]
+fn box_contains_every_particle_and_lobes_add_up() {
+    let Some(sims) = common::gpu_and_cpu(&common::scene("lorenz"), PARTICLES) else {
+        return;
+    };
+    for mut sim in sims {
+        sim.step(2000);
+        let stats = sim.stats().unwrap();
+        assert_eq!(stats.count, 4096);
+        for p in alive_points(&sim) {
+            assert!(p.cmpge(stats.min).all() && p.cmple(stats.max).all(), "{p}");
+        }
+        // * ON THE ATTRACTOR BOTH LOBES ARE VISITED
+        assert_eq!(stats.lobes[0] + stats.lobes[1], 1.);
+        assert!(
+            stats.lobes.iter().all(|&f| (0.2..0.8).contains(&f)),
+            "{stats:?}"
+        );
+        let (slowest, fastest) = stats.speed.unwrap();
+        assert!(0. <= slowest && slowest < fastest, "{stats:?}");
+
+        let line = stats.to_string();
+        let header = EnsembleStats::CSV_HEADER.split(',').count();
+        assert_eq!(line.split(',').count(), header);
+    }
+}
+
+#[test]
+fn only_flows_have_a_speed_and_dead_particles_are_skipped() {
+    let clifford = common::scene("clifford");
+    for backend in [Backend::Gpu, Backend::Cpu] {
+        let Some(mut map) = common::simulation(common::config(&clifford, backend, PARTICLES))
+        else {
+            return;
+        };
+        map.step(10);
+        let stats = map.stats().unwrap();
+        assert_eq!(stats.speed, None);
+        assert!(stats.to_string().contains(",,"));
+
+        let emitter = Scene {
+            emitter: Some(Emitter {
+                rate: 100,
+                ..Emitter::default()
+            }),
+            ..Scene::default()
+        };
+        let mut sim = common::simulation(common::config(&emitter, backend, PARTICLES)).unwrap();
+        assert_eq!(sim.stats(), None, "{backend:?}");
+        sim.step(3);
+        let stats = sim.stats().unwrap();
+        assert_eq!(stats.count, sim.alive_particles());
+        assert_close(&stats, &mirror(&sim, true));
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use glam::Vec3;
+use rand::{rngs::StdRng, SeedableRng};
+use wgpu_lorenz::{
+    coupling::Coupling,
+    emitter::Emitter,
+    scene::System,
+    twins::{mean_separation, Divergence, Twins},
+    Backend, Scene,
+};
+
+#[test]
+fn twins_start_apart_in_contrasting_colors() {
+    let twins = Twins {
+        separation: 0.5,
+        ..Twins::default()
+    };
+    let mut points = vec![Vec3::new(1., 2., 3.); 7];
+    twins.pair(&mut points, &mut StdRng::seed_from_u64(1));
+    for pair in points.chunks_exact(2) {
+        assert!((pair[0].distance(pair[1]) - 0.5).abs() < 1e-6, "{pair:?}");
+    }
+    // * THE ODD PARTICLE OUT IS LEFT ALONE AND NOT COUNTED
+    assert_eq!(points[6], Vec3::new(1., 2., 3.));
+    assert!((mean_separation(&points) - 0.5).abs() < 1e-6);
+    assert_eq!(mean_separation(&points[..1]), 0.);
+    assert_ne!(twins.color(0), twins.color(1));
+    assert_eq!(twins.color(2), twins.color(0));
+
+    let record = Divergence {
+        step: 3,
+        time: 0.5,
+        separation: 1.,
+    };
+    assert_eq!(Divergence::CSV_HEADER.split(',').count(), 4);
+    assert_eq!(record.to_string(), "3,0.5,1,0");
+}
+
+#[test]
+fn separation_matches_on_both_backends() {
+    let Some(mut sims) = common::gpu_and_cpu(&common::scene("butterfly"), 1000) else {
+        return;
+    };
+    let twins = sims[1].config().twins.unwrap();
+    for sim in &sims {
+        assert!((sim.twin_separation() - twins.separation).abs() < 1e-5);
+    }
+    sims.iter_mut().for_each(|sim| sim.step(1000));
+    let [gpu, cpu] = &sims;
+    let (g, c) = (gpu.twin_separation(), cpu.twin_separation());
+    assert!(g > 5. * twins.separation, "{g}");
+    assert!((g - c).abs() < 0.25 * c, "{g} != {c}");
+    for sim in &sims {
+        for (i, instance) in sim.read_raw_instances().iter().enumerate().take(10) {
+            assert!((instance.color() - twins.color(i)).abs().max_element() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn separation_grows_exponentially_then_saturates() {
+    for backend in [Backend::Gpu, Backend::Cpu] {
+        let config = common::config(&common::scene("butterfly"), backend, 1000);
+        let Some(mut sim) = common::simulation(config) else {
+            return;
+        };
+        // * h = 0.005, SO 1000 STEPS ARE 5 TIME UNITS
+        sim.step(1000);
+        let early = sim.twin_separation();
+        sim.step(1000);
+        let late = sim.twin_separation();
+        let exponent = (late.ln() - early.ln()) / 5.;
+        assert!((0.6..1.2).contains(&exponent), "{backend:?}: {exponent}");
+        sim.step(4000);
+        let saturated = sim.twin_separation();
+        sim.step(2000);
+        let growth = sim.twin_separation().ln() - saturated.ln();
+        assert!((5.0..40.).contains(&saturated), "{backend:?}: {saturated}");
+        assert!(growth.abs() < 0.5, "{backend:?}: {growth}");
+    }
+}
+
+#[test]
+fn scenes_load() {
+    let config = common::scene("butterfly").into_config().unwrap();
+    assert_eq!(config.twins.unwrap().separation, 1e-4);
+    assert_eq!(common::scene("lorenz").twins, None);
+
+    let twins = |twins| Scene {
+        particles: 1000,
+        twins: Some(twins),
+        ..Scene::default()
+    };
+    assert!(twins(Twins::default()).into_config().is_ok());
+    common::assert_rejected([
+        twins(Twins {
+            separation: 0.,
+            ..Twins::default()
+        }),
+        twins(Twins {
+            colors: [[f32::NAN, 0., 0.], [0.; 3]],
+            ..Twins::default()
+        }),
+        Scene {
+            system: System::Lorenz96,
+            ..twins(Twins::default())
+        },
+        Scene {
+            emitter: Some(Emitter::default()),
+            ..twins(Twins::default())
+        },
+        Scene {
+            coupling: Some(Coupling::default()),
+            ..twins(Twins::default())
+        },
+    ]);
+}